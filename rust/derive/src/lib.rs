@@ -0,0 +1,163 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+//! Derive macros of the Suricata Rust code.
+
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr};
+
+/// Derive `applayer::AppLayerEvent` for an enum of unit variants.
+///
+/// The name of an event, as used in the rules, is the name of the variant
+/// in snake case, `MalformedData` becoming `malformed_data`. It can be set
+/// with the `name` attribute:
+///
+/// ```ignore
+/// #[derive(AppLayerEvent)]
+/// pub enum IKEV2Event {
+///     MalformedData,
+///     #[name("weak_crypto_nodh")]
+///     WeakCryptoNoDH,
+/// }
+/// ```
+///
+/// The id of an event is the value of the variant, so the enum can set
+/// explicit discriminants.
+#[proc_macro_derive(AppLayerEvent, attributes(name))]
+pub fn derive_app_layer_event(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = input.ident;
+
+    let data = match input.data {
+        Data::Enum(data) => data,
+        _ => panic!("AppLayerEvent can only be derived for enums"),
+    };
+
+    let mut variants = Vec::new();
+    let mut event_names = Vec::new();
+    let mut event_cstrings = Vec::new();
+    for variant in data.variants {
+        match variant.fields {
+            Fields::Unit => {}
+            _ => panic!("AppLayerEvent variants cannot have fields"),
+        }
+        let mut event_name = None;
+        for attr in &variant.attrs {
+            if attr.path.is_ident("name") {
+                let lit: LitStr = attr.parse_args()
+                    .expect("expected #[name(\"event_name\")]");
+                event_name = Some(lit.value());
+            }
+        }
+        let event_name = event_name
+            .unwrap_or_else(|| transform_name(&variant.ident.to_string()));
+        event_cstrings.push(format!("{}\0", event_name));
+        event_names.push(event_name);
+        variants.push(variant.ident);
+    }
+
+    let expanded = quote! {
+        impl crate::applayer::AppLayerEvent for #name {
+            fn from_id(id: i32) -> Option<#name> {
+                match id {
+                    #(_ if id == #name::#variants as i32 => Some(#name::#variants),)*
+                    _ => None,
+                }
+            }
+
+            fn from_name(name: &str) -> Option<#name> {
+                match name {
+                    #(#event_names => Some(#name::#variants),)*
+                    _ => None,
+                }
+            }
+
+            fn id(&self) -> i32 {
+                match *self {
+                    #(#name::#variants => #name::#variants as i32,)*
+                }
+            }
+
+            fn to_cstring(&self) -> &'static str {
+                match *self {
+                    #(#name::#variants => #event_cstrings,)*
+                }
+            }
+
+            extern "C" fn get_event_info(
+                event_name: *const ::std::os::raw::c_char,
+                event_id: *mut ::std::os::raw::c_int,
+                event_type: *mut crate::core::AppLayerEventType)
+                -> ::std::os::raw::c_int
+            {
+                crate::applayer::get_event_info::<#name>(event_name, event_id, event_type)
+            }
+
+            extern "C" fn get_event_info_by_id(
+                event_id: ::std::os::raw::c_int,
+                event_name: *mut *const ::std::os::raw::c_char,
+                event_type: *mut crate::core::AppLayerEventType)
+                -> i8
+            {
+                crate::applayer::get_event_info_by_id::<#name>(event_id, event_name, event_type)
+            }
+        }
+    };
+
+    TokenStream::from(expanded)
+}
+
+/// Convert a variant name to snake case. An acronym is kept as one word,
+/// `WeakCryptoPRF` becoming `weak_crypto_prf`.
+fn transform_name(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut out = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if prev.is_lowercase() || prev.is_numeric() ||
+                (prev.is_uppercase() && next_lower)
+            {
+                out.push('_');
+            }
+        }
+        out.extend(c.to_lowercase());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transform_name() {
+        assert_eq!(transform_name("MalformedData"), "malformed_data");
+        assert_eq!(transform_name("UsmNoAuth"), "usm_no_auth");
+        assert_eq!(transform_name("WeakCryptoPRF"), "weak_crypto_prf");
+        assert_eq!(transform_name("WeakCryptoDH"), "weak_crypto_dh");
+        assert_eq!(transform_name("ZFlagSet"), "z_flag_set");
+        assert_eq!(transform_name("Kerberoasting"), "kerberoasting");
+    }
+}
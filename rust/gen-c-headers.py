@@ -95,6 +95,8 @@ type_map = {
     "KRB5Transaction": "KRB5Transaction",
     "SIPState": "SIPState",
     "SIPTransaction": "SIPTransaction",
+    "RdpTransaction": "RdpTransaction",
+    "DHCPTransaction": "DHCPTransaction",
     "JsonT": "json_t",
     "DetectEngineState": "DetectEngineState",
     "core::DetectEngineState": "DetectEngineState",
@@ -108,6 +110,7 @@ type_map = {
     "Store": "Store",
     "AppProto": "AppProto",
     "DetectDnsOpcode": "void *",
+    "DetectSNMPOid": "void",
 }
 
 def convert_type(rs_type):
@@ -170,6 +173,41 @@ def should_regen(input_filename, output_filename):
         return True
     return False
 
+# Prototypes of the functions exported by the macros of applayer.rs, by
+# macro name. The names of the functions are the identifier arguments of
+# the macro.
+macro_prototypes = {
+    "export_tx_logger": [
+        "json_t *%s(void *tx);",
+    ],
+    "export_logger": [
+        "void *%s(const void *conf);",
+        "void %s(void *logger);",
+        "json_t *%s(void *logger, void *tx);",
+    ],
+    "export_tx_get_buffer": [
+        "uint8_t %s(void *tx, const uint8_t **buffer, uint32_t *buffer_len);",
+    ],
+    "export_tx_lua_string": [
+        "int %s(lua_State *clua, void *tx);",
+    ],
+}
+
+def gen_macro_prototypes(buf, writer):
+    for (macro, args) in re.findall(
+            r"^(export_[a-z_]+)!\((.*?)\);", buf, re.M | re.DOTALL):
+        if macro not in macro_prototypes:
+            continue
+        if macro == "export_logger":
+            names = re.findall(r"(?:new|free|log):\s*([A-Za-z0-9_]+)", args)
+        else:
+            names = [args.split(",")[0].strip()]
+        prototypes = macro_prototypes[macro]
+        if len(names) != len(prototypes):
+            raise Exception("Failed to parse %s!(%s)" % (macro, args))
+        for (prototype, name) in zip(prototypes, names):
+            writer.write(u"%s\n" % (prototype % (name)))
+
 def gen_headers(filename):
 
     output_filename = make_output_filename(filename)
@@ -214,6 +252,8 @@ def gen_headers(filename):
 
         writer.write(u"%s %s(%s);\n" % (returns, fnName, ", ".join(args)))
 
+    gen_macro_prototypes(buf, writer)
+
     if writer.tell() > 0:
         print("Writing %s" % (output_filename))
         if not os.path.exists(os.path.dirname(output_filename)):
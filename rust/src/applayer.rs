@@ -15,7 +15,27 @@
  * 02110-1301, USA.
  */
 
+//! Common application layer types, and the generic C entry points of
+//! the parsers.
+//!
+//! A parser implements `State` for its state, `Transaction` for its
+//! transactions and `AppLayerEvent` for its events, then
+//! `RustParser::new` fills in the parser declaration with the generic
+//! entry points below. The `impl_transaction!` macro implements
+//! `Transaction` for the usual transaction fields, and
+//! `#[derive(AppLayerEvent)]` implements `AppLayerEvent` for event enums.
+//!
+//! The functions called from C are exported with the `export_tx_logger!`,
+//! `export_logger!`, `export_tx_get_buffer!` and `export_tx_lua_string!`
+//! macros, which `gen-c-headers.py` knows about.
+
 use std;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int, c_void};
+
+use crate::core::{self, AppLayerDecoderEvents, AppLayerEventType, AppProto,
+                  DetectEngineState, Flow};
+use crate::parser::{AppLayerParserStateIssetFlag, APP_LAYER_PARSER_EOF};
 
 #[repr(C)]
 pub struct AppLayerGetTxIterTuple {
@@ -96,3 +116,521 @@ macro_rules!export_tx_set_detect_state {
         }
     )
 }
+
+/// Common interface of the transactions of a parser.
+pub trait Transaction {
+    /// Id of the transaction, starting at 1.
+    fn id(&self) -> u64;
+    fn logged(&mut self) -> &mut LoggerFlags;
+    fn de_state(&mut self) -> &mut Option<*mut DetectEngineState>;
+    fn events(&mut self) -> &mut *mut AppLayerDecoderEvents;
+
+    /// Set an event on the transaction.
+    fn set_event<E: AppLayerEvent>(&mut self, event: E) {
+        core::sc_app_layer_decoder_events_set_event_raw(self.events(),
+                                                        event.id() as u8);
+    }
+}
+
+/// Implement `Transaction` for a transaction with the usual `tx_id`,
+/// `logged`, `de_state` and `events` fields.
+#[macro_export]
+macro_rules! impl_transaction {
+    ($type:ty) => (
+        impl $crate::applayer::Transaction for $type {
+            fn id(&self) -> u64 {
+                self.tx_id
+            }
+            fn logged(&mut self) -> &mut $crate::applayer::LoggerFlags {
+                &mut self.logged
+            }
+            fn de_state(&mut self) -> &mut Option<*mut $crate::core::DetectEngineState> {
+                &mut self.de_state
+            }
+            fn events(&mut self) -> &mut *mut $crate::core::AppLayerDecoderEvents {
+                &mut self.events
+            }
+        }
+    )
+}
+
+/// Common interface of the states of a parser, keeping their transactions
+/// in a vector.
+pub trait State: Sized {
+    type Transaction: Transaction;
+    type Event: AppLayerEvent;
+
+    /// Progress of a complete transaction.
+    const PROGRESS_COMPLETE: i32 = 1;
+
+    fn new() -> Self;
+    fn transactions(&mut self) -> &mut Vec<Self::Transaction>;
+    /// Number of transactions created.
+    fn tx_count(&self) -> u64;
+
+    /// Parse data going to the server. Returns false on error.
+    fn parse_request(&mut self, flow: *const Flow, input: &[u8], eof: bool) -> bool;
+
+    /// Parse data going to the client. Returns false on error. By default
+    /// both directions are parsed the same way.
+    fn parse_response(&mut self, flow: *const Flow, input: &[u8], eof: bool) -> bool {
+        self.parse_request(flow, input, eof)
+    }
+
+    /// Progress of a transaction in a direction. Transactions are
+    /// complete by default.
+    fn tx_progress(_tx: &Self::Transaction, _direction: u8) -> i32 {
+        Self::PROGRESS_COMPLETE
+    }
+
+    fn get_tx(&mut self, tx_id: u64) -> Option<&mut Self::Transaction> {
+        self.transactions().iter_mut().find(|tx| tx.id() == tx_id + 1)
+    }
+
+    fn free_tx(&mut self, tx_id: u64) {
+        let transactions = self.transactions();
+        if let Some(index) = transactions.iter().position(|tx| tx.id() == tx_id + 1) {
+            transactions.remove(index);
+        }
+    }
+
+    fn tx_iterator(&mut self, min_tx_id: u64, state: &mut u64)
+                   -> Option<(&Self::Transaction, u64, bool)>
+    {
+        let transactions = self.transactions();
+        let len = transactions.len();
+        let mut index = *state as usize;
+        while index < len {
+            let tx = &transactions[index];
+            if tx.id() < min_tx_id + 1 {
+                index += 1;
+                continue;
+            }
+            *state = index as u64;
+            return Some((tx, tx.id() - 1, (len - index) > 1));
+        }
+        None
+    }
+
+    /// Set an event on the last transaction.
+    fn set_event(&mut self, event: Self::Event) {
+        if let Some(tx) = self.transactions().last_mut() {
+            tx.set_event(event);
+        }
+    }
+}
+
+/// Application layer events of a parser. It is implemented with
+/// `#[derive(AppLayerEvent)]` from the suricata-derive crate.
+pub trait AppLayerEvent: Sized {
+    fn from_id(id: i32) -> Option<Self>;
+    fn from_name(name: &str) -> Option<Self>;
+    fn id(&self) -> i32;
+    /// Name of the event, as a nul terminated string.
+    fn to_cstring(&self) -> &'static str;
+
+    /// C entry point returning the id of an event from its name.
+    extern "C" fn get_event_info(event_name: *const c_char,
+                                 event_id: *mut c_int,
+                                 event_type: *mut AppLayerEventType) -> c_int;
+
+    /// C entry point returning the name of an event from its id.
+    extern "C" fn get_event_info_by_id(event_id: c_int,
+                                       event_name: *mut *const c_char,
+                                       event_type: *mut AppLayerEventType) -> i8;
+}
+
+/// Implementation of `AppLayerEvent::get_event_info`.
+pub fn get_event_info<E: AppLayerEvent>(event_name: *const c_char,
+                                        event_id: *mut c_int,
+                                        event_type: *mut AppLayerEventType)
+                                        -> c_int {
+    if event_name.is_null() {
+        return -1;
+    }
+    let event = match unsafe { CStr::from_ptr(event_name) }.to_str() {
+        Ok(name) => E::from_name(name),
+        Err(_) => None,
+    };
+    match event {
+        Some(event) => {
+            unsafe {
+                *event_type = core::APP_LAYER_EVENT_TYPE_TRANSACTION;
+                *event_id = event.id() as c_int;
+            }
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Implementation of `AppLayerEvent::get_event_info_by_id`.
+pub fn get_event_info_by_id<E: AppLayerEvent>(event_id: c_int,
+                                              event_name: *mut *const c_char,
+                                              event_type: *mut AppLayerEventType)
+                                              -> i8 {
+    match E::from_id(event_id as i32) {
+        Some(event) => {
+            unsafe {
+                *event_name = event.to_cstring().as_ptr() as *const c_char;
+                *event_type = core::APP_LAYER_EVENT_TYPE_TRANSACTION;
+            }
+            0
+        }
+        None => -1,
+    }
+}
+
+// Generic C entry points, filled in the parser declaration by
+// RustParser::new.
+
+pub extern "C" fn state_new<S: State>() -> *mut c_void {
+    let boxed = Box::new(S::new());
+    Box::into_raw(boxed) as *mut c_void
+}
+
+pub extern "C" fn state_free<S: State>(state: *mut c_void) {
+    let _drop: Box<S> = unsafe { Box::from_raw(state as *mut S) };
+}
+
+pub extern "C" fn state_tx_free<S: State>(state: *mut c_void, tx_id: u64) {
+    let state = unsafe { &mut *(state as *mut S) };
+    state.free_tx(tx_id);
+}
+
+pub extern "C" fn state_get_tx<S: State>(state: *mut c_void, tx_id: u64) -> *mut c_void {
+    let state = unsafe { &mut *(state as *mut S) };
+    match state.get_tx(tx_id) {
+        Some(tx) => tx as *mut S::Transaction as *mut c_void,
+        None => std::ptr::null_mut(),
+    }
+}
+
+pub extern "C" fn state_get_tx_count<S: State>(state: *mut c_void) -> u64 {
+    let state = unsafe { &mut *(state as *mut S) };
+    state.tx_count()
+}
+
+pub extern "C" fn state_progress_completion_status<S: State>(_direction: u8) -> c_int {
+    S::PROGRESS_COMPLETE
+}
+
+pub extern "C" fn tx_get_alstate_progress<S: State>(tx: *mut c_void, direction: u8) -> c_int {
+    let tx = unsafe { &mut *(tx as *mut S::Transaction) };
+    S::tx_progress(tx, direction)
+}
+
+fn parser_eof(pstate: *mut c_void) -> bool {
+    unsafe { AppLayerParserStateIssetFlag(pstate, APP_LAYER_PARSER_EOF) > 0 }
+}
+
+pub extern "C" fn parse_request<S: State>(flow: *const Flow,
+                                          state: *mut c_void,
+                                          pstate: *mut c_void,
+                                          input: *const u8,
+                                          input_len: u32,
+                                          _data: *const c_void,
+                                          _flags: u8) -> i32 {
+    let eof = parser_eof(pstate);
+    let state = unsafe { &mut *(state as *mut S) };
+    let buf: &[u8] = if input.is_null() {
+        &[]
+    } else {
+        unsafe { std::slice::from_raw_parts(input, input_len as usize) }
+    };
+    if state.parse_request(flow, buf, eof) {
+        return 1;
+    }
+    return -1;
+}
+
+pub extern "C" fn parse_response<S: State>(flow: *const Flow,
+                                           state: *mut c_void,
+                                           pstate: *mut c_void,
+                                           input: *const u8,
+                                           input_len: u32,
+                                           _data: *const c_void,
+                                           _flags: u8) -> i32 {
+    let eof = parser_eof(pstate);
+    let state = unsafe { &mut *(state as *mut S) };
+    let buf: &[u8] = if input.is_null() {
+        &[]
+    } else {
+        unsafe { std::slice::from_raw_parts(input, input_len as usize) }
+    };
+    if state.parse_response(flow, buf, eof) {
+        return 1;
+    }
+    return -1;
+}
+
+pub extern "C" fn tx_get_logged<S: State>(_state: *mut c_void, tx: *mut c_void) -> u32 {
+    let tx = unsafe { &mut *(tx as *mut S::Transaction) };
+    tx.logged().get()
+}
+
+pub extern "C" fn tx_set_logged<S: State>(_state: *mut c_void, tx: *mut c_void, logged: u32) {
+    let tx = unsafe { &mut *(tx as *mut S::Transaction) };
+    tx.logged().set(logged);
+}
+
+pub extern "C" fn tx_get_detect_state<S: State>(tx: *mut c_void) -> *mut DetectEngineState {
+    let tx = unsafe { &mut *(tx as *mut S::Transaction) };
+    match *tx.de_state() {
+        Some(ds) => ds,
+        None => std::ptr::null_mut(),
+    }
+}
+
+pub extern "C" fn tx_set_detect_state<S: State>(tx: *mut c_void,
+                                                de_state: &mut DetectEngineState) -> c_int {
+    let tx = unsafe { &mut *(tx as *mut S::Transaction) };
+    *tx.de_state() = Some(de_state);
+    0
+}
+
+pub extern "C" fn tx_get_events<S: State>(tx: *mut c_void) -> *mut AppLayerDecoderEvents {
+    let tx = unsafe { &mut *(tx as *mut S::Transaction) };
+    *tx.events()
+}
+
+pub extern "C" fn state_get_tx_iterator<S: State>(_ipproto: u8,
+                                                  _alproto: AppProto,
+                                                  state: *mut c_void,
+                                                  min_tx_id: u64,
+                                                  _max_tx_id: u64,
+                                                  istate: &mut u64)
+                                                  -> AppLayerGetTxIterTuple {
+    let state = unsafe { &mut *(state as *mut S) };
+    match state.tx_iterator(min_tx_id, istate) {
+        Some((tx, out_tx_id, has_next)) => {
+            let c_tx = tx as *const S::Transaction as *mut c_void;
+            AppLayerGetTxIterTuple::with_values(c_tx, out_tx_id, has_next)
+        }
+        None => AppLayerGetTxIterTuple::not_found(),
+    }
+}
+
+/// Export a function returning the JSON record of a transaction, built by
+/// `$log`, a function taking the transaction and returning an
+/// `Option<Json>`.
+#[macro_export]
+macro_rules! export_tx_logger {
+    ($name:ident, $type:ty, $log:expr) => (
+        #[no_mangle]
+        pub extern "C" fn $name(tx: *mut ::std::os::raw::c_void) -> *mut $crate::json::JsonT {
+            let tx = cast_pointer!(tx, $type);
+            let log: fn(&$type) -> Option<$crate::json::Json> = $log;
+            match log(tx) {
+                Some(js) => js.unwrap(),
+                None => ::std::ptr::null_mut(),
+            }
+        }
+    )
+}
+
+/// Loggers configured from their EVE output section.
+pub trait Logger {
+    type Transaction;
+
+    fn new(conf: crate::conf::ConfNode) -> Self;
+    fn log(&self, tx: &Self::Transaction) -> Option<crate::json::Json>;
+}
+
+/// Export the functions creating, freeing and running a `Logger`.
+#[macro_export]
+macro_rules! export_logger {
+    ($type:ty, new: $new:ident, free: $free:ident, log: $log:ident) => (
+        #[no_mangle]
+        pub extern "C" fn $new(conf: *const ::std::os::raw::c_void) -> *mut ::std::os::raw::c_void {
+            let conf = $crate::conf::ConfNode::wrap(conf);
+            let logger: $type = $crate::applayer::Logger::new(conf);
+            Box::into_raw(Box::new(logger)) as *mut ::std::os::raw::c_void
+        }
+
+        #[no_mangle]
+        pub extern "C" fn $free(logger: *mut ::std::os::raw::c_void) {
+            let _drop: Box<$type> = unsafe { Box::from_raw(logger as *mut $type) };
+        }
+
+        #[no_mangle]
+        pub extern "C" fn $log(logger: *mut ::std::os::raw::c_void,
+                               tx: *mut ::std::os::raw::c_void) -> *mut $crate::json::JsonT {
+            let logger = cast_pointer!(logger, $type);
+            let tx = cast_pointer!(tx, <$type as $crate::applayer::Logger>::Transaction);
+            match $crate::applayer::Logger::log(logger, tx) {
+                Some(js) => js.unwrap(),
+                None => ::std::ptr::null_mut(),
+            }
+        }
+    )
+}
+
+/// Export a function returning a buffer of a transaction for detection.
+/// `$get` takes the transaction and returns an `Option<&[u8]>`. The
+/// exported function returns 1 if the buffer is set, 0 otherwise.
+#[macro_export]
+macro_rules! export_tx_get_buffer {
+    ($name:ident, $type:ty, $get:expr) => (
+        #[no_mangle]
+        pub extern "C" fn $name(tx: *mut ::std::os::raw::c_void,
+                                buffer: *mut *const u8,
+                                buffer_len: *mut u32) -> u8 {
+            let tx = cast_pointer!(tx, $type);
+            let get: fn(&$type) -> Option<&[u8]> = $get;
+            match get(tx) {
+                Some(buf) if buf.len() > 0 => {
+                    unsafe {
+                        *buffer = buf.as_ptr();
+                        *buffer_len = buf.len() as u32;
+                    }
+                    1
+                }
+                _ => {
+                    unsafe {
+                        *buffer = ::std::ptr::null();
+                        *buffer_len = 0;
+                    }
+                    0
+                }
+            }
+        }
+    )
+}
+
+/// Export a function pushing a string of a transaction on the Lua stack.
+/// `$get` takes the transaction and returns an `Option<&[u8]>`. The
+/// exported function returns the number of values pushed.
+#[macro_export]
+macro_rules! export_tx_lua_string {
+    ($name:ident, $type:ty, $get:expr) => (
+        #[cfg(feature = "lua")]
+        #[no_mangle]
+        pub extern "C" fn $name(clua: &mut $crate::lua::CLuaState,
+                                tx: *mut ::std::os::raw::c_void) -> ::std::os::raw::c_int {
+            let lua = $crate::lua::LuaState {
+                lua: clua,
+            };
+            let tx = cast_pointer!(tx, $type);
+            let get: fn(&$type) -> Option<&[u8]> = $get;
+            match get(tx) {
+                Some(buf) => {
+                    lua.pushstring(&String::from_utf8_lossy(buf));
+                    1
+                }
+                None => 0,
+            }
+        }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    #[derive(AppLayerEvent, Debug, PartialEq)]
+    pub enum TestEvent {
+        FirstEvent,
+        #[name("second")]
+        SecondEvent,
+    }
+
+    struct TestTransaction {
+        tx_id: u64,
+        logged: LoggerFlags,
+        de_state: Option<*mut DetectEngineState>,
+        events: *mut AppLayerDecoderEvents,
+    }
+
+    impl_transaction!(TestTransaction);
+
+    struct TestState {
+        tx_id: u64,
+        transactions: Vec<TestTransaction>,
+    }
+
+    impl State for TestState {
+        type Transaction = TestTransaction;
+        type Event = TestEvent;
+
+        fn new() -> TestState {
+            TestState {
+                tx_id: 0,
+                transactions: Vec::new(),
+            }
+        }
+
+        fn transactions(&mut self) -> &mut Vec<TestTransaction> {
+            &mut self.transactions
+        }
+
+        fn tx_count(&self) -> u64 {
+            self.tx_id
+        }
+
+        fn parse_request(&mut self, _flow: *const Flow, input: &[u8], _eof: bool) -> bool {
+            for _ in input {
+                self.tx_id += 1;
+                self.transactions.push(TestTransaction {
+                    tx_id: self.tx_id,
+                    logged: LoggerFlags::new(),
+                    de_state: None,
+                    events: std::ptr::null_mut(),
+                });
+            }
+            true
+        }
+    }
+
+    #[test]
+    fn test_app_layer_events() {
+        assert_eq!(TestEvent::from_name("first_event"), Some(TestEvent::FirstEvent));
+        assert_eq!(TestEvent::from_name("second"), Some(TestEvent::SecondEvent));
+        assert_eq!(TestEvent::from_name("second_event"), None);
+        assert_eq!(TestEvent::from_id(0), Some(TestEvent::FirstEvent));
+        assert_eq!(TestEvent::from_id(2), None);
+        assert_eq!(TestEvent::SecondEvent.id(), 1);
+        assert_eq!(TestEvent::SecondEvent.to_cstring(), "second\0");
+
+        let mut event_id: c_int = -1;
+        let mut event_type: AppLayerEventType = 0;
+        let name = CString::new("second").unwrap();
+        assert_eq!(TestEvent::get_event_info(
+            name.as_ptr(), &mut event_id, &mut event_type), 0);
+        assert_eq!(event_id, 1);
+        assert_eq!(event_type, core::APP_LAYER_EVENT_TYPE_TRANSACTION);
+        let name = CString::new("third_event").unwrap();
+        assert_eq!(TestEvent::get_event_info(
+            name.as_ptr(), &mut event_id, &mut event_type), -1);
+
+        let mut event_name: *const c_char = std::ptr::null();
+        assert_eq!(TestEvent::get_event_info_by_id(
+            0, &mut event_name, &mut event_type), 0);
+        assert_eq!(unsafe { CStr::from_ptr(event_name) }.to_str(), Ok("first_event"));
+        assert_eq!(TestEvent::get_event_info_by_id(
+            2, &mut event_name, &mut event_type), -1);
+    }
+
+    #[test]
+    fn test_state_transactions() {
+        let mut state = TestState::new();
+        assert!(state.parse_response(std::ptr::null(), b"abc", false));
+        assert_eq!(state.tx_count(), 3);
+        assert_eq!(state.get_tx(1).map(|tx| tx.id()), Some(2));
+        assert!(state.get_tx(3).is_none());
+
+        state.free_tx(0);
+        assert!(state.get_tx(0).is_none());
+        assert_eq!(state.transactions().len(), 2);
+
+        let mut istate = 0;
+        let (tx, tx_id, has_next) = state.tx_iterator(0, &mut istate).unwrap();
+        assert_eq!((tx.id(), tx_id, has_next), (2, 1, true));
+        let mut istate = 0;
+        let (tx, tx_id, has_next) = state.tx_iterator(2, &mut istate).unwrap();
+        assert_eq!((tx.id(), tx_id, has_next), (3, 2, false));
+        assert_eq!(istate, 1);
+        assert!(state.tx_iterator(3, &mut istate).is_none());
+    }
+}
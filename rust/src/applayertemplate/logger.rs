@@ -15,7 +15,6 @@
  * 02110-1301, USA.
  */
 
-use std;
 use crate::json::*;
 use super::template::TemplateTransaction;
 
@@ -30,11 +29,4 @@ fn log_template(tx: &TemplateTransaction) -> Option<Json> {
     return Some(js);
 }
 
-#[no_mangle]
-pub extern "C" fn rs_template_logger_log(tx: *mut std::os::raw::c_void) -> *mut JsonT {
-    let tx = cast_pointer!(tx, TemplateTransaction);
-    match log_template(tx) {
-        Some(js) => js.unwrap(),
-        None => std::ptr::null_mut(),
-    }
-}
+export_tx_logger!(rs_template_logger_log, TemplateTransaction, log_template);
@@ -18,8 +18,7 @@
 use std;
 use crate::core::{self, ALPROTO_UNKNOWN, AppProto, Flow, IPPROTO_TCP};
 use crate::log::*;
-use std::mem::transmute;
-use crate::applayer::{self, LoggerFlags};
+use crate::applayer::{self, LoggerFlags, Transaction};
 use crate::parser::*;
 use std::ffi::CString;
 use nom;
@@ -27,6 +26,13 @@ use super::parser;
 
 static mut ALPROTO_TEMPLATE: AppProto = ALPROTO_UNKNOWN;
 
+#[repr(u32)]
+#[derive(AppLayerEvent)]
+pub enum TemplateEvent {
+    /// Message that could not be parsed.
+    InvalidMessage,
+}
+
 pub struct TemplateTransaction {
     tx_id: u64,
     pub request: Option<String>,
@@ -37,6 +43,8 @@ pub struct TemplateTransaction {
     events: *mut core::AppLayerDecoderEvents,
 }
 
+impl_transaction!(TemplateTransaction);
+
 impl TemplateTransaction {
     pub fn new() -> TemplateTransaction {
         TemplateTransaction {
@@ -73,42 +81,6 @@ pub struct TemplateState {
 }
 
 impl TemplateState {
-    pub fn new() -> Self {
-        Self {
-            tx_id: 0,
-            request_buffer: Vec::new(),
-            response_buffer: Vec::new(),
-            transactions: Vec::new(),
-        }
-    }
-
-    // Free a transaction by ID.
-    fn free_tx(&mut self, tx_id: u64) {
-        let len = self.transactions.len();
-        let mut found = false;
-        let mut index = 0;
-        for i in 0..len {
-            let tx = &self.transactions[i];
-            if tx.tx_id == tx_id + 1 {
-                found = true;
-                index = i;
-                break;
-            }
-        }
-        if found {
-            self.transactions.remove(index);
-        }
-    }
-
-    pub fn get_tx(&mut self, tx_id: u64) -> Option<&TemplateTransaction> {
-        for tx in &mut self.transactions {
-            if tx.tx_id == tx_id + 1 {
-                return Some(tx);
-            }
-        }
-        return None;
-    }
-
     fn new_tx(&mut self) -> TemplateTransaction {
         let mut tx = TemplateTransaction::new();
         self.tx_id += 1;
@@ -124,8 +96,30 @@ impl TemplateState {
         }
         None
     }
+}
+
+impl applayer::State for TemplateState {
+    type Transaction = TemplateTransaction;
+    type Event = TemplateEvent;
+
+    fn new() -> Self {
+        Self {
+            tx_id: 0,
+            request_buffer: Vec::new(),
+            response_buffer: Vec::new(),
+            transactions: Vec::new(),
+        }
+    }
 
-    fn parse_request(&mut self, input: &[u8]) -> bool {
+    fn transactions(&mut self) -> &mut Vec<TemplateTransaction> {
+        &mut self.transactions
+    }
+
+    fn tx_count(&self) -> u64 {
+        self.tx_id
+    }
+
+    fn parse_request(&mut self, _flow: *const Flow, input: &[u8], _eof: bool) -> bool {
         // We're not interested in empty requests.
         if input.len() == 0 {
             return true;
@@ -155,6 +149,9 @@ impl TemplateState {
                     break;
                 }
                 Err(_) => {
+                    let mut tx = self.new_tx();
+                    tx.set_event(TemplateEvent::InvalidMessage);
+                    self.transactions.push(tx);
                     return false;
                 }
             }
@@ -163,7 +160,7 @@ impl TemplateState {
         return true;
     }
 
-    fn parse_response(&mut self, input: &[u8]) -> bool {
+    fn parse_response(&mut self, _flow: *const Flow, input: &[u8], _eof: bool) -> bool {
         // We're not interested in empty responses.
         if input.len() == 0 {
             return true;
@@ -206,25 +203,12 @@ impl TemplateState {
         return true;
     }
 
-    fn tx_iterator(
-        &mut self,
-        min_tx_id: u64,
-        state: &mut u64,
-    ) -> Option<(&TemplateTransaction, u64, bool)> {
-        let mut index = *state as usize;
-        let len = self.transactions.len();
-
-        while index < len {
-            let tx = &self.transactions[index];
-            if tx.tx_id < min_tx_id + 1 {
-                index += 1;
-                continue;
-            }
-            *state = index as u64;
-            return Some((tx, tx.tx_id - 1, (len - index) > 1));
+    fn tx_progress(tx: &TemplateTransaction, _direction: u8) -> i32 {
+        // Transaction is done if we have a response.
+        if tx.response.is_some() {
+            return 1;
         }
-
-        return None;
+        return 0;
     }
 }
 
@@ -243,15 +227,6 @@ fn probe(input: &[u8]) -> bool {
 
 // C exports.
 
-export_tx_get_detect_state!(
-    rs_template_tx_get_detect_state,
-    TemplateTransaction
-);
-export_tx_set_detect_state!(
-    rs_template_tx_set_detect_state,
-    TemplateTransaction
-);
-
 /// C entry point for a probing parser.
 #[no_mangle]
 pub extern "C" fn rs_template_probing_parser(
@@ -271,242 +246,14 @@ pub extern "C" fn rs_template_probing_parser(
     return ALPROTO_UNKNOWN;
 }
 
-#[no_mangle]
-pub extern "C" fn rs_template_state_new() -> *mut std::os::raw::c_void {
-    let state = TemplateState::new();
-    let boxed = Box::new(state);
-    return unsafe { transmute(boxed) };
-}
-
-#[no_mangle]
-pub extern "C" fn rs_template_state_free(state: *mut std::os::raw::c_void) {
-    // Just unbox...
-    let _drop: Box<TemplateState> = unsafe { transmute(state) };
-}
-
-#[no_mangle]
-pub extern "C" fn rs_template_state_tx_free(
-    state: *mut std::os::raw::c_void,
-    tx_id: u64,
-) {
-    let state = cast_pointer!(state, TemplateState);
-    state.free_tx(tx_id);
-}
-
-#[no_mangle]
-pub extern "C" fn rs_template_parse_request(
-    _flow: *const Flow,
-    state: *mut std::os::raw::c_void,
-    pstate: *mut std::os::raw::c_void,
-    input: *const u8,
-    input_len: u32,
-    _data: *const std::os::raw::c_void,
-    _flags: u8,
-) -> i32 {
-    let eof = unsafe {
-        if AppLayerParserStateIssetFlag(pstate, APP_LAYER_PARSER_EOF) > 0 {
-            true
-        } else {
-            false
-        }
-    };
-
-    if eof {
-        // If needed, handled EOF, or pass it into the parser.
-    }
-
-    let state = cast_pointer!(state, TemplateState);
-    let buf = build_slice!(input, input_len as usize);
-    if state.parse_request(buf) {
-        return 1;
-    }
-    return -1;
-}
-
-#[no_mangle]
-pub extern "C" fn rs_template_parse_response(
-    _flow: *const Flow,
-    state: *mut std::os::raw::c_void,
-    pstate: *mut std::os::raw::c_void,
-    input: *const u8,
-    input_len: u32,
-    _data: *const std::os::raw::c_void,
-    _flags: u8,
-) -> i32 {
-    let _eof = unsafe {
-        if AppLayerParserStateIssetFlag(pstate, APP_LAYER_PARSER_EOF) > 0 {
-            true
-        } else {
-            false
-        }
-    };
-    let state = cast_pointer!(state, TemplateState);
-    let buf = build_slice!(input, input_len as usize);
-    if state.parse_response(buf) {
-        return 1;
-    }
-    return -1;
-}
-
-#[no_mangle]
-pub extern "C" fn rs_template_state_get_tx(
-    state: *mut std::os::raw::c_void,
-    tx_id: u64,
-) -> *mut std::os::raw::c_void {
-    let state = cast_pointer!(state, TemplateState);
-    match state.get_tx(tx_id) {
-        Some(tx) => {
-            return unsafe { transmute(tx) };
-        }
-        None => {
-            return std::ptr::null_mut();
-        }
-    }
-}
-
-#[no_mangle]
-pub extern "C" fn rs_template_state_get_tx_count(
-    state: *mut std::os::raw::c_void,
-) -> u64 {
-    let state = cast_pointer!(state, TemplateState);
-    return state.tx_id;
-}
-
-#[no_mangle]
-pub extern "C" fn rs_template_state_progress_completion_status(
-    _direction: u8,
-) -> std::os::raw::c_int {
-    // This parser uses 1 to signal transaction completion status.
-    return 1;
-}
-
-#[no_mangle]
-pub extern "C" fn rs_template_tx_get_alstate_progress(
-    tx: *mut std::os::raw::c_void,
-    _direction: u8,
-) -> std::os::raw::c_int {
-    let tx = cast_pointer!(tx, TemplateTransaction);
-
-    // Transaction is done if we have a response.
-    if tx.response.is_some() {
-        return 1;
-    }
-    return 0;
-}
-
-#[no_mangle]
-pub extern "C" fn rs_template_tx_get_logged(
-    _state: *mut std::os::raw::c_void,
-    tx: *mut std::os::raw::c_void,
-) -> u32 {
-    let tx = cast_pointer!(tx, TemplateTransaction);
-    return tx.logged.get();
-}
-
-#[no_mangle]
-pub extern "C" fn rs_template_tx_set_logged(
-    _state: *mut std::os::raw::c_void,
-    tx: *mut std::os::raw::c_void,
-    logged: u32,
-) {
-    let tx = cast_pointer!(tx, TemplateTransaction);
-    tx.logged.set(logged);
-}
-
-#[no_mangle]
-pub extern "C" fn rs_template_state_get_events(
-    tx: *mut std::os::raw::c_void
-) -> *mut core::AppLayerDecoderEvents {
-    let tx = cast_pointer!(tx, TemplateTransaction);
-    return tx.events;
-}
-
-#[no_mangle]
-pub extern "C" fn rs_template_state_get_event_info(
-    _event_name: *const std::os::raw::c_char,
-    _event_id: *mut std::os::raw::c_int,
-    _event_type: *mut core::AppLayerEventType,
-) -> std::os::raw::c_int {
-    return -1;
-}
-
-#[no_mangle]
-pub extern "C" fn rs_template_state_get_event_info_by_id(_event_id: std::os::raw::c_int,
-                                                         _event_name: *mut *const std::os::raw::c_char,
-                                                         _event_type: *mut core::AppLayerEventType
-) -> i8 {
-    return -1;
-}
-#[no_mangle]
-pub extern "C" fn rs_template_state_get_tx_iterator(
-    _ipproto: u8,
-    _alproto: AppProto,
-    state: *mut std::os::raw::c_void,
-    min_tx_id: u64,
-    _max_tx_id: u64,
-    istate: &mut u64,
-) -> applayer::AppLayerGetTxIterTuple {
-    let state = cast_pointer!(state, TemplateState);
-    match state.tx_iterator(min_tx_id, istate) {
-        Some((tx, out_tx_id, has_next)) => {
-            let c_tx = unsafe { transmute(tx) };
-            let ires = applayer::AppLayerGetTxIterTuple::with_values(
-                c_tx,
-                out_tx_id,
-                has_next,
-            );
-            return ires;
-        }
-        None => {
-            return applayer::AppLayerGetTxIterTuple::not_found();
-        }
-    }
-}
-
-/// Get the request buffer for a transaction from C.
-///
-/// No required for parsing, but an example function for retrieving a
-/// pointer to the request buffer from C for detection.
-#[no_mangle]
-pub extern "C" fn rs_template_get_request_buffer(
-    tx: *mut std::os::raw::c_void,
-    buf: *mut *const u8,
-    len: *mut u32,
-) -> u8
-{
-    let tx = cast_pointer!(tx, TemplateTransaction);
-    if let Some(ref request) = tx.request {
-        if request.len() > 0 {
-            unsafe {
-                *len = request.len() as u32;
-                *buf = request.as_ptr();
-            }
-            return 1;
-        }
-    }
-    return 0;
-}
-
-/// Get the response buffer for a transaction from C.
-#[no_mangle]
-pub extern "C" fn rs_template_get_response_buffer(
-    tx: *mut std::os::raw::c_void,
-    buf: *mut *const u8,
-    len: *mut u32,
-) -> u8
-{
-    let tx = cast_pointer!(tx, TemplateTransaction);
-    if let Some(ref response) = tx.response {
-        if response.len() > 0 {
-            unsafe {
-                *len = response.len() as u32;
-                *buf = response.as_ptr();
-            }
-            return 1;
-        }
-    }
-    return 0;
-}
+// Get the request and response buffers of a transaction from C.
+//
+// Not required for parsing, but an example of functions retrieving a
+// buffer from C for detection.
+export_tx_get_buffer!(rs_template_get_request_buffer, TemplateTransaction,
+                      |tx| tx.request.as_ref().map(|r| r.as_bytes()));
+export_tx_get_buffer!(rs_template_get_response_buffer, TemplateTransaction,
+                      |tx| tx.response.as_ref().map(|r| r.as_bytes()));
 
 // Parser name as a C style string.
 const PARSER_NAME: &'static [u8] = b"template-rust\0";
@@ -514,56 +261,21 @@ const PARSER_NAME: &'static [u8] = b"template-rust\0";
 #[no_mangle]
 pub unsafe extern "C" fn rs_template_register_parser() {
     let default_port = CString::new("[7000]").unwrap();
-    let parser = RustParser {
-        name: PARSER_NAME.as_ptr() as *const std::os::raw::c_char,
-        default_port: default_port.as_ptr(),
-        ipproto: IPPROTO_TCP,
-        probe_ts: rs_template_probing_parser,
-        probe_tc: rs_template_probing_parser,
-        min_depth: 0,
-        max_depth: 16,
-        state_new: rs_template_state_new,
-        state_free: rs_template_state_free,
-        tx_free: rs_template_state_tx_free,
-        parse_ts: rs_template_parse_request,
-        parse_tc: rs_template_parse_response,
-        get_tx_count: rs_template_state_get_tx_count,
-        get_tx: rs_template_state_get_tx,
-        tx_get_comp_st: rs_template_state_progress_completion_status,
-        tx_get_progress: rs_template_tx_get_alstate_progress,
-        get_tx_logged: Some(rs_template_tx_get_logged),
-        set_tx_logged: Some(rs_template_tx_set_logged),
-        get_de_state: rs_template_tx_get_detect_state,
-        set_de_state: rs_template_tx_set_detect_state,
-        get_events: Some(rs_template_state_get_events),
-        get_eventinfo: Some(rs_template_state_get_event_info),
-        get_eventinfo_byid : Some(rs_template_state_get_event_info_by_id),
-        localstorage_new: None,
-        localstorage_free: None,
-        get_tx_mpm_id: None,
-        set_tx_mpm_id: None,
-        get_files: None,
-        get_tx_iterator: Some(rs_template_state_get_tx_iterator),
-    };
-
-    let ip_proto_str = CString::new("tcp").unwrap();
-
-    if AppLayerProtoDetectConfProtoDetectionEnabled(
-        ip_proto_str.as_ptr(),
-        parser.name,
-    ) != 0
-    {
-        let alproto = AppLayerRegisterProtocolDetection(&parser, 1);
-        ALPROTO_TEMPLATE = alproto;
-        if AppLayerParserConfParserEnabled(
-            ip_proto_str.as_ptr(),
-            parser.name,
-        ) != 0
-        {
-            let _ = AppLayerRegisterParser(&parser, alproto);
+    let parser = RustParser::new::<TemplateState>(
+        PARSER_NAME,
+        default_port.as_ptr(),
+        IPPROTO_TCP,
+        rs_template_probing_parser,
+        rs_template_probing_parser,
+    );
+
+    match register_parser(&parser, "tcp") {
+        Some(alproto) => {
+            ALPROTO_TEMPLATE = alproto;
+            SCLogNotice!("Rust template parser registered.");
+        }
+        None => {
+            SCLogNotice!("Protocol detector and parser disabled for TEMPLATE.");
         }
-        SCLogNotice!("Rust template parser registered.");
-    } else {
-        SCLogNotice!("Protocol detector and parser disabled for TEMPLATE.");
     }
 }
@@ -30,6 +30,7 @@ extern {
                          vptr: *mut *const c_char) -> i8;
     fn ConfGetChildValueBool(conf: *const c_void, key: *const c_char,
                              vptr: *mut c_int) -> i8;
+    fn ParseSizeStringU64(size: *const c_char, res: *mut u64) -> c_int;
 }
 
 // Return the string value of a configuration value.
@@ -72,6 +73,24 @@ pub fn conf_get_bool(key: &str) -> bool {
     return false;
 }
 
+// Return the value of key as a size in bytes. Values can use the
+// same units as in the rest of the configuration, like "64kb" or
+// "1mb". None is returned if the value is not set or is invalid.
+pub fn conf_get_memval(key: &str) -> Option<u64> {
+    let val = conf_get(key)?;
+    let mut res: u64 = 0;
+
+    let ret = unsafe {
+        ParseSizeStringU64(CString::new(val).unwrap().as_ptr(), &mut res)
+    };
+    if ret < 0 {
+        SCLogDebug!("Invalid size value {} for key {}", val, key);
+        return None;
+    }
+
+    return Some(res);
+}
+
 /// Wrap a Suricata ConfNode and expose some of its methods with a
 /// Rust friendly interface.
 pub struct ConfNode {
@@ -57,6 +57,35 @@ extern {
     pub fn StringToAppProto(proto_name: *const u8) -> AppProto;
 }
 
+// Defined in flow.h
+extern {
+    fn FlowGetLastTimeAsParts(flow: *const Flow, secs: *mut u64, usecs: *mut u64);
+    fn FlowGetClientAddr(flow: *const Flow, addr: *mut u8) -> u8;
+}
+
+/// Get the last time seen of a flow, in seconds since the unix epoch.
+pub fn flow_get_last_time(flow: *const Flow) -> u64 {
+    let mut secs: u64 = 0;
+    let mut usecs: u64 = 0;
+    if !flow.is_null() {
+        unsafe { FlowGetLastTimeAsParts(flow, &mut secs, &mut usecs); }
+    }
+    secs
+}
+
+/// Get the address of the client of a flow.
+pub fn flow_get_client_addr(flow: *const Flow) -> Option<std::net::IpAddr> {
+    if flow.is_null() {
+        return None;
+    }
+    let mut addr = [0u8; 16];
+    match unsafe { FlowGetClientAddr(flow, addr.as_mut_ptr()) } {
+        4 => Some(std::net::IpAddr::from([addr[0], addr[1], addr[2], addr[3]])),
+        16 => Some(std::net::IpAddr::from(addr)),
+        _ => None,
+    }
+}
+
 //
 // Function types for calls into C.
 //
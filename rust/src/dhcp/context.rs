@@ -0,0 +1,309 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+//! DHCP context shared between flows.
+//!
+//! The context holds the configured DHCP and DHCPv6 servers and relay
+//! agents, and the rates of DISCOVER and NAK messages seen by the sensor,
+//! used to detect starvation attacks and NAK storms. Rates are counted
+//! over fixed windows of `DHCP_RATE_WINDOW` seconds.
+
+use std::collections::HashSet;
+use std::net::Ipv4Addr;
+use std::sync::Mutex;
+
+use crate::conf::conf_get;
+use crate::log::*;
+
+/// Length of the rate windows, in seconds
+pub const DHCP_RATE_WINDOW: u64 = 10;
+
+/// Default number of client MACs sending a DISCOVER in a window above
+/// which the discover_flood event is raised
+pub const DHCP_DEFAULT_STARVATION_THRESHOLD: u32 = 100;
+/// Default number of NAK messages in a window above which the nak_storm
+/// event is raised
+pub const DHCP_DEFAULT_NAK_STORM_THRESHOLD: u32 = 50;
+
+pub struct DHCPContext {
+    /// Allowed server identifiers. Any server is allowed if empty.
+    servers: Vec<Vec<u8>>,
+    /// Allowed DHCPv6 server DUIDs. Any server is allowed if empty.
+    servers_v6: Vec<Vec<u8>>,
+    /// Allowed relay agent addresses. Any relay agent is allowed if
+    /// empty.
+    relays: Vec<Vec<u8>>,
+    starvation_threshold: u32,
+    nak_storm_threshold: u32,
+
+    discover_window: u64,
+    discover_macs: HashSet<Vec<u8>>,
+    discover_flood: bool,
+
+    nak_window: u64,
+    naks: u32,
+    nak_storm: bool,
+}
+
+/// Parse a comma separated list of IPv4 addresses.
+fn parse_addresses(input: &str) -> Result<Vec<Vec<u8>>, String> {
+    let mut addresses = Vec::new();
+    for addr in input.split(',') {
+        let addr = addr.trim();
+        if addr.len() == 0 {
+            continue;
+        }
+        match addr.parse::<Ipv4Addr>() {
+            Ok(ip) => addresses.push(ip.octets().to_vec()),
+            Err(_) => return Err(addr.to_string()),
+        }
+    }
+    Ok(addresses)
+}
+
+/// Parse a comma separated list of DUIDs in hex, with or without colons.
+fn parse_duids(input: &str) -> Result<Vec<Vec<u8>>, String> {
+    let mut duids = Vec::new();
+    for duid in input.split(',') {
+        let duid = duid.trim();
+        if duid.len() == 0 {
+            continue;
+        }
+        let hex: String = duid.chars().filter(|&c| c != ':').collect();
+        if hex.len() < 4 || hex.len() % 2 != 0 {
+            return Err(duid.to_string());
+        }
+        let mut bytes = Vec::with_capacity(hex.len() / 2);
+        for i in (0..hex.len()).step_by(2) {
+            match hex.get(i..i + 2).map(|b| u8::from_str_radix(b, 16)) {
+                Some(Ok(b)) => bytes.push(b),
+                _ => return Err(duid.to_string()),
+            }
+        }
+        duids.push(bytes);
+    }
+    Ok(duids)
+}
+
+impl DHCPContext {
+    pub fn new() -> DHCPContext {
+        DHCPContext {
+            servers: Vec::new(),
+            servers_v6: Vec::new(),
+            relays: Vec::new(),
+            starvation_threshold: DHCP_DEFAULT_STARVATION_THRESHOLD,
+            nak_storm_threshold: DHCP_DEFAULT_NAK_STORM_THRESHOLD,
+            discover_window: 0,
+            discover_macs: HashSet::new(),
+            discover_flood: false,
+            nak_window: 0,
+            naks: 0,
+            nak_storm: false,
+        }
+    }
+
+    /// Return true if the server identifier is allowed.
+    pub fn is_allowed_server(&self, server_id: &[u8]) -> bool {
+        self.servers.len() == 0 ||
+            self.servers.iter().any(|s| s.as_slice() == server_id)
+    }
+
+    /// Return true if the DHCPv6 server DUID is allowed.
+    pub fn is_allowed_server_v6(&self, duid: &[u8]) -> bool {
+        self.servers_v6.len() == 0 ||
+            self.servers_v6.iter().any(|s| s.as_slice() == duid)
+    }
+
+    /// Return true if the relay agent address is allowed.
+    pub fn is_allowed_relay(&self, giaddr: &[u8]) -> bool {
+        self.relays.len() == 0 ||
+            self.relays.iter().any(|r| r.as_slice() == giaddr)
+    }
+
+    /// Record a DISCOVER sent by a client at time `now`. Returns true the
+    /// first time the number of client MACs in the current window goes
+    /// above the threshold.
+    pub fn add_discover(&mut self, now: u64, mac: &[u8]) -> bool {
+        if self.starvation_threshold == 0 {
+            return false;
+        }
+        let window = now / DHCP_RATE_WINDOW;
+        if window != self.discover_window {
+            self.discover_window = window;
+            self.discover_macs.clear();
+            self.discover_flood = false;
+        }
+        if self.discover_flood {
+            return false;
+        }
+        self.discover_macs.insert(mac.to_vec());
+        if self.discover_macs.len() as u32 > self.starvation_threshold {
+            self.discover_flood = true;
+            self.discover_macs.clear();
+            return true;
+        }
+        false
+    }
+
+    /// Record a NAK at time `now`. Returns true the first time the number
+    /// of NAKs in the current window goes above the threshold.
+    pub fn add_nak(&mut self, now: u64) -> bool {
+        if self.nak_storm_threshold == 0 {
+            return false;
+        }
+        let window = now / DHCP_RATE_WINDOW;
+        if window != self.nak_window {
+            self.nak_window = window;
+            self.naks = 0;
+            self.nak_storm = false;
+        }
+        if self.nak_storm {
+            return false;
+        }
+        self.naks += 1;
+        if self.naks > self.nak_storm_threshold {
+            self.nak_storm = true;
+            return true;
+        }
+        false
+    }
+}
+
+static mut DHCP_CONTEXT: Option<Mutex<DHCPContext>> = None;
+
+/// Initialize the global context from the configuration. Must be called
+/// before the parsers are used. It is shared by DHCP and DHCPv6, so only
+/// the first call has an effect.
+pub fn dhcp_context_init() {
+    if dhcp_with_context(|_| ()).is_some() {
+        return;
+    }
+    let mut ctx = DHCPContext::new();
+    if let Some(val) = conf_get("app-layer.protocols.dhcp.servers") {
+        match parse_addresses(val) {
+            Ok(servers) => { ctx.servers = servers; },
+            Err(addr) => { SCLogWarning!("Invalid address in dhcp.servers: {}", addr); },
+        }
+    }
+    if let Some(val) = conf_get("app-layer.protocols.dhcpv6.servers") {
+        match parse_duids(val) {
+            Ok(servers) => { ctx.servers_v6 = servers; },
+            Err(duid) => { SCLogWarning!("Invalid DUID in dhcpv6.servers: {}", duid); },
+        }
+    }
+    if let Some(val) = conf_get("app-layer.protocols.dhcp.relays") {
+        match parse_addresses(val) {
+            Ok(relays) => { ctx.relays = relays; },
+            Err(addr) => { SCLogWarning!("Invalid address in dhcp.relays: {}", addr); },
+        }
+    }
+    if let Some(val) = conf_get("app-layer.protocols.dhcp.starvation-threshold") {
+        match val.parse::<u32>() {
+            Ok(threshold) => { ctx.starvation_threshold = threshold; },
+            Err(_) => { SCLogWarning!("Invalid value for dhcp.starvation-threshold: {}", val); },
+        }
+    }
+    if let Some(val) = conf_get("app-layer.protocols.dhcp.nak-storm-threshold") {
+        match val.parse::<u32>() {
+            Ok(threshold) => { ctx.nak_storm_threshold = threshold; },
+            Err(_) => { SCLogWarning!("Invalid value for dhcp.nak-storm-threshold: {}", val); },
+        }
+    }
+    unsafe {
+        DHCP_CONTEXT = Some(Mutex::new(ctx));
+    }
+}
+
+/// Run `f` with the global context locked. Returns None if the context is
+/// not initialized.
+pub fn dhcp_with_context<T, F>(f: F) -> Option<T>
+    where F: FnOnce(&mut DHCPContext) -> T
+{
+    let ctx = unsafe {
+        match DHCP_CONTEXT {
+            Some(ref ctx) => ctx,
+            None => return None,
+        }
+    };
+    match ctx.lock() {
+        Ok(mut guard) => Some(f(&mut guard)),
+        Err(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dhcp_context_allowlists() {
+        let mut ctx = DHCPContext::new();
+        assert!(ctx.is_allowed_server(&[10, 0, 0, 1]));
+        assert!(ctx.is_allowed_relay(&[10, 0, 0, 254]));
+
+        ctx.servers = parse_addresses("10.0.0.1, 10.0.0.2").unwrap();
+        ctx.relays = parse_addresses("10.0.1.254").unwrap();
+        assert!(ctx.is_allowed_server(&[10, 0, 0, 2]));
+        assert!(!ctx.is_allowed_server(&[192, 168, 1, 1]));
+        assert!(ctx.is_allowed_relay(&[10, 0, 1, 254]));
+        assert!(!ctx.is_allowed_relay(&[10, 0, 2, 254]));
+
+        assert_eq!(parse_addresses("10.0.0.1,10.0.0"), Err("10.0.0".to_string()));
+
+        assert!(ctx.is_allowed_server_v6(&[0, 3, 0, 1, 0, 0x50, 0x56, 0, 0, 1]));
+        ctx.servers_v6 = parse_duids("00:03:00:01:00:50:56:00:00:01, 0003000100505600000002").unwrap();
+        assert_eq!(ctx.servers_v6.len(), 2);
+        assert!(ctx.is_allowed_server_v6(&[0, 3, 0, 1, 0, 0x50, 0x56, 0, 0, 1]));
+        assert!(!ctx.is_allowed_server_v6(&[0, 3, 0, 1, 0, 0x50, 0x56, 0, 0, 3]));
+        assert_eq!(parse_duids("00:03:0"), Err("00:03:0".to_string()));
+        assert_eq!(parse_duids("00:03:zz"), Err("00:03:zz".to_string()));
+    }
+
+    #[test]
+    fn test_dhcp_context_discover_flood() {
+        let mut ctx = DHCPContext::new();
+        ctx.starvation_threshold = 3;
+        let now = 1000;
+        // the same client retrying is not a flood
+        for _ in 0..10 {
+            assert!(!ctx.add_discover(now, &[0, 1, 2, 3, 4, 5]));
+        }
+        assert!(!ctx.add_discover(now, &[0, 1, 2, 3, 4, 6]));
+        assert!(!ctx.add_discover(now, &[0, 1, 2, 3, 4, 7]));
+        assert!(ctx.add_discover(now + 1, &[0, 1, 2, 3, 4, 8]));
+        // raised once per window
+        assert!(!ctx.add_discover(now + 2, &[0, 1, 2, 3, 4, 9]));
+        // new window
+        assert!(!ctx.add_discover(now + DHCP_RATE_WINDOW, &[0, 1, 2, 3, 4, 9]));
+    }
+
+    #[test]
+    fn test_dhcp_context_nak_storm() {
+        let mut ctx = DHCPContext::new();
+        ctx.nak_storm_threshold = 2;
+        assert!(!ctx.add_nak(1000));
+        assert!(!ctx.add_nak(1000));
+        assert!(ctx.add_nak(1001));
+        assert!(!ctx.add_nak(1002));
+        assert!(!ctx.add_nak(1000 + DHCP_RATE_WINDOW));
+
+        ctx.nak_storm_threshold = 0;
+        for _ in 0..10 {
+            assert!(!ctx.add_nak(2000));
+        }
+    }
+}
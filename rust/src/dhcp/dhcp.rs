@@ -15,16 +15,18 @@
  * 02110-1301, USA.
  */
 
-use crate::applayer;
+use crate::applayer::{self, State};
 use crate::core;
 use crate::core::{ALPROTO_UNKNOWN, AppProto, Flow, IPPROTO_UDP};
 use crate::core::{sc_detect_engine_state_free, sc_app_layer_decoder_events_free_events};
+use crate::dhcp::context::*;
+use crate::dhcp::fingerprint::*;
+use crate::dhcp::lease::*;
 use crate::dhcp::parser::*;
 use crate::log::*;
 use crate::parser::*;
 use std;
-use std::ffi::{CStr,CString};
-use std::mem::transmute;
+use std::ffi::CString;
 
 static mut ALPROTO_DHCP: AppProto = ALPROTO_UNKNOWN;
 
@@ -35,20 +37,42 @@ pub const BOOTP_REPLY: u8 = 2;
 
 // DHCP option types. Names based on IANA naming:
 // https://www.iana.org/assignments/bootp-dhcp-parameters/bootp-dhcp-parameters.xhtml
+pub const DHCP_OPT_PAD: u8 = 0;
 pub const DHCP_OPT_SUBNET_MASK: u8 = 1;
 pub const DHCP_OPT_ROUTERS: u8 = 3;
 pub const DHCP_OPT_DNS_SERVER: u8 = 6;
 pub const DHCP_OPT_HOSTNAME: u8 = 12;
+pub const DHCP_OPT_VENDOR_SPECIFIC: u8 = 43;
 pub const DHCP_OPT_REQUESTED_IP: u8 = 50;
 pub const DHCP_OPT_ADDRESS_TIME: u8 = 51;
 pub const DHCP_OPT_TYPE: u8 = 53;
 pub const DHCP_OPT_SERVER_ID: u8 = 54;
 pub const DHCP_OPT_PARAMETER_LIST: u8 = 55;
+pub const DHCP_OPT_MAX_MESSAGE_SIZE: u8 = 57;
 pub const DHCP_OPT_RENEWAL_TIME: u8 = 58;
 pub const DHCP_OPT_REBINDING_TIME: u8 = 59;
+pub const DHCP_OPT_VENDOR_CLASS: u8 = 60;
 pub const DHCP_OPT_CLIENT_ID: u8 = 61;
+pub const DHCP_OPT_TFTP_SERVER_NAME: u8 = 66;
+pub const DHCP_OPT_BOOTFILE_NAME: u8 = 67;
+pub const DHCP_OPT_USER_CLASS: u8 = 77;
+pub const DHCP_OPT_CLIENT_FQDN: u8 = 81;
+pub const DHCP_OPT_RELAY_AGENT_INFO: u8 = 82;
+pub const DHCP_OPT_DOMAIN_SEARCH: u8 = 119;
+pub const DHCP_OPT_CLASSLESS_ROUTES: u8 = 121;
+pub const DHCP_OPT_MS_CLASSLESS_ROUTES: u8 = 249;
 pub const DHCP_OPT_END: u8 = 255;
 
+/// Client FQDN option flags.
+pub const DHCP_FQDN_FLAG_S: u8 = 0x01;
+pub const DHCP_FQDN_FLAG_O: u8 = 0x02;
+pub const DHCP_FQDN_FLAG_E: u8 = 0x04;
+pub const DHCP_FQDN_FLAG_N: u8 = 0x08;
+
+/// Relay agent information sub-options.
+pub const DHCP_RELAY_AGENT_CIRCUIT_ID: u8 = 1;
+pub const DHCP_RELAY_AGENT_REMOTE_ID: u8 = 2;
+
 /// DHCP message types.
 pub const DHCP_TYPE_DISCOVER: u8 = 1;
 pub const DHCP_TYPE_OFFER: u8 = 2;
@@ -71,17 +95,77 @@ pub const DHCP_PARAM_TFTP_SERVER_NAME: u8 = 66;
 pub const DHCP_PARAM_TFTP_SERVER_IP: u8 = 150;
 
 #[repr(u32)]
+#[derive(AppLayerEvent)]
 pub enum DHCPEvent {
     TruncatedOptions = 0,
     MalformedOptions,
+    /// OFFER or ACK from a server that is not in the configured servers.
+    RogueServer,
+    /// DISCOVERs from more client MACs than the starvation threshold.
+    DiscoverFlood,
+    /// More NAKs than the NAK storm threshold.
+    NakStorm,
+    /// Message relayed by an agent that is not in the configured relays.
+    UnknownRelay,
 }
 
-impl DHCPEvent {
-    fn from_i32(value: i32) -> Option<DHCPEvent> {
-        match value {
-            0 => Some(DHCPEvent::TruncatedOptions),
-            1 => Some(DHCPEvent::MalformedOptions),
-            _ => None,
+/// Return the host name sent by a client, from the host name option or
+/// the client FQDN option.
+fn client_hostname(message: &DHCPMessage) -> Option<String> {
+    if let Some(hostname) = message.get_generic_option(DHCP_OPT_HOSTNAME) {
+        if hostname.len() > 0 {
+            return Some(String::from_utf8_lossy(hostname).to_string());
+        }
+    }
+    for option in &message.options {
+        if let DHCPOptionWrapper::ClientFqdn(ref fqdn) = option.option {
+            if fqdn.name.len() > 0 {
+                return Some(String::from_utf8_lossy(&fqdn.name).to_string());
+            }
+        }
+    }
+    None
+}
+
+/// Update the lease table with the leases acknowledged or released by a
+/// message, and the host names sent by clients.
+fn update_leases(message: &DHCPMessage, now: u64) {
+    let header = &message.header;
+    let mac = &header.clienthw;
+    let hostname = client_hostname(message);
+    match message.get_type() {
+        Some(DHCP_TYPE_ACK) => {
+            // The ACK of an INFORM does not assign an address.
+            let addr = match dhcp_lease_addr(&header.yourip) {
+                Some(addr) => addr,
+                None => { return; }
+            };
+            let mut lease_time = DHCP_DEFAULT_LEASE_TIME;
+            for option in &message.options {
+                if let DHCPOptionWrapper::TimeValue(ref time) = option.option {
+                    if option.code == DHCP_OPT_ADDRESS_TIME {
+                        lease_time = time.seconds;
+                    }
+                }
+            }
+            dhcp_with_leases(|leases| {
+                leases.add(now, addr, mac, Some(mac), hostname.as_ref().map(|h| h.as_str()),
+                           lease_time);
+            });
+        }
+        Some(DHCP_TYPE_RELEASE) => {
+            if let Some(addr) = dhcp_lease_addr(&header.clientip) {
+                dhcp_with_leases(|leases| {
+                    leases.release(&addr, mac);
+                });
+            }
+        }
+        _ => {
+            if let (BOOTP_REQUEST, Some(hostname)) = (header.opcode, hostname) {
+                dhcp_with_leases(|leases| {
+                    leases.set_hostname(mac, &hostname);
+                });
+            }
         }
     }
 }
@@ -92,6 +176,10 @@ impl DHCPEvent {
 pub struct DHCPTransaction {
     tx_id: u64,
     pub message: DHCPMessage,
+    /// Fingerprint of a client message.
+    pub fingerprint: Option<String>,
+    /// Device matching the fingerprint.
+    pub device: Option<DHCPDevice>,
     logged: applayer::LoggerFlags,
     de_state: Option<*mut core::DetectEngineState>,
     events: *mut core::AppLayerDecoderEvents,
@@ -99,9 +187,13 @@ pub struct DHCPTransaction {
 
 impl DHCPTransaction {
     pub fn new(id: u64, message: DHCPMessage) -> DHCPTransaction {
+        let fingerprint = dhcp_fingerprint(&message);
+        let device = dhcp_device_lookup(&message).cloned();
         DHCPTransaction {
             tx_id: id,
             message: message,
+            fingerprint: fingerprint,
+            device: device,
             logged: applayer::LoggerFlags::new(),
             de_state: None,
             events: std::ptr::null_mut(),
@@ -128,8 +220,7 @@ impl Drop for DHCPTransaction {
     }
 }
 
-export_tx_get_detect_state!(rs_dhcp_tx_get_detect_state, DHCPTransaction);
-export_tx_set_detect_state!(rs_dhcp_tx_set_detect_state, DHCPTransaction);
+impl_transaction!(DHCPTransaction);
 
 pub struct DHCPState {
     // Internal transaction ID.
@@ -138,23 +229,23 @@ pub struct DHCPState {
     // List of transactions.
     transactions: Vec<DHCPTransaction>,
 
-    events: u16,
+    // Server identifiers and relay agents already reported in this flow.
+    rogue_servers: Vec<Vec<u8>>,
+    unknown_relays: Vec<Vec<u8>>,
 }
 
 impl DHCPState {
-    pub fn new() -> DHCPState {
-        return DHCPState {
-            tx_id: 0,
-            transactions: Vec::new(),
-            events: 0,
-        };
-    }
-
-    pub fn parse(&mut self, input: &[u8]) -> bool {
+    /// Parse a message. `now` is the capture time in seconds, used for the
+    /// rate based events.
+    pub fn parse(&mut self, input: &[u8], now: u64) -> bool {
         match dhcp_parse(input) {
             Ok((_, message)) => {
                 let malformed_options = message.malformed_options;
                 let truncated_options = message.truncated_options;
+                let events = self.check_message(&message, now);
+                if now > 0 {
+                    update_leases(&message, now);
+                }
                 self.tx_id += 1;
                 let transaction = DHCPTransaction::new(self.tx_id, message);
                 self.transactions.push(transaction);
@@ -164,6 +255,9 @@ impl DHCPState {
                 if truncated_options {
                     self.set_event(DHCPEvent::TruncatedOptions);
                 }
+                for event in events {
+                    self.set_event(event);
+                }
                 return true;
             }
             _ => {
@@ -172,57 +266,78 @@ impl DHCPState {
         }
     }
 
-    pub fn get_tx(&mut self, tx_id: u64) -> Option<&DHCPTransaction> {
-        for tx in &mut self.transactions {
-            if tx.tx_id == tx_id + 1 {
-                return Some(tx);
+    /// Check a message against the configured servers and relay agents,
+    /// and update the DISCOVER and NAK rates of the sensor. Rogue servers
+    /// and unknown relay agents are reported once per flow.
+    fn check_message(&mut self, message: &DHCPMessage, now: u64) -> Vec<DHCPEvent> {
+        let mut events = Vec::new();
+        let msg_type = message.get_type();
+        let server_id = match msg_type {
+            Some(DHCP_TYPE_OFFER) | Some(DHCP_TYPE_ACK) => {
+                message.get_generic_option(DHCP_OPT_SERVER_ID)
             }
-        }
-        return None;
-    }
-
-    fn free_tx(&mut self, tx_id: u64) {
-        let len = self.transactions.len();
-        let mut found = false;
-        let mut index = 0;
-        for i in 0..len {
-            let tx = &self.transactions[i];
-            if tx.tx_id == tx_id + 1 {
-                found = true;
-                index = i;
-                break;
+            _ => None,
+        };
+        let giaddr = &message.header.giaddr;
+        let relayed = giaddr.iter().any(|&b| b != 0);
+
+        let (rogue, unknown_relay, flood, storm) = dhcp_with_context(|ctx| {
+            let rogue = match server_id {
+                Some(id) => !ctx.is_allowed_server(id),
+                None => false,
+            };
+            let unknown_relay = relayed && !ctx.is_allowed_relay(giaddr);
+            let flood = now > 0 && msg_type == Some(DHCP_TYPE_DISCOVER) &&
+                ctx.add_discover(now, &message.header.clienthw);
+            let storm = now > 0 && msg_type == Some(DHCP_TYPE_NAK) &&
+                ctx.add_nak(now);
+            (rogue, unknown_relay, flood, storm)
+        }).unwrap_or((false, false, false, false));
+
+        if let (true, Some(id)) = (rogue, server_id) {
+            if !self.rogue_servers.iter().any(|s| s.as_slice() == id) {
+                self.rogue_servers.push(id.to_vec());
+                events.push(DHCPEvent::RogueServer);
             }
         }
-        if found {
-            self.transactions.remove(index);
+        if unknown_relay && !self.unknown_relays.contains(giaddr) {
+            self.unknown_relays.push(giaddr.to_vec());
+            events.push(DHCPEvent::UnknownRelay);
+        }
+        if flood {
+            events.push(DHCPEvent::DiscoverFlood);
         }
+        if storm {
+            events.push(DHCPEvent::NakStorm);
+        }
+        events
     }
 
-    fn set_event(&mut self, event: DHCPEvent) {
-        if let Some(tx) = self.transactions.last_mut() {
-            core::sc_app_layer_decoder_events_set_event_raw(
-                &mut tx.events, event as u8);
-            self.events += 1;
-        }
+}
+
+impl applayer::State for DHCPState {
+    type Transaction = DHCPTransaction;
+    type Event = DHCPEvent;
+
+    fn new() -> DHCPState {
+        return DHCPState {
+            tx_id: 0,
+            transactions: Vec::new(),
+            rogue_servers: Vec::new(),
+            unknown_relays: Vec::new(),
+        };
     }
 
-    fn get_tx_iterator(&mut self, min_tx_id: u64, state: &mut u64) ->
-        Option<(&DHCPTransaction, u64, bool)>
-    {
-        let mut index = *state as usize;
-        let len = self.transactions.len();
-
-        while index < len {
-            let tx = &self.transactions[index];
-            if tx.tx_id < min_tx_id + 1 {
-                index += 1;
-                continue;
-            }
-            *state = index as u64;
-            return Some((tx, tx.tx_id - 1, (len - index) > 1));
-        }
-        
-        return None;
+    fn transactions(&mut self) -> &mut Vec<DHCPTransaction> {
+        &mut self.transactions
+    }
+
+    fn tx_count(&self) -> u64 {
+        self.tx_id
+    }
+
+    fn parse_request(&mut self, flow: *const Flow, input: &[u8], _eof: bool) -> bool {
+        self.parse(input, core::flow_get_last_time(flow))
     }
 }
 
@@ -248,221 +363,25 @@ pub extern "C" fn rs_dhcp_probing_parser(_flow: *const Flow,
     }
 }
 
-#[no_mangle]
-pub extern "C" fn rs_dhcp_tx_get_alstate_progress(_tx: *mut std::os::raw::c_void,
-                                                  _direction: u8) -> std::os::raw::c_int {
-    // As this is a stateless parser, simply use 1.
-    return 1;
-}
-
-#[no_mangle]
-pub extern "C" fn rs_dhcp_state_progress_completion_status(
-    _direction: u8) -> std::os::raw::c_int {
-    // The presence of a transaction means we are complete.
-    return 1;
-}
-
-#[no_mangle]
-pub extern "C" fn rs_dhcp_state_get_tx(state: *mut std::os::raw::c_void,
-                                       tx_id: u64) -> *mut std::os::raw::c_void {
-    let state = cast_pointer!(state, DHCPState);
-    match state.get_tx(tx_id) {
-        Some(tx) => {
-            return unsafe { transmute(tx) };
-        }
-        None => {
-            return std::ptr::null_mut();
-        }
-    }
-}
-
-#[no_mangle]
-pub extern "C" fn rs_dhcp_state_get_tx_count(state: *mut std::os::raw::c_void) -> u64 {
-    let state = cast_pointer!(state, DHCPState);
-    return state.tx_id;
-}
-
-#[no_mangle]
-pub extern "C" fn rs_dhcp_parse(_flow: *const core::Flow,
-                                state: *mut std::os::raw::c_void,
-                                _pstate: *mut std::os::raw::c_void,
-                                input: *const u8,
-                                input_len: u32,
-                                _data: *const std::os::raw::c_void,
-                                _flags: u8) -> i32 {
-    let state = cast_pointer!(state, DHCPState);
-    let buf = build_slice!(input, input_len as usize);
-    if state.parse(buf) {
-        return 1;
-    }
-    return -1;
-}
-
-#[no_mangle]
-pub extern "C" fn rs_dhcp_state_tx_free(
-    state: *mut std::os::raw::c_void,
-    tx_id: u64)
-{
-    let state = cast_pointer!(state, DHCPState);
-    state.free_tx(tx_id);
-}
-
-#[no_mangle]
-pub extern "C" fn rs_dhcp_state_new() -> *mut std::os::raw::c_void {
-    let state = DHCPState::new();
-    let boxed = Box::new(state);
-    return unsafe {
-        transmute(boxed)
-    };
-}
-
-#[no_mangle]
-pub extern "C" fn rs_dhcp_state_free(state: *mut std::os::raw::c_void) {
-    // Just unbox...
-    let _drop: Box<DHCPState> = unsafe { transmute(state) };
-}
-
-#[no_mangle]
-pub extern "C" fn rs_dhcp_tx_get_logged(_state: *mut std::os::raw::c_void, tx: *mut std::os::raw::c_void) -> u32 {
-    let tx = cast_pointer!(tx, DHCPTransaction);
-    return tx.logged.get();
-}
-
-#[no_mangle]
-pub extern "C" fn rs_dhcp_tx_set_logged(_state: *mut std::os::raw::c_void,
-                                        tx: *mut std::os::raw::c_void,
-                                        logged: u32) {
-    let tx = cast_pointer!(tx, DHCPTransaction);
-    tx.logged.set(logged);
-}
-
-#[no_mangle]
-pub extern "C" fn rs_dhcp_state_get_event_info_by_id(event_id: std::os::raw::c_int,
-                                                     event_name: *mut *const std::os::raw::c_char,
-                                                     event_type: *mut core::AppLayerEventType)
-                                                     -> i8
-{
-    if let Some(e) = DHCPEvent::from_i32(event_id as i32) {
-        let estr = match e {
-            DHCPEvent::TruncatedOptions => { "truncated_options\0" },
-            DHCPEvent::MalformedOptions => { "malformed_options\0" },
-        };
-        unsafe{
-            *event_name = estr.as_ptr() as *const std::os::raw::c_char;
-            *event_type = core::APP_LAYER_EVENT_TYPE_TRANSACTION;
-        };
-        0
-    } else {
-        -1
-    }
-}
-#[no_mangle]
-pub extern "C" fn rs_dhcp_state_get_events(tx: *mut std::os::raw::c_void)
-                                           -> *mut core::AppLayerDecoderEvents
-{
-    let tx = cast_pointer!(tx, DHCPTransaction);
-    return tx.events;
-}
-
-#[no_mangle]
-pub extern "C" fn rs_dhcp_state_get_event_info(
-    event_name: *const std::os::raw::c_char,
-    event_id: *mut std::os::raw::c_int,
-    event_type: *mut core::AppLayerEventType)
-    -> std::os::raw::c_int
-{
-    if event_name == std::ptr::null() {
-        return -1;
-    }
-    let c_event_name: &CStr = unsafe { CStr::from_ptr(event_name) };
-    let event = match c_event_name.to_str() {
-        Ok(s) => {
-            match s {
-                "malformed_options" => DHCPEvent::MalformedOptions as i32,
-                "truncated_options" => DHCPEvent::TruncatedOptions as i32,
-                _ => -1, // unknown event
-            }
-        },
-        Err(_) => -1, // UTF-8 conversion failed
-    };
-    unsafe{
-        *event_type = core::APP_LAYER_EVENT_TYPE_TRANSACTION;
-        *event_id = event as std::os::raw::c_int;
-    };
-    0
-}
-
-#[no_mangle]
-pub extern "C" fn rs_dhcp_state_get_tx_iterator(
-    _ipproto: u8,
-    _alproto: AppProto,
-    state: *mut std::os::raw::c_void,
-    min_tx_id: u64,
-    _max_tx_id: u64,
-    istate: &mut u64)
-    -> applayer::AppLayerGetTxIterTuple
-{
-    let state = cast_pointer!(state, DHCPState);
-    match state.get_tx_iterator(min_tx_id, istate) {
-        Some((tx, out_tx_id, has_next)) => {
-            let c_tx = unsafe { transmute(tx) };
-            let ires = applayer::AppLayerGetTxIterTuple::with_values(
-                c_tx, out_tx_id, has_next);
-            return ires;
-        }
-        None => {
-            return applayer::AppLayerGetTxIterTuple::not_found();
-        }
-    }
-}
-
 const PARSER_NAME: &'static [u8] = b"dhcp\0";
 
 #[no_mangle]
 pub unsafe extern "C" fn rs_dhcp_register_parser() {
     SCLogDebug!("Registering DHCP parser.");
     let ports = CString::new("[67,68]").unwrap();
-    let parser = RustParser {
-        name: PARSER_NAME.as_ptr() as *const std::os::raw::c_char,
-        default_port       : ports.as_ptr(),
-        ipproto            : IPPROTO_UDP,
-        probe_ts           : rs_dhcp_probing_parser,
-        probe_tc           : rs_dhcp_probing_parser,
-        min_depth          : 0,
-        max_depth          : 16,
-        state_new          : rs_dhcp_state_new,
-        state_free         : rs_dhcp_state_free,
-        tx_free            : rs_dhcp_state_tx_free,
-        parse_ts           : rs_dhcp_parse,
-        parse_tc           : rs_dhcp_parse,
-        get_tx_count       : rs_dhcp_state_get_tx_count,
-        get_tx             : rs_dhcp_state_get_tx,
-        tx_get_comp_st     : rs_dhcp_state_progress_completion_status,
-        tx_get_progress    : rs_dhcp_tx_get_alstate_progress,
-        get_tx_logged      : Some(rs_dhcp_tx_get_logged),
-        set_tx_logged      : Some(rs_dhcp_tx_set_logged),
-        get_de_state       : rs_dhcp_tx_get_detect_state,
-        set_de_state       : rs_dhcp_tx_set_detect_state,
-        get_events         : Some(rs_dhcp_state_get_events),
-        get_eventinfo      : Some(rs_dhcp_state_get_event_info),
-        get_eventinfo_byid : None,
-        localstorage_new   : None,
-        localstorage_free  : None,
-        get_tx_mpm_id      : None,
-        set_tx_mpm_id      : None,
-        get_files          : None,
-        get_tx_iterator    : Some(rs_dhcp_state_get_tx_iterator),
-    };
-
-    let ip_proto_str = CString::new("udp").unwrap();
-
-    if AppLayerProtoDetectConfProtoDetectionEnabled(ip_proto_str.as_ptr(), parser.name) != 0 {
-        let alproto = AppLayerRegisterProtocolDetection(&parser, 1);
-        ALPROTO_DHCP = alproto;
-        if AppLayerParserConfParserEnabled(ip_proto_str.as_ptr(), parser.name) != 0 {
-            let _ = AppLayerRegisterParser(&parser, alproto);
+    dhcp_fingerprints_init();
+    dhcp_context_init();
+    dhcp_leases_init();
+    let parser = RustParser::new::<DHCPState>(PARSER_NAME, ports.as_ptr(),
+                                              IPPROTO_UDP,
+                                              rs_dhcp_probing_parser,
+                                              rs_dhcp_probing_parser);
+    match register_parser(&parser, "udp") {
+        Some(alproto) => {
+            ALPROTO_DHCP = alproto;
+        }
+        None => {
+            SCLogDebug!("Protocol detector and parser disabled for DHCP.");
         }
-    } else {
-        SCLogDebug!("Protocol detector and parser disabled for DHCP.");
     }
 }
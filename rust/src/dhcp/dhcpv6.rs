@@ -0,0 +1,336 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+use crate::applayer::{self, State};
+use crate::core;
+use crate::core::{ALPROTO_UNKNOWN, AppProto, Flow, IPPROTO_UDP};
+use crate::core::{sc_detect_engine_state_free, sc_app_layer_decoder_events_free_events};
+use crate::dhcp::context::*;
+use crate::dhcp::dhcpv6_parser::*;
+use crate::dhcp::lease::*;
+use crate::log::*;
+use crate::parser::*;
+use std;
+use std::ffi::CString;
+
+static mut ALPROTO_DHCPV6: AppProto = ALPROTO_UNKNOWN;
+
+static DHCPV6_MIN_FRAME_LEN: u32 = 4;
+
+/// DHCPv6 message types.
+pub const DHCPV6_SOLICIT: u8 = 1;
+pub const DHCPV6_ADVERTISE: u8 = 2;
+pub const DHCPV6_REQUEST: u8 = 3;
+pub const DHCPV6_CONFIRM: u8 = 4;
+pub const DHCPV6_RENEW: u8 = 5;
+pub const DHCPV6_REBIND: u8 = 6;
+pub const DHCPV6_REPLY: u8 = 7;
+pub const DHCPV6_RELEASE: u8 = 8;
+pub const DHCPV6_DECLINE: u8 = 9;
+pub const DHCPV6_RECONFIGURE: u8 = 10;
+pub const DHCPV6_INFORMATION_REQUEST: u8 = 11;
+pub const DHCPV6_RELAY_FORW: u8 = 12;
+pub const DHCPV6_RELAY_REPL: u8 = 13;
+
+// DHCPv6 option codes. Names based on IANA naming:
+// https://www.iana.org/assignments/dhcpv6-parameters/dhcpv6-parameters.xhtml
+pub const DHCPV6_OPT_CLIENTID: u16 = 1;
+pub const DHCPV6_OPT_SERVERID: u16 = 2;
+pub const DHCPV6_OPT_IA_NA: u16 = 3;
+pub const DHCPV6_OPT_IAADDR: u16 = 5;
+pub const DHCPV6_OPT_ORO: u16 = 6;
+pub const DHCPV6_OPT_ELAPSED_TIME: u16 = 8;
+pub const DHCPV6_OPT_RELAY_MSG: u16 = 9;
+pub const DHCPV6_OPT_STATUS_CODE: u16 = 13;
+pub const DHCPV6_OPT_RAPID_COMMIT: u16 = 14;
+pub const DHCPV6_OPT_VENDOR_CLASS: u16 = 16;
+pub const DHCPV6_OPT_INTERFACE_ID: u16 = 18;
+pub const DHCPV6_OPT_DNS_SERVERS: u16 = 23;
+pub const DHCPV6_OPT_DOMAIN_LIST: u16 = 24;
+pub const DHCPV6_OPT_IA_PD: u16 = 25;
+pub const DHCPV6_OPT_IAPREFIX: u16 = 26;
+pub const DHCPV6_OPT_SNTP_SERVERS: u16 = 31;
+pub const DHCPV6_OPT_CLIENT_FQDN: u16 = 39;
+pub const DHCPV6_OPT_NTP_SERVER: u16 = 56;
+pub const DHCPV6_OPT_CLIENT_LINKLAYER_ADDR: u16 = 79;
+
+/// DUID types.
+pub const DHCPV6_DUID_LLT: u16 = 1;
+pub const DHCPV6_DUID_EN: u16 = 2;
+pub const DHCPV6_DUID_LL: u16 = 3;
+pub const DHCPV6_DUID_UUID: u16 = 4;
+
+/// Hardware type of Ethernet link-layer addresses.
+pub const DHCPV6_HW_TYPE_ETHERNET: u16 = 1;
+
+/// Status codes.
+pub const DHCPV6_STATUS_SUCCESS: u16 = 0;
+pub const DHCPV6_STATUS_UNSPEC_FAIL: u16 = 1;
+pub const DHCPV6_STATUS_NO_ADDRS_AVAIL: u16 = 2;
+pub const DHCPV6_STATUS_NO_BINDING: u16 = 3;
+pub const DHCPV6_STATUS_NOT_ON_LINK: u16 = 4;
+pub const DHCPV6_STATUS_USE_MULTICAST: u16 = 5;
+pub const DHCPV6_STATUS_NO_PREFIX_AVAIL: u16 = 6;
+
+#[repr(u32)]
+#[derive(AppLayerEvent)]
+pub enum DHCPv6Event {
+    TruncatedOptions = 0,
+    MalformedOptions,
+    /// ADVERTISE or REPLY from a server that is not in the configured
+    /// servers.
+    RogueServer,
+}
+
+/// Update the lease table with the addresses leased or released by a
+/// message, and the host names sent by clients. Clients are identified by
+/// their DUID.
+fn update_leases(message: &DHCPv6Message, now: u64) {
+    let mac = message.client_mac().map(|mac| mac.as_slice());
+    let message = message.inner();
+    let client = match message.client_id() {
+        Some(duid) => &duid.data,
+        None => { return; }
+    };
+    let hostname = match message.get_option(DHCPV6_OPT_CLIENT_FQDN) {
+        Some(&DHCPv6OptionValue::ClientFqdn(ref fqdn)) if fqdn.name.len() > 0 => {
+            Some(String::from_utf8_lossy(&fqdn.name).to_string())
+        }
+        _ => None,
+    };
+    let mut addresses = Vec::new();
+    for option in &message.options {
+        if let DHCPv6OptionValue::IaNa(ref ia) = option.value {
+            for option in &ia.options {
+                if let DHCPv6OptionValue::IaAddr(ref addr) = option.value {
+                    if let Some(ip) = dhcp_lease_addr(&addr.address) {
+                        addresses.push((ip, addr.valid_lifetime));
+                    }
+                }
+            }
+        }
+    }
+    dhcp_with_leases(|leases| {
+        match message.msg_type {
+            DHCPV6_REPLY => {
+                for (addr, valid_lifetime) in addresses {
+                    // A valid lifetime of 0 ends the lease.
+                    if valid_lifetime == 0 {
+                        leases.release(&addr, client);
+                    } else {
+                        leases.add(now, addr, client, mac,
+                                   hostname.as_ref().map(|h| h.as_str()),
+                                   valid_lifetime);
+                    }
+                }
+            }
+            DHCPV6_RELEASE | DHCPV6_DECLINE => {
+                for (addr, _) in addresses {
+                    leases.release(&addr, client);
+                }
+            }
+            _ => {
+                if let (false, Some(hostname)) = (message.is_reply(), hostname) {
+                    leases.set_hostname(client, &hostname);
+                }
+            }
+        }
+    });
+}
+
+/// Like for DHCPv4, each message is its own transaction.
+pub struct DHCPv6Transaction {
+    tx_id: u64,
+    pub message: DHCPv6Message,
+    logged: applayer::LoggerFlags,
+    de_state: Option<*mut core::DetectEngineState>,
+    events: *mut core::AppLayerDecoderEvents,
+}
+
+impl DHCPv6Transaction {
+    pub fn new(id: u64, message: DHCPv6Message) -> DHCPv6Transaction {
+        DHCPv6Transaction {
+            tx_id: id,
+            message: message,
+            logged: applayer::LoggerFlags::new(),
+            de_state: None,
+            events: std::ptr::null_mut(),
+        }
+    }
+
+    pub fn free(&mut self) {
+        if self.events != std::ptr::null_mut() {
+            sc_app_layer_decoder_events_free_events(&mut self.events);
+        }
+        match self.de_state {
+            Some(state) => {
+                sc_detect_engine_state_free(state);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Drop for DHCPv6Transaction {
+    fn drop(&mut self) {
+        self.free();
+    }
+}
+
+impl_transaction!(DHCPv6Transaction);
+
+pub struct DHCPv6State {
+    // Internal transaction ID.
+    tx_id: u64,
+
+    // List of transactions.
+    transactions: Vec<DHCPv6Transaction>,
+
+    // Server DUIDs already reported in this flow.
+    rogue_servers: Vec<Vec<u8>>,
+}
+
+impl DHCPv6State {
+    /// Parse a message. `now` is the capture time in seconds, used for the
+    /// lease table.
+    pub fn parse(&mut self, input: &[u8], now: u64) -> bool {
+        match dhcpv6_parse(input) {
+            Ok((_, message)) => {
+                let malformed_options = message.malformed_options;
+                let truncated_options = message.truncated_options;
+                let rogue_server = self.check_server(&message);
+                if now > 0 {
+                    update_leases(&message, now);
+                }
+                self.tx_id += 1;
+                let transaction = DHCPv6Transaction::new(self.tx_id, message);
+                self.transactions.push(transaction);
+                if malformed_options {
+                    self.set_event(DHCPv6Event::MalformedOptions);
+                }
+                if truncated_options {
+                    self.set_event(DHCPv6Event::TruncatedOptions);
+                }
+                if rogue_server {
+                    self.set_event(DHCPv6Event::RogueServer);
+                }
+                return true;
+            }
+            _ => {
+                return false;
+            }
+        }
+    }
+
+    /// Check the server DUID of an ADVERTISE or REPLY against the
+    /// configured servers. Rogue servers are reported once per flow.
+    fn check_server(&mut self, message: &DHCPv6Message) -> bool {
+        let message = message.inner();
+        match message.msg_type {
+            DHCPV6_ADVERTISE | DHCPV6_REPLY => {}
+            _ => { return false; }
+        }
+        let duid = match message.server_id() {
+            Some(duid) => &duid.data,
+            None => { return false; }
+        };
+        let allowed = dhcp_with_context(|ctx| {
+            ctx.is_allowed_server_v6(duid)
+        }).unwrap_or(true);
+        if allowed || self.rogue_servers.contains(duid) {
+            return false;
+        }
+        self.rogue_servers.push(duid.to_vec());
+        return true;
+    }
+
+}
+
+impl applayer::State for DHCPv6State {
+    type Transaction = DHCPv6Transaction;
+    type Event = DHCPv6Event;
+
+    fn new() -> DHCPv6State {
+        return DHCPv6State {
+            tx_id: 0,
+            transactions: Vec::new(),
+            rogue_servers: Vec::new(),
+        };
+    }
+
+    fn transactions(&mut self) -> &mut Vec<DHCPv6Transaction> {
+        &mut self.transactions
+    }
+
+    fn tx_count(&self) -> u64 {
+        self.tx_id
+    }
+
+    fn parse_request(&mut self, flow: *const Flow, input: &[u8], _eof: bool) -> bool {
+        self.parse(input, core::flow_get_last_time(flow))
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn rs_dhcpv6_probing_parser(_flow: *const Flow,
+                                           _direction: u8,
+                                           input: *const u8,
+                                           input_len: u32,
+                                           _rdir: *mut u8) -> AppProto
+{
+    if input_len < DHCPV6_MIN_FRAME_LEN {
+        return ALPROTO_UNKNOWN;
+    }
+
+    let slice = build_slice!(input, input_len as usize);
+    if slice[0] < DHCPV6_SOLICIT || slice[0] > DHCPV6_RELAY_REPL {
+        return ALPROTO_UNKNOWN;
+    }
+    match dhcpv6_parse(slice) {
+        Ok((_, message)) => {
+            if message.truncated_options || message.options.len() == 0 {
+                return ALPROTO_UNKNOWN;
+            }
+            return unsafe { ALPROTO_DHCPV6 };
+        }
+        _ => {
+            return ALPROTO_UNKNOWN;
+        }
+    }
+}
+
+const PARSER_NAME: &'static [u8] = b"dhcpv6\0";
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_dhcpv6_register_parser() {
+    SCLogDebug!("Registering DHCPv6 parser.");
+    let ports = CString::new("[546,547]").unwrap();
+    dhcp_context_init();
+    dhcp_leases_init();
+    let parser = RustParser::new::<DHCPv6State>(PARSER_NAME, ports.as_ptr(),
+                                                IPPROTO_UDP,
+                                                rs_dhcpv6_probing_parser,
+                                                rs_dhcpv6_probing_parser);
+    match register_parser(&parser, "udp") {
+        Some(alproto) => {
+            ALPROTO_DHCPV6 = alproto;
+        }
+        None => {
+            SCLogDebug!("Protocol detector and parser disabled for DHCPv6.");
+        }
+    }
+}
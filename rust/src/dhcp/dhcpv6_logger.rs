@@ -0,0 +1,241 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+//! DHCPv6 logger. The records use the same field names as the DHCP
+//! records where the information is the same.
+
+use crate::dhcp::dhcpv6::*;
+use crate::dhcp::dhcpv6_parser::*;
+use crate::dhcp::logger::{format_addr_hex, format_opaque};
+use crate::dns::log::dns_print_addr;
+use crate::json::*;
+use crate::conf::ConfNode;
+use crate::applayer::Logger;
+
+pub struct DHCPv6Logger {
+    extended: bool,
+}
+
+fn message_type_string(msg_type: u8) -> &'static str {
+    match msg_type {
+        DHCPV6_SOLICIT => "solicit",
+        DHCPV6_ADVERTISE => "advertise",
+        DHCPV6_REQUEST => "request",
+        DHCPV6_CONFIRM => "confirm",
+        DHCPV6_RENEW => "renew",
+        DHCPV6_REBIND => "rebind",
+        DHCPV6_REPLY => "reply",
+        DHCPV6_RELEASE => "release",
+        DHCPV6_DECLINE => "decline",
+        DHCPV6_RECONFIGURE => "reconfigure",
+        DHCPV6_INFORMATION_REQUEST => "information_request",
+        DHCPV6_RELAY_FORW => "relay_forw",
+        DHCPV6_RELAY_REPL => "relay_repl",
+        _ => "unknown",
+    }
+}
+
+fn status_code_string(code: u16) -> String {
+    match code {
+        DHCPV6_STATUS_SUCCESS => "success".to_string(),
+        DHCPV6_STATUS_UNSPEC_FAIL => "unspec_fail".to_string(),
+        DHCPV6_STATUS_NO_ADDRS_AVAIL => "no_addrs_avail".to_string(),
+        DHCPV6_STATUS_NO_BINDING => "no_binding".to_string(),
+        DHCPV6_STATUS_NOT_ON_LINK => "not_on_link".to_string(),
+        DHCPV6_STATUS_USE_MULTICAST => "use_multicast".to_string(),
+        DHCPV6_STATUS_NO_PREFIX_AVAIL => "no_prefix_avail".to_string(),
+        _ => code.to_string(),
+    }
+}
+
+impl DHCPv6Logger {
+    fn do_log(&self, tx: &DHCPv6Transaction) -> bool {
+        // Like for DHCP, only the messages confirming a lease are logged
+        // by default.
+        return self.extended || tx.message.inner().msg_type == DHCPV6_REPLY;
+    }
+
+    fn log_relay(&self, js: &Json, message: &DHCPv6Message) {
+        let relay = match message.relay {
+            Some(ref relay) => relay,
+            None => { return; }
+        };
+        let jsr = Json::object();
+        jsr.set_integer("hop_count", relay.hop_count as u64);
+        jsr.set_string("link_address", &dns_print_addr(&relay.link_address));
+        jsr.set_string("peer_address", &dns_print_addr(&relay.peer_address));
+        if let Some(&DHCPv6OptionValue::InterfaceId(ref id)) =
+            message.get_option(DHCPV6_OPT_INTERFACE_ID)
+        {
+            jsr.set_string("interface_id", &format_opaque(id));
+        }
+        js.set("relay", jsr);
+    }
+
+    fn log_option_request(&self, js: &Json, codes: &Vec<u16>) {
+        let params = Json::array();
+        for code in codes {
+            let param = match *code {
+                DHCPV6_OPT_DNS_SERVERS => "dns_servers",
+                DHCPV6_OPT_DOMAIN_LIST => "domain_search",
+                DHCPV6_OPT_SNTP_SERVERS => "sntp_servers",
+                DHCPV6_OPT_NTP_SERVER => "ntp_server",
+                DHCPV6_OPT_CLIENT_FQDN => "client_fqdn",
+                _ => ""
+            };
+            if param.len() > 0 {
+                params.array_append_string(param);
+            }
+        }
+        js.set("params", params);
+    }
+}
+
+impl Logger for DHCPv6Logger {
+    type Transaction = DHCPv6Transaction;
+
+    fn new(conf: ConfNode) -> DHCPv6Logger {
+        return DHCPv6Logger{
+            extended: conf.get_child_bool("extended"),
+        };
+    }
+
+    fn log(&self, tx: &DHCPv6Transaction) -> Option<Json> {
+        if !self.do_log(tx) {
+            return None;
+        }
+
+        let message = tx.message.inner();
+        let js = Json::object();
+
+        if message.is_reply() {
+            js.set_string("type", "reply");
+        } else {
+            js.set_string("type", "request");
+        }
+        js.set_integer("id", message.transaction_id as u64);
+        if let Some(mac) = tx.message.client_mac() {
+            js.set_string("client_mac", &format_addr_hex(mac));
+        }
+        if let Some(duid) = message.client_id() {
+            js.set_string("client_id", &format_addr_hex(&duid.data));
+        }
+        js.set_string("dhcp_type", message_type_string(message.msg_type));
+
+        if self.extended {
+            if let Some(duid) = message.server_id() {
+                js.set_string("server_id", &format_addr_hex(&duid.data));
+            }
+            if tx.message.relay.is_some() {
+                self.log_relay(&js, &tx.message);
+            }
+        }
+
+        let mut address = None;
+        let mut prefix = None;
+        for option in &message.options {
+            match option.value {
+                DHCPv6OptionValue::IaNa(ref ia) | DHCPv6OptionValue::IaPd(ref ia) => {
+                    if message.is_reply() {
+                        js.set_integer("renewal_time", ia.t1 as u64);
+                        if self.extended {
+                            js.set_integer("rebinding_time", ia.t2 as u64);
+                        }
+                    }
+                    for option in &ia.options {
+                        match option.value {
+                            DHCPv6OptionValue::IaAddr(ref addr) => {
+                                if address.is_none() {
+                                    address = Some(addr);
+                                }
+                            }
+                            DHCPv6OptionValue::IaPrefix(ref iaprefix) => {
+                                if prefix.is_none() {
+                                    prefix = Some(iaprefix);
+                                }
+                            }
+                            DHCPv6OptionValue::StatusCode(ref status) => {
+                                if status.code != DHCPV6_STATUS_SUCCESS {
+                                    js.set_string("status", &status_code_string(status.code));
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                DHCPv6OptionValue::StatusCode(ref status) => {
+                    if status.code != DHCPV6_STATUS_SUCCESS {
+                        js.set_string("status", &status_code_string(status.code));
+                    }
+                }
+                DHCPv6OptionValue::ClientFqdn(ref fqdn) => {
+                    js.set_string_from_bytes("client_fqdn", &fqdn.name);
+                }
+                DHCPv6OptionValue::OptionRequest(ref codes) => {
+                    if self.extended {
+                        self.log_option_request(&js, codes);
+                    }
+                }
+                DHCPv6OptionValue::DnsServers(ref servers) => {
+                    if self.extended {
+                        let jsa = Json::array();
+                        for server in servers {
+                            jsa.array_append_string(&dns_print_addr(server));
+                        }
+                        js.set("dns_servers", jsa);
+                    }
+                }
+                DHCPv6OptionValue::DomainList(ref names) => {
+                    if self.extended {
+                        let jsa = Json::array();
+                        for name in names {
+                            jsa.array_append(Json::string_from_bytes(name));
+                        }
+                        js.set("domain_search", jsa);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Addresses and prefixes sent by a client are the ones it asks
+        // for.
+        if let Some(addr) = address {
+            if message.is_reply() {
+                js.set_string("assigned_ip", &dns_print_addr(&addr.address));
+                if self.extended {
+                    js.set_integer("lease_time", addr.valid_lifetime as u64);
+                }
+            } else if self.extended {
+                js.set_string("requested_ip", &dns_print_addr(&addr.address));
+            }
+        }
+        if let Some(prefix) = prefix {
+            let prefix_str = format!("{}/{}", dns_print_addr(&prefix.prefix),
+                                     prefix.prefix_len);
+            if message.is_reply() {
+                js.set_string("assigned_prefix", &prefix_str);
+            } else if self.extended {
+                js.set_string("requested_prefix", &prefix_str);
+            }
+        }
+
+        return Some(js);
+    }
+}
+
+export_logger!(DHCPv6Logger, new: rs_dhcpv6_logger_new, free: rs_dhcpv6_logger_free, log: rs_dhcpv6_logger_log);
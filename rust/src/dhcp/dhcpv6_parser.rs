@@ -0,0 +1,698 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+//! DHCPv6 message parser, RFC 8415.
+
+use crate::dhcp::dhcpv6::*;
+use crate::dns::parser::dns_parse_name;
+use nom::*;
+
+/// Maximum nesting of relay messages and IA options.
+const DHCPV6_MAX_DEPTH: usize = 8;
+
+pub struct DHCPv6Message {
+    pub msg_type: u8,
+    /// Transaction ID. Not set for relay messages.
+    pub transaction_id: u32,
+    /// Header of relay messages.
+    pub relay: Option<DHCPv6RelayHeader>,
+    pub options: Vec<DHCPv6Option>,
+
+    // Set to true if an option value, or an encapsulated message, could
+    // not be decoded.
+    pub malformed_options: bool,
+
+    // Set to true if the options failed to parse due to not enough
+    // data.
+    pub truncated_options: bool,
+}
+
+pub struct DHCPv6RelayHeader {
+    pub hop_count: u8,
+    pub link_address: Vec<u8>,
+    pub peer_address: Vec<u8>,
+}
+
+/// A DHCP unique identifier. The hardware type and link-layer address
+/// are set for DUID-LLT and DUID-LL, the enterprise number for DUID-EN.
+pub struct DHCPv6Duid {
+    pub duid_type: u16,
+    pub hw_type: Option<u16>,
+    pub lladdr: Option<Vec<u8>>,
+    pub enterprise: Option<u32>,
+    /// The whole DUID, including the type.
+    pub data: Vec<u8>,
+}
+
+/// Identity association for non-temporary addresses (IA_NA) or for
+/// prefix delegation (IA_PD).
+pub struct DHCPv6IA {
+    pub iaid: u32,
+    pub t1: u32,
+    pub t2: u32,
+    pub options: Vec<DHCPv6Option>,
+}
+
+pub struct DHCPv6IAAddr {
+    pub address: Vec<u8>,
+    pub preferred_lifetime: u32,
+    pub valid_lifetime: u32,
+    pub options: Vec<DHCPv6Option>,
+}
+
+pub struct DHCPv6IAPrefix {
+    pub preferred_lifetime: u32,
+    pub valid_lifetime: u32,
+    pub prefix_len: u8,
+    pub prefix: Vec<u8>,
+    pub options: Vec<DHCPv6Option>,
+}
+
+pub struct DHCPv6StatusCode {
+    pub code: u16,
+    pub message: Vec<u8>,
+}
+
+/// Client FQDN option (39), RFC 4704.
+pub struct DHCPv6ClientFqdn {
+    pub flags: u8,
+    pub name: Vec<u8>,
+}
+
+pub enum DHCPv6OptionValue {
+    ClientId(DHCPv6Duid),
+    ServerId(DHCPv6Duid),
+    IaNa(DHCPv6IA),
+    IaPd(DHCPv6IA),
+    IaAddr(DHCPv6IAAddr),
+    IaPrefix(DHCPv6IAPrefix),
+    /// Option 6: list of requested option codes.
+    OptionRequest(Vec<u16>),
+    /// Option 9: the message relayed by a RELAY-FORW or RELAY-REPL.
+    RelayMessage(Box<DHCPv6Message>),
+    StatusCode(DHCPv6StatusCode),
+    /// Option 23: list of IPv6 addresses.
+    DnsServers(Vec<Vec<u8>>),
+    /// Option 24: list of domain names.
+    DomainList(Vec<Vec<u8>>),
+    ClientFqdn(DHCPv6ClientFqdn),
+    InterfaceId(Vec<u8>),
+    /// Option 79: hardware type and link-layer address of the client,
+    /// added by the first relay agent.
+    ClientLinkLayerAddr(u16, Vec<u8>),
+    Generic(Vec<u8>),
+    /// An option that could not be decoded.
+    Malformed(Vec<u8>),
+}
+
+pub struct DHCPv6Option {
+    pub code: u16,
+    pub value: DHCPv6OptionValue,
+}
+
+impl DHCPv6Message {
+    /// Return the value of the first option with the given code.
+    pub fn get_option(&self, code: u16) -> Option<&DHCPv6OptionValue> {
+        self.options.iter()
+            .find(|o| o.code == code)
+            .map(|o| &o.value)
+    }
+
+    /// Return the message relayed by a relay message, following nested
+    /// relay messages. Returns the message itself if it is not a relay
+    /// message.
+    pub fn inner(&self) -> &DHCPv6Message {
+        match self.get_option(DHCPV6_OPT_RELAY_MSG) {
+            Some(&DHCPv6OptionValue::RelayMessage(ref message)) => message.inner(),
+            _ => self,
+        }
+    }
+
+    pub fn client_id(&self) -> Option<&DHCPv6Duid> {
+        match self.get_option(DHCPV6_OPT_CLIENTID) {
+            Some(&DHCPv6OptionValue::ClientId(ref duid)) => Some(duid),
+            _ => None,
+        }
+    }
+
+    pub fn server_id(&self) -> Option<&DHCPv6Duid> {
+        match self.get_option(DHCPV6_OPT_SERVERID) {
+            Some(&DHCPv6OptionValue::ServerId(ref duid)) => Some(duid),
+            _ => None,
+        }
+    }
+
+    /// Return the MAC address of the client, from its DUID or from the
+    /// client link-layer address added by a relay agent.
+    pub fn client_mac(&self) -> Option<&Vec<u8>> {
+        if let Some(duid) = self.inner().client_id() {
+            if let (Some(DHCPV6_HW_TYPE_ETHERNET), Some(ref lladdr)) = (duid.hw_type, &duid.lladdr) {
+                if lladdr.len() == 6 {
+                    return Some(lladdr);
+                }
+            }
+        }
+        let mut relay = Some(self);
+        while let Some(message) = relay {
+            if let Some(&DHCPv6OptionValue::ClientLinkLayerAddr(DHCPV6_HW_TYPE_ETHERNET, ref addr)) =
+                message.get_option(DHCPV6_OPT_CLIENT_LINKLAYER_ADDR)
+            {
+                return Some(addr);
+            }
+            relay = match message.get_option(DHCPV6_OPT_RELAY_MSG) {
+                Some(&DHCPv6OptionValue::RelayMessage(ref message)) => Some(message),
+                _ => None,
+            };
+        }
+        None
+    }
+
+    /// Return true for the messages sent by servers and relay agents to
+    /// clients.
+    pub fn is_reply(&self) -> bool {
+        match self.msg_type {
+            DHCPV6_ADVERTISE | DHCPV6_REPLY | DHCPV6_RECONFIGURE |
+            DHCPV6_RELAY_REPL => true,
+            _ => false,
+        }
+    }
+}
+
+named!(parse_option_header<(u16, &[u8])>,
+       do_parse!(
+           code: be_u16 >>
+           len: be_u16 >>
+           data: take!(len) >>
+           ((code, data))
+       )
+);
+
+named!(parse_relay_header<DHCPv6RelayHeader>,
+       do_parse!(
+           hop_count: be_u8 >>
+           link_address: take!(16) >>
+           peer_address: take!(16) >>
+           (
+               DHCPv6RelayHeader{
+                   hop_count: hop_count,
+                   link_address: link_address.to_vec(),
+                   peer_address: peer_address.to_vec(),
+               }
+           )
+       )
+);
+
+named!(parse_ia_header<(u32, u32, u32)>,
+       do_parse!(
+           iaid: be_u32 >>
+           t1: be_u32 >>
+           t2: be_u32 >>
+           ((iaid, t1, t2))
+       )
+);
+
+fn decode_duid(data: &[u8]) -> Option<DHCPv6Duid> {
+    // A DUID is at most 128 bytes, not counting the type.
+    if data.len() < 2 || data.len() > 130 {
+        return None;
+    }
+    let duid_type = (data[0] as u16) << 8 | data[1] as u16;
+    let body = &data[2..];
+    let mut duid = DHCPv6Duid{
+        duid_type: duid_type,
+        hw_type: None,
+        lladdr: None,
+        enterprise: None,
+        data: data.to_vec(),
+    };
+    match duid_type {
+        DHCPV6_DUID_LLT => {
+            if body.len() < 6 {
+                return None;
+            }
+            duid.hw_type = Some((body[0] as u16) << 8 | body[1] as u16);
+            duid.lladdr = Some(body[6..].to_vec());
+        }
+        DHCPV6_DUID_EN => {
+            match be_u32(body) {
+                Ok((_, enterprise)) => { duid.enterprise = Some(enterprise); }
+                Err(_) => { return None; }
+            }
+        }
+        DHCPV6_DUID_LL => {
+            if body.len() < 2 {
+                return None;
+            }
+            duid.hw_type = Some((body[0] as u16) << 8 | body[1] as u16);
+            duid.lladdr = Some(body[2..].to_vec());
+        }
+        DHCPV6_DUID_UUID => {
+            if body.len() != 16 {
+                return None;
+            }
+        }
+        _ => {}
+    }
+    Some(duid)
+}
+
+fn decode_ia(data: &[u8], depth: usize, malformed: &mut bool,
+             truncated: &mut bool) -> Option<DHCPv6IA>
+{
+    let (rem, (iaid, t1, t2)) = parse_ia_header(data).ok()?;
+    Some(DHCPv6IA{
+        iaid: iaid,
+        t1: t1,
+        t2: t2,
+        options: parse_options(rem, depth + 1, malformed, truncated),
+    })
+}
+
+fn decode_iaaddr(data: &[u8], depth: usize, malformed: &mut bool,
+                 truncated: &mut bool) -> Option<DHCPv6IAAddr>
+{
+    let (rem, (address, preferred, valid)) = do_parse!(data,
+        address: take!(16) >>
+        preferred: be_u32 >>
+        valid: be_u32 >>
+        ((address, preferred, valid))
+    ).ok()?;
+    Some(DHCPv6IAAddr{
+        address: address.to_vec(),
+        preferred_lifetime: preferred,
+        valid_lifetime: valid,
+        options: parse_options(rem, depth + 1, malformed, truncated),
+    })
+}
+
+fn decode_iaprefix(data: &[u8], depth: usize, malformed: &mut bool,
+                   truncated: &mut bool) -> Option<DHCPv6IAPrefix>
+{
+    let (rem, (preferred, valid, prefix_len, prefix)) = do_parse!(data,
+        preferred: be_u32 >>
+        valid: be_u32 >>
+        prefix_len: verify!(be_u8, |v| v <= 128) >>
+        prefix: take!(16) >>
+        ((preferred, valid, prefix_len, prefix))
+    ).ok()?;
+    Some(DHCPv6IAPrefix{
+        preferred_lifetime: preferred,
+        valid_lifetime: valid,
+        prefix_len: prefix_len,
+        prefix: prefix.to_vec(),
+        options: parse_options(rem, depth + 1, malformed, truncated),
+    })
+}
+
+/// Parse a list of DNS encoded names, as used by the domain list option.
+fn decode_domain_list(data: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let mut names = Vec::new();
+    let mut rem = data;
+    while rem.len() > 0 {
+        match dns_parse_name(rem, data) {
+            Ok((next, name)) => {
+                names.push(name);
+                rem = next;
+            }
+            Err(_) => { return None; }
+        }
+    }
+    Some(names)
+}
+
+/// Parse the client FQDN option. Clients may send a partial name, without
+/// the final empty label.
+fn decode_client_fqdn(data: &[u8]) -> Option<DHCPv6ClientFqdn> {
+    if data.len() < 1 {
+        return None;
+    }
+    let mut labels = Vec::new();
+    let mut rem = &data[1..];
+    while rem.len() > 0 {
+        let len = rem[0] as usize;
+        if len == 0 {
+            break;
+        }
+        if len > 63 || len + 1 > rem.len() {
+            return None;
+        }
+        labels.push(&rem[1..len + 1]);
+        rem = &rem[len + 1..];
+    }
+    Some(DHCPv6ClientFqdn{
+        flags: data[0],
+        name: labels.join(&b'.'),
+    })
+}
+
+fn decode_addresses(data: &[u8]) -> Option<Vec<Vec<u8>>> {
+    if data.len() % 16 != 0 {
+        return None;
+    }
+    Some(data.chunks(16).map(|a| a.to_vec()).collect())
+}
+
+fn decode_option(code: u16, data: &[u8], depth: usize, malformed: &mut bool,
+                 truncated: &mut bool) -> Option<DHCPv6OptionValue>
+{
+    if depth > DHCPV6_MAX_DEPTH {
+        return None;
+    }
+    let value = match code {
+        DHCPV6_OPT_CLIENTID => DHCPv6OptionValue::ClientId(decode_duid(data)?),
+        DHCPV6_OPT_SERVERID => DHCPv6OptionValue::ServerId(decode_duid(data)?),
+        DHCPV6_OPT_IA_NA => {
+            DHCPv6OptionValue::IaNa(decode_ia(data, depth, malformed, truncated)?)
+        }
+        DHCPV6_OPT_IA_PD => {
+            DHCPv6OptionValue::IaPd(decode_ia(data, depth, malformed, truncated)?)
+        }
+        DHCPV6_OPT_IAADDR => {
+            DHCPv6OptionValue::IaAddr(decode_iaaddr(data, depth, malformed, truncated)?)
+        }
+        DHCPV6_OPT_IAPREFIX => {
+            DHCPv6OptionValue::IaPrefix(decode_iaprefix(data, depth, malformed, truncated)?)
+        }
+        DHCPV6_OPT_ORO => {
+            if data.len() % 2 != 0 {
+                return None;
+            }
+            DHCPv6OptionValue::OptionRequest(data.chunks(2)
+                .map(|c| (c[0] as u16) << 8 | c[1] as u16)
+                .collect())
+        }
+        DHCPV6_OPT_RELAY_MSG => {
+            let (_, message) = parse_message(data, depth + 1).ok()?;
+            *malformed |= message.malformed_options;
+            *truncated |= message.truncated_options;
+            DHCPv6OptionValue::RelayMessage(Box::new(message))
+        }
+        DHCPV6_OPT_STATUS_CODE => {
+            if data.len() < 2 {
+                return None;
+            }
+            DHCPv6OptionValue::StatusCode(DHCPv6StatusCode{
+                code: (data[0] as u16) << 8 | data[1] as u16,
+                message: data[2..].to_vec(),
+            })
+        }
+        DHCPV6_OPT_DNS_SERVERS => DHCPv6OptionValue::DnsServers(decode_addresses(data)?),
+        DHCPV6_OPT_DOMAIN_LIST => DHCPv6OptionValue::DomainList(decode_domain_list(data)?),
+        DHCPV6_OPT_CLIENT_FQDN => DHCPv6OptionValue::ClientFqdn(decode_client_fqdn(data)?),
+        DHCPV6_OPT_INTERFACE_ID => DHCPv6OptionValue::InterfaceId(data.to_vec()),
+        DHCPV6_OPT_CLIENT_LINKLAYER_ADDR => {
+            if data.len() < 2 {
+                return None;
+            }
+            DHCPv6OptionValue::ClientLinkLayerAddr(
+                (data[0] as u16) << 8 | data[1] as u16, data[2..].to_vec())
+        }
+        _ => DHCPv6OptionValue::Generic(data.to_vec()),
+    };
+    Some(value)
+}
+
+/// Parse options until the end of the input. Options that cannot be
+/// decoded are kept as malformed.
+fn parse_options(input: &[u8], depth: usize, malformed: &mut bool,
+                 truncated: &mut bool) -> Vec<DHCPv6Option>
+{
+    let mut options = Vec::new();
+    let mut rem = input;
+    while rem.len() > 0 {
+        match parse_option_header(rem) {
+            Ok((next, (code, data))) => {
+                let value = match decode_option(code, data, depth, malformed, truncated) {
+                    Some(value) => value,
+                    None => {
+                        *malformed = true;
+                        DHCPv6OptionValue::Malformed(data.to_vec())
+                    }
+                };
+                options.push(DHCPv6Option{
+                    code: code,
+                    value: value,
+                });
+                rem = next;
+            }
+            Err(_) => {
+                *truncated = true;
+                break;
+            }
+        }
+    }
+    options
+}
+
+fn parse_message(i: &[u8], depth: usize) -> IResult<&[u8], DHCPv6Message> {
+    let (rem, msg_type) = be_u8(i)?;
+    let (rem, relay, transaction_id) = match msg_type {
+        DHCPV6_RELAY_FORW | DHCPV6_RELAY_REPL => {
+            let (rem, relay) = parse_relay_header(rem)?;
+            (rem, Some(relay), 0)
+        }
+        _ => {
+            let (rem, transaction_id) = be_u24(rem)?;
+            (rem, None, transaction_id)
+        }
+    };
+    let mut malformed = false;
+    let mut truncated = false;
+    let options = parse_options(rem, depth, &mut malformed, &mut truncated);
+    Ok((&rem[rem.len()..], DHCPv6Message{
+        msg_type: msg_type,
+        transaction_id: transaction_id,
+        relay: relay,
+        options: options,
+        malformed_options: malformed,
+        truncated_options: truncated,
+    }))
+}
+
+pub fn dhcpv6_parse(input: &[u8]) -> IResult<&[u8], DHCPv6Message> {
+    parse_message(input, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dhcp::dhcpv6::*;
+    use crate::dhcp::dhcpv6_parser::*;
+
+    /// SOLICIT with a DUID-LLT, an elapsed time, an IA_NA, an IA_PD, an
+    /// option request and a client FQDN.
+    const SOLICIT: &[u8] = &[
+        0x01, 0x10, 0x08, 0x74,
+        // client id: DUID-LLT, ethernet, 00:0c:29:37:f2:51
+        0x00, 0x01, 0x00, 0x0e, 0x00, 0x01, 0x00, 0x01,
+        0x24, 0x6a, 0x57, 0x5f, 0x00, 0x0c, 0x29, 0x37, 0xf2, 0x51,
+        // elapsed time
+        0x00, 0x08, 0x00, 0x02, 0x00, 0x00,
+        // IA_NA, iaid 0x0c2937f2, no address
+        0x00, 0x03, 0x00, 0x0c, 0x0c, 0x29, 0x37, 0xf2,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // IA_PD, iaid 1, no prefix
+        0x00, 0x19, 0x00, 0x0c, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // option request: dns servers, domain list
+        0x00, 0x06, 0x00, 0x04, 0x00, 0x17, 0x00, 0x18,
+        // client fqdn: S flag, partial name "host"
+        0x00, 0x27, 0x00, 0x06, 0x01, 0x04, b'h', b'o', b's', b't',
+    ];
+
+    /// REPLY with a server id (DUID-LL), an IA_NA with an address, an
+    /// IA_PD with a prefix, DNS servers and a domain list.
+    const REPLY: &[u8] = &[
+        0x07, 0x10, 0x08, 0x74,
+        // client id
+        0x00, 0x01, 0x00, 0x0e, 0x00, 0x01, 0x00, 0x01,
+        0x24, 0x6a, 0x57, 0x5f, 0x00, 0x0c, 0x29, 0x37, 0xf2, 0x51,
+        // server id: DUID-LL, ethernet, 00:50:56:00:00:01
+        0x00, 0x02, 0x00, 0x0a, 0x00, 0x03, 0x00, 0x01,
+        0x00, 0x50, 0x56, 0x00, 0x00, 0x01,
+        // IA_NA, t1 1800, t2 2880
+        0x00, 0x03, 0x00, 0x28, 0x0c, 0x29, 0x37, 0xf2,
+        0x00, 0x00, 0x07, 0x08, 0x00, 0x00, 0x0b, 0x40,
+        //   IAADDR 2001:db8::10, preferred 3600, valid 7200
+        0x00, 0x05, 0x00, 0x18,
+        0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10,
+        0x00, 0x00, 0x0e, 0x10, 0x00, 0x00, 0x1c, 0x20,
+        // IA_PD
+        0x00, 0x19, 0x00, 0x29, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x07, 0x08, 0x00, 0x00, 0x0b, 0x40,
+        //   IAPREFIX 2001:db8:1::/56
+        0x00, 0x1a, 0x00, 0x19,
+        0x00, 0x00, 0x0e, 0x10, 0x00, 0x00, 0x1c, 0x20, 0x38,
+        0x20, 0x01, 0x0d, 0xb8, 0x00, 0x01, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // dns servers: 2001:db8::53
+        0x00, 0x17, 0x00, 0x10,
+        0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x53,
+        // domain list: example.com
+        0x00, 0x18, 0x00, 0x0d,
+        0x07, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 0x03, b'c', b'o', b'm', 0x00,
+    ];
+
+    #[test]
+    fn test_dhcpv6_parse_solicit() {
+        let (rem, message) = dhcpv6_parse(SOLICIT).unwrap();
+        assert_eq!(rem.len(), 0);
+        assert_eq!(message.msg_type, DHCPV6_SOLICIT);
+        assert_eq!(message.transaction_id, 0x100874);
+        assert!(!message.malformed_options);
+        assert!(!message.truncated_options);
+        assert!(!message.is_reply());
+        assert_eq!(message.options.len(), 6);
+
+        let duid = message.client_id().unwrap();
+        assert_eq!(duid.duid_type, DHCPV6_DUID_LLT);
+        assert_eq!(duid.hw_type, Some(1));
+        assert_eq!(duid.lladdr, Some(vec![0x00, 0x0c, 0x29, 0x37, 0xf2, 0x51]));
+        assert_eq!(duid.data.len(), 14);
+
+        match message.get_option(DHCPV6_OPT_IA_NA) {
+            Some(&DHCPv6OptionValue::IaNa(ref ia)) => {
+                assert_eq!(ia.iaid, 0x0c2937f2);
+                assert_eq!(ia.options.len(), 0);
+            }
+            _ => panic!("no IA_NA"),
+        }
+        match message.get_option(DHCPV6_OPT_ORO) {
+            Some(&DHCPv6OptionValue::OptionRequest(ref codes)) => {
+                assert_eq!(codes, &vec![DHCPV6_OPT_DNS_SERVERS, DHCPV6_OPT_DOMAIN_LIST]);
+            }
+            _ => panic!("no option request"),
+        }
+        match message.get_option(DHCPV6_OPT_CLIENT_FQDN) {
+            Some(&DHCPv6OptionValue::ClientFqdn(ref fqdn)) => {
+                assert_eq!(fqdn.flags, 1);
+                assert_eq!(fqdn.name, b"host");
+            }
+            _ => panic!("no client fqdn"),
+        }
+    }
+
+    #[test]
+    fn test_dhcpv6_parse_reply() {
+        let (_, message) = dhcpv6_parse(REPLY).unwrap();
+        assert_eq!(message.msg_type, DHCPV6_REPLY);
+        assert!(message.is_reply());
+        assert!(!message.malformed_options);
+        assert!(!message.truncated_options);
+
+        let duid = message.server_id().unwrap();
+        assert_eq!(duid.duid_type, DHCPV6_DUID_LL);
+        assert_eq!(duid.lladdr, Some(vec![0x00, 0x50, 0x56, 0x00, 0x00, 0x01]));
+
+        match message.get_option(DHCPV6_OPT_IA_NA) {
+            Some(&DHCPv6OptionValue::IaNa(ref ia)) => {
+                assert_eq!(ia.t1, 1800);
+                assert_eq!(ia.t2, 2880);
+                match ia.options[0].value {
+                    DHCPv6OptionValue::IaAddr(ref addr) => {
+                        assert_eq!(addr.address[15], 0x10);
+                        assert_eq!(addr.preferred_lifetime, 3600);
+                        assert_eq!(addr.valid_lifetime, 7200);
+                    }
+                    _ => panic!("no IAADDR"),
+                }
+            }
+            _ => panic!("no IA_NA"),
+        }
+        match message.get_option(DHCPV6_OPT_IA_PD) {
+            Some(&DHCPv6OptionValue::IaPd(ref ia)) => {
+                match ia.options[0].value {
+                    DHCPv6OptionValue::IaPrefix(ref prefix) => {
+                        assert_eq!(prefix.prefix_len, 56);
+                        assert_eq!(&prefix.prefix[..6], &[0x20, 0x01, 0x0d, 0xb8, 0x00, 0x01]);
+                    }
+                    _ => panic!("no IAPREFIX"),
+                }
+            }
+            _ => panic!("no IA_PD"),
+        }
+        match message.get_option(DHCPV6_OPT_DNS_SERVERS) {
+            Some(&DHCPv6OptionValue::DnsServers(ref servers)) => {
+                assert_eq!(servers.len(), 1);
+                assert_eq!(servers[0][15], 0x53);
+            }
+            _ => panic!("no dns servers"),
+        }
+        match message.get_option(DHCPV6_OPT_DOMAIN_LIST) {
+            Some(&DHCPv6OptionValue::DomainList(ref names)) => {
+                assert_eq!(names, &vec![b"example.com".to_vec()]);
+            }
+            _ => panic!("no domain list"),
+        }
+    }
+
+    #[test]
+    fn test_dhcpv6_parse_relay() {
+        let mut buf = vec![DHCPV6_RELAY_FORW, 0x00];
+        // link address 2001:db8:2::1
+        buf.extend_from_slice(&[0x20, 0x01, 0x0d, 0xb8, 0x00, 0x02, 0x00, 0x00,
+                                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01]);
+        // peer address fe80::20c:29ff:fe37:f251
+        buf.extend_from_slice(&[0xfe, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                                0x02, 0x0c, 0x29, 0xff, 0xfe, 0x37, 0xf2, 0x51]);
+        // interface id "eth0"
+        buf.extend_from_slice(&[0x00, 0x12, 0x00, 0x04, b'e', b't', b'h', b'0']);
+        // relay message
+        buf.extend_from_slice(&[0x00, 0x09, 0x00, SOLICIT.len() as u8]);
+        buf.extend_from_slice(SOLICIT);
+
+        let (_, message) = dhcpv6_parse(&buf).unwrap();
+        assert_eq!(message.msg_type, DHCPV6_RELAY_FORW);
+        assert!(!message.malformed_options);
+        let relay = message.relay.as_ref().unwrap();
+        assert_eq!(relay.hop_count, 0);
+        assert_eq!(relay.link_address[5], 0x02);
+        assert_eq!(relay.peer_address[0], 0xfe);
+        let inner = message.inner();
+        assert_eq!(inner.msg_type, DHCPV6_SOLICIT);
+        assert_eq!(inner.transaction_id, 0x100874);
+    }
+
+    #[test]
+    fn test_dhcpv6_parse_malformed() {
+        // DNS servers with a length that is not a multiple of 16.
+        let buf: &[u8] = &[
+            0x07, 0x00, 0x00, 0x01,
+            0x00, 0x17, 0x00, 0x04, 0x20, 0x01, 0x0d, 0xb8,
+        ];
+        let (_, message) = dhcpv6_parse(buf).unwrap();
+        assert!(message.malformed_options);
+        assert!(!message.truncated_options);
+        match message.options[0].value {
+            DHCPv6OptionValue::Malformed(ref data) => { assert_eq!(data.len(), 4); }
+            _ => panic!("not malformed"),
+        }
+
+        // Option longer than the message.
+        let buf: &[u8] = &[
+            0x07, 0x00, 0x00, 0x01,
+            0x00, 0x17, 0x00, 0x10, 0x20, 0x01, 0x0d, 0xb8,
+        ];
+        let (_, message) = dhcpv6_parse(buf).unwrap();
+        assert!(message.truncated_options);
+        assert_eq!(message.options.len(), 0);
+
+        // Truncated header.
+        assert!(dhcpv6_parse(&[0x01, 0x00]).is_err());
+    }
+}
@@ -0,0 +1,316 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+//! DHCP client fingerprinting.
+//!
+//! The fingerprint of a client message is made of the parameter request
+//! list (option 55), the maximum message size (option 57), the vendor
+//! class identifier (option 60) and the order of the options, for
+//! example `55=1,3,6,15;57=1500;60=MSFT 5.0;order=53,61,12,60,55`.
+//! Absent options are left out.
+//!
+//! Like in the Fingerbank database, devices are identified by their
+//! parameter request list and vendor class. A built-in table is always
+//! loaded. Additional entries can be loaded from the file set in
+//! `app-layer.protocols.dhcp.fingerprints-file`, one entry per line:
+//!
+//! `<parameter request list>|<vendor class prefix>|<os family>|<device type>`
+//!
+//! One of the first two fields may be empty to match any value. Lines
+//! starting with `#` are ignored.
+
+use std;
+
+use crate::conf::conf_get;
+use crate::dhcp::dhcp::*;
+use crate::dhcp::parser::DHCPMessage;
+use crate::log::*;
+
+/// Devices known by default.
+const BUILTIN_FINGERPRINTS: &[(&str, &str, &str, &str)] = &[
+    ("1,3,6,15,31,33,43,44,46,47,119,121,249,252", "MSFT 5.0", "Windows", "Computer"),
+    ("1,15,3,6,44,46,47,31,33,121,249,43,252", "MSFT 5.0", "Windows", "Computer"),
+    ("1,15,3,6,44,46,47,31,33,121,249,43", "MSFT 5.0", "Windows", "Computer"),
+    ("1,15,3,6,44,46,47,31,33,249,43", "MSFT 5.0", "Windows", "Computer"),
+    ("", "MSFT", "Windows", "Computer"),
+    ("1,121,3,6,15,119,252,95,44,46", "", "macOS", "Computer"),
+    ("1,121,3,6,15,114,119,252,95,44,46", "", "macOS", "Computer"),
+    ("1,121,3,6,15,119,252", "", "iOS", "Phone or Tablet"),
+    ("1,121,3,6,15,114,119,252", "", "iOS", "Phone or Tablet"),
+    ("1,3,6,15,26,28,51,58,59,43", "", "Android", "Phone or Tablet"),
+    ("1,3,6,15,26,28,51,58,59,43,114", "", "Android", "Phone or Tablet"),
+    ("", "android-dhcp-", "Android", "Phone or Tablet"),
+    ("1,28,2,3,15,6,119,12,44,47,26,121,42", "", "Linux", "Computer"),
+    ("", "dhcpcd-", "Linux", "Computer"),
+    ("", "udhcp", "Linux", "Embedded Device"),
+    ("", "Cisco Systems, Inc. IP Phone", "Cisco", "VoIP Phone"),
+    ("", "Hewlett-Packard JetDirect", "HP JetDirect", "Printer"),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DHCPDevice {
+    pub os_family: String,
+    pub device_type: String,
+}
+
+struct FingerprintEntry {
+    params: Option<Vec<u8>>,
+    vendor: Option<Vec<u8>>,
+    device: DHCPDevice,
+}
+
+pub struct FingerprintTable {
+    entries: Vec<FingerprintEntry>,
+}
+
+fn parse_params(input: &str) -> Option<Vec<u8>> {
+    let mut params = Vec::new();
+    for param in input.split(',') {
+        match param.trim().parse::<u8>() {
+            Ok(param) => params.push(param),
+            Err(_) => return None,
+        }
+    }
+    Some(params)
+}
+
+fn format_params(params: &[u8]) -> String {
+    let params: Vec<String> = params.iter().map(|p| p.to_string()).collect();
+    params.join(",")
+}
+
+impl FingerprintTable {
+    pub fn new() -> FingerprintTable {
+        FingerprintTable {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Create a table with the built-in entries.
+    pub fn builtin() -> FingerprintTable {
+        let mut table = FingerprintTable::new();
+        for &(params, vendor, os_family, device_type) in BUILTIN_FINGERPRINTS {
+            let _ = table.add(params, vendor, os_family, device_type);
+        }
+        table
+    }
+
+    fn add(&mut self, params: &str, vendor: &str, os_family: &str,
+           device_type: &str) -> bool
+    {
+        let params = if params.len() > 0 {
+            match parse_params(params) {
+                Some(params) => Some(params),
+                None => return false,
+            }
+        } else {
+            None
+        };
+        let vendor = if vendor.len() > 0 {
+            Some(vendor.as_bytes().to_vec())
+        } else {
+            None
+        };
+        if params.is_none() && vendor.is_none() {
+            return false;
+        }
+        if os_family.len() == 0 && device_type.len() == 0 {
+            return false;
+        }
+        self.entries.push(FingerprintEntry {
+            params: params,
+            vendor: vendor,
+            device: DHCPDevice {
+                os_family: os_family.to_string(),
+                device_type: device_type.to_string(),
+            },
+        });
+        true
+    }
+
+    /// Load entries from the content of a table file. Returns the number
+    /// of entries added, or the number of the first invalid line. Nothing
+    /// is added if a line is invalid.
+    pub fn load(&mut self, content: &str) -> Result<usize, usize> {
+        let mut loaded = FingerprintTable::new();
+        for (n, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.len() == 0 || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('|').map(|f| f.trim()).collect();
+            if fields.len() != 4 ||
+                !loaded.add(fields[0], fields[1], fields[2], fields[3])
+            {
+                return Err(n + 1);
+            }
+        }
+        let count = loaded.entries.len();
+        self.entries.append(&mut loaded.entries);
+        Ok(count)
+    }
+
+    /// Return the device for a parameter request list and vendor class.
+    /// Entries matching on both are preferred, then the ones matching on
+    /// the parameter request list, then the longest vendor class prefix.
+    pub fn lookup(&self, params: Option<&[u8]>, vendor: Option<&[u8]>)
+                  -> Option<&DHCPDevice>
+    {
+        self.entries.iter()
+            .filter(|e| match e.params {
+                Some(ref p) => params == Some(p.as_slice()),
+                None => true,
+            })
+            .filter(|e| match e.vendor {
+                Some(ref v) => vendor.map_or(false, |vendor| vendor.starts_with(v)),
+                None => true,
+            })
+            .max_by_key(|e| (e.params.is_some() && e.vendor.is_some(),
+                             e.params.is_some(),
+                             e.vendor.as_ref().map_or(0, |v| v.len())))
+            .map(|e| &e.device)
+    }
+}
+
+/// Build the fingerprint of a client message. Returns None for server
+/// messages.
+pub fn dhcp_fingerprint(message: &DHCPMessage) -> Option<String> {
+    if message.header.opcode != BOOTP_REQUEST {
+        return None;
+    }
+    let mut fields = Vec::new();
+    if let Some(params) = message.get_generic_option(DHCP_OPT_PARAMETER_LIST) {
+        fields.push(format!("55={}", format_params(params)));
+    }
+    if let Some(size) = message.get_generic_option(DHCP_OPT_MAX_MESSAGE_SIZE) {
+        if size.len() == 2 {
+            fields.push(format!("57={}", (size[0] as u16) << 8 | size[1] as u16));
+        }
+    }
+    if let Some(vendor) = message.get_generic_option(DHCP_OPT_VENDOR_CLASS) {
+        fields.push(format!("60={}", String::from_utf8_lossy(vendor)));
+    }
+    let order: Vec<u8> = message.options.iter()
+        .map(|o| o.code)
+        .filter(|&code| code != DHCP_OPT_PAD && code != DHCP_OPT_END)
+        .collect();
+    fields.push(format!("order={}", format_params(&order)));
+    Some(fields.join(";"))
+}
+
+static mut FINGERPRINTS: Option<FingerprintTable> = None;
+
+/// Initialize the global table with the built-in entries and the entries
+/// of the configured file, if any.
+pub fn dhcp_fingerprints_init() {
+    let mut table = FingerprintTable::builtin();
+    if let Some(filename) = conf_get("app-layer.protocols.dhcp.fingerprints-file") {
+        match std::fs::read_to_string(filename) {
+            Ok(content) => {
+                match table.load(&content) {
+                    Ok(count) => {
+                        SCLogConfig!("DHCP: loaded {} fingerprints from {}", count, filename);
+                    },
+                    Err(line) => {
+                        SCLogWarning!("DHCP: invalid fingerprint entry in {} line {}", filename, line);
+                    },
+                }
+            },
+            Err(e) => {
+                SCLogWarning!("DHCP: failed to read fingerprints file {}: {}", filename, e);
+            },
+        }
+    }
+    unsafe {
+        FINGERPRINTS = Some(table);
+    }
+}
+
+/// Look up the device that sent a client message in the global table.
+pub fn dhcp_device_lookup(message: &DHCPMessage) -> Option<&'static DHCPDevice> {
+    if message.header.opcode != BOOTP_REQUEST {
+        return None;
+    }
+    let params = message.get_generic_option(DHCP_OPT_PARAMETER_LIST);
+    let vendor = message.get_generic_option(DHCP_OPT_VENDOR_CLASS);
+    unsafe {
+        match FINGERPRINTS {
+            Some(ref table) => table.lookup(params, vendor),
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dhcp::parser::dhcp_parse;
+
+    #[test]
+    fn test_dhcp_fingerprint() {
+        let pcap = include_bytes!("discover.pcap");
+        let payload = &pcap[24 + 16 + 42..];
+        let (_, message) = dhcp_parse(payload).unwrap();
+        assert_eq!(dhcp_fingerprint(&message),
+                   Some("55=1,3,6,42;order=53,61,50,55".to_string()));
+
+        let pcap = include_bytes!("ack.pcap");
+        let payload = &pcap[24 + 16 + 42..];
+        let (_, message) = dhcp_parse(payload).unwrap();
+        assert_eq!(dhcp_fingerprint(&message), None);
+    }
+
+    #[test]
+    fn test_dhcp_fingerprint_lookup() {
+        let table = FingerprintTable::builtin();
+        let windows = parse_params("1,3,6,15,31,33,43,44,46,47,119,121,249,252").unwrap();
+        let device = table.lookup(Some(&windows), Some(b"MSFT 5.0")).unwrap();
+        assert_eq!(device.os_family, "Windows");
+        assert_eq!(device.device_type, "Computer");
+
+        let android = b"android-dhcp-9";
+        let device = table.lookup(Some(&[1, 3, 6]), Some(android)).unwrap();
+        assert_eq!(device.os_family, "Android");
+
+        let ios = parse_params("1,121,3,6,15,119,252").unwrap();
+        let device = table.lookup(Some(&ios), None).unwrap();
+        assert_eq!(device.os_family, "iOS");
+
+        assert_eq!(table.lookup(Some(&[1, 3, 6]), None), None);
+        assert_eq!(table.lookup(None, None), None);
+    }
+
+    #[test]
+    fn test_dhcp_fingerprint_load() {
+        let mut table = FingerprintTable::builtin();
+        let content = "# local entries\n\n1,3,6,42||Linux|Thin Client\n\
+                       1,3,6,42|Acme|AcmeOS|Printer\n";
+        assert_eq!(table.load(content), Ok(2));
+        let device = table.lookup(Some(&[1, 3, 6, 42]), None).unwrap();
+        assert_eq!(device.device_type, "Thin Client");
+        let device = table.lookup(Some(&[1, 3, 6, 42]), Some(b"Acme 2.0")).unwrap();
+        assert_eq!(device.os_family, "AcmeOS");
+
+        assert_eq!(table.load("1,3,6|Linux|Computer\n"), Err(1));
+        assert_eq!(table.load("# comment\n||Linux|Computer\n"), Err(2));
+        assert_eq!(table.load("1,3,600||Linux|Computer\n"), Err(1));
+
+        // a file with an invalid line adds nothing
+        assert_eq!(table.load("1,3,6||Linux|Router\n1,3,600||Linux|Computer\n"), Err(2));
+        assert_eq!(table.lookup(Some(&[1, 3, 6]), None), None);
+    }
+}
@@ -0,0 +1,300 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+//! DHCP lease table.
+//!
+//! The table maps the addresses leased by DHCP ACKs and DHCPv6 REPLYs to
+//! the MAC address and host name of the client, until the lease expires
+//! or is released. It is shared by all the flows seen by the sensor, so
+//! that other loggers can add the MAC address and host name of the hosts
+//! to their records with `dhcp_lease_lookup` or `dhcp_lease_log`.
+//!
+//! The size of the table is set with `app-layer.protocols.dhcp.max-leases`.
+//! When it is full, expired leases are removed, and new leases are dropped
+//! if none has expired.
+
+use std;
+use std::collections::{HashMap, HashSet};
+use std::ffi::CStr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::os::raw::c_char;
+use std::sync::Mutex;
+
+use crate::conf::conf_get;
+use crate::dhcp::logger::format_addr_hex;
+use crate::json::*;
+use crate::log::*;
+
+/// Default maximum number of leases in the table
+pub const DHCP_DEFAULT_MAX_LEASES: usize = 65536;
+
+/// Lease time used for an ACK without lease time option, in seconds
+pub const DHCP_DEFAULT_LEASE_TIME: u32 = 86400;
+
+/// Lease time meaning that the lease never expires
+pub const DHCP_INFINITE_LEASE_TIME: u32 = 0xffffffff;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DHCPLease {
+    /// Client identifier, the MAC address for DHCP, the DUID for DHCPv6.
+    client: Vec<u8>,
+    /// MAC address of the client. DHCPv6 clients do not always send it.
+    pub mac: Option<Vec<u8>>,
+    pub hostname: Option<String>,
+    /// Expiry time in seconds.
+    pub expires: u64,
+}
+
+pub struct LeaseTable {
+    leases: HashMap<IpAddr, DHCPLease>,
+    /// Host names sent by the clients, by client identifier: the MAC
+    /// address for DHCP, the DUID for DHCPv6.
+    hostnames: HashMap<Vec<u8>, String>,
+    max_leases: usize,
+}
+
+impl LeaseTable {
+    pub fn new(max_leases: usize) -> LeaseTable {
+        LeaseTable {
+            leases: HashMap::new(),
+            hostnames: HashMap::new(),
+            max_leases: max_leases,
+        }
+    }
+
+    /// Record the host name sent by a client. The ACK or REPLY usually
+    /// does not repeat it.
+    pub fn set_hostname(&mut self, client: &[u8], hostname: &str) {
+        if !self.hostnames.contains_key(client) &&
+            self.hostnames.len() >= self.max_leases
+        {
+            return;
+        }
+        self.hostnames.insert(client.to_vec(), hostname.to_string());
+    }
+
+    /// Add or renew the lease of `addr` for `lease_time` seconds from
+    /// `now`. Returns false if the table is full.
+    pub fn add(&mut self, now: u64, addr: IpAddr, client: &[u8],
+               mac: Option<&[u8]>, hostname: Option<&str>,
+               lease_time: u32) -> bool
+    {
+        if !self.leases.contains_key(&addr) &&
+            self.leases.len() >= self.max_leases
+        {
+            self.expire(now);
+            if self.leases.len() >= self.max_leases {
+                return false;
+            }
+        }
+        if let Some(hostname) = hostname {
+            self.set_hostname(client, hostname);
+        }
+        let hostname = self.hostnames.get(client).cloned();
+        let expires = if lease_time == DHCP_INFINITE_LEASE_TIME {
+            std::u64::MAX
+        } else {
+            now + lease_time as u64
+        };
+        self.leases.insert(addr, DHCPLease {
+            client: client.to_vec(),
+            mac: mac.map(|mac| mac.to_vec()),
+            hostname: hostname,
+            expires: expires,
+        });
+        true
+    }
+
+    /// Remove the lease of `addr` released by a client. The lease is
+    /// kept if it belongs to another client.
+    pub fn release(&mut self, addr: &IpAddr, client: &[u8]) {
+        let owned = match self.leases.get(addr) {
+            Some(lease) => lease.client.as_slice() == client,
+            None => false,
+        };
+        if owned {
+            self.leases.remove(addr);
+        }
+    }
+
+    /// Return the lease of `addr`, if not expired at `now`.
+    pub fn lookup(&self, now: u64, addr: &IpAddr) -> Option<&DHCPLease> {
+        self.leases.get(addr).filter(|lease| lease.expires > now)
+    }
+
+    /// Remove the leases expired at `now`, and the host names of the
+    /// clients without lease.
+    pub fn expire(&mut self, now: u64) {
+        self.leases.retain(|_, lease| lease.expires > now);
+        let clients: HashSet<&Vec<u8>> = self.leases.values()
+            .map(|lease| &lease.client)
+            .collect();
+        self.hostnames.retain(|client, _| clients.contains(client));
+    }
+
+    pub fn len(&self) -> usize {
+        self.leases.len()
+    }
+}
+
+/// Convert an IPv4 or IPv6 address from a message. Returns None for the
+/// unspecified address.
+pub fn dhcp_lease_addr(bytes: &[u8]) -> Option<IpAddr> {
+    let addr = match bytes.len() {
+        4 => IpAddr::V4(Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3])),
+        16 => {
+            let mut octets = [0; 16];
+            octets.copy_from_slice(bytes);
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+        _ => return None,
+    };
+    if addr.is_unspecified() {
+        return None;
+    }
+    Some(addr)
+}
+
+static mut DHCP_LEASES: Option<Mutex<LeaseTable>> = None;
+
+/// Initialize the global lease table from the configuration. It is shared
+/// by DHCP and DHCPv6, so only the first call has an effect. A maximum
+/// of 0 leases disables the table.
+pub fn dhcp_leases_init() {
+    if dhcp_with_leases(|_| ()).is_some() {
+        return;
+    }
+    let mut max_leases = DHCP_DEFAULT_MAX_LEASES;
+    if let Some(val) = conf_get("app-layer.protocols.dhcp.max-leases") {
+        match val.parse::<usize>() {
+            Ok(val) => { max_leases = val; },
+            Err(_) => { SCLogWarning!("Invalid value for dhcp.max-leases: {}", val); },
+        }
+    }
+    if max_leases == 0 {
+        SCLogConfig!("DHCP lease table disabled");
+        return;
+    }
+    unsafe {
+        DHCP_LEASES = Some(Mutex::new(LeaseTable::new(max_leases)));
+    }
+}
+
+/// Run `f` with the global lease table locked. Returns None if the table
+/// is not initialized or disabled.
+pub fn dhcp_with_leases<T, F>(f: F) -> Option<T>
+    where F: FnOnce(&mut LeaseTable) -> T
+{
+    let leases = unsafe {
+        match DHCP_LEASES {
+            Some(ref leases) => leases,
+            None => return None,
+        }
+    };
+    match leases.lock() {
+        Ok(mut guard) => Some(f(&mut guard)),
+        Err(_) => None,
+    }
+}
+
+/// Return the lease of `addr` at time `now`, in seconds.
+pub fn dhcp_lease_lookup(addr: &IpAddr, now: u64) -> Option<DHCPLease> {
+    dhcp_with_leases(|leases| leases.lookup(now, addr).cloned()).unwrap_or(None)
+}
+
+/// Add the MAC address and host name leased with `addr` to a record, as
+/// `<prefix>_mac` and `<prefix>_hostname`.
+pub fn dhcp_lease_log(js: &Json, prefix: &str, addr: &IpAddr, now: u64) {
+    if let Some(lease) = dhcp_lease_lookup(addr, now) {
+        if let Some(ref mac) = lease.mac {
+            js.set_string(&format!("{}_mac", prefix), &format_addr_hex(mac));
+        }
+        if let Some(ref hostname) = lease.hostname {
+            js.set_string(&format!("{}_hostname", prefix), hostname);
+        }
+    }
+}
+
+fn parse_addr(addr: *const c_char) -> Option<IpAddr> {
+    if addr.is_null() {
+        return None;
+    }
+    let addr = unsafe { CStr::from_ptr(addr) };
+    addr.to_str().ok()?.parse::<IpAddr>().ok()
+}
+
+/// Add the MAC addresses and host names of the source and destination of
+/// a record, given as strings like the `src_ip` and `dest_ip` fields.
+#[no_mangle]
+pub extern "C" fn rs_dhcp_lease_log_json(js: *mut JsonT,
+                                         src_ip: *const c_char,
+                                         dest_ip: *const c_char,
+                                         now: u64)
+{
+    let js = Json { js: js };
+    if let Some(addr) = parse_addr(src_ip) {
+        dhcp_lease_log(&js, "src", &addr, now);
+    }
+    if let Some(addr) = parse_addr(dest_ip) {
+        dhcp_lease_log(&js, "dest", &addr, now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dhcp_lease_table() {
+        let mut table = LeaseTable::new(2);
+        let mac = [0x00, 0x0b, 0x82, 0x01, 0xfc, 0x42];
+        let addr: IpAddr = "192.168.0.10".parse().unwrap();
+
+        table.set_hostname(&mac, "laptop");
+        assert!(table.add(1000, addr, &mac, Some(&mac), None, 3600));
+        let lease = table.lookup(1000, &addr).unwrap();
+        assert_eq!(lease.mac, Some(mac.to_vec()));
+        assert_eq!(lease.hostname, Some("laptop".to_string()));
+        assert_eq!(lease.expires, 4600);
+        assert_eq!(table.lookup(4600, &addr), None);
+
+        // release by another client is ignored
+        table.release(&addr, &[0, 1, 2, 3, 4, 5]);
+        assert!(table.lookup(2000, &addr).is_some());
+        table.release(&addr, &mac);
+        assert_eq!(table.lookup(2000, &addr), None);
+    }
+
+    #[test]
+    fn test_dhcp_lease_table_full() {
+        let mut table = LeaseTable::new(2);
+        let addrs: Vec<IpAddr> = ["10.0.0.1", "10.0.0.2", "2001:db8::1"].iter()
+            .map(|a| a.parse().unwrap()).collect();
+        assert!(table.add(1000, addrs[0], b"a", None, Some("a"), 60));
+        assert!(table.add(1000, addrs[1], b"b", None, None, DHCP_INFINITE_LEASE_TIME));
+        assert!(!table.add(1030, addrs[2], b"c", None, None, 60));
+        // renewing an existing lease works when full, and keeps the
+        // host name
+        assert!(table.add(1030, addrs[0], b"a", None, None, 60));
+        assert_eq!(table.lookup(1030, &addrs[0]).unwrap().hostname,
+                   Some("a".to_string()));
+        assert!(table.add(1100, addrs[2], b"c", None, None, 60));
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.lookup(1100, &addrs[0]), None);
+        assert!(table.lookup(std::u64::MAX - 1, &addrs[1]).is_some());
+    }
+}
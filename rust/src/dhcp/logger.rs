@@ -15,27 +15,18 @@
  * 02110-1301, USA.
  */
 
-use std;
-use std::os::raw::c_void;
-
 use crate::dhcp::dhcp::*;
-use crate::dhcp::parser::{DHCPOptionWrapper,DHCPOptGeneric};
+use crate::dhcp::parser::{DHCPOptionWrapper,DHCPOptGeneric,DHCPOptRelayAgentInfo,DHCPRoute};
 use crate::dns::log::dns_print_addr;
 use crate::json::*;
 use crate::conf::ConfNode;
+use crate::applayer::Logger;
 
 pub struct DHCPLogger {
     extended: bool,
 }
 
 impl DHCPLogger {
-    
-    pub fn new(conf: ConfNode) -> DHCPLogger {
-        return DHCPLogger{
-            extended: conf.get_child_bool("extended"),
-        };
-    }
-
     fn get_type(&self, tx: &DHCPTransaction) -> Option<u8> {
         let options = &tx.message.options;
         for option in options {
@@ -75,7 +66,113 @@ impl DHCPLogger {
         return true;
     }
 
-    pub fn log(&self, tx: &DHCPTransaction) -> Option<Json> {
+    fn log_opt_type(&self, js: &Json, option: &DHCPOptGeneric) {
+        if option.data.len() > 0 {
+            let dhcp_type = match option.data[0] {
+                DHCP_TYPE_DISCOVER => "discover",
+                DHCP_TYPE_OFFER => "offer",
+                DHCP_TYPE_REQUEST => "request",
+                DHCP_TYPE_DECLINE => "decline",
+                DHCP_TYPE_ACK => "ack",
+                DHCP_TYPE_NAK => "nak",
+                DHCP_TYPE_RELEASE => "release",
+                DHCP_TYPE_INFORM => "inform",
+                _ => "unknown"
+            };
+            js.set_string("dhcp_type", dhcp_type);
+        }
+    }
+
+    fn log_opt_parameters(&self, js: &Json, option: &DHCPOptGeneric) {
+        let params = Json::array();
+        for i in &option.data {
+            let param = match *i {
+                DHCP_PARAM_SUBNET_MASK => "subnet_mask",
+                DHCP_PARAM_ROUTER => "router",
+                DHCP_PARAM_DNS_SERVER => "dns_server",
+                DHCP_PARAM_DOMAIN => "domain",
+                DHCP_PARAM_ARP_TIMEOUT => "arp_timeout",
+                DHCP_PARAM_NTP_SERVER => "ntp_server",
+                DHCP_PARAM_TFTP_SERVER_NAME => "tftp_server_name",
+                DHCP_PARAM_TFTP_SERVER_IP => "tftp_server_ip",
+                _ => ""
+            };
+            if param.len() > 0 {
+                params.array_append_string(param);
+            }
+        }
+        js.set("params", params);
+    }
+    
+    fn log_opt_dns_server(&self, js: &Json, option: &DHCPOptGeneric) {
+        let servers = Json::array();
+        for i in 0..(option.data.len() / 4) {
+            servers.array_append_string(&dns_print_addr(
+                &option.data[(i * 4)..(i * 4) + 4].to_vec()))
+        }
+        js.set("dns_servers", servers);
+    }
+    
+    fn log_opt_routers(&self, js: &Json, option: &DHCPOptGeneric) {
+        let routers = Json::array();
+        for i in 0..(option.data.len() / 4) {
+            routers.array_append_string(&dns_print_addr(
+                &option.data[(i * 4)..(i * 4) + 4].to_vec()))
+        }
+        js.set("routers", routers);
+    }
+
+    fn log_opt_relay_agent_info(&self, js: &Json, info: &DHCPOptRelayAgentInfo) {
+        let jsr = Json::object();
+        if let Some(ref circuit_id) = info.circuit_id {
+            jsr.set_string("circuit_id", &format_opaque(circuit_id));
+        }
+        if let Some(ref remote_id) = info.remote_id {
+            jsr.set_string("remote_id", &format_opaque(remote_id));
+        }
+        js.set("relay_agent", jsr);
+    }
+
+    fn log_opt_classless_routes(&self, js: &Json, routes: &Vec<DHCPRoute>) {
+        let jsa = Json::array();
+        for route in routes {
+            let jsr = Json::object();
+            jsr.set_string("destination", &format!("{}/{}",
+                dns_print_addr(&route.destination), route.prefix_len));
+            jsr.set_string("router", &dns_print_addr(&route.router));
+            jsa.array_append(jsr);
+        }
+        js.set("classless_routes", jsa);
+    }
+
+}
+
+pub fn format_addr_hex(input: &Vec<u8>) -> String {
+    let parts: Vec<String> = input.iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    return parts.join(":");
+}
+
+/// Format an identifier that is often, but not always, printable: as a
+/// string if it is printable, in hex otherwise.
+pub fn format_opaque(input: &Vec<u8>) -> String {
+    if input.len() > 0 && input.iter().all(|&b| b >= 0x20 && b < 0x7f) {
+        return String::from_utf8_lossy(input).into_owned();
+    }
+    return format_addr_hex(input);
+}
+
+impl Logger for DHCPLogger {
+    type Transaction = DHCPTransaction;
+
+    fn new(conf: ConfNode) -> DHCPLogger {
+        return DHCPLogger{
+            extended: conf.get_child_bool("extended"),
+        };
+    }
+
+    fn log(&self, tx: &DHCPTransaction) -> Option<Json> {
         if !self.do_log(tx) {
             return None;
         }
@@ -177,104 +274,76 @@ impl DHCPLogger {
                                 self.log_opt_routers(&js, option);
                             }
                         }
+                        DHCP_OPT_VENDOR_CLASS => {
+                            if option.data.len() > 0 {
+                                js.set_string_from_bytes("vendor_class",
+                                                         &option.data);
+                            }
+                        }
+                        DHCP_OPT_VENDOR_SPECIFIC => {
+                            if self.extended {
+                                js.set_string("vendor_specific",
+                                              &format_addr_hex(&option.data));
+                            }
+                        }
+                        DHCP_OPT_TFTP_SERVER_NAME => {
+                            if self.extended && option.data.len() > 0 {
+                                js.set_string_from_bytes("tftp_server_name",
+                                                         &option.data);
+                            }
+                        }
+                        DHCP_OPT_BOOTFILE_NAME => {
+                            if self.extended && option.data.len() > 0 {
+                                js.set_string_from_bytes("bootfile_name",
+                                                         &option.data);
+                            }
+                        }
                         _ => {}
                     }
                 }
+                &DHCPOptionWrapper::UserClass(ref classes) => {
+                    let jsa = Json::array();
+                    for class in classes {
+                        jsa.array_append(Json::string_from_bytes(class));
+                    }
+                    js.set("user_class", jsa);
+                }
+                &DHCPOptionWrapper::ClientFqdn(ref fqdn) => {
+                    js.set_string_from_bytes("client_fqdn", &fqdn.name);
+                }
+                &DHCPOptionWrapper::RelayAgentInfo(ref info) => {
+                    if self.extended {
+                        self.log_opt_relay_agent_info(&js, info);
+                    }
+                }
+                &DHCPOptionWrapper::ClasslessRoutes(ref routes) => {
+                    if self.extended {
+                        self.log_opt_classless_routes(&js, routes);
+                    }
+                }
+                &DHCPOptionWrapper::DomainSearch(ref names) => {
+                    if self.extended {
+                        let jsa = Json::array();
+                        for name in names {
+                            jsa.array_append(Json::string_from_bytes(name));
+                        }
+                        js.set("domain_search", jsa);
+                    }
+                }
                 _ => {}
             }
         }
-        
-        return Some(js);
-    }
 
-    fn log_opt_type(&self, js: &Json, option: &DHCPOptGeneric) {
-        if option.data.len() > 0 {
-            let dhcp_type = match option.data[0] {
-                DHCP_TYPE_DISCOVER => "discover",
-                DHCP_TYPE_OFFER => "offer",
-                DHCP_TYPE_REQUEST => "request",
-                DHCP_TYPE_DECLINE => "decline",
-                DHCP_TYPE_ACK => "ack",
-                DHCP_TYPE_NAK => "nak",
-                DHCP_TYPE_RELEASE => "release",
-                DHCP_TYPE_INFORM => "inform",
-                _ => "unknown"
-            };
-            js.set_string("dhcp_type", dhcp_type);
+        if let Some(ref fingerprint) = tx.fingerprint {
+            js.set_string("fingerprint", fingerprint);
         }
-    }
-
-    fn log_opt_parameters(&self, js: &Json, option: &DHCPOptGeneric) {
-        let params = Json::array();
-        for i in &option.data {
-            let param = match *i {
-                DHCP_PARAM_SUBNET_MASK => "subnet_mask",
-                DHCP_PARAM_ROUTER => "router",
-                DHCP_PARAM_DNS_SERVER => "dns_server",
-                DHCP_PARAM_DOMAIN => "domain",
-                DHCP_PARAM_ARP_TIMEOUT => "arp_timeout",
-                DHCP_PARAM_NTP_SERVER => "ntp_server",
-                DHCP_PARAM_TFTP_SERVER_NAME => "tftp_server_name",
-                DHCP_PARAM_TFTP_SERVER_IP => "tftp_server_ip",
-                _ => ""
-            };
-            if param.len() > 0 {
-                params.array_append_string(param);
-            }
+        if let Some(ref device) = tx.device {
+            js.set_string("os_family", &device.os_family);
+            js.set_string("device_type", &device.device_type);
         }
-        js.set("params", params);
-    }
-    
-    fn log_opt_dns_server(&self, js: &Json, option: &DHCPOptGeneric) {
-        let servers = Json::array();
-        for i in 0..(option.data.len() / 4) {
-            servers.array_append_string(&dns_print_addr(
-                &option.data[(i * 4)..(i * 4) + 4].to_vec()))
-        }
-        js.set("dns_servers", servers);
-    }
-    
-    fn log_opt_routers(&self, js: &Json, option: &DHCPOptGeneric) {
-        let routers = Json::array();
-        for i in 0..(option.data.len() / 4) {
-            routers.array_append_string(&dns_print_addr(
-                &option.data[(i * 4)..(i * 4) + 4].to_vec()))
-        }
-        js.set("routers", routers);
+        
+        return Some(js);
     }
-
-}
-
-fn format_addr_hex(input: &Vec<u8>) -> String {
-    let parts: Vec<String> = input.iter()
-        .map(|b| format!("{:02x}", b))
-        .collect();
-    return parts.join(":");
 }
 
-#[no_mangle]
-pub extern "C" fn rs_dhcp_logger_new(conf: *const c_void) -> *mut std::os::raw::c_void {
-    let conf = ConfNode::wrap(conf);
-    let boxed = Box::new(DHCPLogger::new(conf));
-    return unsafe{std::mem::transmute(boxed)};
-}
-
-#[no_mangle]
-pub extern "C" fn rs_dhcp_logger_free(logger: *mut std::os::raw::c_void) {
-    let _: Box<DHCPLogger> = unsafe{std::mem::transmute(logger)};
-}
-
-#[no_mangle]
-pub extern "C" fn rs_dhcp_logger_log(logger: *mut std::os::raw::c_void,
-                                     tx: *mut std::os::raw::c_void) -> *mut JsonT {
-    let logger = cast_pointer!(logger, DHCPLogger);
-    let tx = cast_pointer!(tx, DHCPTransaction);
-    match logger.log(tx) {
-        Some(js) => {
-            return js.unwrap();
-        }
-        _ => {
-            return std::ptr::null_mut();
-        }
-    }
-}
+export_logger!(DHCPLogger, new: rs_dhcp_logger_new, free: rs_dhcp_logger_free, log: rs_dhcp_logger_log);
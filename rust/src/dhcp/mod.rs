@@ -18,3 +18,10 @@
 pub mod dhcp;
 pub mod parser;
 pub mod logger;
+pub mod fingerprint;
+pub mod context;
+pub mod detect;
+pub mod dhcpv6;
+pub mod dhcpv6_parser;
+pub mod dhcpv6_logger;
+pub mod lease;
@@ -18,6 +18,7 @@
 use std::cmp::min;
 
 use crate::dhcp::dhcp::*;
+use crate::dns::parser::dns_parse_name;
 use nom::*;
 
 pub struct DHCPMessage {
@@ -34,6 +35,28 @@ pub struct DHCPMessage {
     pub truncated_options: bool,
 }
 
+impl DHCPMessage {
+    /// Return the data of the first generic option with the given code.
+    pub fn get_generic_option(&self, code: u8) -> Option<&[u8]> {
+        for option in &self.options {
+            if option.code == code {
+                if let DHCPOptionWrapper::Generic(ref option) = option.option {
+                    return Some(&option.data);
+                }
+            }
+        }
+        None
+    }
+
+    /// Return the DHCP message type.
+    pub fn get_type(&self) -> Option<u8> {
+        match self.get_generic_option(DHCP_OPT_TYPE) {
+            Some(data) if data.len() > 0 => Some(data[0]),
+            _ => None,
+        }
+    }
+}
+
 pub struct DHCPHeader {
     pub opcode: u8,
     pub htype: u8,
@@ -66,10 +89,39 @@ pub struct DHCPOptGeneric {
     pub data: Vec<u8>,
 }
 
+/// Client FQDN option (81), RFC 4702.
+pub struct DHCPOptClientFqdn {
+    pub flags: u8,
+    pub name: Vec<u8>,
+}
+
+/// Relay agent information option (82), RFC 3046.
+pub struct DHCPOptRelayAgentInfo {
+    pub circuit_id: Option<Vec<u8>>,
+    pub remote_id: Option<Vec<u8>>,
+}
+
+/// A route of the classless static route options (121 and 249),
+/// RFC 3442.
+pub struct DHCPRoute {
+    pub destination: Vec<u8>,
+    pub prefix_len: u8,
+    pub router: Vec<u8>,
+}
+
 pub enum DHCPOptionWrapper {
     ClientId(DHCPOptClientId),
     TimeValue(DHCPOptTimeValue),
     Generic(DHCPOptGeneric),
+    /// Option 77: list of user classes.
+    UserClass(Vec<Vec<u8>>),
+    ClientFqdn(DHCPOptClientFqdn),
+    RelayAgentInfo(DHCPOptRelayAgentInfo),
+    ClasslessRoutes(Vec<DHCPRoute>),
+    /// Option 119: list of domain names.
+    DomainSearch(Vec<Vec<u8>>),
+    /// An option that could not be decoded.
+    Malformed(DHCPOptGeneric),
     End,
 }
 
@@ -169,6 +221,141 @@ named!(pub parse_generic_option<DHCPOption>,
            ))
 );
 
+/// Parse the user class option. RFC 3004 defines it as a list of
+/// length prefixed classes, but some clients send a single string, so
+/// fall back to that if the data is not a valid list.
+fn decode_user_class(data: &[u8]) -> Option<DHCPOptionWrapper> {
+    let mut classes = Vec::new();
+    let mut rem = data;
+    while rem.len() > 0 {
+        let len = rem[0] as usize;
+        if len == 0 || len + 1 > rem.len() {
+            return Some(DHCPOptionWrapper::UserClass(vec![data.to_vec()]));
+        }
+        classes.push(rem[1..len + 1].to_vec());
+        rem = &rem[len + 1..];
+    }
+    Some(DHCPOptionWrapper::UserClass(classes))
+}
+
+/// Parse the client FQDN option. The name is in DNS wire format when the
+/// E flag is set, and in ASCII otherwise.
+fn decode_client_fqdn(data: &[u8]) -> Option<DHCPOptionWrapper> {
+    if data.len() < 3 {
+        return None;
+    }
+    let flags = data[0];
+    let name = &data[3..];
+    let name = if flags & DHCP_FQDN_FLAG_E != 0 {
+        match dns_parse_name(name, name) {
+            Ok((_, name)) => name,
+            Err(_) => { return None; }
+        }
+    } else {
+        name.to_vec()
+    };
+    Some(DHCPOptionWrapper::ClientFqdn(DHCPOptClientFqdn{
+        flags: flags,
+        name: name,
+    }))
+}
+
+/// Parse the sub-options of the relay agent information option.
+fn decode_relay_agent_info(data: &[u8]) -> Option<DHCPOptionWrapper> {
+    let mut info = DHCPOptRelayAgentInfo{
+        circuit_id: None,
+        remote_id: None,
+    };
+    let mut rem = data;
+    while rem.len() > 0 {
+        if rem.len() < 2 || rem[1] as usize + 2 > rem.len() {
+            return None;
+        }
+        let value = rem[2..rem[1] as usize + 2].to_vec();
+        match rem[0] {
+            DHCP_RELAY_AGENT_CIRCUIT_ID => { info.circuit_id = Some(value); }
+            DHCP_RELAY_AGENT_REMOTE_ID => { info.remote_id = Some(value); }
+            _ => {}
+        }
+        rem = &rem[rem[1] as usize + 2..];
+    }
+    Some(DHCPOptionWrapper::RelayAgentInfo(info))
+}
+
+/// Parse the classless static routes. Each route is the prefix length,
+/// the significant octets of the destination, and the router.
+fn decode_classless_routes(data: &[u8]) -> Option<DHCPOptionWrapper> {
+    let mut routes = Vec::new();
+    let mut rem = data;
+    while rem.len() > 0 {
+        let prefix_len = rem[0];
+        if prefix_len > 32 {
+            return None;
+        }
+        let significant = (prefix_len as usize + 7) / 8;
+        if rem.len() < 1 + significant + 4 {
+            return None;
+        }
+        let mut destination = rem[1..1 + significant].to_vec();
+        destination.resize(4, 0);
+        routes.push(DHCPRoute{
+            destination: destination,
+            prefix_len: prefix_len,
+            router: rem[1 + significant..1 + significant + 4].to_vec(),
+        });
+        rem = &rem[1 + significant + 4..];
+    }
+    Some(DHCPOptionWrapper::ClasslessRoutes(routes))
+}
+
+/// Parse the domain search list, a list of DNS encoded names where
+/// compression pointers are relative to the start of the option.
+fn decode_domain_search(data: &[u8]) -> Option<DHCPOptionWrapper> {
+    let mut names = Vec::new();
+    let mut rem = data;
+    while rem.len() > 0 {
+        match dns_parse_name(rem, data) {
+            Ok((next, name)) => {
+                names.push(name);
+                rem = next;
+            }
+            Err(_) => { return None; }
+        }
+    }
+    Some(DHCPOptionWrapper::DomainSearch(names))
+}
+
+/// Parse an option with a decoder for its value. If the value cannot be
+/// decoded, the option is returned as malformed.
+fn parse_decoded_option(i: &[u8]) -> IResult<&[u8], DHCPOption> {
+    let (rem, (code, data)) = do_parse!(i,
+        code: be_u8 >>
+        len: be_u8 >>
+        data: take!(len) >>
+        ((code, data))
+    )?;
+    let option = match code {
+        DHCP_OPT_USER_CLASS => decode_user_class(data),
+        DHCP_OPT_CLIENT_FQDN => decode_client_fqdn(data),
+        DHCP_OPT_RELAY_AGENT_INFO => decode_relay_agent_info(data),
+        DHCP_OPT_CLASSLESS_ROUTES |
+        DHCP_OPT_MS_CLASSLESS_ROUTES => decode_classless_routes(data),
+        DHCP_OPT_DOMAIN_SEARCH => decode_domain_search(data),
+        _ => None,
+    };
+    let option = match option {
+        Some(option) => option,
+        None => DHCPOptionWrapper::Malformed(DHCPOptGeneric{
+            data: data.to_vec(),
+        }),
+    };
+    Ok((rem, DHCPOption{
+        code: code,
+        data: None,
+        option: option,
+    }))
+}
+
 // Parse a single DHCP option. When option 255 (END) is parsed, the remaining
 // data will be consumed.
 named!(pub parse_option<DHCPOption>,
@@ -187,6 +374,12 @@ named!(pub parse_option<DHCPOption>,
                DHCP_OPT_ADDRESS_TIME => call!(parse_address_time_option) |
                DHCP_OPT_RENEWAL_TIME => call!(parse_address_time_option) |
                DHCP_OPT_REBINDING_TIME => call!(parse_address_time_option) |
+               DHCP_OPT_USER_CLASS => call!(parse_decoded_option) |
+               DHCP_OPT_CLIENT_FQDN => call!(parse_decoded_option) |
+               DHCP_OPT_RELAY_AGENT_INFO => call!(parse_decoded_option) |
+               DHCP_OPT_DOMAIN_SEARCH => call!(parse_decoded_option) |
+               DHCP_OPT_CLASSLESS_ROUTES => call!(parse_decoded_option) |
+               DHCP_OPT_MS_CLASSLESS_ROUTES => call!(parse_decoded_option) |
                _ => call!(parse_generic_option)
        ));
 
@@ -199,12 +392,15 @@ pub fn dhcp_parse(input: &[u8]) -> IResult<&[u8], DHCPMessage> {
         Ok((rem, header)) => {
             let mut options = Vec::new();
             let mut next = rem;
-            let malformed_options = false;
+            let mut malformed_options = false;
             let mut truncated_options = false;
             loop {
                 match parse_option(next) {
                     Ok((rem, option)) => {
                         let done = option.code == DHCP_OPT_END;
+                        if let DHCPOptionWrapper::Malformed(_) = option.option {
+                            malformed_options = true;
+                        }
                         options.push(option);
                         next = rem;
                         if done {
@@ -314,4 +510,155 @@ mod tests {
             _ => { panic!("failed"); }
         }
     }
+
+    #[test]
+    fn test_parse_client_fqdn() {
+        // E flag set, name in DNS wire format.
+        let buf: &[u8] = &[
+            0x51, 0x0c, 0x05, 0x00, 0x00,
+            0x04, b'h', b'o', b's', b't', 0x02, b'l', b'n', 0x00,
+        ];
+        match parse_option(buf) {
+            Ok((rem, option)) => {
+                assert_eq!(rem.len(), 0);
+                match option.option {
+                    DHCPOptionWrapper::ClientFqdn(fqdn) => {
+                        assert_eq!(fqdn.flags, DHCP_FQDN_FLAG_E | DHCP_FQDN_FLAG_S);
+                        assert_eq!(fqdn.name, b"host.ln");
+                    }
+                    _ => { panic!("not a client fqdn"); }
+                }
+            }
+            _ => { panic!("failed"); }
+        }
+
+        // ASCII name.
+        let buf: &[u8] = &[0x51, 0x06, 0x00, 0xff, 0xff, b'p', b'c', b'1'];
+        match parse_option(buf) {
+            Ok((_, option)) => {
+                match option.option {
+                    DHCPOptionWrapper::ClientFqdn(fqdn) => {
+                        assert_eq!(fqdn.name, b"pc1");
+                    }
+                    _ => { panic!("not a client fqdn"); }
+                }
+            }
+            _ => { panic!("failed"); }
+        }
+    }
+
+    #[test]
+    fn test_parse_relay_agent_info() {
+        let buf: &[u8] = &[
+            0x52, 0x0e,
+            0x01, 0x04, b'e', b't', b'h', b'1',
+            0x02, 0x06, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55,
+        ];
+        match parse_option(buf) {
+            Ok((_, option)) => {
+                match option.option {
+                    DHCPOptionWrapper::RelayAgentInfo(info) => {
+                        assert_eq!(info.circuit_id, Some(b"eth1".to_vec()));
+                        assert_eq!(info.remote_id,
+                                   Some(vec![0x00, 0x11, 0x22, 0x33, 0x44, 0x55]));
+                    }
+                    _ => { panic!("not relay agent info"); }
+                }
+            }
+            _ => { panic!("failed"); }
+        }
+
+        // Truncated sub-option.
+        let buf: &[u8] = &[0x52, 0x04, 0x01, 0x08, b'e', b't'];
+        match parse_option(buf) {
+            Ok((_, option)) => {
+                match option.option {
+                    DHCPOptionWrapper::Malformed(_) => {}
+                    _ => { panic!("not malformed"); }
+                }
+            }
+            _ => { panic!("failed"); }
+        }
+    }
+
+    #[test]
+    fn test_parse_classless_routes() {
+        // 10.0.0.0/8 via 192.168.1.1, 0.0.0.0/0 via 192.168.1.254
+        let buf: &[u8] = &[
+            0x79, 0x0b,
+            0x08, 0x0a, 0xc0, 0xa8, 0x01, 0x01,
+            0x00, 0xc0, 0xa8, 0x01, 0xfe,
+        ];
+        match parse_option(buf) {
+            Ok((_, option)) => {
+                match option.option {
+                    DHCPOptionWrapper::ClasslessRoutes(routes) => {
+                        assert_eq!(routes.len(), 2);
+                        assert_eq!(routes[0].destination, &[10, 0, 0, 0]);
+                        assert_eq!(routes[0].prefix_len, 8);
+                        assert_eq!(routes[0].router, &[192, 168, 1, 1]);
+                        assert_eq!(routes[1].destination, &[0, 0, 0, 0]);
+                        assert_eq!(routes[1].prefix_len, 0);
+                    }
+                    _ => { panic!("not classless routes"); }
+                }
+            }
+            _ => { panic!("failed"); }
+        }
+    }
+
+    #[test]
+    fn test_parse_domain_search() {
+        // example.com and corp.example.com, using a compression pointer.
+        let buf: &[u8] = &[
+            0x77, 0x14,
+            0x07, b'e', b'x', b'a', b'm', b'p', b'l', b'e',
+            0x03, b'c', b'o', b'm', 0x00,
+            0x04, b'c', b'o', b'r', b'p', 0xc0, 0x00,
+        ];
+        match parse_option(buf) {
+            Ok((rem, option)) => {
+                assert_eq!(rem.len(), 0);
+                match option.option {
+                    DHCPOptionWrapper::DomainSearch(names) => {
+                        assert_eq!(names.len(), 2);
+                        assert_eq!(names[0], b"example.com");
+                        assert_eq!(names[1], b"corp.example.com");
+                    }
+                    _ => { panic!("not a domain search list"); }
+                }
+            }
+            _ => { panic!("failed"); }
+        }
+    }
+
+    #[test]
+    fn test_parse_user_class() {
+        let buf: &[u8] = &[0x4d, 0x08, 0x03, b'a', b'b', b'c', 0x03, b'x', b'y', b'z'];
+        match parse_option(buf) {
+            Ok((_, option)) => {
+                match option.option {
+                    DHCPOptionWrapper::UserClass(classes) => {
+                        assert_eq!(classes, vec![b"abc".to_vec(), b"xyz".to_vec()]);
+                    }
+                    _ => { panic!("not a user class"); }
+                }
+            }
+            _ => { panic!("failed"); }
+        }
+
+        // Not a list, as sent by some clients.
+        let buf: &[u8] = &[0x4d, 0x04, b'i', b'p', b'x', b'e'];
+        match parse_option(buf) {
+            Ok((_, option)) => {
+                match option.option {
+                    DHCPOptionWrapper::UserClass(classes) => {
+                        assert_eq!(classes, vec![b"ipxe".to_vec()]);
+                    }
+                    _ => { panic!("not a user class"); }
+                }
+            }
+            _ => { panic!("failed"); }
+        }
+    }
 }
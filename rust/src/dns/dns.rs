@@ -21,7 +21,7 @@ use std;
 use std::mem::transmute;
 
 use crate::log::*;
-use crate::applayer::LoggerFlags;
+use crate::applayer::{AppLayerEvent, LoggerFlags};
 use crate::core;
 use crate::dns::parser;
 
@@ -123,13 +123,18 @@ pub const DNS_RCODE_BADTRUNC: u16 = 22;
 const MAX_TRANSACTIONS: usize = 32;
 
 #[repr(u32)]
+#[derive(AppLayerEvent)]
 pub enum DNSEvent {
+    #[name("unsollicited_response")]
     UnsolicitedResponse = 0,
     MalformedData,
+    #[name("not_a_request")]
     NotRequest,
+    #[name("not_a_response")]
     NotResponse,
     ZFlagSet,
     Flooded,
+    #[name("state_memcap_reached")]
     StateMemCapReached,
 }
 
@@ -759,6 +764,24 @@ pub extern "C" fn rs_dns_state_get_events(tx: *mut std::os::raw::c_void)
     return tx.events;
 }
 
+#[no_mangle]
+pub extern "C" fn rs_dns_state_get_event_info(event_name: *const std::os::raw::c_char,
+                                              event_id: *mut std::os::raw::c_int,
+                                              event_type: *mut core::AppLayerEventType)
+                                              -> std::os::raw::c_int
+{
+    DNSEvent::get_event_info(event_name, event_id, event_type)
+}
+
+#[no_mangle]
+pub extern "C" fn rs_dns_state_get_event_info_by_id(event_id: std::os::raw::c_int,
+                                                    event_name: *mut *const std::os::raw::c_char,
+                                                    event_type: *mut core::AppLayerEventType)
+                                                    -> std::os::raw::c_int
+{
+    DNSEvent::get_event_info_by_id(event_id, event_name, event_type) as std::os::raw::c_int
+}
+
 #[no_mangle]
 pub extern "C" fn rs_dns_tx_get_query_name(tx: &mut DNSTransaction,
                                        i: u16,
@@ -869,7 +892,8 @@ pub extern "C" fn rs_dns_probe_tcp(direction: u8,
 #[cfg(test)]
 mod tests {
 
-    use crate::dns::dns::DNSState;
+    use crate::applayer::AppLayerEvent;
+    use crate::dns::dns::{DNSEvent, DNSState};
 
     #[test]
     fn test_dns_parse_request_tcp_valid() {
@@ -1014,4 +1038,18 @@ mod tests {
         let mut state = DNSState::new();
         assert_eq!(0, state.parse_response_tcp(&request));
     }
+
+    #[test]
+    fn test_dns_event_names() {
+        // The names used by rules/dns-events.rules.
+        let names = ["unsollicited_response", "malformed_data", "not_a_request",
+                     "not_a_response", "z_flag_set", "flooded",
+                     "state_memcap_reached"];
+        for (id, name) in names.iter().enumerate() {
+            let event = DNSEvent::from_name(name).unwrap();
+            assert_eq!(event.id(), id as i32);
+            assert_eq!(event.to_cstring(), format!("{}\0", name));
+        }
+        assert!(DNSEvent::from_name("unsolicited_response").is_none());
+    }
 }
@@ -32,29 +32,14 @@ pub extern "C" fn rs_dns_lua_get_tx_id(clua: &mut CLuaState,
     lua.pushinteger(tx.tx_id() as i64);
 }
 
-#[no_mangle]
-pub extern "C" fn rs_dns_lua_get_rrname(clua: &mut CLuaState,
-                                        tx: &mut DNSTransaction)
-                                        -> c_int
-{
-    let lua = LuaState{
-        lua: clua,
+export_tx_lua_string!(rs_dns_lua_get_rrname, DNSTransaction, |tx| {
+    let queries = match (&tx.request, &tx.response) {
+        (&Some(ref request), _) => &request.queries,
+        (&None, &Some(ref response)) => &response.queries,
+        (&None, &None) => return None,
     };
-
-    if let &Some(ref request) = &tx.request {
-        for query in &request.queries {
-            lua.pushstring(&String::from_utf8_lossy(&query.name));
-            return 1;
-        }
-    } else if let &Some(ref response) = &tx.response {
-        for query in &response.queries {
-            lua.pushstring(&String::from_utf8_lossy(&query.name));
-            return 1;
-        }
-    }
-
-    return 0;
-}
+    queries.first().map(|query| &query.name[..])
+});
 
 #[no_mangle]
 pub extern "C" fn rs_dns_lua_get_query_table(clua: &mut CLuaState,
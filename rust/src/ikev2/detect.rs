@@ -0,0 +1,156 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+//! IKE detection keyword support
+
+use crate::ikev2::ipsec_parser::IkeV2Transform;
+use crate::ikev2::ikev2::{IKEV2State,IKEV2Transaction};
+
+/// Return the transform type (RFC 7296 section 3.3.2) and id of a
+/// transform.
+fn transform_type_and_id(xform: &IkeV2Transform) -> Option<(u8, u32)> {
+    match *xform {
+        IkeV2Transform::Encryption(ref e) => Some((1, e.0 as u32)),
+        IkeV2Transform::PRF(ref p)        => Some((2, p.0 as u32)),
+        IkeV2Transform::Auth(ref a)       => Some((3, a.0 as u32)),
+        IkeV2Transform::DH(ref dh)        => Some((4, dh.0 as u32)),
+        IkeV2Transform::ESN(ref e)        => Some((5, e.0 as u32)),
+        _                                 => None,
+    }
+}
+
+fn transforms_contain(transforms: &Vec<Vec<IkeV2Transform>>,
+                      xform_type: u8, value: u32) -> bool
+{
+    transforms.iter().flat_map(|p| p.iter()).any(|t| {
+        transform_type_and_id(t) == Some((xform_type, value))
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn rs_ikev2_tx_get_exch_type(tx: &mut IKEV2Transaction,
+                                            exch_type: *mut u32) -> u8
+{
+    unsafe {
+        *exch_type = tx.hdr.exch_type.0 as u32;
+    }
+    1
+}
+
+#[no_mangle]
+pub extern "C" fn rs_ikev2_tx_get_init_spi(tx: &mut IKEV2Transaction,
+                                           spi: *mut u64) -> u8
+{
+    unsafe {
+        *spi = tx.hdr.init_spi;
+    }
+    1
+}
+
+#[no_mangle]
+pub extern "C" fn rs_ikev2_tx_get_resp_spi(tx: &mut IKEV2Transaction,
+                                           spi: *mut u64) -> u8
+{
+    // the responder SPI is 0 in the first message
+    if tx.hdr.resp_spi == 0 {
+        return 0;
+    }
+    unsafe {
+        *spi = tx.hdr.resp_spi;
+    }
+    1
+}
+
+/// Get the `i`th notify type of the transaction.
+#[no_mangle]
+pub extern "C" fn rs_ikev2_tx_get_notify(tx: &mut IKEV2Transaction,
+                                         i: u32,
+                                         notify_type: *mut u32) -> u8
+{
+    match tx.notify_types.get(i as usize) {
+        Some(n) => {
+            unsafe {
+                *notify_type = n.0 as u32;
+            }
+            1
+        },
+        None => 0,
+    }
+}
+
+/// Get the number of proposals of the initiator. For IKEv1, the proposals
+/// of the transaction are counted.
+#[no_mangle]
+pub extern "C" fn rs_ikev2_state_get_nb_proposals(state: &mut IKEV2State,
+                                                  tx: &mut IKEV2Transaction,
+                                                  nb: *mut u32) -> u8
+{
+    let count = match tx.ikev1 {
+        Some(ref info) => info.proposals.len(),
+        None => {
+            if state.client_transforms.len() == 0 {
+                return 0;
+            }
+            state.client_transforms.len()
+        },
+    };
+    unsafe {
+        *nb = count as u32;
+    }
+    1
+}
+
+/// Check if the responder selected the transform `value` of type
+/// `xform_type`.
+#[no_mangle]
+pub extern "C" fn rs_ikev2_state_has_chosen_transform(state: &mut IKEV2State,
+                                                      xform_type: u8,
+                                                      value: u32) -> u8
+{
+    transforms_contain(&state.server_transforms, xform_type, value) as u8
+}
+
+/// Check if the initiator proposed the transform `value` of type
+/// `xform_type` in any of its proposals.
+#[no_mangle]
+pub extern "C" fn rs_ikev2_state_has_proposed_transform(state: &mut IKEV2State,
+                                                        xform_type: u8,
+                                                        value: u32) -> u8
+{
+    transforms_contain(&state.client_transforms, xform_type, value) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ikev2::ipsec_parser::*;
+
+    #[test]
+    fn test_transforms_contain() {
+        let transforms = vec![
+            vec![IkeV2Transform::Encryption(IkeTransformEncType::ENCR_3DES),
+                 IkeV2Transform::DH(IkeTransformDHType::Modp1024)],
+            vec![IkeV2Transform::Encryption(IkeTransformEncType::ENCR_AES_CBC),
+                 IkeV2Transform::DH(IkeTransformDHType::Modp2048)],
+        ];
+        assert!(transforms_contain(&transforms, 1, 3));
+        assert!(transforms_contain(&transforms, 1, 12));
+        assert!(transforms_contain(&transforms, 4, 14));
+        assert!(!transforms_contain(&transforms, 4, 5));
+        assert!(!transforms_contain(&transforms, 2, 3));
+    }
+}
@@ -0,0 +1,393 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+//! IKEv1 (ISAKMP, RFC 2408/2409) payload parsing.
+//!
+//! The ISAKMP header has the same layout as the IKEv2 one, so it is parsed
+//! using `parse_ikev2_header`. Only the payloads of unencrypted messages can
+//! be parsed, which covers the SA negotiation of main and aggressive mode,
+//! and the identities of aggressive mode.
+
+use nom::*;
+
+// Exchange types (RFC 2408 section 3.1, RFC 2409 section 5)
+pub const IKEV1_EXCH_BASE: u8 = 1;
+pub const IKEV1_EXCH_MAIN_MODE: u8 = 2;
+pub const IKEV1_EXCH_AUTH_ONLY: u8 = 3;
+pub const IKEV1_EXCH_AGGRESSIVE: u8 = 4;
+pub const IKEV1_EXCH_INFORMATIONAL: u8 = 5;
+pub const IKEV1_EXCH_QUICK_MODE: u8 = 32;
+
+// Header flags
+pub const IKEV1_FLAG_ENCRYPTION: u8 = 0x01;
+pub const IKEV1_FLAG_COMMIT: u8 = 0x02;
+pub const IKEV1_FLAG_AUTH_ONLY: u8 = 0x04;
+
+// Payload types (RFC 2408 section 3.1, RFC 3947)
+pub const IKEV1_PAYLOAD_NONE: u8 = 0;
+pub const IKEV1_PAYLOAD_SA: u8 = 1;
+pub const IKEV1_PAYLOAD_PROPOSAL: u8 = 2;
+pub const IKEV1_PAYLOAD_TRANSFORM: u8 = 3;
+pub const IKEV1_PAYLOAD_KE: u8 = 4;
+pub const IKEV1_PAYLOAD_ID: u8 = 5;
+pub const IKEV1_PAYLOAD_CERT: u8 = 6;
+pub const IKEV1_PAYLOAD_CERT_REQUEST: u8 = 7;
+pub const IKEV1_PAYLOAD_HASH: u8 = 8;
+pub const IKEV1_PAYLOAD_SIGNATURE: u8 = 9;
+pub const IKEV1_PAYLOAD_NONCE: u8 = 10;
+pub const IKEV1_PAYLOAD_NOTIFY: u8 = 11;
+pub const IKEV1_PAYLOAD_DELETE: u8 = 12;
+pub const IKEV1_PAYLOAD_VENDOR_ID: u8 = 13;
+pub const IKEV1_PAYLOAD_NAT_D: u8 = 20;
+pub const IKEV1_PAYLOAD_NAT_OA: u8 = 21;
+
+// Identification types (RFC 2407 section 4.6.2.1)
+pub const IKEV1_ID_IPV4_ADDR: u8 = 1;
+pub const IKEV1_ID_FQDN: u8 = 2;
+pub const IKEV1_ID_USER_FQDN: u8 = 3;
+pub const IKEV1_ID_IPV6_ADDR: u8 = 5;
+pub const IKEV1_ID_DER_ASN1_DN: u8 = 9;
+pub const IKEV1_ID_KEY_ID: u8 = 11;
+
+/// Return a printable name for an IKEv1 payload type.
+pub fn ikev1_payload_type_string(payload_type: u8) -> String {
+    match payload_type {
+        IKEV1_PAYLOAD_SA           => "SecurityAssociation".to_string(),
+        IKEV1_PAYLOAD_PROPOSAL     => "Proposal".to_string(),
+        IKEV1_PAYLOAD_TRANSFORM    => "Transform".to_string(),
+        IKEV1_PAYLOAD_KE           => "KeyExchange".to_string(),
+        IKEV1_PAYLOAD_ID           => "Identification".to_string(),
+        IKEV1_PAYLOAD_CERT         => "Certificate".to_string(),
+        IKEV1_PAYLOAD_CERT_REQUEST => "CertificateRequest".to_string(),
+        IKEV1_PAYLOAD_HASH         => "Hash".to_string(),
+        IKEV1_PAYLOAD_SIGNATURE    => "Signature".to_string(),
+        IKEV1_PAYLOAD_NONCE        => "Nonce".to_string(),
+        IKEV1_PAYLOAD_NOTIFY       => "Notification".to_string(),
+        IKEV1_PAYLOAD_DELETE       => "Delete".to_string(),
+        IKEV1_PAYLOAD_VENDOR_ID    => "VendorID".to_string(),
+        IKEV1_PAYLOAD_NAT_D        => "NatDiscovery".to_string(),
+        IKEV1_PAYLOAD_NAT_OA       => "NatOriginalAddress".to_string(),
+        _                          => format!("Unknown({})", payload_type),
+    }
+}
+
+/// Return a printable name for a phase 1 SA attribute type
+/// (RFC 2409 appendix A).
+pub fn ikev1_attribute_type_string(attr_type: u16) -> String {
+    match attr_type {
+        1  => "encryption_algorithm".to_string(),
+        2  => "hash_algorithm".to_string(),
+        3  => "authentication_method".to_string(),
+        4  => "group_description".to_string(),
+        5  => "group_type".to_string(),
+        11 => "life_type".to_string(),
+        12 => "life_duration".to_string(),
+        13 => "prf".to_string(),
+        14 => "key_length".to_string(),
+        _  => format!("attribute_{}", attr_type),
+    }
+}
+
+/// Return a printable name for an identification type. The values are
+/// shared by IKEv1 and IKEv2 (RFC 7296 section 3.5).
+pub fn identity_type_string(id_type: u8) -> String {
+    match id_type {
+        IKEV1_ID_IPV4_ADDR    => "ipv4_addr".to_string(),
+        IKEV1_ID_FQDN         => "fqdn".to_string(),
+        IKEV1_ID_USER_FQDN    => "user_fqdn".to_string(),
+        IKEV1_ID_IPV6_ADDR    => "ipv6_addr".to_string(),
+        IKEV1_ID_DER_ASN1_DN  => "der_asn1_dn".to_string(),
+        IKEV1_ID_KEY_ID       => "key_id".to_string(),
+        _                     => format!("{}", id_type),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct IkeV1PayloadHeader {
+    pub next_payload: u8,
+    pub length: u16,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum IkeV1AttributeValue {
+    /// Attribute in TV format (AF bit set)
+    Basic(u16),
+    /// Attribute in TLV format
+    Variable(Vec<u8>),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct IkeV1Attribute {
+    pub attr_type: u16,
+    pub value: IkeV1AttributeValue,
+}
+
+impl IkeV1Attribute {
+    /// Return the attribute value as an integer. Variable length values
+    /// longer than 8 bytes are not representable and return None.
+    pub fn numeric_value(&self) -> Option<u64> {
+        match self.value {
+            IkeV1AttributeValue::Basic(v) => Some(v as u64),
+            IkeV1AttributeValue::Variable(ref v) => {
+                if v.len() > 8 {
+                    return None;
+                }
+                Some(v.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64))
+            },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct IkeV1Transform {
+    pub number: u8,
+    pub transform_id: u8,
+    pub attributes: Vec<IkeV1Attribute>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct IkeV1Proposal {
+    pub number: u8,
+    pub protocol_id: u8,
+    pub spi: Vec<u8>,
+    pub transforms: Vec<IkeV1Transform>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct IkeV1SecurityAssociation {
+    pub doi: u32,
+    pub situation: u32,
+    pub proposals: Vec<IkeV1Proposal>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct IkeV1Identity {
+    pub id_type: u8,
+    pub protocol_id: u8,
+    pub port: u16,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum IkeV1PayloadContent {
+    SA(IkeV1SecurityAssociation),
+    Identification(IkeV1Identity),
+    VendorID(Vec<u8>),
+    /// Payload not interpreted, or failing to parse
+    Other,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct IkeV1Payload {
+    pub payload_type: u8,
+    pub content: IkeV1PayloadContent,
+}
+
+named!(pub parse_ikev1_payload_header<IkeV1PayloadHeader>,
+    do_parse!(
+        next_payload: be_u8
+        >> _reserved: be_u8
+        >> length: verify!(be_u16, |v| v >= 4)
+        >> (IkeV1PayloadHeader{
+                next_payload: next_payload,
+                length: length,
+            })
+    ));
+
+named!(parse_ikev1_attribute<IkeV1Attribute>,
+    do_parse!(
+        af_type: be_u16
+        >> basic: cond!(af_type & 0x8000 != 0, be_u16)
+        >> variable: cond!(af_type & 0x8000 == 0, length_data!(be_u16))
+        >> (IkeV1Attribute{
+                attr_type: af_type & 0x7fff,
+                value: match basic {
+                    Some(v) => IkeV1AttributeValue::Basic(v),
+                    None => IkeV1AttributeValue::Variable(
+                        variable.unwrap_or(&[]).to_vec()),
+                },
+            })
+    ));
+
+named!(parse_ikev1_transform<IkeV1Transform>,
+    do_parse!(
+        hdr: parse_ikev1_payload_header
+        >> number: be_u8
+        >> transform_id: be_u8
+        >> _reserved: be_u16
+        >> attributes: flat_map!(take!((hdr.length as usize).saturating_sub(8)),
+                                 many0!(complete!(parse_ikev1_attribute)))
+        >> (IkeV1Transform{
+                number: number,
+                transform_id: transform_id,
+                attributes: attributes,
+            })
+    ));
+
+named!(parse_ikev1_proposal<IkeV1Proposal>,
+    do_parse!(
+        hdr: parse_ikev1_payload_header
+        >> number: be_u8
+        >> protocol_id: be_u8
+        >> spi_size: be_u8
+        >> _num_transforms: be_u8
+        >> spi: take!(spi_size)
+        >> transforms: flat_map!(take!((hdr.length as usize).saturating_sub(8 + spi_size as usize)),
+                                 many0!(complete!(parse_ikev1_transform)))
+        >> (IkeV1Proposal{
+                number: number,
+                protocol_id: protocol_id,
+                spi: spi.to_vec(),
+                transforms: transforms,
+            })
+    ));
+
+named!(pub parse_ikev1_sa<IkeV1SecurityAssociation>,
+    do_parse!(
+        doi: be_u32
+        >> situation: be_u32
+        >> proposals: many0!(complete!(parse_ikev1_proposal))
+        >> (IkeV1SecurityAssociation{
+                doi: doi,
+                situation: situation,
+                proposals: proposals,
+            })
+    ));
+
+named!(pub parse_ikev1_identity<IkeV1Identity>,
+    do_parse!(
+        id_type: be_u8
+        >> protocol_id: be_u8
+        >> port: be_u16
+        >> data: rest
+        >> (IkeV1Identity{
+                id_type: id_type,
+                protocol_id: protocol_id,
+                port: port,
+                data: data.to_vec(),
+            })
+    ));
+
+fn parse_ikev1_payload_content(payload_type: u8, data: &[u8]) -> IkeV1PayloadContent {
+    match payload_type {
+        IKEV1_PAYLOAD_SA => {
+            match parse_ikev1_sa(data) {
+                Ok((_, sa)) => IkeV1PayloadContent::SA(sa),
+                Err(_) => IkeV1PayloadContent::Other,
+            }
+        },
+        IKEV1_PAYLOAD_ID => {
+            match parse_ikev1_identity(data) {
+                Ok((_, id)) => IkeV1PayloadContent::Identification(id),
+                Err(_) => IkeV1PayloadContent::Other,
+            }
+        },
+        IKEV1_PAYLOAD_VENDOR_ID => IkeV1PayloadContent::VendorID(data.to_vec()),
+        _ => IkeV1PayloadContent::Other,
+    }
+}
+
+/// Parse the chain of payloads of an unencrypted ISAKMP message, starting
+/// with a payload of type `first`.
+pub fn parse_ikev1_payload_list(i: &[u8], first: u8) -> IResult<&[u8], Vec<IkeV1Payload>> {
+    let mut payloads = Vec::new();
+    let mut payload_type = first;
+    let mut rem = i;
+    while payload_type != IKEV1_PAYLOAD_NONE {
+        let (r, hdr) = parse_ikev1_payload_header(rem)?;
+        let (r, data) = take!(r, hdr.length as usize - 4)?;
+        payloads.push(IkeV1Payload{
+            payload_type: payload_type,
+            content: parse_ikev1_payload_content(payload_type, data),
+        });
+        payload_type = hdr.next_payload;
+        rem = r;
+    }
+    Ok((rem, payloads))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // SA (one proposal, one transform), ID (KEY_ID "vpngroup") and the
+    // RFC 3947 vendor ID, as found in an aggressive mode initiator message.
+    const PAYLOADS: &[u8] = &[
+        // SA
+        0x05, 0x00, 0x00, 0x30, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x01,
+        // proposal
+        0x00, 0x00, 0x00, 0x24, 0x01, 0x01, 0x00, 0x01,
+        // transform
+        0x00, 0x00, 0x00, 0x1c, 0x01, 0x01, 0x00, 0x00,
+        0x80, 0x01, 0x00, 0x07, 0x80, 0x0e, 0x00, 0x80,
+        0x80, 0x02, 0x00, 0x02, 0x00, 0x0c, 0x00, 0x04,
+        0x00, 0x00, 0x70, 0x80,
+        // ID
+        0x0d, 0x00, 0x00, 0x10, 0x0b, 0x11, 0x01, 0xf4,
+        0x76, 0x70, 0x6e, 0x67, 0x72, 0x6f, 0x75, 0x70,
+        // VID
+        0x00, 0x00, 0x00, 0x14, 0x4a, 0x13, 0x1c, 0x81,
+        0x07, 0x03, 0x58, 0x45, 0x5c, 0x57, 0x28, 0xf2,
+        0x0e, 0x95, 0x45, 0x2f,
+    ];
+
+    #[test]
+    fn test_parse_ikev1_payload_list() {
+        let (rem, payloads) = parse_ikev1_payload_list(PAYLOADS, IKEV1_PAYLOAD_SA).unwrap();
+        assert_eq!(rem.len(), 0);
+        assert_eq!(payloads.len(), 3);
+
+        match payloads[0].content {
+            IkeV1PayloadContent::SA(ref sa) => {
+                assert_eq!(sa.doi, 1);
+                assert_eq!(sa.proposals.len(), 1);
+                let xform = &sa.proposals[0].transforms[0];
+                assert_eq!(xform.transform_id, 1);
+                assert_eq!(xform.attributes.len(), 4);
+                assert_eq!(xform.attributes[0].attr_type, 1);
+                assert_eq!(xform.attributes[0].value, IkeV1AttributeValue::Basic(7));
+                assert_eq!(xform.attributes[3].attr_type, 12);
+                assert_eq!(xform.attributes[3].numeric_value(), Some(28800));
+            },
+            _ => panic!("expected SA payload"),
+        }
+        match payloads[1].content {
+            IkeV1PayloadContent::Identification(ref id) => {
+                assert_eq!(id.id_type, IKEV1_ID_KEY_ID);
+                assert_eq!(id.port, 500);
+                assert_eq!(id.data, b"vpngroup");
+            },
+            _ => panic!("expected ID payload"),
+        }
+        match payloads[2].content {
+            IkeV1PayloadContent::VendorID(ref vid) => {
+                assert_eq!(vid.len(), 16);
+                assert_eq!(vid[0], 0x4a);
+            },
+            _ => panic!("expected VendorID payload"),
+        }
+    }
+
+    #[test]
+    fn test_parse_ikev1_payload_list_truncated() {
+        match parse_ikev1_payload_list(&PAYLOADS[..60], IKEV1_PAYLOAD_SA) {
+            Err(Err::Incomplete(_)) => (),
+            _ => panic!("expected incomplete"),
+        }
+    }
+}
@@ -18,19 +18,28 @@
 // written by Pierre Chifflier  <chifflier@wzdftpd.net>
 
 use crate::ikev2::ipsec_parser::*;
+use crate::ikev2::ikev1::*;
+use crate::ikev2::vendor::ikev2_vendor_ids_init;
 use crate::ikev2::state::IKEV2ConnectionState;
 use crate::core;
 use crate::core::{AppProto,Flow,ALPROTO_UNKNOWN,ALPROTO_FAILED,STREAM_TOSERVER,STREAM_TOCLIENT};
-use crate::applayer;
+use crate::applayer::{self, AppLayerEvent};
 use crate::parser::*;
 use std;
-use std::ffi::{CStr,CString};
+use std::ffi::CString;
 
 use crate::log::*;
 
 use nom;
+use nom::{be_u8,be_u16};
+
+/// Encrypted and Authenticated payload (SK), RFC 7296 section 3.14
+pub const IKEV2_PAYLOAD_ENCRYPTED: u8 = 46;
+/// Encrypted and Authenticated Fragment payload (SKF), RFC 7383 section 2.5
+pub const IKEV2_PAYLOAD_ENCRYPTED_FRAGMENT: u8 = 53;
 
 #[repr(u32)]
+#[derive(AppLayerEvent)]
 pub enum IKEV2Event {
     MalformedData = 0,
     NoEncryption,
@@ -38,30 +47,14 @@ pub enum IKEV2Event {
     WeakCryptoPRF,
     WeakCryptoDH,
     WeakCryptoAuth,
+    #[name("weak_crypto_nodh")]
     WeakCryptoNoDH,
+    #[name("weak_crypto_noauth")]
     WeakCryptoNoAuth,
     InvalidProposal,
     UnknownProposal,
 }
 
-impl IKEV2Event {
-    fn from_i32(value: i32) -> Option<IKEV2Event> {
-        match value {
-            0 => Some(IKEV2Event::MalformedData),
-            1 => Some(IKEV2Event::NoEncryption),
-            2 => Some(IKEV2Event::WeakCryptoEnc),
-            3 => Some(IKEV2Event::WeakCryptoPRF),
-            4 => Some(IKEV2Event::WeakCryptoDH),
-            5 => Some(IKEV2Event::WeakCryptoAuth),
-            6 => Some(IKEV2Event::WeakCryptoNoDH),
-            7 => Some(IKEV2Event::WeakCryptoNoAuth),
-            8 => Some(IKEV2Event::InvalidProposal),
-            9 => Some(IKEV2Event::UnknownProposal),
-            _ => None,
-        }
-    }
-}
-
 pub struct IKEV2State {
     /// List of transactions for this session
     transactions: Vec<IKEV2Transaction>,
@@ -117,9 +110,79 @@ pub struct IKEV2Transaction {
     events: *mut core::AppLayerDecoderEvents,
 
     logged: applayer::LoggerFlags,
+
+    /// Direction of the message
+    pub direction: u8,
+
+    /// Size of the encrypted data (SK or SKF payload, or IKEv1 encrypted
+    /// message body), 0 if the message is not encrypted
+    pub encrypted_size: u32,
+
+    /// Fragment information, if the message is an IKEv2 fragment (RFC 7383)
+    pub fragment: Option<IKEV2Fragment>,
+
+    /// IKEv1 (ISAKMP) message information, if the message is IKEv1
+    pub ikev1: Option<IKEV1Info>,
+
+    /// Vendor ID payloads
+    pub vendor_ids: Vec<Vec<u8>>,
+
+    /// CA hashes (SHA-1 of the CA public keys) from the Certificate
+    /// Request payloads
+    pub ca_hashes: Vec<Vec<u8>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct IKEV2Fragment {
+    pub number: u16,
+    pub total: u16,
+}
+
+#[derive(Debug)]
+pub struct IKEV1Info {
+    /// Payload types, in order of appearance
+    pub payload_types: Vec<u8>,
+
+    /// Proposals from the SA payload. The responder returns the selected
+    /// proposal only.
+    pub proposals: Vec<IkeV1Proposal>,
+
+    /// Identification payload, only visible in aggressive mode
+    pub identity: Option<IkeV1Identity>,
+
+    /// True if the message body is encrypted
+    pub encrypted: bool,
 }
 
+impl IKEV1Info {
+    pub fn new() -> IKEV1Info {
+        IKEV1Info {
+            payload_types: Vec::new(),
+            proposals: Vec::new(),
+            identity: None,
+            encrypted: false,
+        }
+    }
+}
 
+named!(parse_ikev2_raw_payload<(u8, &[u8])>,
+    do_parse!(
+        next_payload: be_u8
+        >> _flags: be_u8
+        >> length: verify!(be_u16, |v| v >= 4)
+        >> data: take!(length - 4)
+        >> ((next_payload, data))
+    ));
+
+named!(parse_ikev2_fragment_header<IKEV2Fragment>,
+    do_parse!(
+        number: be_u16
+        >> total: be_u16
+        >> (IKEV2Fragment{
+                number: number,
+                total: total,
+            })
+    ));
 
 impl IKEV2State {
     pub fn new() -> IKEV2State {
@@ -150,7 +213,7 @@ impl IKEV2State {
                     return 1;
                 }
                 // Rule 0: check version
-                if hdr.maj_ver != 2 || hdr.min_ver != 0 {
+                if (hdr.maj_ver != 1 && hdr.maj_ver != 2) || hdr.min_ver != 0 {
                     self.set_event(IKEV2Event::MalformedData);
                     return -1;
                 }
@@ -158,14 +221,18 @@ impl IKEV2State {
                     self.set_event(IKEV2Event::MalformedData);
                     return -1;
                 }
-                // only analyse IKE_SA, other payloads are encrypted
+                if hdr.maj_ver == 1 {
+                    return self.parse_ikev1(rem, hdr, direction);
+                }
+                // the payloads of other exchanges are encrypted
                 if hdr.exch_type != IkeExchangeType::IKE_SA_INIT {
-                    return 0;
+                    return self.parse_encrypted(rem, hdr, direction);
                 }
                 let mut tx = self.new_tx();
                 // use init_spi as transaction identifier
                 tx.xid = hdr.init_spi;
                 tx.hdr = (*hdr).clone();
+                tx.direction = direction;
                 self.transactions.push(tx);
                 let mut payload_types = Vec::new();
                 let mut errors = 0;
@@ -197,12 +264,25 @@ impl IKEV2State {
                                     }
                                     notify_types.push(n.notify_type);
                                 },
-                                // XXX CertificateRequest
+                                IkeV2PayloadContent::VendorID(ref v) => {
+                                    SCLogDebug!("Vendor ID: {:?}", v.vendor_id);
+                                    if let Some(tx) = self.transactions.last_mut() {
+                                        tx.vendor_ids.push(v.vendor_id.to_vec());
+                                    }
+                                },
+                                IkeV2PayloadContent::CertificateRequest(ref cr) => {
+                                    // the CA field is a list of SHA-1 hashes
+                                    // of the trusted CA public keys
+                                    if let Some(tx) = self.transactions.last_mut() {
+                                        for hash in cr.ca_data.chunks(20) {
+                                            tx.ca_hashes.push(hash.to_vec());
+                                        }
+                                    }
+                                },
                                 // XXX Certificate
                                 // XXX Authentication
                                 // XXX TSi
                                 // XXX TSr
-                                // XXX IDr
                                 _ => {
                                     SCLogDebug!("Unknown payload content {:?}", payload.content);
                                 },
@@ -233,6 +313,107 @@ impl IKEV2State {
         }
     }
 
+    /// Parse the payloads of an IKE_AUTH, CREATE_CHILD_SA or INFORMATIONAL
+    /// message. These are protected by an Encrypted payload, or split
+    /// in Encrypted Fragment payloads (RFC 7383), so only the unprotected
+    /// payload chain is inspected.
+    fn parse_encrypted(&mut self, i: &[u8], hdr: &IkeV2Header, direction: u8) -> i32 {
+        let mut tx = self.new_tx();
+        tx.xid = hdr.init_spi;
+        tx.hdr = hdr.clone();
+        tx.direction = direction;
+        let mut malformed = false;
+        let mut payload_type = hdr.next_payload.0;
+        let mut rem = i;
+        while payload_type != 0 {
+            let (r, (next_payload, data)) = match parse_ikev2_raw_payload(rem) {
+                Ok(res) => res,
+                Err(_) => {
+                    SCLogDebug!("Error while parsing IKEV2 payload {}", payload_type);
+                    malformed = true;
+                    break;
+                },
+            };
+            tx.payload_types.push(IkePayloadType(payload_type));
+            match payload_type {
+                IKEV2_PAYLOAD_ENCRYPTED => {
+                    // the Encrypted payload is always the last one
+                    tx.encrypted_size = data.len() as u32;
+                    break;
+                },
+                IKEV2_PAYLOAD_ENCRYPTED_FRAGMENT => {
+                    match parse_ikev2_fragment_header(data) {
+                        Ok((encrypted, fragment)) => {
+                            SCLogDebug!("Fragment {}/{}", fragment.number, fragment.total);
+                            if fragment.number == 0 || fragment.number > fragment.total {
+                                malformed = true;
+                            }
+                            tx.encrypted_size = encrypted.len() as u32;
+                            tx.fragment = Some(fragment);
+                        },
+                        Err(_) => {
+                            malformed = true;
+                        },
+                    }
+                    break;
+                },
+                _ => (),
+            }
+            payload_type = next_payload;
+            rem = r;
+        }
+        self.transactions.push(tx);
+        if malformed {
+            self.set_event(IKEV2Event::MalformedData);
+        }
+        1
+    }
+
+    /// Parse an IKEv1 (ISAKMP) message.
+    fn parse_ikev1(&mut self, i: &[u8], hdr: &IkeV2Header, direction: u8) -> i32 {
+        let mut tx = self.new_tx();
+        tx.xid = hdr.init_spi;
+        tx.hdr = hdr.clone();
+        tx.direction = direction;
+        let mut info = IKEV1Info::new();
+        let mut malformed = false;
+        if hdr.flags & IKEV1_FLAG_ENCRYPTION != 0 {
+            info.encrypted = true;
+            tx.encrypted_size = i.len() as u32;
+        } else {
+            match parse_ikev1_payload_list(i, hdr.next_payload.0) {
+                Ok((_, payloads)) => {
+                    for payload in payloads {
+                        info.payload_types.push(payload.payload_type);
+                        match payload.content {
+                            IkeV1PayloadContent::SA(sa) => {
+                                info.proposals.extend(sa.proposals);
+                            },
+                            IkeV1PayloadContent::Identification(id) => {
+                                SCLogDebug!("IKEv1 identity: {:?}", id);
+                                info.identity = Some(id);
+                            },
+                            IkeV1PayloadContent::VendorID(vid) => {
+                                tx.vendor_ids.push(vid);
+                            },
+                            IkeV1PayloadContent::Other => (),
+                        }
+                    }
+                },
+                Err(e) => {
+                    SCLogDebug!("parse_ikev1_payload_list: {:?}", e);
+                    malformed = true;
+                },
+            }
+        }
+        tx.ikev1 = Some(info);
+        self.transactions.push(tx);
+        if malformed {
+            self.set_event(IKEV2Event::MalformedData);
+        }
+        1
+    }
+
     fn free(&mut self) {
         // All transactions are freed when the `transactions` object is freed.
         // But let's be explicit
@@ -427,6 +608,12 @@ impl IKEV2Transaction {
             de_state: None,
             events: std::ptr::null_mut(),
             logged: applayer::LoggerFlags::new(),
+            direction: 0,
+            encrypted_size: 0,
+            fragment: None,
+            ikev1: None,
+            vendor_ids: Vec::new(),
+            ca_hashes: Vec::new(),
         }
     }
 
@@ -479,22 +666,14 @@ pub extern "C" fn rs_ikev2_parse_request(_flow: *const core::Flow,
 #[no_mangle]
 pub extern "C" fn rs_ikev2_parse_response(_flow: *const core::Flow,
                                        state: *mut std::os::raw::c_void,
-                                       pstate: *mut std::os::raw::c_void,
+                                       _pstate: *mut std::os::raw::c_void,
                                        input: *const u8,
                                        input_len: u32,
                                        _data: *const std::os::raw::c_void,
                                        _flags: u8) -> i32 {
     let buf = build_slice!(input,input_len as usize);
     let state = cast_pointer!(state,IKEV2State);
-    let res = state.parse(buf, STREAM_TOCLIENT);
-    if state.connection_state == IKEV2ConnectionState::ParsingDone {
-        unsafe{
-            AppLayerParserStateSetFlag(pstate, APP_LAYER_PARSER_NO_INSPECTION |
-                                       APP_LAYER_PARSER_NO_REASSEMBLY |
-                                       APP_LAYER_PARSER_BYPASS_READY)
-        };
-    }
-    res
+    state.parse(buf, STREAM_TOCLIENT)
 }
 
 #[no_mangle]
@@ -595,69 +774,6 @@ pub extern "C" fn rs_ikev2_state_get_events(tx: *mut std::os::raw::c_void)
     return tx.events;
 }
 
-#[no_mangle]
-pub extern "C" fn rs_ikev2_state_get_event_info_by_id(event_id: std::os::raw::c_int,
-                                                      event_name: *mut *const std::os::raw::c_char,
-                                                      event_type: *mut core::AppLayerEventType)
-                                                      -> i8
-{
-    if let Some(e) = IKEV2Event::from_i32(event_id as i32) {
-        let estr = match e {
-            IKEV2Event::MalformedData    => { "malformed_data\0" },
-            IKEV2Event::NoEncryption     => { "no_encryption\0" },
-            IKEV2Event::WeakCryptoEnc    => { "weak_crypto_enc\0" },
-            IKEV2Event::WeakCryptoPRF    => { "weak_crypto_prf\0" },
-            IKEV2Event::WeakCryptoDH     => { "weak_crypto_dh\0" },
-            IKEV2Event::WeakCryptoAuth   => { "weak_crypto_auth\0" },
-            IKEV2Event::WeakCryptoNoDH   => { "weak_crypto_nodh\0" },
-            IKEV2Event::WeakCryptoNoAuth => { "weak_crypto_noauth\0" },
-            IKEV2Event::InvalidProposal  => { "invalid_proposal\0" },
-            IKEV2Event::UnknownProposal  => { "unknown_proposal\0" },
-        };
-        unsafe{
-            *event_name = estr.as_ptr() as *const std::os::raw::c_char;
-            *event_type = core::APP_LAYER_EVENT_TYPE_TRANSACTION;
-        };
-        0
-    } else {
-        -1
-    }
-}
-
-#[no_mangle]
-pub extern "C" fn rs_ikev2_state_get_event_info(event_name: *const std::os::raw::c_char,
-                                              event_id: *mut std::os::raw::c_int,
-                                              event_type: *mut core::AppLayerEventType)
-                                              -> std::os::raw::c_int
-{
-    if event_name == std::ptr::null() { return -1; }
-    let c_event_name: &CStr = unsafe { CStr::from_ptr(event_name) };
-    let event = match c_event_name.to_str() {
-        Ok(s) => {
-            match s {
-                "malformed_data"     => IKEV2Event::MalformedData as i32,
-                "no_encryption"      => IKEV2Event::NoEncryption as i32,
-                "weak_crypto_enc"    => IKEV2Event::WeakCryptoEnc as i32,
-                "weak_crypto_prf"    => IKEV2Event::WeakCryptoPRF as i32,
-                "weak_crypto_auth"   => IKEV2Event::WeakCryptoAuth as i32,
-                "weak_crypto_dh"     => IKEV2Event::WeakCryptoDH as i32,
-                "weak_crypto_nodh"   => IKEV2Event::WeakCryptoNoDH as i32,
-                "weak_crypto_noauth" => IKEV2Event::WeakCryptoNoAuth as i32,
-                "invalid_proposal"   => IKEV2Event::InvalidProposal as i32,
-                "unknown_proposal"   => IKEV2Event::UnknownProposal as i32,
-                _                    => -1, // unknown event
-            }
-        },
-        Err(_) => -1, // UTF-8 conversion failed
-    };
-    unsafe{
-        *event_type = core::APP_LAYER_EVENT_TYPE_TRANSACTION;
-        *event_id = event as std::os::raw::c_int;
-    };
-    0
-}
-
-
 static mut ALPROTO_IKEV2 : AppProto = ALPROTO_UNKNOWN;
 
 #[no_mangle]
@@ -670,12 +786,25 @@ pub extern "C" fn rs_ikev2_probing_parser(_flow: *const Flow,
     let alproto = unsafe{ ALPROTO_IKEV2 };
     match parse_ikev2_header(slice) {
         Ok((_, ref hdr)) => {
-            if hdr.maj_ver != 2 || hdr.min_ver != 0 {
+            let valid_exch_type = match hdr.maj_ver {
+                1 => match hdr.exch_type.0 {
+                    IKEV1_EXCH_BASE ..= IKEV1_EXCH_INFORMATIONAL |
+                    IKEV1_EXCH_QUICK_MODE => true,
+                    _ => false,
+                },
+                2 => hdr.exch_type.0 >= 34 && hdr.exch_type.0 <= 37,
+                _ => {
+                    SCLogDebug!("ipsec_probe: could be ipsec, but with unsupported/invalid version {}.{}",
+                            hdr.maj_ver, hdr.min_ver);
+                    return unsafe{ALPROTO_FAILED};
+                },
+            };
+            if hdr.min_ver != 0 {
                 SCLogDebug!("ipsec_probe: could be ipsec, but with unsupported/invalid version {}.{}",
                         hdr.maj_ver, hdr.min_ver);
                 return unsafe{ALPROTO_FAILED};
             }
-            if hdr.exch_type.0 < 34 || hdr.exch_type.0 > 37 {
+            if !valid_exch_type {
                 SCLogDebug!("ipsec_probe: could be ipsec, but with unsupported/invalid exchange type {}",
                        hdr.exch_type.0);
                 return unsafe{ALPROTO_FAILED};
@@ -700,6 +829,7 @@ const PARSER_NAME : &'static [u8] = b"ikev2\0";
 #[no_mangle]
 pub unsafe extern "C" fn rs_register_ikev2_parser() {
     let default_port = CString::new("500").unwrap();
+    ikev2_vendor_ids_init();
     let parser = RustParser {
         name               : PARSER_NAME.as_ptr() as *const std::os::raw::c_char,
         default_port       : default_port.as_ptr(),
@@ -722,8 +852,8 @@ pub unsafe extern "C" fn rs_register_ikev2_parser() {
         get_de_state       : rs_ikev2_state_get_tx_detect_state,
         set_de_state       : rs_ikev2_state_set_tx_detect_state,
         get_events         : Some(rs_ikev2_state_get_events),
-        get_eventinfo      : Some(rs_ikev2_state_get_event_info),
-        get_eventinfo_byid : Some(rs_ikev2_state_get_event_info_by_id),
+        get_eventinfo      : Some(IKEV2Event::get_event_info),
+        get_eventinfo_byid : Some(IKEV2Event::get_event_info_by_id),
         localstorage_new   : None,
         localstorage_free  : None,
         get_tx_mpm_id      : None,
@@ -748,7 +878,7 @@ pub unsafe extern "C" fn rs_register_ikev2_parser() {
 
 #[cfg(test)]
 mod tests {
-    use super::IKEV2State;
+    use super::*;
 
     #[test]
     fn test_ikev2_parse_request_valid() {
@@ -765,4 +895,131 @@ mod tests {
         let mut state = IKEV2State::new();
         assert_eq!(1, state.parse(REQ, 0));
     }
+
+    #[test]
+    fn test_ikev2_parse_ike_auth_fragment() {
+        // IKE_AUTH request, first of two Encrypted Fragment payloads
+        const REQ : &[u8] = &[
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08,
+            0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18,
+            0x35, 0x20, 0x23, 0x08, 0x00, 0x00, 0x00, 0x01,
+            0x00, 0x00, 0x00, 0x34,
+            0x23, 0x00, 0x00, 0x18, 0x00, 0x01, 0x00, 0x02,
+            0xde, 0xad, 0xbe, 0xef, 0xde, 0xad, 0xbe, 0xef,
+            0xde, 0xad, 0xbe, 0xef, 0xde, 0xad, 0xbe, 0xef,
+        ];
+
+        let mut state = IKEV2State::new();
+        assert_eq!(1, state.parse(REQ, STREAM_TOSERVER));
+        let tx = state.get_tx_by_id(0).unwrap();
+        assert_eq!(tx.hdr.exch_type, IkeExchangeType::IKE_AUTH);
+        assert_eq!(tx.hdr.msg_id, 1);
+        assert_eq!(tx.encrypted_size, 16);
+        assert_eq!(tx.fragment, Some(IKEV2Fragment{ number: 1, total: 2 }));
+        assert_eq!(tx.payload_types, vec![IkePayloadType(IKEV2_PAYLOAD_ENCRYPTED_FRAGMENT)]);
+        assert!(tx.events.is_null());
+    }
+
+    #[test]
+    fn test_ikev2_parse_vendor_id_certreq() {
+        // IKE_SA_INIT response reduced to a strongSwan Vendor ID and a
+        // Certificate Request with one CA hash
+        const RESP : &[u8] = &[
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08,
+            0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18,
+            0x2b, 0x20, 0x22, 0x20, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x49,
+            0x26, 0x00, 0x00, 0x14, 0x88, 0x2f, 0xe5, 0x6d,
+            0x6f, 0xd2, 0x0d, 0xbc, 0x22, 0x51, 0x61, 0x3b,
+            0x2e, 0xbe, 0x5b, 0xeb,
+            0x00, 0x00, 0x00, 0x19, 0x04, 0x00, 0x01, 0x02,
+            0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a,
+            0x0b, 0x0c, 0x0d, 0x0e, 0x0f, 0x10, 0x11, 0x12,
+            0x13,
+        ];
+
+        let mut state = IKEV2State::new();
+        assert_eq!(1, state.parse(RESP, STREAM_TOCLIENT));
+        let tx = state.get_tx_by_id(0).unwrap();
+        assert_eq!(tx.vendor_ids.len(), 1);
+        assert_eq!(tx.vendor_ids[0][0], 0x88);
+        assert_eq!(tx.ca_hashes.len(), 1);
+        assert_eq!(tx.ca_hashes[0].len(), 20);
+        assert_eq!(tx.ca_hashes[0][19], 0x13);
+    }
+
+    #[test]
+    fn test_ikev1_parse_aggressive_mode() {
+        // Aggressive mode initiator message: SA, ID (KEY_ID "vpngroup")
+        // and a vendor ID
+        const REQ : &[u8] = &[
+            0xa1, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7, 0xa8,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x01, 0x10, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x70,
+            0x05, 0x00, 0x00, 0x30, 0x00, 0x00, 0x00, 0x01,
+            0x00, 0x00, 0x00, 0x01,
+            0x00, 0x00, 0x00, 0x24, 0x01, 0x01, 0x00, 0x01,
+            0x00, 0x00, 0x00, 0x1c, 0x01, 0x01, 0x00, 0x00,
+            0x80, 0x01, 0x00, 0x07, 0x80, 0x0e, 0x00, 0x80,
+            0x80, 0x02, 0x00, 0x02, 0x00, 0x0c, 0x00, 0x04,
+            0x00, 0x00, 0x70, 0x80,
+            0x0d, 0x00, 0x00, 0x10, 0x0b, 0x11, 0x01, 0xf4,
+            0x76, 0x70, 0x6e, 0x67, 0x72, 0x6f, 0x75, 0x70,
+            0x00, 0x00, 0x00, 0x14, 0x4a, 0x13, 0x1c, 0x81,
+            0x07, 0x03, 0x58, 0x45, 0x5c, 0x57, 0x28, 0xf2,
+            0x0e, 0x95, 0x45, 0x2f,
+        ];
+
+        let mut state = IKEV2State::new();
+        assert_eq!(1, state.parse(REQ, STREAM_TOSERVER));
+        let tx = state.get_tx_by_id(0).unwrap();
+        assert_eq!(tx.hdr.maj_ver, 1);
+        assert!(tx.events.is_null());
+        let info = tx.ikev1.as_ref().unwrap();
+        assert!(!info.encrypted);
+        assert_eq!(info.payload_types, vec![IKEV1_PAYLOAD_SA, IKEV1_PAYLOAD_ID,
+                                            IKEV1_PAYLOAD_VENDOR_ID]);
+        assert_eq!(info.proposals.len(), 1);
+        assert_eq!(tx.vendor_ids.len(), 1);
+        let id = info.identity.as_ref().unwrap();
+        assert_eq!(id.id_type, IKEV1_ID_KEY_ID);
+        assert_eq!(id.data, b"vpngroup");
+    }
+
+    #[test]
+    fn test_ikev1_parse_main_mode_encrypted() {
+        // Main mode message 5: the identity is in the encrypted body
+        const REQ : &[u8] = &[
+            0xa1, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7, 0xa8,
+            0xb1, 0xb2, 0xb3, 0xb4, 0xb5, 0xb6, 0xb7, 0xb8,
+            0x05, 0x10, 0x02, 0x01, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x2c,
+            0x3f, 0x8e, 0x1c, 0x52, 0x90, 0x0b, 0x6d, 0x24,
+            0xe7, 0x11, 0x4a, 0xc9, 0x5d, 0x02, 0x73, 0xf8,
+        ];
+
+        let mut state = IKEV2State::new();
+        assert_eq!(1, state.parse(REQ, STREAM_TOSERVER));
+        let tx = state.get_tx_by_id(0).unwrap();
+        assert!(tx.events.is_null());
+        assert_eq!(tx.encrypted_size, 16);
+        let info = tx.ikev1.as_ref().unwrap();
+        assert!(info.encrypted);
+        assert!(info.payload_types.is_empty());
+        assert!(info.identity.is_none());
+    }
+
+    #[test]
+    fn test_ikev2_event_info() {
+        let mut event_id: std::os::raw::c_int = 0;
+        let mut event_type: crate::core::AppLayerEventType = 0;
+        let name = std::ffi::CString::new("malformed_data").unwrap();
+        assert_eq!(IKEV2Event::get_event_info(name.as_ptr(), &mut event_id, &mut event_type), 0);
+        assert_eq!(event_id, IKEV2Event::MalformedData as std::os::raw::c_int);
+
+        // An unknown name fails, so the rule using it fails to load.
+        let name = std::ffi::CString::new("no_such_event").unwrap();
+        assert_eq!(IKEV2Event::get_event_info(name.as_ptr(), &mut event_id, &mut event_type), -1);
+    }
 }
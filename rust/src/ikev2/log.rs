@@ -17,10 +17,98 @@
 
 // written by Pierre Chifflier  <chifflier@wzdftpd.net>
 
+use std::net::{Ipv4Addr,Ipv6Addr};
+
 use crate::json::*;
-use crate::ikev2::ikev2::{IKEV2State,IKEV2Transaction};
+use crate::core::STREAM_TOSERVER;
+use crate::ikev2::ikev2::{IKEV2State,IKEV2Transaction,IKEV1Info,IKEV2_PAYLOAD_ENCRYPTED_FRAGMENT};
+use crate::ikev2::ikev1::*;
+use crate::ikev2::vendor::ikev2_vendor_id_lookup;
+
+use crate::ikev2::ipsec_parser::{IKEV2_FLAG_INITIATOR,IkePayloadType};
+
+fn bytes_to_hex(input: &[u8]) -> String {
+    input.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn ikev2_payload_type_string(payload_type: &IkePayloadType) -> String {
+    // RFC 7383 postdates the payload types known to ipsec-parser
+    if payload_type.0 == IKEV2_PAYLOAD_ENCRYPTED_FRAGMENT {
+        return "EncryptedFragment".to_string();
+    }
+    format!("{:?}", payload_type)
+}
+
+/// Format the value of an IKEv1 identity.
+fn identity_value(id_type: u8, data: &[u8]) -> String {
+    match id_type {
+        IKEV1_ID_IPV4_ADDR if data.len() == 4 => {
+            Ipv4Addr::new(data[0], data[1], data[2], data[3]).to_string()
+        },
+        IKEV1_ID_IPV6_ADDR if data.len() == 16 => {
+            let mut a = [0u8; 16];
+            a.copy_from_slice(data);
+            Ipv6Addr::from(a).to_string()
+        },
+        IKEV1_ID_FQDN | IKEV1_ID_USER_FQDN | IKEV1_ID_KEY_ID => {
+            String::from_utf8_lossy(data).to_string()
+        },
+        _ => bytes_to_hex(data),
+    }
+}
+
+fn vendor_ids_to_json(vendor_ids: &Vec<Vec<u8>>) -> Json {
+    let jsa = Json::array();
+    for vid in vendor_ids.iter() {
+        let jsv = Json::object();
+        jsv.set_string("vendor_id", &bytes_to_hex(vid));
+        if let Some(name) = ikev2_vendor_id_lookup(vid) {
+            jsv.set_string("name", name);
+        }
+        jsa.array_append(jsv);
+    }
+    jsa
+}
 
-use crate::ikev2::ipsec_parser::IKEV2_FLAG_INITIATOR;
+fn ikev1_to_json(info: &IKEV1Info) -> Json {
+    let js = Json::object();
+    js.set_boolean("encrypted", info.encrypted);
+    let jsa = Json::array();
+    for p in info.proposals.iter() {
+        let jsp = Json::object();
+        jsp.set_integer("number", p.number as u64);
+        jsp.set_integer("protocol_id", p.protocol_id as u64);
+        let jst = Json::array();
+        for t in p.transforms.iter() {
+            let jsx = Json::object();
+            jsx.set_integer("transform_id", t.transform_id as u64);
+            for attr in t.attributes.iter() {
+                let name = ikev1_attribute_type_string(attr.attr_type);
+                match attr.numeric_value() {
+                    Some(v) => jsx.set_integer(&name, v),
+                    None => {
+                        if let IkeV1AttributeValue::Variable(ref v) = attr.value {
+                            jsx.set_string(&name, &bytes_to_hex(v));
+                        }
+                    },
+                }
+            }
+            jst.array_append(jsx);
+        }
+        jsp.set("transforms", jst);
+        jsa.array_append(jsp);
+    }
+    js.set("proposals", jsa);
+    if let Some(ref id) = info.identity {
+        let jsi = Json::object();
+        jsi.set_string("type", &identity_type_string(id.id_type));
+        jsi.set_integer("protocol_id", id.protocol_id as u64);
+        jsi.set_integer("port", id.port as u64);
+        jsi.set_string("value", &identity_value(id.id_type, &id.data));
+        js.set("identity", jsi);
+    }
+    js
+}
 
 #[no_mangle]
 pub extern "C" fn rs_ikev2_log_json_response(state: &mut IKEV2State, tx: &mut IKEV2Transaction) -> *mut JsonT
@@ -30,28 +118,63 @@ pub extern "C" fn rs_ikev2_log_json_response(state: &mut IKEV2State, tx: &mut IK
     js.set_integer("version_minor", tx.hdr.min_ver as u64);
     js.set_integer("exchange_type", tx.hdr.exch_type.0 as u64);
     js.set_integer("message_id", tx.hdr.msg_id as u64);
+    js.set_integer("flags", tx.hdr.flags as u64);
     js.set_string("init_spi", &format!("{:016x}", tx.hdr.init_spi));
     js.set_string("resp_spi", &format!("{:016x}", tx.hdr.resp_spi));
-    if tx.hdr.flags & IKEV2_FLAG_INITIATOR != 0 {
-        js.set_string("role", &"initiator");
+    if let Some(ref info) = tx.ikev1 {
+        // IKEv1 has no initiator flag, use the direction instead
+        if tx.direction == STREAM_TOSERVER {
+            js.set_string("role", &"initiator");
+        } else {
+            js.set_string("role", &"responder");
+        }
+        let jsa = Json::array();
+        for payload in info.payload_types.iter() {
+            jsa.array_append_string(&ikev1_payload_type_string(*payload));
+        }
+        js.set("payload", jsa);
+        js.set("ikev1", ikev1_to_json(info));
     } else {
-        js.set_string("role", &"responder");
-        js.set_string("alg_enc", &format!("{:?}", state.alg_enc));
-        js.set_string("alg_auth", &format!("{:?}", state.alg_auth));
-        js.set_string("alg_prf", &format!("{:?}", state.alg_prf));
-        js.set_string("alg_dh", &format!("{:?}", state.alg_dh));
-        js.set_string("alg_esn", &format!("{:?}", state.alg_esn));
-    }
-    js.set_integer("errors", tx.errors as u64);
-    let jsa = Json::array();
-    for payload in tx.payload_types.iter() {
-        jsa.array_append_string(&format!("{:?}", payload));
+        if tx.hdr.flags & IKEV2_FLAG_INITIATOR != 0 {
+            js.set_string("role", &"initiator");
+        } else {
+            js.set_string("role", &"responder");
+            js.set_string("alg_enc", &format!("{:?}", state.alg_enc));
+            js.set_string("alg_auth", &format!("{:?}", state.alg_auth));
+            js.set_string("alg_prf", &format!("{:?}", state.alg_prf));
+            js.set_string("alg_dh", &format!("{:?}", state.alg_dh));
+            js.set_string("alg_esn", &format!("{:?}", state.alg_esn));
+        }
+        js.set_integer("errors", tx.errors as u64);
+        let jsa = Json::array();
+        for payload in tx.payload_types.iter() {
+            jsa.array_append_string(&ikev2_payload_type_string(payload));
+        }
+        js.set("payload", jsa);
+        let jsa = Json::array();
+        for notify in tx.notify_types.iter() {
+            jsa.array_append_string(&format!("{:?}", notify));
+        }
+        js.set("notify", jsa);
     }
-    js.set("payload", jsa);
-    let jsa = Json::array();
-    for notify in tx.notify_types.iter() {
-        jsa.array_append_string(&format!("{:?}", notify));
+    if tx.vendor_ids.len() > 0 {
+        js.set("vendor_ids", vendor_ids_to_json(&tx.vendor_ids));
+    }
+    if tx.ca_hashes.len() > 0 {
+        let jsa = Json::array();
+        for hash in tx.ca_hashes.iter() {
+            jsa.array_append_string(&bytes_to_hex(hash));
+        }
+        js.set("ca_hashes", jsa);
+    }
+    if tx.encrypted_size > 0 {
+        js.set_integer("encrypted_payload_size", tx.encrypted_size as u64);
+    }
+    if let Some(ref fragment) = tx.fragment {
+        let jsf = Json::object();
+        jsf.set_integer("number", fragment.number as u64);
+        jsf.set_integer("total", fragment.total as u64);
+        js.set("fragment", jsf);
     }
-    js.set("notify", jsa);
     return js.unwrap();
 }
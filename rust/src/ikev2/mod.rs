@@ -20,5 +20,8 @@
 extern crate ipsec_parser;
 
 pub mod ikev2;
+pub mod ikev1;
+pub mod vendor;
 pub mod state;
 pub mod log;
+pub mod detect;
@@ -0,0 +1,206 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+//! Mapping of IKE Vendor ID payloads to implementations.
+//!
+//! A built-in table is always loaded. Additional entries can be loaded
+//! from the file set in `app-layer.protocols.ikev2.vendor-ids-file`, one
+//! entry per line: the Vendor ID (or a prefix of it) in hex, followed by
+//! the name. Lines starting with `#` are ignored.
+
+use std;
+
+use crate::conf::conf_get;
+use crate::log::*;
+
+/// Vendor IDs known by default. Many implementations append a version
+/// to a fixed prefix, so entries are matched as prefixes.
+const BUILTIN_VENDOR_IDS: &[(&str, &str)] = &[
+    ("4a131c81070358455c5728f20e95452f", "RFC 3947 NAT-T"),
+    ("90cb80913ebb696e086381b5ec427b1f", "draft-ietf-ipsec-nat-t-ike-02"),
+    ("7d9419a65310ca6f2c179d9215529d56", "draft-ietf-ipsec-nat-t-ike-03"),
+    ("afcad71368a1f1c96b8696fc77570100", "Dead Peer Detection"),
+    ("09002689dfd6b712", "XAUTH"),
+    ("4048b7d56ebce88525e7de7f00d6c2d3", "IKE Fragmentation"),
+    ("882fe56d6fd20dbc2251613b2ebe5beb", "strongSwan"),
+    ("12f5f28c457168a9702d9fe274cc", "Cisco Unity"),
+    ("434953434f28434f505952494748542926", "Cisco IOS"),
+    ("464c455856504e2d535550504f52544544", "Cisco FlexVPN"),
+    ("8299031757a36082c6a621de", "Fortinet FortiGate"),
+    ("1e2b516905991c7d7c96fcbfb587e461", "MS NT5 ISAKMPOAKLEY"),
+    ("1e2b516905991c7d7c96fcbfb587e46100000002", "Windows 2000"),
+    ("1e2b516905991c7d7c96fcbfb587e46100000003", "Windows XP SP1"),
+    ("1e2b516905991c7d7c96fcbfb587e46100000004", "Windows Server 2003 or XP SP2"),
+    ("1e2b516905991c7d7c96fcbfb587e46100000005", "Windows Vista"),
+    ("1e2b516905991c7d7c96fcbfb587e46100000006", "Windows Server 2008"),
+    ("1e2b516905991c7d7c96fcbfb587e46100000007", "Windows 7"),
+    ("1e2b516905991c7d7c96fcbfb587e46100000008", "Windows Server 2008 R2"),
+    ("1e2b516905991c7d7c96fcbfb587e46100000009", "Windows 8 or later"),
+    ("26244d38eddb61b3172a36e3d0cfb819", "Microsoft Vid-Initial-Contact"),
+    ("fb1de3cdf341b7ea16b7e5be0855f120", "MS-Negotiation Discovery Capable"),
+    ("e3a5966a76379fe707228231e5ce8652", "IKE CGA version 1"),
+];
+
+pub struct VendorIdTable {
+    entries: Vec<(Vec<u8>, String)>,
+}
+
+fn hex_to_bytes(input: &str) -> Option<Vec<u8>> {
+    if input.len() == 0 || input.len() % 2 != 0 {
+        return None;
+    }
+    let mut bytes = Vec::with_capacity(input.len() / 2);
+    for i in (0..input.len()).step_by(2) {
+        match u8::from_str_radix(input.get(i..i + 2)?, 16) {
+            Ok(b) => bytes.push(b),
+            Err(_) => return None,
+        }
+    }
+    Some(bytes)
+}
+
+impl VendorIdTable {
+    pub fn new() -> VendorIdTable {
+        VendorIdTable {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Create a table with the built-in entries.
+    pub fn builtin() -> VendorIdTable {
+        let mut table = VendorIdTable::new();
+        for &(hex, name) in BUILTIN_VENDOR_IDS {
+            if let Some(prefix) = hex_to_bytes(hex) {
+                table.add(prefix, name);
+            }
+        }
+        table
+    }
+
+    pub fn add(&mut self, prefix: Vec<u8>, name: &str) {
+        self.entries.push((prefix, name.to_string()));
+    }
+
+    /// Load entries from the content of a table file. Returns the number
+    /// of entries added, or the number of the first invalid line. Nothing
+    /// is added if a line is invalid.
+    pub fn load(&mut self, content: &str) -> Result<usize, usize> {
+        let mut loaded = VendorIdTable::new();
+        for (n, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.len() == 0 || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.splitn(2, char::is_whitespace);
+            let prefix = match parts.next().and_then(hex_to_bytes) {
+                Some(prefix) => prefix,
+                None => return Err(n + 1),
+            };
+            let name = match parts.next() {
+                Some(name) if name.trim().len() > 0 => name.trim(),
+                _ => return Err(n + 1),
+            };
+            loaded.add(prefix, name);
+        }
+        let count = loaded.entries.len();
+        self.entries.append(&mut loaded.entries);
+        Ok(count)
+    }
+
+    /// Return the name of the implementation for a Vendor ID, using the
+    /// longest matching prefix.
+    pub fn lookup(&self, vendor_id: &[u8]) -> Option<&str> {
+        self.entries.iter()
+            .filter(|&&(ref prefix, _)| vendor_id.starts_with(prefix))
+            .max_by_key(|&&(ref prefix, _)| prefix.len())
+            .map(|&(_, ref name)| name.as_str())
+    }
+}
+
+static mut VENDOR_IDS: Option<VendorIdTable> = None;
+
+/// Initialize the global table with the built-in entries and the entries
+/// of the configured file, if any.
+pub fn ikev2_vendor_ids_init() {
+    let mut table = VendorIdTable::builtin();
+    if let Some(filename) = conf_get("app-layer.protocols.ikev2.vendor-ids-file") {
+        match std::fs::read_to_string(filename) {
+            Ok(content) => {
+                match table.load(&content) {
+                    Ok(count) => {
+                        SCLogConfig!("IKE: loaded {} vendor IDs from {}", count, filename);
+                    },
+                    Err(line) => {
+                        SCLogWarning!("IKE: invalid vendor ID entry in {} line {}", filename, line);
+                    },
+                }
+            },
+            Err(e) => {
+                SCLogWarning!("IKE: failed to read vendor IDs file {}: {}", filename, e);
+            },
+        }
+    }
+    unsafe {
+        VENDOR_IDS = Some(table);
+    }
+}
+
+/// Look up a Vendor ID in the global table.
+pub fn ikev2_vendor_id_lookup(vendor_id: &[u8]) -> Option<&'static str> {
+    unsafe {
+        match VENDOR_IDS {
+            Some(ref table) => table.lookup(vendor_id),
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vendor_id_builtin() {
+        let table = VendorIdTable::builtin();
+        let vid = hex_to_bytes("1e2b516905991c7d7c96fcbfb587e46100000007").unwrap();
+        assert_eq!(table.lookup(&vid), Some("Windows 7"));
+        let vid = hex_to_bytes("1e2b516905991c7d7c96fcbfb587e461000000ff").unwrap();
+        assert_eq!(table.lookup(&vid), Some("MS NT5 ISAKMPOAKLEY"));
+        let vid = hex_to_bytes("12f5f28c457168a9702d9fe274cc0100").unwrap();
+        assert_eq!(table.lookup(&vid), Some("Cisco Unity"));
+        assert_eq!(table.lookup(&[0x01, 0x02, 0x03]), None);
+    }
+
+    #[test]
+    fn test_vendor_id_load() {
+        let mut table = VendorIdTable::builtin();
+        let content = "# local entries\n\n882fe56d6fd20dbc2251613b2ebe5beb00 strongSwan 5.x\n";
+        assert_eq!(table.load(content), Ok(1));
+        let vid = hex_to_bytes("882fe56d6fd20dbc2251613b2ebe5beb0001").unwrap();
+        assert_eq!(table.lookup(&vid), Some("strongSwan 5.x"));
+        let vid = hex_to_bytes("882fe56d6fd20dbc2251613b2ebe5beb").unwrap();
+        assert_eq!(table.lookup(&vid), Some("strongSwan"));
+
+        assert_eq!(table.load("abc strongSwan\n"), Err(1));
+        assert_eq!(table.load("# comment\n0102\n"), Err(2));
+
+        // a file with an invalid line adds nothing
+        let content = "0a0b0c Acme VPN\n0102\n";
+        assert_eq!(table.load(content), Err(2));
+        assert_eq!(table.lookup(&[0x0a, 0x0b, 0x0c]), None);
+    }
+}
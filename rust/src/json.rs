@@ -73,6 +73,12 @@ impl Json {
         };
     }
 
+    pub fn integer(val: u64) -> Json {
+        return Json{
+            js: unsafe{json_integer(val)},
+        };
+    }
+
     pub fn unwrap(&self) -> *mut JsonT {
         return self.js;
     }
@@ -109,6 +115,12 @@ impl Json {
         }
     }
 
+    /// Set a signed integer. jansson integers are signed, so the value
+    /// keeps its sign once passed through the unsigned binding.
+    pub fn set_signed_integer(&self, key: &str, val: i64) {
+        self.set_integer(key, val as u64);
+    }
+
     pub fn set_boolean(&self, key: &str, val: bool) {
         unsafe {
             json_object_set_new(self.js,
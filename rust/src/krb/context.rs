@@ -0,0 +1,393 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+//! Kerberos context shared between flows.
+//!
+//! The context holds the fingerprints of the TGTs issued by the KDCs, so
+//! that a TGS-REQ using a TGT that was never issued on the monitored links
+//! can be detected, the client principals of the tickets issued for the
+//! kpasswd service, counters per client principal, and the names seen per
+//! client address. Over UDP every exchange is usually a flow of its own,
+//! so password spraying and Kerberoasting are counted per client address.
+//!
+//! All tables are bounded: the oldest entries are evicted first.
+
+use std::collections::{HashMap,HashSet,VecDeque};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash,Hasher};
+use std::net::IpAddr;
+use std::sync::Mutex;
+#[cfg(test)]
+use std::sync::Once;
+
+use crate::conf::{conf_get,conf_get_bool};
+use crate::log::*;
+
+/// Maximum number of ticket fingerprints kept
+const KRB5_MAX_TICKETS: usize = 65536;
+/// Maximum number of principals kept
+const KRB5_MAX_PRINCIPALS: usize = 65536;
+/// Maximum number of client addresses kept
+const KRB5_MAX_SOURCES: usize = 65536;
+/// Maximum number of distinct names kept per client address, and so the
+/// maximum value of the thresholds counting them
+pub const KRB5_MAX_SOURCE_NAMES: usize = 256;
+/// Period, in seconds, over which the names of a client address are
+/// counted
+const KRB5_SOURCE_PERIOD: u64 = 3600;
+
+/// Counters for a client principal
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct KRB5PrincipalStats {
+    pub as_req: u32,
+    pub as_rep: u32,
+    pub tgs_rep: u32,
+    pub errors: u32,
+    /// KDC_ERR_PREAUTH_FAILED errors since the last AS-REP
+    pub preauth_failures: u32,
+    /// Tickets issued for the kpasswd service (kadmin/changepw)
+    pub kpasswd_tickets: u32,
+}
+
+/// Names seen from a client address since the start of the period
+#[derive(Debug, Default)]
+pub struct KRB5SourceStats {
+    start: u64,
+    /// Client principals which failed pre-authentication
+    preauth_failed_principals: HashSet<String>,
+    /// Services requested in TGS-REQ offering only weak encryption types
+    weak_tgs_snames: HashSet<String>,
+}
+
+fn add_name(names: &mut HashSet<String>, name: &str) -> usize {
+    if names.len() < KRB5_MAX_SOURCE_NAMES && !names.contains(name) {
+        names.insert(name.to_string());
+    }
+    names.len()
+}
+
+impl KRB5SourceStats {
+    /// Record a principal failing pre-authentication, and return the
+    /// number of distinct principals
+    pub fn add_preauth_failure(&mut self, principal: &str) -> usize {
+        add_name(&mut self.preauth_failed_principals, principal)
+    }
+
+    /// Record a service requested with weak encryption types only, and
+    /// return the number of distinct services
+    pub fn add_weak_tgs_sname(&mut self, sname: &str) -> usize {
+        add_name(&mut self.weak_tgs_snames, sname)
+    }
+}
+
+pub struct KRB5Context {
+    tickets: HashSet<u64>,
+    tickets_order: VecDeque<u64>,
+    kpasswd_tickets: HashMap<u64, String>,
+    kpasswd_tickets_order: VecDeque<u64>,
+    principals: HashMap<String, KRB5PrincipalStats>,
+    principals_order: VecDeque<String>,
+    sources: HashMap<IpAddr, KRB5SourceStats>,
+    sources_order: VecDeque<IpAddr>,
+}
+
+/// Return the fingerprint of a ticket, computed on its encrypted part
+pub fn ticket_fingerprint(cipher: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    cipher.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl KRB5Context {
+    pub fn new() -> KRB5Context {
+        KRB5Context {
+            tickets: HashSet::new(),
+            tickets_order: VecDeque::new(),
+            kpasswd_tickets: HashMap::new(),
+            kpasswd_tickets_order: VecDeque::new(),
+            principals: HashMap::new(),
+            principals_order: VecDeque::new(),
+            sources: HashMap::new(),
+            sources_order: VecDeque::new(),
+        }
+    }
+
+    /// Record a TGT issued by a KDC
+    pub fn add_ticket(&mut self, fingerprint: u64) {
+        if !self.tickets.insert(fingerprint) {
+            return;
+        }
+        self.tickets_order.push_back(fingerprint);
+        if self.tickets_order.len() > KRB5_MAX_TICKETS {
+            if let Some(old) = self.tickets_order.pop_front() {
+                self.tickets.remove(&old);
+            }
+        }
+    }
+
+    pub fn has_ticket(&self, fingerprint: u64) -> bool {
+        self.tickets.contains(&fingerprint)
+    }
+
+    /// Record the client principal of a ticket issued for the kpasswd
+    /// service, which is encrypted in the ticket itself
+    pub fn add_kpasswd_ticket(&mut self, fingerprint: u64, principal: &str) {
+        if self.kpasswd_tickets.insert(fingerprint, principal.to_string()).is_some() {
+            return;
+        }
+        self.kpasswd_tickets_order.push_back(fingerprint);
+        if self.kpasswd_tickets_order.len() > KRB5_MAX_TICKETS {
+            if let Some(old) = self.kpasswd_tickets_order.pop_front() {
+                self.kpasswd_tickets.remove(&old);
+            }
+        }
+    }
+
+    /// Return the client principal of a ticket issued for the kpasswd
+    /// service, if it was seen
+    pub fn get_kpasswd_principal(&self, fingerprint: u64) -> Option<String> {
+        self.kpasswd_tickets.get(&fingerprint).cloned()
+    }
+
+    /// Update the counters of a principal, and return a copy of them
+    pub fn update_principal<F>(&mut self, principal: &str, f: F) -> KRB5PrincipalStats
+        where F: FnOnce(&mut KRB5PrincipalStats)
+    {
+        if !self.principals.contains_key(principal) {
+            self.principals.insert(principal.to_string(), KRB5PrincipalStats::default());
+            self.principals_order.push_back(principal.to_string());
+            if self.principals_order.len() > KRB5_MAX_PRINCIPALS {
+                if let Some(old) = self.principals_order.pop_front() {
+                    self.principals.remove(&old);
+                }
+            }
+        }
+        let stats = self.principals.entry(principal.to_string())
+            .or_insert_with(KRB5PrincipalStats::default);
+        f(stats);
+        *stats
+    }
+
+    /// Run `f` on the names seen from a client address. The names are
+    /// cleared when the period started at `ts` is over.
+    pub fn update_source<T, F>(&mut self, addr: IpAddr, ts: u64, f: F) -> T
+        where F: FnOnce(&mut KRB5SourceStats) -> T
+    {
+        if !self.sources.contains_key(&addr) {
+            self.sources_order.push_back(addr);
+            if self.sources_order.len() > KRB5_MAX_SOURCES {
+                if let Some(old) = self.sources_order.pop_front() {
+                    self.sources.remove(&old);
+                }
+            }
+        }
+        let stats = self.sources.entry(addr).or_insert_with(|| KRB5SourceStats {
+            start: ts,
+            ..KRB5SourceStats::default()
+        });
+        if ts >= stats.start + KRB5_SOURCE_PERIOD {
+            *stats = KRB5SourceStats {
+                start: ts,
+                ..KRB5SourceStats::default()
+            };
+        }
+        f(stats)
+    }
+}
+
+static mut KRB5_CONTEXT: Option<Mutex<KRB5Context>> = None;
+static mut KRB5_TRACK_TICKETS: bool = false;
+
+/// Default number of pre-authentication failures of a principal since its
+/// last successful AS exchange before raising the RepeatedPreauthFailures
+/// event
+const KRB5_DEFAULT_PREAUTH_FAILURES: u32 = 5;
+/// Default number of distinct principals failing pre-authentication from a
+/// client address before raising the PasswordSpraying event
+const KRB5_DEFAULT_SPRAYING_PRINCIPALS: usize = 3;
+/// Default number of distinct services requested with weak encryption
+/// types only from a client address before raising the Kerberoasting event
+const KRB5_DEFAULT_KERBEROASTING_SERVICES: usize = 5;
+
+static mut KRB5_PREAUTH_FAILURES: u32 = KRB5_DEFAULT_PREAUTH_FAILURES;
+static mut KRB5_SPRAYING_PRINCIPALS: usize = KRB5_DEFAULT_SPRAYING_PRINCIPALS;
+static mut KRB5_KERBEROASTING_SERVICES: usize = KRB5_DEFAULT_KERBEROASTING_SERVICES;
+
+/// Read a threshold counting names per client address
+fn conf_get_name_threshold(name: &str, default: usize) -> usize {
+    let key = format!("app-layer.protocols.krb5.{}", name);
+    match conf_get(&key) {
+        Some(val) => {
+            match val.parse::<usize>() {
+                Ok(v) if v > 0 && v <= KRB5_MAX_SOURCE_NAMES => v,
+                _ => {
+                    SCLogWarning!("Invalid value for krb5.{}: {}, must be between 1 and {}",
+                                  name, val, KRB5_MAX_SOURCE_NAMES);
+                    default
+                }
+            }
+        }
+        None => default,
+    }
+}
+
+/// Initialize the global context. Must be called before the parsers are
+/// used.
+pub fn krb5_context_init() {
+    unsafe {
+        if let Some(val) = conf_get("app-layer.protocols.krb5.preauth-failures") {
+            match val.parse::<u32>() {
+                Ok(v) if v > 0 => { KRB5_PREAUTH_FAILURES = v; },
+                _ => { SCLogWarning!("Invalid value for krb5.preauth-failures: {}", val); },
+            }
+        }
+        KRB5_SPRAYING_PRINCIPALS = conf_get_name_threshold(
+            "spraying-principals", KRB5_DEFAULT_SPRAYING_PRINCIPALS);
+        KRB5_KERBEROASTING_SERVICES = conf_get_name_threshold(
+            "kerberoasting-services", KRB5_DEFAULT_KERBEROASTING_SERVICES);
+    }
+    krb5_context_setup(conf_get_bool("app-layer.protocols.krb5.track-tickets"));
+}
+
+fn krb5_context_setup(track_tickets: bool) {
+    unsafe {
+        KRB5_TRACK_TICKETS = track_tickets;
+        KRB5_CONTEXT = Some(Mutex::new(KRB5Context::new()));
+    }
+}
+
+/// Initialize the global context once for the unit tests, with ticket
+/// tracking enabled
+#[cfg(test)]
+pub fn krb5_context_init_test() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| krb5_context_setup(true));
+}
+
+/// Return true if TGS-REQ using unknown TGTs should be reported
+pub fn krb5_track_tickets() -> bool {
+    unsafe { KRB5_TRACK_TICKETS }
+}
+
+/// Return the threshold of the RepeatedPreauthFailures event
+pub fn krb5_preauth_failures() -> u32 {
+    unsafe { KRB5_PREAUTH_FAILURES }
+}
+
+/// Return the threshold of the PasswordSpraying event
+pub fn krb5_spraying_principals() -> usize {
+    unsafe { KRB5_SPRAYING_PRINCIPALS }
+}
+
+/// Return the threshold of the Kerberoasting event
+pub fn krb5_kerberoasting_services() -> usize {
+    unsafe { KRB5_KERBEROASTING_SERVICES }
+}
+
+/// Run `f` with the global context locked. Returns None if the context is
+/// not initialized.
+pub fn krb5_with_context<T, F>(f: F) -> Option<T>
+    where F: FnOnce(&mut KRB5Context) -> T
+{
+    let ctx = unsafe {
+        match KRB5_CONTEXT {
+            Some(ref ctx) => ctx,
+            None => return None,
+        }
+    };
+    match ctx.lock() {
+        Ok(mut guard) => Some(f(&mut guard)),
+        Err(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_krb5_context_tickets() {
+        let mut ctx = KRB5Context::new();
+        let t1 = ticket_fingerprint(&[1, 2, 3, 4]);
+        let t2 = ticket_fingerprint(&[1, 2, 3, 5]);
+        ctx.add_ticket(t1);
+        assert!(ctx.has_ticket(t1));
+        assert!(!ctx.has_ticket(t2));
+        for i in 0..KRB5_MAX_TICKETS as u64 {
+            ctx.add_ticket(i);
+        }
+        // oldest entry evicted
+        assert!(!ctx.has_ticket(t1));
+        assert_eq!(ctx.tickets.len(), KRB5_MAX_TICKETS);
+    }
+
+    #[test]
+    fn test_krb5_context_kpasswd_tickets() {
+        let mut ctx = KRB5Context::new();
+        let t1 = ticket_fingerprint(&[1, 2, 3, 4]);
+        let t2 = ticket_fingerprint(&[1, 2, 3, 5]);
+        ctx.add_kpasswd_ticket(t1, "alice@CONTOSO.LOCAL");
+        assert_eq!(ctx.get_kpasswd_principal(t1), Some("alice@CONTOSO.LOCAL".to_string()));
+        assert_eq!(ctx.get_kpasswd_principal(t2), None);
+        for i in 0..KRB5_MAX_TICKETS as u64 {
+            ctx.add_kpasswd_ticket(i, "bob@CONTOSO.LOCAL");
+        }
+        // oldest entry evicted
+        assert_eq!(ctx.get_kpasswd_principal(t1), None);
+        assert_eq!(ctx.kpasswd_tickets.len(), KRB5_MAX_TICKETS);
+    }
+
+    #[test]
+    fn test_krb5_context_principals() {
+        let mut ctx = KRB5Context::new();
+        ctx.update_principal("alice@CONTOSO.LOCAL", |s| s.as_req += 1);
+        let stats = ctx.update_principal("alice@CONTOSO.LOCAL", |s| {
+            s.errors += 1;
+            s.preauth_failures += 1;
+        });
+        assert_eq!(stats.as_req, 1);
+        assert_eq!(stats.errors, 1);
+        assert_eq!(stats.preauth_failures, 1);
+        let stats = ctx.update_principal("bob@CONTOSO.LOCAL", |_| ());
+        assert_eq!(stats, KRB5PrincipalStats::default());
+    }
+
+    #[test]
+    fn test_krb5_context_sources() {
+        let mut ctx = KRB5Context::new();
+        let client: IpAddr = "10.0.0.1".parse().unwrap();
+        let other: IpAddr = "10.0.0.2".parse().unwrap();
+        // the same source over several flows
+        assert_eq!(ctx.update_source(client, 100, |s| s.add_preauth_failure("alice@C.L")), 1);
+        assert_eq!(ctx.update_source(client, 101, |s| s.add_preauth_failure("bob@C.L")), 2);
+        assert_eq!(ctx.update_source(client, 102, |s| s.add_preauth_failure("bob@C.L")), 2);
+        assert_eq!(ctx.update_source(other, 102, |s| s.add_preauth_failure("carol@C.L")), 1);
+        assert_eq!(ctx.update_source(client, 103, |s| s.add_weak_tgs_sname("MSSQLSvc/db")), 1);
+
+        // names are cleared when the period is over
+        let ts = 100 + KRB5_SOURCE_PERIOD;
+        assert_eq!(ctx.update_source(client, ts, |s| s.add_preauth_failure("carol@C.L")), 1);
+        assert_eq!(ctx.update_source(client, ts, |s| s.add_weak_tgs_sname("HTTP/web")), 1);
+
+        // the number of names is bounded
+        for i in 0..KRB5_MAX_SOURCE_NAMES + 10 {
+            ctx.update_source(other, 200, |s| s.add_weak_tgs_sname(&format!("svc/{}", i)));
+        }
+        assert_eq!(ctx.update_source(other, 200, |s| s.add_weak_tgs_sname("svc/x")),
+                   KRB5_MAX_SOURCE_NAMES);
+    }
+}
@@ -0,0 +1,115 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+//! Kerberos change and set password protocol framing (RFC 3244), used
+//! on port 464.
+//!
+//! Requests contain an AP-REQ followed by a KRB-PRIV holding the new
+//! password. Replies contain an AP-REP followed by a KRB-PRIV holding the
+//! result, or an empty AP-REP followed by a KRB-ERROR.
+
+use nom::{be_u16, ErrorKind, IResult};
+
+/// Change password (RFC 3244 section 2)
+pub const KPASSWD_VERSION_CHANGE: u16 = 0x0001;
+/// Set password (RFC 3244 section 2)
+pub const KPASSWD_VERSION_SET: u16 = 0xff80;
+
+const KPASSWD_INVALID_LENGTH: u32 = 1;
+
+pub struct KpasswdMessage<'a> {
+    pub version: u16,
+    /// AP-REQ in requests, AP-REP in replies (empty on error)
+    pub ap: &'a [u8],
+    /// KRB-PRIV, or KRB-ERROR in error replies
+    pub data: &'a [u8],
+}
+
+pub fn parse_kpasswd_message<'a>(i: &'a [u8]) -> IResult<&'a [u8], KpasswdMessage<'a>> {
+    let (rem, len) = be_u16(i)?;
+    let (rem, version) = be_u16(rem)?;
+    let (rem, ap_len) = be_u16(rem)?;
+    if (len as usize) < 6 + ap_len as usize {
+        return Err(nom::Err::Error(error_position!(
+            i,
+            ErrorKind::Custom(KPASSWD_INVALID_LENGTH)
+        )));
+    }
+    let (rem, ap) = take!(rem, ap_len)?;
+    let (rem, data) = take!(rem, len as usize - 6 - ap_len as usize)?;
+    Ok((rem, KpasswdMessage { version, ap, data }))
+}
+
+/// Return true if the input starts with a kpasswd message. Messages
+/// carry an AP-REQ, an AP-REP or, in error replies, a KRB-ERROR.
+pub fn probe_kpasswd(i: &[u8]) -> bool {
+    match parse_kpasswd_message(i) {
+        Ok((_, msg)) => {
+            if msg.version != KPASSWD_VERSION_CHANGE && msg.version != KPASSWD_VERSION_SET {
+                return false;
+            }
+            match (msg.ap.first(), msg.data.first()) {
+                (Some(&0x6e), _) | (Some(&0x6f), _) => true,
+                (None, Some(&0x7e)) => true,
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kpasswd_parse_request() {
+        let buf = [
+            0x00, 0x0c, 0xff, 0x80, 0x00, 0x02, // header
+            0x6e, 0x00, // AP-REQ
+            0x75, 0x02, 0x30, 0x00, // KRB-PRIV
+            0x01, // next message
+        ];
+        let (rem, msg) = parse_kpasswd_message(&buf).unwrap();
+        assert_eq!(rem, &[0x01]);
+        assert_eq!(msg.version, KPASSWD_VERSION_SET);
+        assert_eq!(msg.ap, &[0x6e, 0x00]);
+        assert_eq!(msg.data, &[0x75, 0x02, 0x30, 0x00]);
+        assert!(probe_kpasswd(&buf));
+    }
+
+    #[test]
+    fn test_kpasswd_parse_error_reply() {
+        let buf = [0x00, 0x08, 0x00, 0x01, 0x00, 0x00, 0x7e, 0x00];
+        let (_, msg) = parse_kpasswd_message(&buf).unwrap();
+        assert_eq!(msg.version, KPASSWD_VERSION_CHANGE);
+        assert!(msg.ap.is_empty());
+        assert!(probe_kpasswd(&buf));
+    }
+
+    #[test]
+    fn test_kpasswd_invalid() {
+        // AP-REQ length larger than the message
+        let buf = [0x00, 0x06, 0x00, 0x01, 0x00, 0x02, 0x6e, 0x00];
+        assert!(parse_kpasswd_message(&buf).is_err());
+        // truncated
+        assert!(parse_kpasswd_message(&buf[..4]).is_err());
+        // AS-REQ
+        let buf = [0x6a, 0x81, 0xa0, 0x30, 0x81, 0x9d, 0xa1, 0x03];
+        assert!(!probe_kpasswd(&buf));
+    }
+}
@@ -18,33 +18,41 @@
 // written by Pierre Chifflier  <chifflier@wzdftpd.net>
 
 use std;
-use std::ffi::{CStr,CString};
+use std::ffi::CString;
+use std::net::IpAddr;
 use nom;
 use nom::be_u32;
-use der_parser::der_read_element_header;
+use der_parser::{DerObject,DerObjectContent,der_read_element_header};
 use kerberos_parser::krb5_parser;
-use kerberos_parser::krb5::{EncryptionType,ErrorCode,MessageType,PrincipalName,Realm};
-use crate::applayer;
+use kerberos_parser::krb5::{AddressType,EncryptedData,EncryptionType,ErrorCode,HostAddress,KdcRep,KdcReq,MessageType,PAType,PrincipalName,Realm,Ticket};
+use crate::applayer::{self, AppLayerEvent};
 use crate::core;
 use crate::core::{AppProto,Flow,ALPROTO_FAILED,ALPROTO_UNKNOWN,STREAM_TOCLIENT,STREAM_TOSERVER,sc_detect_engine_state_free};
 use crate::parser::*;
+use crate::krb::context::{KRB5PrincipalStats,krb5_context_init,krb5_track_tickets,krb5_with_context,ticket_fingerprint};
+use crate::krb::context::{krb5_kerberoasting_services,krb5_preauth_failures,krb5_spraying_principals};
+use crate::krb::kpasswd::{parse_kpasswd_message,probe_kpasswd};
 
 use crate::log::*;
 
+/// PA-FX-FAST (RFC 6113), not named by kerberos-parser
+const PA_FX_FAST: PAType = PAType(136);
+/// PA-ENCRYPTED-CHALLENGE (RFC 6113), not named by kerberos-parser
+const PA_ENCRYPTED_CHALLENGE: PAType = PAType(138);
+
 #[repr(u32)]
+#[derive(AppLayerEvent)]
 pub enum KRB5Event {
     MalformedData = 0,
     WeakEncryption,
-}
-
-impl KRB5Event {
-    fn from_i32(value: i32) -> Option<KRB5Event> {
-        match value {
-            0 => Some(KRB5Event::MalformedData),
-            1 => Some(KRB5Event::WeakEncryption),
-            _ => None,
-        }
-    }
+    NoPreauth,
+    Kerberoasting,
+    OverpassTheHash,
+    UnusualPacRequest,
+    UnknownTgt,
+    UnusualKrbtgtEtype,
+    RepeatedPreauthFailures,
+    PasswordSpraying,
 }
 
 pub struct KRB5State {
@@ -55,6 +63,24 @@ pub struct KRB5State {
     pub record_tc: usize,
     pub defrag_buf_tc: Vec<u8>,
 
+    /// Whether the last AS-REQ contained pre-authentication data
+    as_req_preauth: Option<bool>,
+
+    /// Client principal of the last AS-REQ
+    as_req_principal: Option<String>,
+
+    /// Address of the client, to count names per source across flows
+    client_addr: Option<IpAddr>,
+
+    /// Last time seen of the flow, in seconds
+    ts: u64,
+
+    /// Version of the last kpasswd request
+    kpasswd_version: Option<u16>,
+
+    /// Client principal of the last kpasswd request
+    kpasswd_principal: Option<String>,
+
     /// List of transactions for this session
     transactions: Vec<KRB5Transaction>,
 
@@ -76,6 +102,33 @@ pub struct KRB5Transaction {
     /// Encryption used (only in AS-REP and TGS-REP)
     pub etype: Option<EncryptionType>,
 
+    /// Encryption types requested by the client (only in AS-REQ and TGS-REQ)
+    pub req_etypes: Vec<EncryptionType>,
+    /// KDC options requested by the client, as the first 32 bits
+    pub kdc_options: Option<u32>,
+    /// Types of the pre-authentication data sent by the client
+    pub padata_types: Vec<PAType>,
+    /// Value of the PA-PAC-REQUEST include-pac flag, if present
+    pub include_pac: Option<bool>,
+    /// Requested expiration time of the ticket
+    pub till: Option<String>,
+    /// Requested renewal time of the ticket, if present
+    pub rtime: Option<String>,
+    /// Addresses for which the ticket is requested
+    pub addresses: Vec<String>,
+
+    /// Encryption of the ticket (in AS-REP and TGS-REP, or the TGT in TGS-REQ)
+    pub ticket_etype: Option<EncryptionType>,
+
+    /// Counters of the client principal, after this transaction
+    pub principal_stats: Option<KRB5PrincipalStats>,
+
+    /// kpasswd protocol version, if the message was sent to kpasswd
+    pub kpasswd_version: Option<u16>,
+    /// Client principal of the kpasswd ticket, if it was issued in a
+    /// monitored AS-REP or TGS-REP
+    pub kpasswd_principal: Option<String>,
+
     /// Error code, if request has failed
     pub error_code: Option<ErrorCode>,
 
@@ -107,75 +160,78 @@ impl KRB5State {
             defrag_buf_ts: Vec::new(),
             record_tc: 0,
             defrag_buf_tc: Vec::new(),
+            as_req_preauth: None,
+            as_req_principal: None,
+            client_addr: None,
+            ts: 0,
+            kpasswd_version: None,
+            kpasswd_principal: None,
             transactions: Vec::new(),
             tx_id: 0,
         }
     }
 
+    /// Record the client address and the time of the flow, used to count
+    /// the names seen per source
+    fn update_flow(&mut self, flow: *const core::Flow) {
+        if self.client_addr.is_none() {
+            self.client_addr = core::flow_get_client_addr(flow);
+        }
+        self.ts = core::flow_get_last_time(flow);
+    }
+
     /// Parse a Kerberos request message
     ///
     /// Returns The number of messages parsed, or -1 on error
-    fn parse(&mut self, i: &[u8], _direction: u8) -> i32 {
+    fn parse(&mut self, i: &[u8], direction: u8) -> i32 {
+        if probe_kpasswd(i) {
+            return self.parse_kpasswd(i, direction);
+        }
         match der_read_element_header(i) {
             Ok((_rem,hdr)) => {
                 // Kerberos messages start with an APPLICATION header
                 if hdr.class != 0b01 { return 1; }
                 match hdr.tag {
                     10 => {
+                        let res = krb5_parser::parse_as_req(i);
+                        if let Ok((_,kdc_req)) = res {
+                            self.handle_kdc_req(kdc_req, MessageType::KRB_AS_REQ);
+                        };
                         self.req_id = hdr.tag;
                     },
                     11 => {
                         let res = krb5_parser::parse_as_rep(i);
                         if let Ok((_,kdc_rep)) = res {
-                            let mut tx = self.new_tx();
-                            tx.msg_type = MessageType::KRB_AS_REP;
-                            tx.cname = Some(kdc_rep.cname);
-                            tx.realm = Some(kdc_rep.crealm);
-                            tx.sname = Some(kdc_rep.ticket.sname);
-                            tx.etype = Some(kdc_rep.enc_part.etype);
-                            self.transactions.push(tx);
-                            if test_weak_encryption(kdc_rep.enc_part.etype) {
-                                self.set_event(KRB5Event::WeakEncryption);
-                            }
+                            self.handle_kdc_rep(kdc_rep, MessageType::KRB_AS_REP);
                         };
+                        self.as_req_preauth = None;
                         self.req_id = 0;
                     },
                     12 => {
+                        let res = krb5_parser::parse_tgs_req(i);
+                        if let Ok((_,kdc_req)) = res {
+                            self.handle_kdc_req(kdc_req, MessageType::KRB_TGS_REQ);
+                        };
                         self.req_id = hdr.tag;
                     },
                     13 => {
                         let res = krb5_parser::parse_tgs_rep(i);
                         if let Ok((_,kdc_rep)) = res {
-                            let mut tx = self.new_tx();
-                            tx.msg_type = MessageType::KRB_TGS_REP;
-                            tx.cname = Some(kdc_rep.cname);
-                            tx.realm = Some(kdc_rep.crealm);
-                            tx.sname = Some(kdc_rep.ticket.sname);
-                            tx.etype = Some(kdc_rep.enc_part.etype);
-                            self.transactions.push(tx);
-                            if test_weak_encryption(kdc_rep.enc_part.etype) {
-                                self.set_event(KRB5Event::WeakEncryption);
-                            }
+                            self.handle_kdc_rep(kdc_rep, MessageType::KRB_TGS_REP);
                         };
                         self.req_id = 0;
                     },
                     14 => {
+                        self.handle_ap_req(i);
                         self.req_id = hdr.tag;
                     },
                     15 => {
+                        self.handle_ap_rep(i);
                         self.req_id = 0;
                     },
                     30 => {
-                        let res = krb5_parser::parse_krb_error(i);
-                        if let Ok((_,error)) = res {
-                            let mut tx = self.new_tx();
-                            tx.msg_type = MessageType(self.req_id as u32);
-                            tx.cname = error.cname;
-                            tx.realm = error.crealm;
-                            tx.sname = Some(error.sname);
-                            tx.error_code = Some(error.error_code);
-                            self.transactions.push(tx);
-                        };
+                        self.handle_krb_error(i);
+                        self.as_req_preauth = None;
                         self.req_id = 0;
                     },
                     _ => { SCLogDebug!("unknown/unsupported tag {}", hdr.tag); },
@@ -195,6 +251,277 @@ impl KRB5State {
         }
     }
 
+    /// Create a transaction for a KRB-ERROR, and update the counters of
+    /// the client principal. Returns true if the message was parsed.
+    fn handle_krb_error(&mut self, i: &[u8]) -> bool {
+        let res = krb5_parser::parse_krb_error(i);
+        if let Ok((_,error)) = res {
+            let mut tx = self.new_tx();
+            tx.msg_type = MessageType(self.req_id as u32);
+            tx.cname = error.cname;
+            tx.realm = error.crealm;
+            tx.sname = Some(error.sname);
+            tx.error_code = Some(error.error_code);
+            // the client name is optional in errors
+            let principal = match (tx.cname.as_ref(), tx.realm.as_ref()) {
+                (Some(cname), Some(realm)) => Some(principal_name(cname, realm)),
+                (Some(cname), None) => Some(principal_name(cname, &error.realm)),
+                _ => self.as_req_principal.clone(),
+            };
+            let preauth_failed = error.error_code == ErrorCode::KDC_ERR_PREAUTH_FAILED;
+            if let Some(ref principal) = principal {
+                tx.principal_stats = krb5_with_context(|ctx| {
+                    ctx.update_principal(principal, |s| {
+                        s.errors += 1;
+                        if preauth_failed {
+                            s.preauth_failures += 1;
+                        }
+                    })
+                });
+            }
+            let failures = tx.principal_stats.map_or(0, |s| s.preauth_failures);
+            self.transactions.push(tx);
+            if preauth_failed {
+                if failures >= krb5_preauth_failures() {
+                    self.set_event(KRB5Event::RepeatedPreauthFailures);
+                }
+                if let (Some(principal), Some(addr)) = (principal, self.client_addr) {
+                    let ts = self.ts;
+                    let principals = krb5_with_context(|ctx| {
+                        ctx.update_source(addr, ts, |s| s.add_preauth_failure(&principal))
+                    });
+                    if principals.map_or(false, |n| n >= krb5_spraying_principals()) {
+                        self.set_event(KRB5Event::PasswordSpraying);
+                    }
+                }
+            }
+            return true;
+        }
+        false
+    }
+
+    /// Create a transaction for an AP-REQ, with the ticket information
+    fn handle_ap_req(&mut self, i: &[u8]) -> bool {
+        let res = krb5_parser::parse_ap_req(i);
+        if let Ok((_,ap_req)) = res {
+            let mut tx = self.new_tx();
+            tx.msg_type = MessageType::KRB_AP_REQ;
+            let ticket_enc = ticket_enc_part(&ap_req.ticket);
+            tx.ticket_etype = ticket_enc.as_ref().map(|e| e.etype);
+            // the client name is encrypted in the ticket, use the one
+            // of the reply which issued it
+            if is_kpasswd_service(&ap_req.ticket.sname) {
+                if let Some(ref enc_part) = ticket_enc {
+                    let fingerprint = ticket_fingerprint(enc_part.cipher);
+                    tx.kpasswd_principal = krb5_with_context(|ctx| {
+                        ctx.get_kpasswd_principal(fingerprint)
+                    }).unwrap_or(None);
+                }
+            }
+            tx.realm = Some(ap_req.ticket.realm);
+            tx.sname = Some(ap_req.ticket.sname);
+            self.transactions.push(tx);
+            return true;
+        }
+        false
+    }
+
+    /// Create a transaction for an AP-REP
+    fn handle_ap_rep(&mut self, i: &[u8]) -> bool {
+        let res = krb5_parser::parse_ap_rep(i);
+        if let Ok((_,ap_rep)) = res {
+            let mut tx = self.new_tx();
+            tx.msg_type = MessageType::KRB_AP_REP;
+            tx.etype = Some(ap_rep.enc_part.etype);
+            self.transactions.push(tx);
+            return true;
+        }
+        false
+    }
+
+    /// Parse a kpasswd message. The password and the result are in the
+    /// encrypted KRB-PRIV, so only the AP-REQ, AP-REP or KRB-ERROR are
+    /// used.
+    fn parse_kpasswd(&mut self, i: &[u8], direction: u8) -> i32 {
+        match parse_kpasswd_message(i) {
+            Ok((_,msg)) => {
+                let created = if direction == STREAM_TOSERVER {
+                    self.kpasswd_version = Some(msg.version);
+                    self.kpasswd_principal = None;
+                    self.req_id = 14;
+                    self.handle_ap_req(msg.ap)
+                } else if msg.ap.is_empty() {
+                    let created = self.handle_krb_error(msg.data);
+                    self.req_id = 0;
+                    created
+                } else {
+                    self.req_id = 0;
+                    self.handle_ap_rep(msg.ap)
+                };
+                if !created {
+                    let mut tx = self.new_tx();
+                    tx.msg_type = MessageType(if direction == STREAM_TOSERVER { 14 } else { 15 });
+                    self.transactions.push(tx);
+                    self.set_event(KRB5Event::MalformedData);
+                }
+                // replies always use version 1, report the request version
+                // and principal
+                let version = self.kpasswd_version.unwrap_or(msg.version);
+                if let Some(tx) = self.transactions.last_mut() {
+                    tx.kpasswd_version = Some(version);
+                    if direction == STREAM_TOSERVER {
+                        self.kpasswd_principal = tx.kpasswd_principal.clone();
+                    } else {
+                        tx.kpasswd_principal = self.kpasswd_principal.clone();
+                    }
+                }
+                0
+            },
+            _ => {
+                SCLogDebug!("Error while parsing kpasswd data");
+                self.set_event(KRB5Event::MalformedData);
+                -1
+            },
+        }
+    }
+
+    /// Create a transaction for an AS-REQ or TGS-REQ, and check the
+    /// request for indicators of attacks
+    fn handle_kdc_req(&mut self, kdc_req: KdcReq, msg_type: MessageType) {
+        let mut tx = self.new_tx();
+        tx.msg_type = msg_type;
+        tx.kdc_options = kdc_options_value(&kdc_req.req_body.kdc_options);
+        tx.till = kerberos_time_string(&kdc_req.req_body.till);
+        tx.rtime = kdc_req.req_body.rtime.as_ref().and_then(kerberos_time_string);
+        tx.addresses = kdc_req.req_body.addresses.iter().map(host_address_string).collect();
+        let mut tgt = None;
+        for padata in kdc_req.padata.iter() {
+            tx.padata_types.push(padata.padata_type);
+            if padata.padata_type == PAType::PA_PAC_REQUEST {
+                tx.include_pac = parse_pac_request(padata.padata_value);
+            } else if padata.padata_type == PAType::PA_TGS_REQ {
+                // the AP-REQ contains the ticket used to authenticate
+                if let Ok((_,ap_req)) = krb5_parser::parse_ap_req(padata.padata_value) {
+                    if let Some(enc_part) = ticket_enc_part(&ap_req.ticket) {
+                        tx.ticket_etype = Some(enc_part.etype);
+                        if is_krbtgt(&ap_req.ticket.sname) {
+                            tgt = Some(ticket_fingerprint(enc_part.cipher));
+                        }
+                    }
+                }
+            }
+        }
+        tx.cname = kdc_req.req_body.cname;
+        tx.realm = Some(kdc_req.req_body.realm);
+        tx.sname = kdc_req.req_body.sname;
+        tx.req_etypes = kdc_req.req_body.etype;
+
+        let weak_only = test_weak_etypes_only(&tx.req_etypes);
+        let has_enc_timestamp = tx.padata_types.contains(&PAType::PA_ENC_TS);
+        let preauth = has_enc_timestamp ||
+            tx.padata_types.contains(&PA_FX_FAST) ||
+            tx.padata_types.contains(&PA_ENCRYPTED_CHALLENGE);
+        let no_pac = tx.include_pac == Some(false);
+        let sname = tx.sname.as_ref().map(|s| format!("{}", s));
+        if msg_type == MessageType::KRB_AS_REQ {
+            self.as_req_principal = match (tx.cname.as_ref(), tx.realm.as_ref()) {
+                (Some(cname), Some(realm)) => Some(principal_name(cname, realm)),
+                _ => None,
+            };
+            if let Some(ref principal) = self.as_req_principal {
+                tx.principal_stats = krb5_with_context(|ctx| {
+                    ctx.update_principal(principal, |s| s.as_req += 1)
+                });
+            }
+        }
+        self.transactions.push(tx);
+
+        // a TGT that was not issued on the monitored links could have
+        // been forged (golden ticket)
+        if let Some(fingerprint) = tgt {
+            if is_unknown_tgt(fingerprint) {
+                self.set_event(KRB5Event::UnknownTgt);
+            }
+        }
+
+        if msg_type == MessageType::KRB_AS_REQ {
+            self.as_req_preauth = Some(preauth);
+            // timestamp encrypted with the RC4 key, i.e the NT hash
+            if has_enc_timestamp && weak_only {
+                self.set_event(KRB5Event::OverpassTheHash);
+            }
+            // a TGT without PAC is not needed by regular clients
+            if no_pac {
+                self.set_event(KRB5Event::UnusualPacRequest);
+            }
+        } else if weak_only {
+            if let (Some(sname), Some(addr)) = (sname, self.client_addr) {
+                let ts = self.ts;
+                let snames = krb5_with_context(|ctx| {
+                    ctx.update_source(addr, ts, |s| s.add_weak_tgs_sname(&sname))
+                });
+                if snames.map_or(false, |n| n >= krb5_kerberoasting_services()) {
+                    self.set_event(KRB5Event::Kerberoasting);
+                }
+            }
+        }
+    }
+
+    /// Create a transaction for an AS-REP or TGS-REP, and record the
+    /// issued ticket
+    fn handle_kdc_rep(&mut self, kdc_rep: KdcRep, msg_type: MessageType) {
+        let mut tx = self.new_tx();
+        tx.msg_type = msg_type;
+        let etype = kdc_rep.enc_part.etype;
+        let ticket_enc = ticket_enc_part(&kdc_rep.ticket);
+        let ticket_etype = ticket_enc.as_ref().map(|e| e.etype);
+        let is_tgt = is_krbtgt(&kdc_rep.ticket.sname);
+        let is_kpasswd = is_kpasswd_service(&kdc_rep.ticket.sname);
+        let fingerprint = ticket_enc.as_ref().map(|e| ticket_fingerprint(e.cipher));
+        let principal = principal_name(&kdc_rep.cname, &kdc_rep.crealm);
+        tx.principal_stats = krb5_with_context(|ctx| {
+            if let Some(fingerprint) = fingerprint {
+                if is_tgt {
+                    ctx.add_ticket(fingerprint);
+                }
+                if is_kpasswd {
+                    ctx.add_kpasswd_ticket(fingerprint, &principal);
+                }
+            }
+            ctx.update_principal(&principal, |s| {
+                if msg_type == MessageType::KRB_AS_REP {
+                    s.as_rep += 1;
+                    s.preauth_failures = 0;
+                } else {
+                    s.tgs_rep += 1;
+                }
+                if is_kpasswd {
+                    s.kpasswd_tickets += 1;
+                }
+            })
+        });
+        tx.cname = Some(kdc_rep.cname);
+        tx.realm = Some(kdc_rep.crealm);
+        tx.sname = Some(kdc_rep.ticket.sname);
+        tx.etype = Some(etype);
+        tx.ticket_etype = ticket_etype;
+        self.transactions.push(tx);
+        if test_weak_encryption(etype) {
+            self.set_event(KRB5Event::WeakEncryption);
+        }
+        if msg_type == MessageType::KRB_AS_REP {
+            // the ticket was issued without the client
+            // proving the knowledge of its key
+            if self.as_req_preauth == Some(false) {
+                self.set_event(KRB5Event::NoPreauth);
+            }
+        } else if is_tgt && ticket_etype.map_or(false, test_weak_encryption) {
+            // TGTs are encrypted with the key of the krbtgt account,
+            // which supports AES in current domains
+            self.set_event(KRB5Event::UnusualKrbtgtEtype);
+        }
+    }
+
     pub fn free(&mut self) {
         // All transactions are freed when the `transactions` object is freed.
         // But let's be explicit
@@ -235,6 +562,17 @@ impl KRB5Transaction {
             realm: None,
             sname: None,
             etype: None,
+            req_etypes: Vec::new(),
+            kdc_options: None,
+            padata_types: Vec::new(),
+            include_pac: None,
+            till: None,
+            rtime: None,
+            addresses: Vec::new(),
+            ticket_etype: None,
+            principal_stats: None,
+            kpasswd_version: None,
+            kpasswd_principal: None,
             error_code: None,
             id: id,
             de_state: None,
@@ -268,6 +606,119 @@ pub fn test_weak_encryption(alg:EncryptionType) -> bool {
     }
 }
 
+/// Return the principal name, as `name@REALM`
+pub fn principal_name(cname: &PrincipalName, realm: &Realm) -> String {
+    format!("{}@{}", cname, realm.0)
+}
+
+/// Return true if the principal is the ticket-granting service
+/// Parse the encrypted part of a ticket, which kerberos-parser leaves as
+/// raw EncryptedData
+fn ticket_enc_part<'a>(ticket: &Ticket<'a>) -> Option<EncryptedData<'a>> {
+    krb5_parser::parse_encrypted(ticket.enc_part).ok().map(|(_,enc_part)| enc_part)
+}
+
+/// Return true if a TGT was not issued on the monitored links, and this
+/// should be reported
+fn is_unknown_tgt(fingerprint: u64) -> bool {
+    krb5_track_tickets() &&
+        krb5_with_context(|ctx| ctx.has_ticket(fingerprint)) == Some(false)
+}
+
+fn is_krbtgt(sname: &PrincipalName) -> bool {
+    sname.name_string.get(0).map_or(false, |s| s.eq_ignore_ascii_case("krbtgt"))
+}
+
+/// Return true if the principal is the password changing service
+fn is_kpasswd_service(sname: &PrincipalName) -> bool {
+    sname.name_string.len() == 2 &&
+        sname.name_string[0] == "kadmin" && sname.name_string[1] == "changepw"
+}
+
+/// Return true if the list of encryption types is not empty and contains
+/// only weak ciphers, like a client asking for RC4-HMAC only
+pub fn test_weak_etypes_only(etypes: &[EncryptionType]) -> bool {
+    etypes.len() > 0 && etypes.iter().all(|&e| test_weak_encryption(e))
+}
+
+/// Names of the KDC options (RFC 4120 section 5.4.1), indexed by bit
+/// number. Bit 0 is the most significant bit.
+const KDC_OPTION_NAMES: &[(u32, &str)] = &[
+    (1, "forwardable"),
+    (2, "forwarded"),
+    (3, "proxiable"),
+    (4, "proxy"),
+    (5, "allow-postdate"),
+    (6, "postdated"),
+    (8, "renewable"),
+    (14, "cname-in-addl-tkt"),
+    (15, "canonicalize"),
+    (26, "disable-transited-check"),
+    (27, "renewable-ok"),
+    (28, "enc-tkt-in-skey"),
+    (30, "renew"),
+    (31, "validate"),
+];
+
+/// Return the names of the KDC options set in `options`
+pub fn kdc_option_names(options: u32) -> Vec<&'static str> {
+    KDC_OPTION_NAMES.iter()
+        .filter(|&&(bit, _)| options & (0x8000_0000 >> bit) != 0)
+        .map(|&(_, name)| name)
+        .collect()
+}
+
+/// Return the first 32 bits of the KDC options bit string
+fn kdc_options_value(obj: &DerObject) -> Option<u32> {
+    match obj.content {
+        DerObjectContent::BitString(_, ref b) => {
+            let mut value = 0;
+            for i in 0..4 {
+                value <<= 8;
+                value |= *b.data.get(i).unwrap_or(&0) as u32;
+            }
+            Some(value)
+        },
+        _ => None,
+    }
+}
+
+fn kerberos_time_string(obj: &DerObject) -> Option<String> {
+    match obj.content {
+        DerObjectContent::GeneralizedTime(t) => {
+            std::str::from_utf8(t).ok().map(|s| s.to_string())
+        },
+        _ => None,
+    }
+}
+
+fn host_address_string(addr: &HostAddress) -> String {
+    let data = addr.address;
+    if addr.addr_type == AddressType::IPV4 && data.len() == 4 {
+        format!("{}", std::net::Ipv4Addr::new(data[0], data[1], data[2], data[3]))
+    } else if addr.addr_type == AddressType::IPV6 && data.len() == 16 {
+        let mut a = [0u8; 16];
+        a.copy_from_slice(data);
+        format!("{}", std::net::Ipv6Addr::from(a))
+    } else if addr.addr_type == AddressType::NETBIOS {
+        String::from_utf8_lossy(data).trim_end().to_string()
+    } else {
+        to_hex_string(data)
+    }
+}
+
+/// Parse the value of a PA-PAC-REQUEST, and return the include-pac flag
+///
+/// KERB-PA-PAC-REQUEST ::= SEQUENCE {
+///     include-pac[0] BOOLEAN
+/// }
+fn parse_pac_request(i: &[u8]) -> Option<bool> {
+    match i {
+        &[0x30, 0x05, 0xa0, 0x03, 0x01, 0x01, v] => Some(v != 0),
+        _ => None,
+    }
+}
+
 
 
 
@@ -374,27 +825,6 @@ pub extern "C" fn rs_krb5_state_get_tx_detect_state(
     }
 }
 
-#[no_mangle]
-pub extern "C" fn rs_krb5_state_get_event_info_by_id(event_id: std::os::raw::c_int,
-                                                     event_name: *mut *const std::os::raw::c_char,
-                                                     event_type: *mut core::AppLayerEventType)
-                                                     -> i8
-{
-    if let Some(e) = KRB5Event::from_i32(event_id as i32) {
-        let estr = match e {
-            KRB5Event::MalformedData  => { "malformed_data\0" },
-            KRB5Event::WeakEncryption => { "weak_encryption\0" },
-        };
-        unsafe{
-            *event_name = estr.as_ptr() as *const std::os::raw::c_char;
-            *event_type = core::APP_LAYER_EVENT_TYPE_TRANSACTION;
-        };
-        0
-    } else {
-        -1
-    }
-}
-
 #[no_mangle]
 pub extern "C" fn rs_krb5_state_get_events(tx: *mut std::os::raw::c_void)
                                           -> *mut core::AppLayerDecoderEvents
@@ -403,30 +833,6 @@ pub extern "C" fn rs_krb5_state_get_events(tx: *mut std::os::raw::c_void)
     return tx.events;
 }
 
-#[no_mangle]
-pub extern "C" fn rs_krb5_state_get_event_info(event_name: *const std::os::raw::c_char,
-                                              event_id: *mut std::os::raw::c_int,
-                                              event_type: *mut core::AppLayerEventType)
-                                              -> std::os::raw::c_int
-{
-    if event_name == std::ptr::null() { return -1; }
-    let c_event_name: &CStr = unsafe { CStr::from_ptr(event_name) };
-    let event = match c_event_name.to_str() {
-        Ok(s) => {
-            match s {
-                "malformed_data"     => KRB5Event::MalformedData as i32,
-                "weak_encryption"    => KRB5Event::WeakEncryption as i32,
-                _                    => -1, // unknown event
-            }
-        },
-        Err(_) => -1, // UTF-8 conversion failed
-    };
-    unsafe{
-        *event_type = core::APP_LAYER_EVENT_TYPE_TRANSACTION;
-        *event_id = event as std::os::raw::c_int;
-    };
-    0
-}
 static mut ALPROTO_KRB5 : AppProto = ALPROTO_UNKNOWN;
 
 #[no_mangle]
@@ -438,6 +844,8 @@ pub extern "C" fn rs_krb5_probing_parser(_flow: *const Flow,
     let slice = build_slice!(input,input_len as usize);
     let alproto = unsafe{ ALPROTO_KRB5 };
     if slice.len() <= 10 { return unsafe{ALPROTO_FAILED}; }
+    // kpasswd messages start with a small header
+    if probe_kpasswd(slice) { return alproto; }
     match der_read_element_header(slice) {
         Ok((rem, ref hdr)) => {
             // Kerberos messages start with an APPLICATION header
@@ -492,7 +900,7 @@ pub extern "C" fn rs_krb5_probing_parser_tcp(_flow: *const Flow,
 }
 
 #[no_mangle]
-pub extern "C" fn rs_krb5_parse_request(_flow: *const core::Flow,
+pub extern "C" fn rs_krb5_parse_request(flow: *const core::Flow,
                                        state: *mut std::os::raw::c_void,
                                        _pstate: *mut std::os::raw::c_void,
                                        input: *const u8,
@@ -501,11 +909,12 @@ pub extern "C" fn rs_krb5_parse_request(_flow: *const core::Flow,
                                        _flags: u8) -> i32 {
     let buf = build_slice!(input,input_len as usize);
     let state = cast_pointer!(state,KRB5State);
+    state.update_flow(flow);
     state.parse(buf, STREAM_TOSERVER)
 }
 
 #[no_mangle]
-pub extern "C" fn rs_krb5_parse_response(_flow: *const core::Flow,
+pub extern "C" fn rs_krb5_parse_response(flow: *const core::Flow,
                                        state: *mut std::os::raw::c_void,
                                        _pstate: *mut std::os::raw::c_void,
                                        input: *const u8,
@@ -514,11 +923,12 @@ pub extern "C" fn rs_krb5_parse_response(_flow: *const core::Flow,
                                        _flags: u8) -> i32 {
     let buf = build_slice!(input,input_len as usize);
     let state = cast_pointer!(state,KRB5State);
+    state.update_flow(flow);
     state.parse(buf, STREAM_TOCLIENT)
 }
 
 #[no_mangle]
-pub extern "C" fn rs_krb5_parse_request_tcp(_flow: *const core::Flow,
+pub extern "C" fn rs_krb5_parse_request_tcp(flow: *const core::Flow,
                                        state: *mut std::os::raw::c_void,
                                        _pstate: *mut std::os::raw::c_void,
                                        input: *const u8,
@@ -528,6 +938,7 @@ pub extern "C" fn rs_krb5_parse_request_tcp(_flow: *const core::Flow,
     if input_len < 4 { return -1; }
     let buf = build_slice!(input,input_len as usize);
     let state = cast_pointer!(state,KRB5State);
+    state.update_flow(flow);
 
     let mut v : Vec<u8>;
     let mut status = 0;
@@ -576,7 +987,7 @@ pub extern "C" fn rs_krb5_parse_request_tcp(_flow: *const core::Flow,
 }
 
 #[no_mangle]
-pub extern "C" fn rs_krb5_parse_response_tcp(_flow: *const core::Flow,
+pub extern "C" fn rs_krb5_parse_response_tcp(flow: *const core::Flow,
                                        state: *mut std::os::raw::c_void,
                                        _pstate: *mut std::os::raw::c_void,
                                        input: *const u8,
@@ -586,6 +997,7 @@ pub extern "C" fn rs_krb5_parse_response_tcp(_flow: *const core::Flow,
     if input_len < 4 { return -1; }
     let buf = build_slice!(input,input_len as usize);
     let state = cast_pointer!(state,KRB5State);
+    state.update_flow(flow);
 
     let mut v : Vec<u8>;
     let mut status = 0;
@@ -638,7 +1050,8 @@ const PARSER_NAME : &'static [u8] = b"krb5\0";
 
 #[no_mangle]
 pub unsafe extern "C" fn rs_register_krb5_parser() {
-    let default_port = CString::new("88").unwrap();
+    krb5_context_init();
+    let default_port = CString::new("[88,464]").unwrap();
     let mut parser = RustParser {
         name               : PARSER_NAME.as_ptr() as *const std::os::raw::c_char,
         default_port       : default_port.as_ptr(),
@@ -661,8 +1074,8 @@ pub unsafe extern "C" fn rs_register_krb5_parser() {
         get_de_state       : rs_krb5_state_get_tx_detect_state,
         set_de_state       : rs_krb5_state_set_tx_detect_state,
         get_events         : Some(rs_krb5_state_get_events),
-        get_eventinfo      : Some(rs_krb5_state_get_event_info),
-        get_eventinfo_byid : Some(rs_krb5_state_get_event_info_by_id),
+        get_eventinfo      : Some(KRB5Event::get_event_info),
+        get_eventinfo_byid : Some(KRB5Event::get_event_info_by_id),
         localstorage_new   : None,
         localstorage_free  : None,
         get_tx_mpm_id      : None,
@@ -700,3 +1113,152 @@ pub unsafe extern "C" fn rs_register_krb5_parser() {
         SCLogDebug!("Protocol detector and parser disabled for KRB5/TCP.");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::krb::context::krb5_context_init_test;
+
+    #[test]
+    fn test_krb5_parse_pac_request() {
+        let no_pac = [0x30, 0x05, 0xa0, 0x03, 0x01, 0x01, 0x00];
+        let pac = [0x30, 0x05, 0xa0, 0x03, 0x01, 0x01, 0xff];
+        assert_eq!(parse_pac_request(&no_pac), Some(false));
+        assert_eq!(parse_pac_request(&pac), Some(true));
+        assert_eq!(parse_pac_request(&pac[..6]), None);
+    }
+
+    #[test]
+    fn test_krb5_kdc_options() {
+        // forwardable, renewable, canonicalize, renewable-ok
+        assert_eq!(kdc_option_names(0x40810010),
+                   vec!["forwardable", "renewable", "canonicalize", "renewable-ok"]);
+        assert!(kdc_option_names(0).is_empty());
+    }
+
+    #[test]
+    fn test_krb5_principal() {
+        use kerberos_parser::krb5::NameType;
+        let krbtgt = PrincipalName{
+            name_type: NameType::KRB_NT_SRV_INST,
+            name_string: vec!["krbtgt".to_string(), "CONTOSO.LOCAL".to_string()],
+        };
+        let user = PrincipalName{
+            name_type: NameType::KRB_NT_PRINCIPAL,
+            name_string: vec!["alice".to_string()],
+        };
+        assert!(is_krbtgt(&krbtgt));
+        assert!(!is_krbtgt(&user));
+        assert_eq!(principal_name(&user, &Realm("CONTOSO.LOCAL".to_string())),
+                   "alice@CONTOSO.LOCAL");
+    }
+
+    #[test]
+    fn test_krb5_tgt_tracking() {
+        krb5_context_init_test();
+        // TGS-REQ for cifs/srv.contoso.local, authenticated with a TGT
+        // encrypted with RC4-HMAC
+        let tgs_req: &[u8] = &[
+            0x6c, 0x82, 0x01, 0x2e, 0x30, 0x82, 0x01, 0x2a, 0xa1, 0x03, 0x02, 0x01,
+            0x05, 0xa2, 0x03, 0x02, 0x01, 0x0c, 0xa3, 0x81, 0xb9, 0x30, 0x81, 0xb6,
+            0x30, 0x81, 0xb3, 0xa1, 0x03, 0x02, 0x01, 0x01, 0xa2, 0x81, 0xab, 0x04,
+            0x81, 0xa8, 0x6e, 0x81, 0xa5, 0x30, 0x81, 0xa2, 0xa0, 0x03, 0x02, 0x01,
+            0x05, 0xa1, 0x03, 0x02, 0x01, 0x0e, 0xa2, 0x07, 0x03, 0x05, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0xa3, 0x70, 0x61, 0x6e, 0x30, 0x6c, 0xa0, 0x03, 0x02,
+            0x01, 0x05, 0xa1, 0x0f, 0x1b, 0x0d, 0x43, 0x4f, 0x4e, 0x54, 0x4f, 0x53,
+            0x4f, 0x2e, 0x4c, 0x4f, 0x43, 0x41, 0x4c, 0xa2, 0x22, 0x30, 0x20, 0xa0,
+            0x03, 0x02, 0x01, 0x02, 0xa1, 0x19, 0x30, 0x17, 0x1b, 0x06, 0x6b, 0x72,
+            0x62, 0x74, 0x67, 0x74, 0x1b, 0x0d, 0x43, 0x4f, 0x4e, 0x54, 0x4f, 0x53,
+            0x4f, 0x2e, 0x4c, 0x4f, 0x43, 0x41, 0x4c, 0xa3, 0x30, 0x30, 0x2e, 0xa0,
+            0x03, 0x02, 0x01, 0x17, 0xa1, 0x03, 0x02, 0x01, 0x02, 0xa2, 0x22, 0x04,
+            0x20, 0xa0, 0xa0, 0xa0, 0xa0, 0xa0, 0xa0, 0xa0, 0xa0, 0xa0, 0xa0, 0xa0,
+            0xa0, 0xa0, 0xa0, 0xa0, 0xa0, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06,
+            0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f, 0xa4, 0x1b, 0x30,
+            0x19, 0xa0, 0x03, 0x02, 0x01, 0x12, 0xa2, 0x12, 0x04, 0x10, 0x60, 0x61,
+            0x62, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69, 0x6a, 0x6b, 0x6c, 0x6d,
+            0x6e, 0x6f, 0xa4, 0x62, 0x30, 0x60, 0xa0, 0x07, 0x03, 0x05, 0x00, 0x40,
+            0x81, 0x00, 0x10, 0xa2, 0x0f, 0x1b, 0x0d, 0x43, 0x4f, 0x4e, 0x54, 0x4f,
+            0x53, 0x4f, 0x2e, 0x4c, 0x4f, 0x43, 0x41, 0x4c, 0xa3, 0x24, 0x30, 0x22,
+            0xa0, 0x03, 0x02, 0x01, 0x02, 0xa1, 0x1b, 0x30, 0x19, 0x1b, 0x04, 0x63,
+            0x69, 0x66, 0x73, 0x1b, 0x11, 0x73, 0x72, 0x76, 0x2e, 0x63, 0x6f, 0x6e,
+            0x74, 0x6f, 0x73, 0x6f, 0x2e, 0x6c, 0x6f, 0x63, 0x61, 0x6c, 0xa5, 0x11,
+            0x18, 0x0f, 0x32, 0x30, 0x33, 0x37, 0x30, 0x39, 0x31, 0x33, 0x30, 0x32,
+            0x34, 0x38, 0x30, 0x35, 0x5a, 0xa7, 0x04, 0x02, 0x02, 0x30, 0x39, 0xa8,
+            0x05, 0x30, 0x03, 0x02, 0x01, 0x12,
+        ];
+        // TGS-REP for alice@CONTOSO.LOCAL, issuing the same TGT
+        let tgs_rep: &[u8] = &[
+            0x6d, 0x81, 0xc1, 0x30, 0x81, 0xbe, 0xa0, 0x03, 0x02, 0x01, 0x05, 0xa1,
+            0x03, 0x02, 0x01, 0x0d, 0xa3, 0x0f, 0x1b, 0x0d, 0x43, 0x4f, 0x4e, 0x54,
+            0x4f, 0x53, 0x4f, 0x2e, 0x4c, 0x4f, 0x43, 0x41, 0x4c, 0xa4, 0x12, 0x30,
+            0x10, 0xa0, 0x03, 0x02, 0x01, 0x01, 0xa1, 0x09, 0x30, 0x07, 0x1b, 0x05,
+            0x61, 0x6c, 0x69, 0x63, 0x65, 0xa5, 0x70, 0x61, 0x6e, 0x30, 0x6c, 0xa0,
+            0x03, 0x02, 0x01, 0x05, 0xa1, 0x0f, 0x1b, 0x0d, 0x43, 0x4f, 0x4e, 0x54,
+            0x4f, 0x53, 0x4f, 0x2e, 0x4c, 0x4f, 0x43, 0x41, 0x4c, 0xa2, 0x22, 0x30,
+            0x20, 0xa0, 0x03, 0x02, 0x01, 0x02, 0xa1, 0x19, 0x30, 0x17, 0x1b, 0x06,
+            0x6b, 0x72, 0x62, 0x74, 0x67, 0x74, 0x1b, 0x0d, 0x43, 0x4f, 0x4e, 0x54,
+            0x4f, 0x53, 0x4f, 0x2e, 0x4c, 0x4f, 0x43, 0x41, 0x4c, 0xa3, 0x30, 0x30,
+            0x2e, 0xa0, 0x03, 0x02, 0x01, 0x17, 0xa1, 0x03, 0x02, 0x01, 0x02, 0xa2,
+            0x22, 0x04, 0x20, 0xa0, 0xa0, 0xa0, 0xa0, 0xa0, 0xa0, 0xa0, 0xa0, 0xa0,
+            0xa0, 0xa0, 0xa0, 0xa0, 0xa0, 0xa0, 0xa0, 0x00, 0x01, 0x02, 0x03, 0x04,
+            0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f, 0xa6,
+            0x1b, 0x30, 0x19, 0xa0, 0x03, 0x02, 0x01, 0x12, 0xa2, 0x12, 0x04, 0x10,
+            0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x4b,
+            0x4c, 0x4d, 0x4e, 0x4f,
+        ];
+        // encrypted part of the TGT
+        let cipher = [
+            0xa0, 0xa0, 0xa0, 0xa0, 0xa0, 0xa0, 0xa0, 0xa0,
+            0xa0, 0xa0, 0xa0, 0xa0, 0xa0, 0xa0, 0xa0, 0xa0,
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07,
+            0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+        ];
+        let fingerprint = ticket_fingerprint(&cipher);
+
+        let mut state = KRB5State::new();
+        assert_eq!(state.parse(tgs_req, STREAM_TOSERVER), 0);
+        {
+            let tx = &state.transactions[0];
+            assert_eq!(tx.msg_type, MessageType::KRB_TGS_REQ);
+            assert_eq!(tx.padata_types, vec![PAType::PA_TGS_REQ]);
+            assert_eq!(tx.ticket_etype, Some(EncryptionType::RC4_HMAC));
+        }
+        // the TGT was never issued on the monitored links
+        assert!(is_unknown_tgt(fingerprint));
+
+        assert_eq!(state.parse(tgs_rep, STREAM_TOCLIENT), 0);
+        {
+            let tx = &state.transactions[1];
+            assert_eq!(tx.msg_type, MessageType::KRB_TGS_REP);
+            assert_eq!(tx.etype, Some(EncryptionType::AES256_CTS_HMAC_SHA1_96));
+            assert_eq!(tx.ticket_etype, Some(EncryptionType::RC4_HMAC));
+            // conditions of unusual-krbtgt-etype
+            assert!(is_krbtgt(tx.sname.as_ref().unwrap()));
+            assert!(tx.ticket_etype.map_or(false, test_weak_encryption));
+        }
+        // the TGT is now known
+        assert!(!is_unknown_tgt(fingerprint));
+        assert!(is_unknown_tgt(ticket_fingerprint(&cipher[1..])));
+    }
+
+    #[test]
+    fn test_krb5_weak_etypes_only() {
+        assert!(test_weak_etypes_only(&[EncryptionType::RC4_HMAC]));
+        assert!(!test_weak_etypes_only(&[EncryptionType::RC4_HMAC,
+                                         EncryptionType::AES256_CTS_HMAC_SHA1_96]));
+        assert!(!test_weak_etypes_only(&[]));
+    }
+
+    #[test]
+    fn test_krb5_event_info() {
+        let mut event_id: std::os::raw::c_int = 0;
+        let mut event_type: crate::core::AppLayerEventType = 0;
+        let name = std::ffi::CString::new("malformed_data").unwrap();
+        assert_eq!(KRB5Event::get_event_info(name.as_ptr(), &mut event_id, &mut event_type), 0);
+        assert_eq!(event_id, KRB5Event::MalformedData as std::os::raw::c_int);
+
+        // An unknown name fails, so the rule using it fails to load.
+        let name = std::ffi::CString::new("no_such_event").unwrap();
+        assert_eq!(KRB5Event::get_event_info(name.as_ptr(), &mut event_id, &mut event_type), -1);
+    }
+}
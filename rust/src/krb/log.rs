@@ -18,7 +18,8 @@
 // written by Pierre Chifflier  <chifflier@wzdftpd.net>
 
 use crate::json::*;
-use crate::krb::krb5::{KRB5State,KRB5Transaction,test_weak_encryption};
+use crate::krb::kpasswd::{KPASSWD_VERSION_CHANGE,KPASSWD_VERSION_SET};
+use crate::krb::krb5::{KRB5State,KRB5Transaction,kdc_option_names,test_weak_encryption};
 
 #[no_mangle]
 pub extern "C" fn rs_krb5_log_json_response(_state: &mut KRB5State, tx: &mut KRB5Transaction) -> *mut JsonT
@@ -53,6 +54,70 @@ pub extern "C" fn rs_krb5_log_json_response(_state: &mut KRB5State, tx: &mut KRB
     js.set_string("sname", &sname);
     js.set_string("encryption", &encryption);
     js.set_boolean("weak_encryption", tx.etype.map_or(false,test_weak_encryption));
+    if let Some(ref x) = tx.ticket_etype {
+        js.set_string("ticket_encryption", &format!("{:?}", x));
+    }
+    if tx.req_etypes.len() > 0 {
+        let jsa = Json::array();
+        for etype in tx.req_etypes.iter() {
+            jsa.array_append_string(&format!("{:?}", etype));
+        }
+        js.set("req_encryption", jsa);
+    }
+    if let Some(options) = tx.kdc_options {
+        let jsa = Json::array();
+        for name in kdc_option_names(options) {
+            jsa.array_append_string(name);
+        }
+        js.set("kdc_options", jsa);
+    }
+    if tx.padata_types.len() > 0 {
+        let jsa = Json::array();
+        for padata_type in tx.padata_types.iter() {
+            jsa.array_append_string(&format!("{:?}", padata_type));
+        }
+        js.set("padata", jsa);
+    }
+    if let Some(include_pac) = tx.include_pac {
+        js.set_boolean("include_pac", include_pac);
+    }
+    if let Some(ref till) = tx.till {
+        js.set_string("till", till);
+    }
+    if let Some(ref rtime) = tx.rtime {
+        js.set_string("rtime", rtime);
+    }
+    if tx.addresses.len() > 0 {
+        let jsa = Json::array();
+        for addr in tx.addresses.iter() {
+            jsa.array_append_string(addr);
+        }
+        js.set("addresses", jsa);
+    }
+    if let Some(version) = tx.kpasswd_version {
+        let jsk = Json::object();
+        let operation = match version {
+            KPASSWD_VERSION_CHANGE => "change_password",
+            KPASSWD_VERSION_SET    => "set_password",
+            _                      => "unknown",
+        };
+        jsk.set_string("operation", operation);
+        jsk.set_integer("version", version as u64);
+        if let Some(ref principal) = tx.kpasswd_principal {
+            jsk.set_string("principal", principal);
+        }
+        js.set("kpasswd", jsk);
+    }
+    if let Some(ref stats) = tx.principal_stats {
+        let jss = Json::object();
+        jss.set_integer("as_req", stats.as_req as u64);
+        jss.set_integer("as_rep", stats.as_rep as u64);
+        jss.set_integer("tgs_rep", stats.tgs_rep as u64);
+        jss.set_integer("errors", stats.errors as u64);
+        jss.set_integer("preauth_failures", stats.preauth_failures as u64);
+        jss.set_integer("kpasswd_tickets", stats.kpasswd_tickets as u64);
+        js.set("principal_stats", jss);
+    }
     return js.unwrap();
 }
 
@@ -18,5 +18,7 @@
 // written by Pierre Chifflier  <chifflier@wzdftpd.net>
 
 pub mod krb5;
+pub mod context;
+pub mod kpasswd;
 pub mod detect;
 pub mod log;
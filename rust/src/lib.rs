@@ -28,6 +28,8 @@ extern crate memchr;
 #[macro_use]
 extern crate num_derive;
 extern crate widestring;
+#[macro_use]
+extern crate suricata_derive;
 
 extern crate der_parser;
 extern crate kerberos_parser;
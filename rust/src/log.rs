@@ -85,6 +85,13 @@ macro_rules!do_log {
     }
 }
 
+#[macro_export]
+macro_rules!SCLogWarning {
+    ($($arg:tt)*) => {
+        do_log!(Level::Warning, file!(), line!(), function!(), 0, $($arg)*);
+    }
+}
+
 #[macro_export]
 macro_rules!SCLogNotice {
     ($($arg:tt)*) => {
@@ -21,14 +21,14 @@
 use std;
 use std::mem::transmute;
 use std::collections::{HashMap};
-use std::ffi::CStr;
 
 use nom;
 
 use crate::log::*;
 use crate::applayer;
-use crate::applayer::LoggerFlags;
+use crate::applayer::{AppLayerEvent, LoggerFlags};
 use crate::core::*;
+use crate::conf::*;
 use crate::filetracker::*;
 use crate::filecontainer::*;
 
@@ -40,6 +40,11 @@ use crate::nfs::nfs3_records::*;
 
 pub static mut SURICATA_NFS_FILE_CONFIG: Option<&'static SuricataFileContext> = None;
 
+/// Limits for the TCP defragmentation buffers. Can be set through
+/// app-layer.protocols.nfs.{toserver,toclient}-buffer-memcap.
+pub static mut NFS_CFG_TS_BUFFER_MEMCAP: usize = 1000000;
+pub static mut NFS_CFG_TC_BUFFER_MEMCAP: usize = 100000;
+
 /*
  * Record parsing.
  *
@@ -84,21 +89,12 @@ pub static mut SURICATA_NFS_FILE_CONFIG: Option<&'static SuricataFileContext> =
  */
 
 #[repr(u32)]
+#[derive(AppLayerEvent)]
 pub enum NFSEvent {
     MalformedData = 0,
     NonExistingVersion = 1,
     UnsupportedVersion = 2,
-}
-
-impl NFSEvent {
-    fn from_i32(value: i32) -> Option<NFSEvent> {
-        match value {
-            0 => Some(NFSEvent::MalformedData),
-            1 => Some(NFSEvent::NonExistingVersion),
-            2 => Some(NFSEvent::UnsupportedVersion),
-            _ => None,
-        }
-    }
+    BufferMemcapExceeded = 3,
 }
 
 
@@ -961,6 +957,32 @@ impl NFSState {
         return 0
     }
 
+    /// Queue data in the TCP defragmentation buffer of a direction. If
+    /// this would exceed the configured memcap the buffer is dropped and
+    /// an event is set. Returns false in that case.
+    fn tcp_buffer_queue(&mut self, direction: u8, data: &[u8]) -> bool {
+        let exceeded = {
+            let (buffer, memcap) = if direction == STREAM_TOSERVER {
+                (&mut self.tcp_buffer_ts, unsafe { NFS_CFG_TS_BUFFER_MEMCAP })
+            } else {
+                (&mut self.tcp_buffer_tc, unsafe { NFS_CFG_TC_BUFFER_MEMCAP })
+            };
+            if buffer.len() + data.len() > memcap {
+                SCLogDebug!("direction {} buffer memcap exceeded: {} + {} > {}",
+                        direction, buffer.len(), data.len(), memcap);
+                buffer.clear();
+                true
+            } else {
+                buffer.extend_from_slice(data);
+                false
+            }
+        };
+        if exceeded {
+            self.set_event(NFSEvent::BufferMemcapExceeded);
+        }
+        !exceeded
+    }
+
     /// Parsing function, handling TCP chunks fragmentation
     pub fn parse_tcp_data_ts<'b>(&mut self, i: &'b[u8]) -> u32 {
         let mut v : Vec<u8>;
@@ -972,21 +994,12 @@ impl NFSState {
             0 => i,
             _ => {
                 v = self.tcp_buffer_ts.split_off(0);
-                // sanity check vector length to avoid memory exhaustion
-                if self.tcp_buffer_ts.len() + i.len() > 1000000 {
-                    SCLogDebug!("parse_tcp_data_ts: TS buffer exploded {} {}",
-                            self.tcp_buffer_ts.len(), i.len());
-                    return 1;
-                };
                 v.extend_from_slice(i);
                 v.as_slice()
             },
         };
         //SCLogDebug!("tcp_buffer ({})",tcp_buffer.len());
         let mut cur_i = tcp_buffer;
-        if cur_i.len() > 1000000 {
-            SCLogDebug!("BUG buffer exploded: {}", cur_i.len());
-        }
         // take care of in progress file chunk transfers
         // and skip buffer beyond it
         let consumed = self.filetracker_update(STREAM_TOSERVER, cur_i, 0);
@@ -1008,7 +1021,9 @@ impl NFSState {
                     },
                     0 => {
                         SCLogDebug!("incomplete, queue and retry with the next block (input {}). Looped {} times.", cur_i.len(), cnt);
-                        self.tcp_buffer_tc.extend_from_slice(cur_i);
+                        if !self.tcp_buffer_queue(STREAM_TOSERVER, cur_i) {
+                            return 1;
+                        }
                         return 0;
                     },
                     -1 => {
@@ -1027,6 +1042,35 @@ impl NFSState {
             match parse_rpc_request_partial(cur_i) {
                 Ok((_, ref rpc_phdr)) => {
                     let rec_size = (rpc_phdr.hdr.frag_len + 4) as usize;
+
+                    // multi fragment record: reassemble before parsing
+                    if !rpc_phdr.hdr.frag_is_last {
+                        match rpc_record_defrag(cur_i, unsafe { NFS_CFG_TS_BUFFER_MEMCAP }) {
+                            RpcRecordDefrag::Complete(record, consumed) => {
+                                cur_i = &cur_i[consumed..];
+                                match parse_rpc(&record) {
+                                    Ok((_, ref rpc_record)) => {
+                                        status |= self.process_request_record(rpc_record);
+                                    },
+                                    _ => {
+                                        self.set_event(NFSEvent::MalformedData);
+                                        status = 1;
+                                    },
+                                }
+                                continue;
+                            },
+                            RpcRecordDefrag::Incomplete => {
+                                if !self.tcp_buffer_queue(STREAM_TOSERVER, cur_i) {
+                                    return 1;
+                                }
+                                break;
+                            },
+                            RpcRecordDefrag::TooLarge => {
+                                self.set_event(NFSEvent::BufferMemcapExceeded);
+                                return 1;
+                            },
+                        }
+                    }
                     //SCLogDebug!("rec_size {}/{}", rec_size, cur_i.len());
                     //SCLogDebug!("cur_i {:?}", cur_i);
 
@@ -1071,7 +1115,9 @@ impl NFSState {
                                 }
                             }
                         }
-                        self.tcp_buffer_ts.extend_from_slice(cur_i);
+                        if !self.tcp_buffer_queue(STREAM_TOSERVER, cur_i) {
+                            return 1;
+                        }
                         break;
                     }
 
@@ -1102,7 +1148,9 @@ impl NFSState {
                 },
                 Err(nom::Err::Incomplete(_)) => {
                     SCLogDebug!("Fragmentation required (TCP level) 2");
-                    self.tcp_buffer_ts.extend_from_slice(cur_i);
+                    if !self.tcp_buffer_queue(STREAM_TOSERVER, cur_i) {
+                        return 1;
+                    }
                     break;
                 },
                 Err(nom::Err::Error(_e)) |
@@ -1127,12 +1175,6 @@ impl NFSState {
             0 => i,
             _ => {
                 v = self.tcp_buffer_tc.split_off(0);
-                // sanity check vector length to avoid memory exhaustion
-                if self.tcp_buffer_tc.len() + i.len() > 100000 {
-                    SCLogDebug!("TC buffer exploded");
-                    return 1;
-                };
-
                 v.extend_from_slice(i);
                 v.as_slice()
             },
@@ -1140,9 +1182,6 @@ impl NFSState {
         SCLogDebug!("TC tcp_buffer ({}), input ({})",tcp_buffer.len(), i.len());
 
         let mut cur_i = tcp_buffer;
-        if cur_i.len() > 100000 {
-            SCLogDebug!("parse_tcp_data_tc: BUG buffer exploded {}", cur_i.len());
-        }
 
         // take care of in progress file chunk transfers
         // and skip buffer beyond it
@@ -1165,7 +1204,9 @@ impl NFSState {
                     },
                     0 => {
                         SCLogDebug!("incomplete, queue and retry with the next block (input {}). Looped {} times.", cur_i.len(), cnt);
-                        self.tcp_buffer_tc.extend_from_slice(cur_i);
+                        if !self.tcp_buffer_queue(STREAM_TOCLIENT, cur_i) {
+                            return 1;
+                        }
                         return 0;
                     },
                     -1 => {
@@ -1184,6 +1225,35 @@ impl NFSState {
             match parse_rpc_packet_header(cur_i) {
                 Ok((_, ref rpc_hdr)) => {
                     let rec_size = (rpc_hdr.frag_len + 4) as usize;
+
+                    // multi fragment record: reassemble before parsing
+                    if !rpc_hdr.frag_is_last {
+                        match rpc_record_defrag(cur_i, unsafe { NFS_CFG_TC_BUFFER_MEMCAP }) {
+                            RpcRecordDefrag::Complete(record, consumed) => {
+                                cur_i = &cur_i[consumed..];
+                                match parse_rpc_reply(&record) {
+                                    Ok((_, ref rpc_record)) => {
+                                        status |= self.process_reply_record(rpc_record);
+                                    },
+                                    _ => {
+                                        self.set_event(NFSEvent::MalformedData);
+                                        status = 1;
+                                    },
+                                }
+                                continue;
+                            },
+                            RpcRecordDefrag::Incomplete => {
+                                if !self.tcp_buffer_queue(STREAM_TOCLIENT, cur_i) {
+                                    return 1;
+                                }
+                                break;
+                            },
+                            RpcRecordDefrag::TooLarge => {
+                                self.set_event(NFSEvent::BufferMemcapExceeded);
+                                return 1;
+                            },
+                        }
+                    }
                     // see if we have all data available
                     if rec_size > cur_i.len() {
                         // special case: avoid buffering file read blobs
@@ -1230,7 +1300,9 @@ impl NFSState {
                                 }
                             }
                         }
-                        self.tcp_buffer_tc.extend_from_slice(cur_i);
+                        if !self.tcp_buffer_queue(STREAM_TOCLIENT, cur_i) {
+                            return 1;
+                        }
                         break;
                     }
 
@@ -1260,7 +1332,9 @@ impl NFSState {
                 },
                 Err(nom::Err::Incomplete(_)) => {
                     SCLogDebug!("REPLY: insufficient data for HDR");
-                    self.tcp_buffer_tc.extend_from_slice(cur_i);
+                    if !self.tcp_buffer_queue(STREAM_TOCLIENT, cur_i) {
+                        return 1;
+                    }
                     break;
                 },
                 Err(nom::Err::Error(_e)) |
@@ -1281,17 +1355,11 @@ impl NFSState {
             match parse_rpc_udp_request(input) {
                 Ok((_, ref rpc_record)) => {
                     self.is_udp = true;
-                    match rpc_record.progver {
-                        3 => {
-                            status |= self.process_request_record(rpc_record);
-                        },
-                        2 => {
-                            status |= self.process_request_record_v2(rpc_record);
-                        },
-                        _ => { status = 1; },
-                    }
+                    status |= self.process_request_record(rpc_record);
                 },
                 Err(nom::Err::Incomplete(_)) => {
+                    // truncated datagram, e.g. due to lost IP fragments
+                    self.set_event(NFSEvent::MalformedData);
                 },
                 Err(nom::Err::Error(_e)) |
                 Err(nom::Err::Failure(_e)) => { SCLogDebug!("Parsing failed: {:?}", _e); }
@@ -1311,6 +1379,8 @@ impl NFSState {
                     status |= self.process_reply_record(rpc_record);
                 },
                 Err(nom::Err::Incomplete(_)) => {
+                    // truncated datagram, e.g. due to lost IP fragments
+                    self.set_event(NFSEvent::MalformedData);
                 },
                 Err(nom::Err::Error(_e)) |
                 Err(nom::Err::Failure(_e)) => { SCLogDebug!("Parsing failed: {:?}", _e); }
@@ -1613,20 +1683,7 @@ pub extern "C" fn rs_nfs_state_get_event_info_by_id(event_id: std::os::raw::c_in
                                               event_type: *mut AppLayerEventType)
                                               -> i8
 {
-    if let Some(e) = NFSEvent::from_i32(event_id as i32) {
-        let estr = match e {
-            NFSEvent::MalformedData => { "malformed_data\0" },
-            NFSEvent::NonExistingVersion => { "non_existing_version\0" },
-            NFSEvent::UnsupportedVersion => { "unsupported_version\0" },
-        };
-        unsafe{
-            *event_name = estr.as_ptr() as *const std::os::raw::c_char;
-            *event_type = APP_LAYER_EVENT_TYPE_TRANSACTION;
-        };
-        0
-    } else {
-        -1
-    }
+    NFSEvent::get_event_info_by_id(event_id, event_name, event_type)
 }
 #[no_mangle]
 pub extern "C" fn rs_nfs_state_get_event_info(event_name: *const std::os::raw::c_char,
@@ -1634,24 +1691,7 @@ pub extern "C" fn rs_nfs_state_get_event_info(event_name: *const std::os::raw::c
                                               event_type: *mut AppLayerEventType)
                                               -> i8
 {
-    if event_name == std::ptr::null() {
-        return -1;
-    }
-    let c_event_name: &CStr = unsafe { CStr::from_ptr(event_name) };
-    let event = match c_event_name.to_str() {
-        Ok(s) => {
-            match s {
-                "malformed_data" => NFSEvent::MalformedData as i32,
-                _ => -1, // unknown event
-            }
-        },
-        Err(_) => -1, // UTF-8 conversion failed
-    };
-    unsafe{
-        *event_type = APP_LAYER_EVENT_TYPE_TRANSACTION;
-        *event_id = event as std::os::raw::c_int;
-    };
-    0
+    NFSEvent::get_event_info(event_name, event_id, event_type) as i8
 }
 
 /// return procedure(s) in the tx. At 0 return the main proc,
@@ -1703,6 +1743,13 @@ pub extern "C" fn rs_nfs_init(context: &'static mut SuricataFileContext)
 {
     unsafe {
         SURICATA_NFS_FILE_CONFIG = Some(context);
+
+        if let Some(val) = conf_get_memval("app-layer.protocols.nfs.toserver-buffer-memcap") {
+            NFS_CFG_TS_BUFFER_MEMCAP = val as usize;
+        }
+        if let Some(val) = conf_get_memval("app-layer.protocols.nfs.toclient-buffer-memcap") {
+            NFS_CFG_TC_BUFFER_MEMCAP = val as usize;
+        }
     }
 }
 
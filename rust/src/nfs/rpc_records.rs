@@ -359,3 +359,80 @@ named!(pub parse_rpc_udp_reply<RpcReplyPacket>,
            }
    ))
 );
+
+/// Result of trying to reassemble a multi-fragment TCP record.
+#[derive(Debug,PartialEq)]
+pub enum RpcRecordDefrag {
+    /// reassembled record and the number of input bytes it used
+    Complete(Vec<u8>, usize),
+    /// not all fragments are available yet
+    Incomplete,
+    /// the record would be larger than the limit
+    TooLarge,
+}
+
+/// RPC over TCP uses record marking (RFC 5531, section 11): a record
+/// can be sent as multiple fragments, each with its own 4 byte header.
+/// Only the last fragment has the 'last fragment' bit set.
+///
+/// Reassemble the fragments into a single record with one header that
+/// has the last fragment bit set, so that the regular record parsers
+/// can be used on it.
+pub fn rpc_record_defrag(i: &[u8], max_size: usize) -> RpcRecordDefrag {
+    let mut data: Vec<u8> = Vec::new();
+    let mut offset = 0;
+    loop {
+        if i.len() < offset + 4 {
+            return RpcRecordDefrag::Incomplete;
+        }
+        let fraghdr = ((i[offset] as u32) << 24) |
+                      ((i[offset + 1] as u32) << 16) |
+                      ((i[offset + 2] as u32) << 8) |
+                       (i[offset + 3] as u32);
+        let is_last = fraghdr & 0x8000_0000 != 0;
+        let frag_len = (fraghdr & 0x7fff_ffff) as usize;
+
+        if data.len() + frag_len > max_size {
+            return RpcRecordDefrag::TooLarge;
+        }
+        let start = offset + 4;
+        if i.len() < start + frag_len {
+            return RpcRecordDefrag::Incomplete;
+        }
+        data.extend_from_slice(&i[start..start + frag_len]);
+        offset = start + frag_len;
+
+        if is_last {
+            break;
+        }
+    }
+
+    let len = data.len() as u32 | 0x8000_0000;
+    let mut record = Vec::with_capacity(data.len() + 4);
+    record.push((len >> 24) as u8);
+    record.push((len >> 16) as u8);
+    record.push((len >> 8) as u8);
+    record.push(len as u8);
+    record.extend_from_slice(&data);
+    RpcRecordDefrag::Complete(record, offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rpc_record_defrag() {
+        let buf: &[u8] = &[
+            0x00, 0x00, 0x00, 0x02, 0x01, 0x02,         // fragment 1
+            0x80, 0x00, 0x00, 0x03, 0x03, 0x04, 0x05,   // last fragment
+            0xff,                                       // next record
+        ];
+        assert_eq!(rpc_record_defrag(buf, 1000),
+                RpcRecordDefrag::Complete(vec![0x80, 0x00, 0x00, 0x05,
+                        0x01, 0x02, 0x03, 0x04, 0x05], 13));
+        assert_eq!(rpc_record_defrag(&buf[..10], 1000),
+                RpcRecordDefrag::Incomplete);
+        assert_eq!(rpc_record_defrag(buf, 4), RpcRecordDefrag::TooLarge);
+    }
+}
@@ -0,0 +1,45 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+use crate::ntp::ntp::NTPTransaction;
+
+#[no_mangle]
+pub extern "C" fn rs_ntp_tx_get_mode(tx: &mut NTPTransaction,
+                                     mode: *mut u32)
+{
+    unsafe {
+        *mode = tx.mode.0 as u32;
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn rs_ntp_tx_get_stratum(tx: &mut NTPTransaction,
+                                        stratum: *mut u32)
+{
+    unsafe {
+        *stratum = tx.stratum as u32;
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn rs_ntp_tx_get_version(tx: &mut NTPTransaction,
+                                        version: *mut u32)
+{
+    unsafe {
+        *version = tx.version as u32;
+    }
+}
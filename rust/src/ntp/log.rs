@@ -0,0 +1,103 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+use crate::json::*;
+use crate::ntp::ntp::{NTPState,NTPTransaction,NTPControlInfo,NTPPrivateInfo,
+                      ntp_refid_to_string,ntp_ts_to_string};
+use crate::ntp::ntp_parser::NtpMode;
+use std::borrow::Cow;
+
+fn str_of_mode(m: NtpMode) -> Cow<'static, str> {
+    match m {
+        NtpMode::Reserved          => Cow::Borrowed("reserved"),
+        NtpMode::SymmetricActive   => Cow::Borrowed("symmetric_active"),
+        NtpMode::SymmetricPassive  => Cow::Borrowed("symmetric_passive"),
+        NtpMode::Client            => Cow::Borrowed("client"),
+        NtpMode::Server            => Cow::Borrowed("server"),
+        NtpMode::Broadcast         => Cow::Borrowed("broadcast"),
+        NtpMode::NtpControlMessage => Cow::Borrowed("control"),
+        NtpMode::Private           => Cow::Borrowed("private"),
+        x                          => Cow::Owned(format!("{}", x.0)),
+    }
+}
+
+/// Convert a NTP short format value (16.16 fixed point seconds) to
+/// microseconds
+fn short_format_to_usecs(v: u32) -> u64 {
+    ((v as u64) * 1_000_000) >> 16
+}
+
+fn set_timestamp(js: &Json, key: &str, ts: u64) {
+    if let Some(s) = ntp_ts_to_string(ts) {
+        js.set_string(key, &s);
+    }
+}
+
+fn log_control(js: &Json, control: &NTPControlInfo) {
+    let jsc = Json::object();
+    jsc.set_boolean("response", control.response);
+    jsc.set_integer("opcode", control.opcode as u64);
+    jsc.set_integer("sequence", control.sequence as u64);
+    jsc.set_integer("association_id", control.association_id as u64);
+    jsc.set_integer("count", control.count as u64);
+    if control.error {
+        jsc.set_boolean("error", true);
+    }
+    js.set("control", jsc);
+}
+
+fn log_private(js: &Json, private: &NTPPrivateInfo) {
+    let jsp = Json::object();
+    jsp.set_boolean("response", private.response);
+    jsp.set_integer("implementation", private.implementation as u64);
+    jsp.set_integer("request_code", private.request_code as u64);
+    jsp.set_integer("items", private.nb_items as u64);
+    jsp.set_integer("item_size", private.item_size as u64);
+    if private.error != 0 {
+        jsp.set_integer("error", private.error as u64);
+    }
+    js.set("private", jsp);
+}
+
+#[no_mangle]
+pub extern "C" fn rs_ntp_log_json(state: &mut NTPState, tx: &mut NTPTransaction) -> *mut JsonT
+{
+    let js = Json::object();
+    js.set_integer("version", tx.version as u64);
+    js.set_string("mode", &str_of_mode(tx.mode));
+    if let Some(ref control) = tx.control {
+        log_control(&js, control);
+    } else if let Some(ref private) = tx.private {
+        log_private(&js, private);
+    } else {
+        js.set_integer("leap_indicator", tx.leap as u64);
+        js.set_integer("stratum", tx.stratum as u64);
+        js.set_signed_integer("poll", tx.poll as i64);
+        js.set_signed_integer("precision", tx.precision as i64);
+        js.set_string("reference_id", &ntp_refid_to_string(tx.stratum, tx.xid));
+        js.set_integer("root_delay", short_format_to_usecs(tx.root_delay));
+        js.set_integer("root_dispersion", short_format_to_usecs(tx.root_dispersion));
+        set_timestamp(&js, "origin_time", tx.ts_orig);
+        set_timestamp(&js, "receive_time", tx.ts_recv);
+        set_timestamp(&js, "transmit_time", tx.ts_xmit);
+    }
+    if tx.control.is_some() || tx.private.is_some() {
+        js.set_integer("request_bytes", state.request_bytes);
+        js.set_integer("response_bytes", state.response_bytes);
+    }
+    return js.unwrap();
+}
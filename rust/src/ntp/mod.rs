@@ -17,4 +17,9 @@
 
 // written by Pierre Chifflier  <chifflier@wzdftpd.net>
 
+extern crate ntp_parser;
+
 pub mod ntp;
+pub mod parser;
+pub mod log;
+pub mod detect;
@@ -17,37 +17,33 @@
 
 // written by Pierre Chifflier  <chifflier@wzdftpd.net>
 
-extern crate ntp_parser;
-use self::ntp_parser::*;
+use crate::ntp::ntp_parser::*;
 use crate::core;
 use crate::core::{AppProto,Flow,ALPROTO_UNKNOWN,ALPROTO_FAILED};
-use crate::applayer;
+use crate::applayer::{self, AppLayerEvent};
+use crate::conf::conf_get;
+use crate::ntp::parser::*;
 use crate::parser::*;
 use std;
-use std::ffi::{CStr,CString};
+use std::ffi::CString;
 
 use crate::log::*;
 
 use nom;
 
 #[repr(u32)]
+#[derive(AppLayerEvent)]
 pub enum NTPEvent {
     UnsolicitedResponse = 0,
     MalformedData,
     NotRequest,
     NotResponse,
-}
-
-impl NTPEvent {
-    fn from_i32(value: i32) -> Option<NTPEvent> {
-        match value {
-            0 => Some(NTPEvent::UnsolicitedResponse),
-            1 => Some(NTPEvent::MalformedData),
-            2 => Some(NTPEvent::NotRequest),
-            3 => Some(NTPEvent::NotResponse),
-            _ => None,
-        }
-    }
+    MonlistRequest,
+    Amplification,
+    TimeOffset,
+    UnexpectedLeapIndicator,
+    InvalidStratum,
+    KissOfDeath,
 }
 
 pub struct NTPState {
@@ -59,6 +55,36 @@ pub struct NTPState {
 
     /// tx counter for assigning incrementing id's to tx's
     tx_id: u64,
+
+    /// Number of bytes seen in requests and responses, to detect
+    /// amplification
+    pub request_bytes: u64,
+    pub response_bytes: u64,
+
+    /// Set once the amplification event has been raised
+    amplification: bool,
+}
+
+/// Mode 6 control message
+#[derive(Debug)]
+pub struct NTPControlInfo {
+    pub response: bool,
+    pub error: bool,
+    pub opcode: u8,
+    pub sequence: u16,
+    pub association_id: u16,
+    pub count: u16,
+}
+
+/// Mode 7 private message
+#[derive(Debug)]
+pub struct NTPPrivateInfo {
+    pub response: bool,
+    pub implementation: u8,
+    pub request_code: u8,
+    pub error: u8,
+    pub nb_items: u16,
+    pub item_size: u16,
 }
 
 #[derive(Debug)]
@@ -66,6 +92,32 @@ pub struct NTPTransaction {
     /// The NTP reference ID
     pub xid: u32,
 
+    /// Leap indicator
+    pub leap: u8,
+    /// Protocol version
+    pub version: u8,
+    /// Association mode
+    pub mode: NtpMode,
+    /// Stratum of the sender
+    pub stratum: u8,
+    /// Maximum interval between successive messages (log2 seconds)
+    pub poll: i8,
+    /// Precision of the system clock (log2 seconds)
+    pub precision: i8,
+    /// Total round-trip delay to the reference clock (NTP short format)
+    pub root_delay: u32,
+    /// Total dispersion to the reference clock (NTP short format)
+    pub root_dispersion: u32,
+    /// Origin, receive and transmit timestamps (NTP timestamp format)
+    pub ts_orig: u64,
+    pub ts_recv: u64,
+    pub ts_xmit: u64,
+
+    /// Mode 6 control message
+    pub control: Option<NTPControlInfo>,
+    /// Mode 7 private message
+    pub private: Option<NTPPrivateInfo>,
+
     /// The internal transaction id
     id: u64,
 
@@ -86,23 +138,63 @@ impl NTPState {
             transactions: Vec::new(),
             events: 0,
             tx_id: 0,
+            request_bytes: 0,
+            response_bytes: 0,
+            amplification: false,
         }
     }
 }
 
 impl NTPState {
-    /// Parse an NTP request message
+    /// Parse an NTP message. `now` is the capture time in seconds since the
+    /// unix epoch, or 0 if unknown.
     ///
     /// Returns The number of messages parsed, or -1 on error
-    fn parse(&mut self, i: &[u8], _direction: u8) -> i32 {
+    fn parse(&mut self, i: &[u8], direction: u8, now: u64) -> i32 {
+        if direction == 0 {
+            self.request_bytes += i.len() as u64;
+        } else {
+            self.response_bytes += i.len() as u64;
+        }
+        let r = match ntp_message_mode(i) {
+            Some(NTP_MODE_CONTROL) => self.parse_control(i),
+            Some(NTP_MODE_PRIVATE) => self.parse_private(i),
+            _ => self.parse_packet(i, now),
+        };
+        self.check_amplification();
+        r
+    }
+
+    fn parse_packet(&mut self, i: &[u8], now: u64) -> i32 {
         match parse_ntp(i) {
             Ok((_,ref msg)) => {
                 // SCLogDebug!("parse_ntp: {:?}",msg);
-                if msg.mode == NtpMode::SymmetricActive || msg.mode == NtpMode::Client {
-                    let mut tx = self.new_tx();
-                    // use the reference id as identifier
-                    tx.xid = msg.ref_id;
-                    self.transactions.push(tx);
+                match msg.mode {
+                    NtpMode::SymmetricActive |
+                    NtpMode::SymmetricPassive |
+                    NtpMode::Client |
+                    NtpMode::Server |
+                    NtpMode::Broadcast => {
+                        let mut tx = self.new_tx();
+                        // use the reference id as identifier
+                        tx.xid = msg.ref_id;
+                        tx.leap = msg.li;
+                        tx.version = msg.version;
+                        tx.mode = msg.mode;
+                        tx.stratum = msg.stratum;
+                        tx.poll = msg.poll;
+                        tx.precision = msg.precision;
+                        tx.root_delay = msg.root_delay;
+                        tx.root_dispersion = msg.root_dispersion;
+                        tx.ts_orig = msg.ts_orig;
+                        tx.ts_recv = msg.ts_recv;
+                        tx.ts_xmit = msg.ts_xmit;
+                        self.transactions.push(tx);
+                        if msg.mode == NtpMode::Server || msg.mode == NtpMode::Broadcast {
+                            self.check_server_time(now);
+                        }
+                    },
+                    _ => (),
                 }
                 1
             },
@@ -119,6 +211,123 @@ impl NTPState {
         }
     }
 
+    fn parse_control(&mut self, i: &[u8]) -> i32 {
+        match parse_ntp_control(i) {
+            Ok((_, msg)) => {
+                let mut tx = self.new_tx();
+                tx.version = msg.version;
+                tx.mode = NtpMode::NtpControlMessage;
+                tx.control = Some(NTPControlInfo{
+                    response: msg.response,
+                    error: msg.error,
+                    opcode: msg.opcode,
+                    sequence: msg.sequence,
+                    association_id: msg.association_id,
+                    count: msg.count,
+                });
+                self.transactions.push(tx);
+                1
+            },
+            Err(_) => {
+                SCLogDebug!("Error while parsing NTP control message");
+                self.set_event(NTPEvent::MalformedData);
+                -1
+            },
+        }
+    }
+
+    fn parse_private(&mut self, i: &[u8]) -> i32 {
+        match parse_ntp_private(i) {
+            Ok((_, msg)) => {
+                let mut tx = self.new_tx();
+                tx.version = msg.version;
+                tx.mode = NtpMode::Private;
+                tx.private = Some(NTPPrivateInfo{
+                    response: msg.response,
+                    implementation: msg.implementation,
+                    request_code: msg.request_code,
+                    error: msg.error,
+                    nb_items: msg.nb_items,
+                    item_size: msg.item_size,
+                });
+                self.transactions.push(tx);
+                if !msg.response && (msg.request_code == NTP_REQ_MON_GETLIST ||
+                                     msg.request_code == NTP_REQ_MON_GETLIST_1) {
+                    self.set_event(NTPEvent::MonlistRequest);
+                }
+                1
+            },
+            Err(_) => {
+                SCLogDebug!("Error while parsing NTP private message");
+                self.set_event(NTPEvent::MalformedData);
+                -1
+            },
+        }
+    }
+
+    /// Check the time information sent by a server, as it would be used
+    /// by the clients to set their clock
+    fn check_server_time(&mut self, now: u64) {
+        let (leap, stratum, ref_id, ts_xmit) = match self.transactions.last() {
+            Some(tx) => (tx.leap, tx.stratum, tx.xid, tx.ts_xmit),
+            None => { return; },
+        };
+        if stratum == 0 {
+            // kiss-o'-death, RFC 5905 section 7.4
+            match ntp_refid_to_string(stratum, ref_id).as_str() {
+                "DENY" | "RSTR" | "RATE" => {
+                    self.set_event(NTPEvent::KissOfDeath);
+                },
+                _ => {
+                    self.set_event(NTPEvent::InvalidStratum);
+                },
+            }
+            // kiss-o'-death messages carry no usable time
+            return;
+        }
+        if stratum >= NTP_STRATUM_UNSYNCHRONIZED {
+            self.set_event(NTPEvent::InvalidStratum);
+        }
+        let xmit = ntp_ts_to_unix(ts_xmit).map(|(secs, _)| secs);
+        let max_offset = unsafe { NTP_MAX_TIME_OFFSET };
+        if let Some(xmit) = xmit {
+            if now != 0 && max_offset != 0 &&
+                (xmit > now.saturating_add(max_offset) ||
+                 xmit.saturating_add(max_offset) < now)
+            {
+                SCLogDebug!("NTP transmit time {} is {} seconds away from capture time",
+                            xmit, if xmit > now { xmit - now } else { now - xmit });
+                self.set_event(NTPEvent::TimeOffset);
+            }
+        }
+        // leap seconds are only inserted or deleted at the end of June or
+        // December
+        if leap == NTP_LEAP_ADD_SECOND || leap == NTP_LEAP_DEL_SECOND {
+            let secs = xmit.unwrap_or(now);
+            if secs != 0 {
+                let (_, month, _) = civil_from_days(secs / 86_400);
+                if month != 6 && month != 12 {
+                    self.set_event(NTPEvent::UnexpectedLeapIndicator);
+                }
+            }
+        }
+    }
+
+    /// Raise the amplification event once the responses are larger than
+    /// the requests by more than the configured ratio
+    fn check_amplification(&mut self) {
+        let ratio = unsafe { NTP_AMPLIFICATION_RATIO };
+        if self.amplification || ratio == 0 || self.request_bytes == 0 {
+            return;
+        }
+        if self.response_bytes > self.request_bytes.saturating_mul(ratio) {
+            SCLogDebug!("NTP amplification: {} response bytes for {} request bytes",
+                        self.response_bytes, self.request_bytes);
+            self.amplification = true;
+            self.set_event(NTPEvent::Amplification);
+        }
+    }
+
     fn free(&mut self) {
         // All transactions are freed when the `transactions` object is freed.
         // But let's be explicit
@@ -156,6 +365,19 @@ impl NTPTransaction {
     pub fn new(id: u64) -> NTPTransaction {
         NTPTransaction {
             xid: 0,
+            leap: 0,
+            version: 0,
+            mode: NtpMode::Reserved,
+            stratum: 0,
+            poll: 0,
+            precision: 0,
+            root_delay: 0,
+            root_dispersion: 0,
+            ts_orig: 0,
+            ts_recv: 0,
+            ts_xmit: 0,
+            control: None,
+            private: None,
             id: id,
             de_state: None,
             events: std::ptr::null_mut(),
@@ -176,6 +398,69 @@ impl Drop for NTPTransaction {
     }
 }
 
+/// Leap indicator values
+pub const NTP_LEAP_ADD_SECOND: u8 = 1;
+pub const NTP_LEAP_DEL_SECOND: u8 = 2;
+
+/// Stratum of unsynchronized servers
+pub const NTP_STRATUM_UNSYNCHRONIZED: u8 = 16;
+
+/// Seconds between the NTP epoch (1900-01-01) and the unix epoch
+pub const NTP_UNIX_EPOCH_DELTA: u64 = 2_208_988_800;
+
+/// Convert a 64-bit NTP timestamp to seconds and microseconds since the
+/// unix epoch. Returns None for timestamps before 1970, which includes the
+/// zero timestamp used for unknown values.
+pub fn ntp_ts_to_unix(ts: u64) -> Option<(u64, u32)> {
+    let secs = ts >> 32;
+    if secs < NTP_UNIX_EPOCH_DELTA {
+        return None;
+    }
+    let usecs = ((ts & 0xffff_ffff) * 1_000_000) >> 32;
+    Some((secs - NTP_UNIX_EPOCH_DELTA, usecs as u32))
+}
+
+/// Convert a number of days since the unix epoch to a (year, month, day)
+/// date in the proleptic Gregorian calendar.
+pub fn civil_from_days(days: u64) -> (u64, u32, u32) {
+    // see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Format a 64-bit NTP timestamp as an ISO 8601 UTC date
+pub fn ntp_ts_to_string(ts: u64) -> Option<String> {
+    let (secs, usecs) = ntp_ts_to_unix(ts)?;
+    let (year, month, day) = civil_from_days(secs / 86_400);
+    let rem = secs % 86_400;
+    Some(format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}",
+                 year, month, day, rem / 3600, (rem / 60) % 60, rem % 60, usecs))
+}
+
+/// Decode the reference id. For stratum 0 (kiss code) and 1 (reference
+/// clock) it is a four character ASCII string, otherwise it holds the
+/// IPv4 address (or a hash of the IPv6 address) of the upstream server.
+pub fn ntp_refid_to_string(stratum: u8, ref_id: u32) -> String {
+    let b = [(ref_id >> 24) as u8, (ref_id >> 16) as u8,
+             (ref_id >> 8) as u8, ref_id as u8];
+    if stratum <= 1 {
+        b.iter()
+            .take_while(|&&c| c != 0)
+            .map(|&c| if c >= 0x20 && c < 0x7f { c as char } else { '.' })
+            .collect()
+    } else {
+        format!("{}.{}.{}.{}", b[0], b[1], b[2], b[3])
+    }
+}
+
 
 
 
@@ -199,7 +484,7 @@ pub extern "C" fn rs_ntp_state_free(state: *mut std::os::raw::c_void) {
 }
 
 #[no_mangle]
-pub extern "C" fn rs_ntp_parse_request(_flow: *const core::Flow,
+pub extern "C" fn rs_ntp_parse_request(flow: *const core::Flow,
                                        state: *mut std::os::raw::c_void,
                                        _pstate: *mut std::os::raw::c_void,
                                        input: *const u8,
@@ -208,11 +493,11 @@ pub extern "C" fn rs_ntp_parse_request(_flow: *const core::Flow,
                                        _flags: u8) -> i32 {
     let buf = build_slice!(input,input_len as usize);
     let state = cast_pointer!(state,NTPState);
-    state.parse(buf, 0)
+    state.parse(buf, 0, core::flow_get_last_time(flow))
 }
 
 #[no_mangle]
-pub extern "C" fn rs_ntp_parse_response(_flow: *const core::Flow,
+pub extern "C" fn rs_ntp_parse_response(flow: *const core::Flow,
                                        state: *mut std::os::raw::c_void,
                                        _pstate: *mut std::os::raw::c_void,
                                        input: *const u8,
@@ -221,7 +506,7 @@ pub extern "C" fn rs_ntp_parse_response(_flow: *const core::Flow,
                                        _flags: u8) -> i32 {
     let buf = build_slice!(input,input_len as usize);
     let state = cast_pointer!(state,NTPState);
-    state.parse(buf, 1)
+    state.parse(buf, 1, core::flow_get_last_time(flow))
 }
 
 #[no_mangle]
@@ -273,18 +558,20 @@ pub extern "C" fn rs_ntp_tx_get_alstate_progress(_tx: *mut std::os::raw::c_void,
 
 
 #[no_mangle]
-pub extern "C" fn rs_ntp_tx_set_logged(_state: &mut NTPState,
-                                       tx: &mut NTPTransaction,
+pub extern "C" fn rs_ntp_tx_set_logged(_state: *mut std::os::raw::c_void,
+                                       tx: *mut std::os::raw::c_void,
                                        logged: u32)
 {
+    let tx = cast_pointer!(tx,NTPTransaction);
     tx.logged.set(logged);
 }
 
 #[no_mangle]
-pub extern "C" fn rs_ntp_tx_get_logged(_state: &mut NTPState,
-                                       tx: &mut NTPTransaction)
+pub extern "C" fn rs_ntp_tx_get_logged(_state: *mut std::os::raw::c_void,
+                                       tx: *mut std::os::raw::c_void)
                                        -> u32
 {
+    let tx = cast_pointer!(tx,NTPTransaction);
     return tx.logged.get();
 }
 
@@ -311,29 +598,6 @@ pub extern "C" fn rs_ntp_state_get_tx_detect_state(
     }
 }
 
-#[no_mangle]
-pub extern "C" fn rs_ntp_state_get_event_info_by_id(event_id: std::os::raw::c_int,
-                                                    event_name: *mut *const std::os::raw::c_char,
-                                                    event_type: *mut core::AppLayerEventType)
-                                                    -> i8
-{
-    if let Some(e) = NTPEvent::from_i32(event_id as i32) {
-        let estr = match e {
-            NTPEvent::UnsolicitedResponse => { "unsolicited_response\0" },
-            NTPEvent::MalformedData       => { "malformed_data\0" },
-            NTPEvent::NotRequest          => { "not_request\0" },
-            NTPEvent::NotResponse         => { "not_response\0" },
-        };
-        unsafe{
-            *event_name = estr.as_ptr() as *const std::os::raw::c_char;
-            *event_type = core::APP_LAYER_EVENT_TYPE_TRANSACTION;
-        };
-        0
-    } else {
-        -1
-    }
-}
-
 #[no_mangle]
 pub extern "C" fn rs_ntp_state_get_events(tx: *mut std::os::raw::c_void)
                                           -> *mut core::AppLayerDecoderEvents
@@ -342,32 +606,19 @@ pub extern "C" fn rs_ntp_state_get_events(tx: *mut std::os::raw::c_void)
     return tx.events;
 }
 
-#[no_mangle]
-pub extern "C" fn rs_ntp_state_get_event_info(event_name: *const std::os::raw::c_char,
-                                              event_id: *mut std::os::raw::c_int,
-                                              event_type: *mut core::AppLayerEventType)
-                                              -> std::os::raw::c_int
-{
-    if event_name == std::ptr::null() { return -1; }
-    let c_event_name: &CStr = unsafe { CStr::from_ptr(event_name) };
-    let event = match c_event_name.to_str() {
-        Ok(s) => {
-            match s {
-                "malformed_data" => NTPEvent::MalformedData as i32,
-                _ => -1, // unknown event
-            }
-        },
-        Err(_) => -1, // UTF-8 conversion failed
-    };
-    unsafe{
-        *event_type = core::APP_LAYER_EVENT_TYPE_TRANSACTION;
-        *event_id = event as std::os::raw::c_int;
-    };
-    0
-}
+static mut ALPROTO_NTP : AppProto = ALPROTO_UNKNOWN;
 
+/// Default ratio of response to request bytes above which the
+/// amplification event is raised
+const NTP_DEFAULT_AMPLIFICATION_RATIO : u64 = 10;
 
-static mut ALPROTO_NTP : AppProto = ALPROTO_UNKNOWN;
+static mut NTP_AMPLIFICATION_RATIO : u64 = NTP_DEFAULT_AMPLIFICATION_RATIO;
+
+/// Default maximum offset, in seconds, between the transmit time of a
+/// server and the capture time
+const NTP_DEFAULT_MAX_TIME_OFFSET : u64 = 600;
+
+static mut NTP_MAX_TIME_OFFSET : u64 = NTP_DEFAULT_MAX_TIME_OFFSET;
 
 #[no_mangle]
 pub extern "C" fn ntp_probing_parser(_flow: *const Flow,
@@ -377,9 +628,16 @@ pub extern "C" fn ntp_probing_parser(_flow: *const Flow,
 {
     let slice: &[u8] = unsafe { std::slice::from_raw_parts(input as *mut u8, input_len as usize) };
     let alproto = unsafe{ ALPROTO_NTP };
-    match parse_ntp(slice) {
-        Ok((_, ref msg)) => {
-            if msg.version == 3 || msg.version == 4 {
+    // control and private messages are also sent by older ntpq and ntpdc
+    // versions
+    let (min_version, res) = match ntp_message_mode(slice) {
+        Some(NTP_MODE_CONTROL) => (2, parse_ntp_control(slice).map(|(_, msg)| msg.version)),
+        Some(NTP_MODE_PRIVATE) => (2, parse_ntp_private(slice).map(|(_, msg)| msg.version)),
+        _ => (3, parse_ntp(slice).map(|(_, msg)| msg.version)),
+    };
+    match res {
+        Ok(version) => {
+            if version >= min_version && version <= 4 {
                 return alproto;
             } else {
                 return unsafe{ALPROTO_FAILED};
@@ -416,13 +674,13 @@ pub unsafe extern "C" fn rs_register_ntp_parser() {
         get_tx             : rs_ntp_state_get_tx,
         tx_get_comp_st     : rs_ntp_state_progress_completion_status,
         tx_get_progress    : rs_ntp_tx_get_alstate_progress,
-        get_tx_logged      : None,
-        set_tx_logged      : None,
+        get_tx_logged      : Some(rs_ntp_tx_get_logged),
+        set_tx_logged      : Some(rs_ntp_tx_set_logged),
         get_de_state       : rs_ntp_state_get_tx_detect_state,
         set_de_state       : rs_ntp_state_set_tx_detect_state,
         get_events         : Some(rs_ntp_state_get_events),
-        get_eventinfo      : Some(rs_ntp_state_get_event_info),
-        get_eventinfo_byid : Some(rs_ntp_state_get_event_info_by_id),
+        get_eventinfo      : Some(NTPEvent::get_event_info),
+        get_eventinfo_byid : Some(NTPEvent::get_event_info_by_id),
         localstorage_new   : None,
         localstorage_free  : None,
         get_tx_mpm_id      : None,
@@ -431,6 +689,19 @@ pub unsafe extern "C" fn rs_register_ntp_parser() {
         get_tx_iterator    : None,
     };
 
+    if let Some(val) = conf_get("app-layer.protocols.ntp.amplification-ratio") {
+        match val.parse::<u64>() {
+            Ok(ratio) => { NTP_AMPLIFICATION_RATIO = ratio; },
+            Err(_) => { SCLogWarning!("Invalid value for ntp.amplification-ratio: {}", val); },
+        }
+    }
+    if let Some(val) = conf_get("app-layer.protocols.ntp.max-time-offset") {
+        match val.parse::<u64>() {
+            Ok(offset) => { NTP_MAX_TIME_OFFSET = offset; },
+            Err(_) => { SCLogWarning!("Invalid value for ntp.max-time-offset: {}", val); },
+        }
+    }
+
     let ip_proto_str = CString::new("udp").unwrap();
     if AppLayerProtoDetectConfProtoDetectionEnabled(ip_proto_str.as_ptr(), parser.name) != 0 {
         let alproto = AppLayerRegisterProtocolDetection(&parser, 1);
@@ -447,21 +718,175 @@ pub unsafe extern "C" fn rs_register_ntp_parser() {
 
 #[cfg(test)]
 mod tests {
-    use super::NTPState;
+    use super::*;
+
+    // A UDP NTP v4 response, in server mode, stratum 1 (GPS)
+    const RESP : &[u8] = &[
+        0x24, 0x01, 0x00, 0xe9, 0x00, 0x00, 0x00, 0x10,
+        0x00, 0x00, 0x00, 0x20, 0x47, 0x50, 0x53, 0x00,
+        0xdf, 0xd5, 0x2c, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x18, 0x57, 0xab, 0xc3, 0x4a, 0x5f, 0x2c, 0xfe,
+        0xdf, 0xd5, 0x2c, 0x00, 0x80, 0x00, 0x00, 0x00,
+        0xdf, 0xd5, 0x2c, 0x00, 0x80, 0x00, 0x00, 0x00,
+    ];
+
+    // A UDP NTP v4 request, in client mode
+    const REQ : &[u8] = &[
+        0x23, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x18, 0x57, 0xab, 0xc3, 0x4a, 0x5f, 0x2c, 0xfe
+    ];
 
     #[test]
     fn test_ntp_parse_request_valid() {
-        // A UDP NTP v4 request, in client mode
-        const REQ : &[u8] = &[
-            0x23, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x18, 0x57, 0xab, 0xc3, 0x4a, 0x5f, 0x2c, 0xfe
+        let mut state = NTPState::new();
+        assert_eq!(1, state.parse(REQ, 0, 0));
+        assert_eq!(1, state.transactions.len());
+        let tx = &state.transactions[0];
+        assert_eq!(tx.mode, NtpMode::Client);
+        assert_eq!(tx.version, 4);
+        assert_eq!(tx.ts_xmit, 0x1857abc34a5f2cfe);
+    }
+
+    #[test]
+    fn test_ntp_parse_response_valid() {
+        let mut state = NTPState::new();
+        assert_eq!(1, state.parse(RESP, 1, 0));
+        assert_eq!(1, state.transactions.len());
+        let tx = &state.transactions[0];
+        assert_eq!(tx.mode, NtpMode::Server);
+        assert_eq!(tx.stratum, 1);
+        assert_eq!(tx.poll, 0);
+        assert_eq!(tx.precision, -23);
+        assert_eq!(tx.root_dispersion, 0x20);
+        assert_eq!(ntp_refid_to_string(tx.stratum, tx.xid), "GPS");
+        assert_eq!(ntp_ts_to_string(tx.ts_xmit),
+                   Some("2019-01-01T00:00:00.500000".to_string()));
+    }
+
+    /// Build a server response from RESP
+    fn server_response(leap: u8, stratum: u8, ref_id: &[u8], ts_xmit: u64) -> Vec<u8> {
+        let mut buf = RESP.to_vec();
+        buf[0] = (leap << 6) | (buf[0] & 0x3f);
+        buf[1] = stratum;
+        buf[12..16].copy_from_slice(ref_id);
+        for i in 0..8 {
+            buf[40 + i] = (ts_xmit >> (56 - 8 * i)) as u8;
+        }
+        buf
+    }
+
+    #[test]
+    fn test_ntp_server_time_anomalies() {
+        // 2019-01-01T00:00:00 and 2019-06-30T00:00:00
+        const JAN_1: u64 = 1_546_300_800;
+        const JUN_30: u64 = 1_561_852_800;
+        let ntp_ts = |secs: u64| (secs + NTP_UNIX_EPOCH_DELTA) << 32;
+
+        let mut state = NTPState::new();
+        assert_eq!(1, state.parse(RESP, 1, JAN_1 + 60));
+        assert_eq!(state.events, 0);
+
+        // transmit time one hour in the past
+        assert_eq!(1, state.parse(RESP, 1, JAN_1 + 3600));
+        assert_eq!(state.events, 1);
+
+        // leap second announced in January, but not in June
+        let buf = server_response(NTP_LEAP_ADD_SECOND, 1, b"GPS\0", ntp_ts(JAN_1));
+        assert_eq!(1, state.parse(&buf, 1, JAN_1));
+        assert_eq!(state.events, 2);
+        let buf = server_response(NTP_LEAP_DEL_SECOND, 1, b"GPS\0", ntp_ts(JUN_30));
+        assert_eq!(1, state.parse(&buf, 1, JUN_30));
+        assert_eq!(state.events, 2);
+
+        // unsynchronized server
+        let buf = server_response(3, NTP_STRATUM_UNSYNCHRONIZED, &[0; 4], ntp_ts(JAN_1));
+        assert_eq!(1, state.parse(&buf, 1, JAN_1));
+        assert_eq!(state.events, 3);
+
+        // kiss-o'-death, with a zero transmit time
+        let buf = server_response(3, 0, b"RATE", 0);
+        assert_eq!(1, state.parse(&buf, 1, JAN_1));
+        assert_eq!(state.events, 4);
+
+        // client requests are not checked
+        assert_eq!(1, state.parse(REQ, 0, JAN_1));
+        assert_eq!(state.events, 4);
+    }
+
+    #[test]
+    fn test_ntp_monlist_amplification() {
+        // ntpdc -c monlist request and the first packet of the response
+        const MONLIST_REQ : &[u8] = &[
+            0x17, 0x00, 0x03, 0x2a, 0x00, 0x00, 0x00, 0x00,
         ];
+        let mut monlist_resp = vec![0xd7, 0x00, 0x03, 0x2a, 0x00, 0x06, 0x00, 0x48];
+        monlist_resp.extend_from_slice(&[0; 6 * 72]);
 
         let mut state = NTPState::new();
-        assert_eq!(1, state.parse(REQ, 0));
+        assert_eq!(1, state.parse(MONLIST_REQ, 0, 0));
+        {
+            let tx = &state.transactions[0];
+            assert_eq!(tx.mode, NtpMode::Private);
+            let private = tx.private.as_ref().unwrap();
+            assert_eq!(private.request_code, NTP_REQ_MON_GETLIST_1);
+            assert!(!private.response);
+        }
+        assert_eq!(state.events, 1);
+        assert!(!state.amplification);
+
+        assert_eq!(1, state.parse(&monlist_resp, 1, 0));
+        assert_eq!(2, state.transactions.len());
+        assert_eq!(state.transactions[1].private.as_ref().unwrap().nb_items, 6);
+        assert_eq!(state.request_bytes, 8);
+        assert_eq!(state.response_bytes, 440);
+        assert!(state.amplification);
+        assert_eq!(state.events, 2);
+
+        // the event is only raised once per flow
+        assert_eq!(1, state.parse(&monlist_resp, 1, 0));
+        assert_eq!(state.events, 2);
+    }
+
+    #[test]
+    fn test_ntp_control_request() {
+        // ntpq -c rv request
+        const READVAR : &[u8] = &[
+            0x26, 0x02, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+        ];
+        let mut state = NTPState::new();
+        assert_eq!(1, state.parse(READVAR, 0, 0));
+        let tx = &state.transactions[0];
+        assert_eq!(tx.mode, NtpMode::NtpControlMessage);
+        assert_eq!(tx.version, 4);
+        assert_eq!(tx.control.as_ref().unwrap().opcode, 2);
+        assert_eq!(state.events, 0);
+    }
+
+    #[test]
+    fn test_ntp_time_conversion() {
+        assert_eq!(ntp_ts_to_unix(0), None);
+        assert_eq!(ntp_ts_to_unix(NTP_UNIX_EPOCH_DELTA << 32), Some((0, 0)));
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(17_897), (2019, 1, 1));
+        assert_eq!(ntp_refid_to_string(2, 0xc0a80001), "192.168.0.1");
+    }
+
+    #[test]
+    fn test_ntp_event_info() {
+        let mut event_id: std::os::raw::c_int = 0;
+        let mut event_type: crate::core::AppLayerEventType = 0;
+        let name = std::ffi::CString::new("malformed_data").unwrap();
+        assert_eq!(NTPEvent::get_event_info(name.as_ptr(), &mut event_id, &mut event_type), 0);
+        assert_eq!(event_id, NTPEvent::MalformedData as std::os::raw::c_int);
+
+        // An unknown name fails, so the rule using it fails to load.
+        let name = std::ffi::CString::new("no_such_event").unwrap();
+        assert_eq!(NTPEvent::get_event_info(name.as_ptr(), &mut event_id, &mut event_type), -1);
     }
 }
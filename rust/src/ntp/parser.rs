@@ -0,0 +1,182 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+//! Parsers for the NTP mode 6 (control) and mode 7 (private) messages,
+//! which are not handled by ntp-parser.
+
+use nom::{be_u8, be_u16, rest};
+
+pub const NTP_MODE_CONTROL: u8 = 6;
+pub const NTP_MODE_PRIVATE: u8 = 7;
+
+/// Mode 7 request codes used to retrieve the monitoring list
+pub const NTP_REQ_MON_GETLIST: u8 = 20;
+pub const NTP_REQ_MON_GETLIST_1: u8 = 42;
+
+/// Mode 6 control message, RFC 1305 appendix B
+#[derive(Debug, PartialEq)]
+pub struct NtpControlMessage<'a> {
+    pub version: u8,
+    pub response: bool,
+    pub error: bool,
+    pub more: bool,
+    pub opcode: u8,
+    pub sequence: u16,
+    pub status: u16,
+    pub association_id: u16,
+    pub offset: u16,
+    pub count: u16,
+    pub data: &'a [u8],
+}
+
+/// Mode 7 private message, as implemented by ntpd and used by ntpdc
+#[derive(Debug, PartialEq)]
+pub struct NtpPrivateMessage<'a> {
+    pub version: u8,
+    pub response: bool,
+    pub more: bool,
+    pub auth: bool,
+    pub sequence: u8,
+    pub implementation: u8,
+    pub request_code: u8,
+    pub error: u8,
+    pub nb_items: u16,
+    pub item_size: u16,
+    pub data: &'a [u8],
+}
+
+/// Get the mode of a NTP message from its first byte
+pub fn ntp_message_mode(i: &[u8]) -> Option<u8> {
+    i.first().map(|b| b & 0x07)
+}
+
+named!(pub parse_ntp_control<NtpControlMessage>,
+    do_parse!(
+        b0: be_u8
+        >> b1: be_u8
+        >> sequence: be_u16
+        >> status: be_u16
+        >> association_id: be_u16
+        >> offset: be_u16
+        >> count: be_u16
+        >> data: rest
+        >> (
+            NtpControlMessage{
+                version: (b0 >> 3) & 0x07,
+                response: b1 & 0x80 != 0,
+                error: b1 & 0x40 != 0,
+                more: b1 & 0x20 != 0,
+                opcode: b1 & 0x1f,
+                sequence: sequence,
+                status: status,
+                association_id: association_id,
+                offset: offset,
+                count: count,
+                data: data,
+            }
+        )
+    )
+);
+
+named!(pub parse_ntp_private<NtpPrivateMessage>,
+    do_parse!(
+        b0: be_u8
+        >> b1: be_u8
+        >> implementation: be_u8
+        >> request_code: be_u8
+        >> err_nb_items: be_u16
+        >> item_size: be_u16
+        >> data: rest
+        >> (
+            NtpPrivateMessage{
+                version: (b0 >> 3) & 0x07,
+                response: b0 & 0x80 != 0,
+                more: b0 & 0x40 != 0,
+                auth: b1 & 0x80 != 0,
+                sequence: b1 & 0x7f,
+                implementation: implementation,
+                request_code: request_code,
+                error: (err_nb_items >> 12) as u8,
+                nb_items: err_nb_items & 0x0fff,
+                item_size: item_size & 0x0fff,
+                data: data,
+            }
+        )
+    )
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ntp_private_monlist() {
+        // ntpdc -c monlist request, implementation XNTPD
+        let buf: &[u8] = &[
+            0x17, 0x00, 0x03, 0x2a, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(ntp_message_mode(buf), Some(NTP_MODE_PRIVATE));
+        let (rem, msg) = parse_ntp_private(buf).unwrap();
+        assert_eq!(rem.len(), 0);
+        assert_eq!(msg.version, 2);
+        assert!(!msg.response);
+        assert_eq!(msg.implementation, 3);
+        assert_eq!(msg.request_code, NTP_REQ_MON_GETLIST_1);
+        assert_eq!(msg.nb_items, 0);
+        assert_eq!(msg.data.len(), 8);
+
+        // first packet of the response, 6 items of 72 bytes
+        let buf: &[u8] = &[
+            0xd7, 0x00, 0x03, 0x2a, 0x00, 0x06, 0x00, 0x48,
+        ];
+        let (_, msg) = parse_ntp_private(buf).unwrap();
+        assert!(msg.response);
+        assert!(msg.more);
+        assert_eq!(msg.error, 0);
+        assert_eq!(msg.nb_items, 6);
+        assert_eq!(msg.item_size, 72);
+    }
+
+    #[test]
+    fn test_parse_ntp_control_readvar() {
+        // ntpq -c rv request
+        let buf: &[u8] = &[
+            0x16, 0x02, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(ntp_message_mode(buf), Some(NTP_MODE_CONTROL));
+        let (_, msg) = parse_ntp_control(buf).unwrap();
+        assert_eq!(msg.version, 2);
+        assert!(!msg.response);
+        assert_eq!(msg.opcode, 2);
+        assert_eq!(msg.sequence, 1);
+        assert_eq!(msg.count, 0);
+
+        let buf: &[u8] = &[
+            0x16, 0x82, 0x00, 0x01, 0x06, 0x18, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x04, 0x61, 0x3d, 0x31, 0x0d,
+        ];
+        let (_, msg) = parse_ntp_control(buf).unwrap();
+        assert!(msg.response);
+        assert_eq!(msg.status, 0x0618);
+        assert_eq!(msg.count, 4);
+        assert_eq!(msg.data, b"a=1\r");
+
+        assert!(parse_ntp_control(&buf[..8]).is_err());
+    }
+}
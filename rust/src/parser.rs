@@ -23,8 +23,9 @@ use crate::core::{DetectEngineState,Flow,AppLayerEventType,AppLayerDecoderEvents
 use crate::filecontainer::FileContainer;
 use crate::applayer;
 
+use std::ffi::CString;
 use std::os::raw::{c_void,c_char,c_int};
-use crate::applayer::{AppLayerGetTxIterTuple};
+use crate::applayer::{AppLayerEvent,AppLayerGetTxIterTuple,State};
 
 /// Rust parser declaration
 #[repr(C)]
@@ -102,6 +103,63 @@ pub struct RustParser {
     pub get_tx_iterator:    Option<GetTxIteratorFn>,
 }
 
+impl RustParser {
+    /// Parser declaration using the generic entry points of
+    /// `applayer::State` for the state `S`.
+    pub fn new<S: State>(name: &'static [u8], default_port: *const c_char,
+                         ipproto: c_int, probe_ts: ProbeFn,
+                         probe_tc: ProbeFn) -> RustParser
+    {
+        RustParser {
+            name: name.as_ptr() as *const c_char,
+            default_port: default_port,
+            ipproto: ipproto,
+            probe_ts: probe_ts,
+            probe_tc: probe_tc,
+            min_depth: 0,
+            max_depth: 16,
+            state_new: applayer::state_new::<S>,
+            state_free: applayer::state_free::<S>,
+            tx_free: applayer::state_tx_free::<S>,
+            parse_ts: applayer::parse_request::<S>,
+            parse_tc: applayer::parse_response::<S>,
+            get_tx_count: applayer::state_get_tx_count::<S>,
+            get_tx: applayer::state_get_tx::<S>,
+            tx_get_comp_st: applayer::state_progress_completion_status::<S>,
+            tx_get_progress: applayer::tx_get_alstate_progress::<S>,
+            get_tx_logged: Some(applayer::tx_get_logged::<S>),
+            set_tx_logged: Some(applayer::tx_set_logged::<S>),
+            get_de_state: applayer::tx_get_detect_state::<S>,
+            set_de_state: applayer::tx_set_detect_state::<S>,
+            get_events: Some(applayer::tx_get_events::<S>),
+            get_eventinfo: Some(<S::Event as AppLayerEvent>::get_event_info),
+            get_eventinfo_byid: Some(<S::Event as AppLayerEvent>::get_event_info_by_id),
+            localstorage_new: None,
+            localstorage_free: None,
+            get_tx_mpm_id: None,
+            set_tx_mpm_id: None,
+            get_files: None,
+            get_tx_iterator: Some(applayer::state_get_tx_iterator::<S>),
+        }
+    }
+}
+
+/// Register the protocol detection of a parser and, if enabled, the
+/// parser itself. Returns None if the protocol detection is disabled.
+pub unsafe fn register_parser(parser: &RustParser, ipproto: &str) -> Option<AppProto> {
+    let ip_proto_str = CString::new(ipproto).unwrap();
+    if AppLayerProtoDetectConfProtoDetectionEnabled(ip_proto_str.as_ptr(),
+                                                    parser.name) == 0 {
+        return None;
+    }
+    let alproto = AppLayerRegisterProtocolDetection(parser, 1);
+    if AppLayerParserConfParserEnabled(ip_proto_str.as_ptr(),
+                                       parser.name) != 0 {
+        let _ = AppLayerRegisterParser(parser, alproto);
+    }
+    Some(alproto)
+}
+
 
 
 
@@ -0,0 +1,140 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+//! RDP detection keyword support
+
+use rdp::parser::*;
+use rdp::rdp::{RdpTransaction, RdpTransactionItem};
+
+/// the `i`th channel name: requested channels on the mcs connect request,
+/// channels the server assigned an id to on the mcs connect response
+fn tx_get_channel(tx: &RdpTransaction, i: usize) -> Option<&str> {
+    match tx.item {
+        RdpTransactionItem::McsConnectRequest(ref mcs) => {
+            for child in &mcs.children {
+                if let McsConnectRequestChild::CsNet(ref net) = child {
+                    return net.channels.get(i).map(|c| c.name.as_str());
+                }
+            }
+            None
+        }
+        RdpTransactionItem::McsConnectResponse(_) => tx
+            .channels
+            .iter()
+            .filter(|c| c.channel_id != 0)
+            .nth(i)
+            .map(|c| c.name.as_str()),
+        _ => None,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_rdp_tx_get_channel(
+    tx: &mut RdpTransaction,
+    i: u16,
+    buffer: *mut *const u8,
+    buffer_len: *mut u32,
+) -> u8 {
+    if let Some(name) = tx_get_channel(tx, i as usize) {
+        *buffer = name.as_ptr();
+        *buffer_len = name.len() as u32;
+        return 1;
+    }
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_rdp_tx_get_cookie(
+    tx: &mut RdpTransaction,
+    buffer: *mut *const u8,
+    buffer_len: *mut u32,
+) -> u8 {
+    if let RdpTransactionItem::X224ConnectionRequest(ref x224) = tx.item {
+        if let Some(ref cookie) = x224.cookie {
+            *buffer = cookie.mstshash.as_ptr();
+            *buffer_len = cookie.mstshash.len() as u32;
+            return 1;
+        }
+    }
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_rdp_tx_get_requested_protocols(
+    tx: &mut RdpTransaction,
+    value: *mut u32,
+) -> u8 {
+    if let RdpTransactionItem::X224ConnectionRequest(ref x224) = tx.item {
+        if let Some(ref req) = x224.negotiation_request {
+            *value = req.protocols.bits();
+            return 1;
+        }
+    }
+    0
+}
+
+/// client core data of the mcs connect request, if any
+fn tx_get_client_core(tx: &RdpTransaction) -> Option<&CsClientCoreData> {
+    if let RdpTransactionItem::McsConnectRequest(ref mcs) = tx.item {
+        for child in &mcs.children {
+            if let McsConnectRequestChild::CsClientCore(ref core) = child {
+                return Some(core);
+            }
+        }
+    }
+    None
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_rdp_tx_get_client_name(
+    tx: &mut RdpTransaction,
+    buffer: *mut *const u8,
+    buffer_len: *mut u32,
+) -> u8 {
+    if let Some(core) = tx_get_client_core(tx) {
+        if core.client_name.len() > 0 {
+            *buffer = core.client_name.as_ptr();
+            *buffer_len = core.client_name.len() as u32;
+            return 1;
+        }
+    }
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_rdp_tx_get_client_build(
+    tx: &mut RdpTransaction,
+    value: *mut u32,
+) -> u8 {
+    if let Some(core) = tx_get_client_core(tx) {
+        *value = core.client_build.number;
+        return 1;
+    }
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_rdp_tx_get_keyboard_layout(
+    tx: &mut RdpTransaction,
+    value: *mut u32,
+) -> u8 {
+    if let Some(core) = tx_get_client_core(tx) {
+        *value = core.keyboard_layout;
+        return 1;
+    }
+    0
+}
@@ -20,3 +20,6 @@
 // custom errors the parser can emit
 pub const RDP_UNIMPLEMENTED_LENGTH_DETERMINANT: u32 = 128;
 pub const RDP_NOT_X224_CLASS_0_ERROR: u32 = 129;
+pub const RDP_UNKNOWN_CERTIFICATE_VERSION: u32 = 130;
+pub const RDP_NOT_MCS_DOMAIN_PDU: u32 = 131;
+pub const RDP_UNKNOWN_SECURITY_PDU: u32 = 132;
@@ -17,7 +17,7 @@
 
 // Author: Zach Kelly <zach.kelly@lmco.com>
 
-use super::rdp::{RdpTransaction, RdpTransactionItem};
+use super::rdp::{RdpChannel, RdpTransaction, RdpTransactionItem};
 use json::{Json, JsonT};
 use rdp::parser::*;
 use rdp::windows;
@@ -51,9 +51,55 @@ fn to_json(tx: &RdpTransaction) -> Option<Json> {
             mcs_req_to_json(&js, mcs);
         }
 
-        RdpTransactionItem::McsConnectResponse(_) => {
-            // no additional JSON data beyond `event_type`
-            js.set_string("event_type", "connect_response");
+        RdpTransactionItem::McsConnectResponse(ref mcs) => {
+            mcs_resp_to_json(&js, mcs);
+            if tx.channels.len() > 0 {
+                js.set("channels", channels_to_json(&tx.channels));
+            }
+        }
+
+        RdpTransactionItem::McsErectDomainRequest => {
+            js.set_string("event_type", "erect_domain_request");
+        }
+
+        RdpTransactionItem::McsAttachUserRequest => {
+            js.set_string("event_type", "attach_user_request");
+        }
+
+        RdpTransactionItem::McsAttachUserConfirm(ref confirm) => {
+            js.set_string("event_type", "attach_user_confirm");
+            js.set_integer("result", confirm.result as u64);
+            if let Some(initiator) = confirm.initiator {
+                js.set_integer("user_id", initiator as u64);
+            }
+        }
+
+        RdpTransactionItem::McsChannelJoinRequest(ref join) => {
+            js.set_string("event_type", "channel_join_request");
+            js.set_integer("user_id", join.initiator as u64);
+            js.set_integer("channel_id", join.channel_id as u64);
+        }
+
+        RdpTransactionItem::McsChannelJoinConfirm(ref confirm) => {
+            js.set_string("event_type", "channel_join_confirm");
+            js.set_integer("result", confirm.result as u64);
+            js.set_integer("user_id", confirm.initiator as u64);
+            js.set_integer("requested_channel_id", confirm.requested as u64);
+            if let Some(channel_id) = confirm.channel_id {
+                js.set_integer("channel_id", channel_id as u64);
+            }
+        }
+
+        RdpTransactionItem::SecurityExchange(ref exchange) => {
+            js.set_string("event_type", "security_exchange");
+            js.set_integer(
+                "encrypted_client_random_length",
+                exchange.encrypted_client_random_len as u64,
+            );
+        }
+
+        RdpTransactionItem::ClientInfo(ref info) => {
+            client_info_to_json(&js, info);
         }
 
         RdpTransactionItem::TlsCertificateChain(chain) => {
@@ -418,7 +464,7 @@ fn mcs_req_to_json(js: &Json, mcs: &McsConnectRequest) {
                 if net.channels.len() > 0 {
                     let channels = Json::array();
                     for channel in &net.channels {
-                        channels.array_append_string(&channel);
+                        channels.array_append_string(&channel.name);
                     }
                     js.set("channels", channels);
                 }
@@ -429,6 +475,266 @@ fn mcs_req_to_json(js: &Json, mcs: &McsConnectRequest) {
     }
 }
 
+/// json helper for the channel inventory of a McsConnectResponse
+fn channels_to_json(channels: &[RdpChannel]) -> Json {
+    use rdp::parser::ChannelOptions as Flags;
+
+    let js_channels = Json::array();
+    for channel in channels {
+        let js_channel = Json::object();
+        js_channel.set_string("name", &channel.name);
+        let kind = match channel.kind {
+            ChannelKind::Clipboard => "clipboard",
+            ChannelKind::DeviceRedirection => "device_redirection",
+            ChannelKind::Audio => "audio",
+            ChannelKind::DynamicChannels => "dynamic_channels",
+            ChannelKind::RemoteApp => "remote_app",
+            ChannelKind::Other => "other",
+        };
+        js_channel.set_string("type", kind);
+        if channel.channel_id != 0 {
+            js_channel.set_integer("channel_id", channel.channel_id as u64);
+        }
+        js_channel.set_boolean("assigned", channel.channel_id != 0);
+
+        let options = Json::array();
+        if channel.options.contains(Flags::CHANNEL_OPTION_INITIALIZED) {
+            options.array_append_string("initialized");
+        }
+        if channel.options.contains(Flags::CHANNEL_OPTION_ENCRYPT_RDP) {
+            options.array_append_string("encrypt_rdp");
+        }
+        if channel.options.contains(Flags::CHANNEL_OPTION_ENCRYPT_SC) {
+            options.array_append_string("encrypt_sc");
+        }
+        if channel.options.contains(Flags::CHANNEL_OPTION_ENCRYPT_CS) {
+            options.array_append_string("encrypt_cs");
+        }
+        if channel.options.contains(Flags::CHANNEL_OPTION_PRI_HIGH) {
+            options.array_append_string("pri_high");
+        }
+        if channel.options.contains(Flags::CHANNEL_OPTION_PRI_MED) {
+            options.array_append_string("pri_med");
+        }
+        if channel.options.contains(Flags::CHANNEL_OPTION_PRI_LOW) {
+            options.array_append_string("pri_low");
+        }
+        if channel.options.contains(Flags::CHANNEL_OPTION_COMPRESS_RDP) {
+            options.array_append_string("compress_rdp");
+        }
+        if channel.options.contains(Flags::CHANNEL_OPTION_COMPRESS) {
+            options.array_append_string("compress");
+        }
+        if channel.options.contains(Flags::CHANNEL_OPTION_SHOW_PROTOCOL) {
+            options.array_append_string("show_protocol");
+        }
+        if channel.options.contains(Flags::REMOTE_CONTROL_PERSISTENT) {
+            options.array_append_string("remote_control_persistent");
+        }
+        js_channel.set("options", options);
+
+        js_channels.array_append(js_channel);
+    }
+    return js_channels;
+}
+
+/// json helper for McsConnectResponse
+fn mcs_resp_to_json(js: &Json, mcs: &McsConnectResponse) {
+    let unknown = String::from("unknown");
+
+    js.set_string("event_type", "connect_response");
+    if mcs.children.len() == 0 {
+        return;
+    }
+
+    let js_server = Json::object();
+    for child in &mcs.children {
+        match child {
+            McsConnectResponseChild::ScCore(ref core) => {
+                match core.version {
+                    Some(ref ver) => js_server
+                        .set_string("version", &version_to_string(ver, "v")),
+                    None => js_server.set_string("version", &unknown),
+                }
+
+                if let Some(ref protocols) = core.client_requested_protocols {
+                    js_server.set(
+                        "client_requested_protocols",
+                        protocols_to_json(protocols),
+                    );
+                }
+
+                if let Some(ref early_capability_flags) =
+                    core.early_capability_flags
+                {
+                    use rdp::parser::ServerEarlyCapabilityFlags as Flags;
+
+                    if !early_capability_flags.is_empty() {
+                        let flags = Json::array();
+                        if early_capability_flags
+                            .contains(Flags::RNS_UD_SC_EDGE_ACTIONS_SUPPORTED_V1)
+                        {
+                            flags.array_append_string("edge_actions_v1");
+                        }
+                        if early_capability_flags
+                            .contains(Flags::RNS_UD_SC_DYNAMIC_DST_SUPPORTED)
+                        {
+                            flags.array_append_string("dynamic_dst");
+                        }
+                        if early_capability_flags
+                            .contains(Flags::RNS_UD_SC_EDGE_ACTIONS_SUPPORTED_V2)
+                        {
+                            flags.array_append_string("edge_actions_v2");
+                        }
+                        if early_capability_flags.contains(
+                            Flags::RNS_UD_SC_SKIP_CHANNELJOIN_SUPPORTED,
+                        ) {
+                            flags.array_append_string("skip_channeljoin");
+                        }
+                        js_server.set("capabilities", flags);
+                    }
+                }
+            }
+
+            McsConnectResponseChild::ScSecurity(ref sec) => {
+                let method = match sec.encryption_method {
+                    Some(EncryptionMethod::EncryptionMethodNone) => "none",
+                    Some(EncryptionMethod::EncryptionMethod40Bit) => "40bit",
+                    Some(EncryptionMethod::EncryptionMethod128Bit) => "128bit",
+                    Some(EncryptionMethod::EncryptionMethod56Bit) => "56bit",
+                    Some(EncryptionMethod::EncryptionMethodFips) => "fips",
+                    None => "unknown",
+                };
+                js_server.set_string("encryption_method", method);
+
+                let level = match sec.encryption_level {
+                    Some(EncryptionLevel::EncryptionLevelNone) => "none",
+                    Some(EncryptionLevel::EncryptionLevelLow) => "low",
+                    Some(EncryptionLevel::EncryptionLevelClientCompatible) => {
+                        "client_compatible"
+                    }
+                    Some(EncryptionLevel::EncryptionLevelHigh) => "high",
+                    Some(EncryptionLevel::EncryptionLevelFips) => "fips",
+                    None => "unknown",
+                };
+                js_server.set_string("encryption_level", level);
+
+                if sec.server_random.len() > 0 {
+                    js_server.set_string(
+                        "server_random",
+                        &bytes_to_hex(&sec.server_random),
+                    );
+                }
+
+                match sec.server_certificate {
+                    Some(ServerCertificate::Proprietary(ref cert)) => {
+                        let js_cert = Json::object();
+                        js_cert.set_string("type", "proprietary");
+                        js_cert.set_integer("key_bits", cert.key_bitlen as u64);
+                        js_cert.set_integer(
+                            "public_exponent",
+                            cert.public_exponent as u64,
+                        );
+                        js_server.set("certificate", js_cert);
+                    }
+                    Some(ServerCertificate::X509Chain(ref chain)) => {
+                        let js_cert = Json::object();
+                        js_cert.set_string("type", "x509");
+                        let js_chain = Json::array();
+                        for blob in chain {
+                            match parse_x509_der(blob) {
+                                Ok((_, cert)) => {
+                                    js_chain.array_append_string(
+                                        &cert
+                                            .tbs_certificate
+                                            .serial
+                                            .to_str_radix(16),
+                                    );
+                                }
+                                _ => {}
+                            }
+                        }
+                        js_cert.set("x509_serials", js_chain);
+                        js_server.set("certificate", js_cert);
+                    }
+                    None => {}
+                }
+            }
+
+            McsConnectResponseChild::ScNet(ref net) => {
+                js_server
+                    .set_integer("mcs_channel_id", net.mcs_channel_id as u64);
+                if net.channel_ids.len() > 0 {
+                    let ids = Json::array();
+                    for id in &net.channel_ids {
+                        ids.array_append(Json::integer(*id as u64));
+                    }
+                    js_server.set("channel_ids", ids);
+                }
+            }
+
+            McsConnectResponseChild::ScUnknown(_) => {}
+        }
+    }
+    js.set("server", js_server);
+}
+
+/// json helper for ClientInfo
+fn client_info_to_json(js: &Json, info: &ClientInfo) {
+    js.set_string("event_type", "client_info");
+    let js_info = Json::object();
+    if info.domain.len() > 0 {
+        js_info.set_string("domain", &info.domain);
+    }
+    if info.user_name.len() > 0 {
+        js_info.set_string("username", &info.user_name);
+    }
+    js_info.set_boolean(
+        "autologon",
+        info.flags.contains(ClientInfoFlags::INFO_AUTOLOGON),
+    );
+    js_info.set_boolean("password_present", info.password_present);
+    if info.alternate_shell.len() > 0 {
+        js_info.set_string("alternate_shell", &info.alternate_shell);
+    }
+    if info.working_dir.len() > 0 {
+        js_info.set_string("working_dir", &info.working_dir);
+    }
+    js_info.set_integer("code_page", info.code_page as u64);
+    js.set("client_info", js_info);
+}
+
+/// converts ProtocolFlags to a json list of protocol names
+fn protocols_to_json(protocols: &ProtocolFlags) -> Json {
+    let js = Json::array();
+    // PROTOCOL_RDP is 0, so it is only listed if nothing else is
+    if protocols.is_empty() {
+        js.array_append_string("rdp");
+    }
+    if protocols.contains(ProtocolFlags::PROTOCOL_SSL) {
+        js.array_append_string("ssl");
+    }
+    if protocols.contains(ProtocolFlags::PROTOCOL_HYBRID) {
+        js.array_append_string("hybrid");
+    }
+    if protocols.contains(ProtocolFlags::PROTOCOL_RDSTLS) {
+        js.array_append_string("rds_tls");
+    }
+    if protocols.contains(ProtocolFlags::PROTOCOL_HYBRID_EX) {
+        js.array_append_string("hybrid_ex");
+    }
+    js
+}
+
+/// converts a byte slice to a lowercase hex string
+fn bytes_to_hex(input: &[u8]) -> String {
+    let mut s = String::with_capacity(input.len() * 2);
+    for b in input {
+        s.push_str(&format!("{:02x}", b));
+    }
+    s
+}
+
 /// converts RdpClientVersion to a string, using the provided prefix
 fn version_to_string<'a>(ver: &RdpClientVersion, prefix: &'a str) -> String {
     let mut result = String::from(prefix);
@@ -511,6 +817,7 @@ mod tests {
             sas_sequence: None,
             keyboard_layout: 0x409,
             client_build: windows::OperatingSystem {
+                number: 17763,
                 build: windows::Build::Win10_17763,
                 suffix: windows::Suffix::Rs5,
             },
@@ -547,6 +854,7 @@ mod tests {
             sas_sequence: None,
             keyboard_layout: 0x409,
             client_build: windows::OperatingSystem {
+                number: 17763,
                 build: windows::Build::Win10_17763,
                 suffix: windows::Suffix::Rs5,
             },
@@ -583,6 +891,7 @@ mod tests {
             sas_sequence: None,
             keyboard_layout: 0x409,
             client_build: windows::OperatingSystem {
+                number: 17763,
                 build: windows::Build::Win10_17763,
                 suffix: windows::Suffix::Rs5,
             },
@@ -619,6 +928,7 @@ mod tests {
             sas_sequence: None,
             keyboard_layout: 0x409,
             client_build: windows::OperatingSystem {
+                number: 17763,
                 build: windows::Build::Win10_17763,
                 suffix: windows::Suffix::Rs5,
             },
@@ -645,6 +955,11 @@ mod tests {
         assert!(get_color_depth(&core_data).is_none());
     }
 
+    #[test]
+    fn test_bytes_to_hex() {
+        assert_eq!("00ab10", bytes_to_hex(&[0x00, 0xab, 0x10]));
+    }
+
     #[test]
     fn test_keyboard_string() {
         assert_eq!("enhanced", keyboard_to_string(&KeyboardType::KbEnhanced));
@@ -27,7 +27,10 @@
 //! * x.691-spec: <https://www.itu.int/rec/T-REC-X.691/en>
 
 use nom::{be_u16, be_u8, le_u16, le_u32, le_u8, ErrorKind, IResult};
-use rdp::error::RDP_NOT_X224_CLASS_0_ERROR;
+use rdp::error::{
+    RDP_NOT_MCS_DOMAIN_PDU, RDP_NOT_X224_CLASS_0_ERROR,
+    RDP_UNKNOWN_CERTIFICATE_VERSION, RDP_UNKNOWN_SECURITY_PDU,
+};
 use rdp::util::{
     le_slice_to_string, parse_per_length_determinant, utf7_slice_to_string,
 };
@@ -230,6 +233,12 @@ pub struct X223Data {
 pub enum X223DataChild {
     McsConnectRequest(McsConnectRequest),
     McsConnectResponse(McsConnectResponse),
+    McsErectDomainRequest,
+    McsAttachUserRequest,
+    McsAttachUserConfirm(McsAttachUserConfirm),
+    McsChannelJoinRequest(McsChannelJoinRequest),
+    McsChannelJoinConfirm(McsChannelJoinConfirm),
+    McsSendDataRequest(McsSendDataRequest),
     Raw(Vec<u8>),
 }
 
@@ -412,7 +421,62 @@ pub enum DesktopOrientation {
 /// rdp-spec, section 2.2.1.3.4
 #[derive(Clone, Debug, PartialEq)]
 pub struct CsNet {
-    pub channels: Vec<String>,
+    pub channels: Vec<ChannelDef>,
+}
+
+/// rdp-spec, section 2.2.1.3.4.1
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChannelDef {
+    pub name: String,
+    pub options: ChannelOptions,
+}
+
+// rdp-spec, section 2.2.1.3.4.1
+bitflags! {
+    #[derive(Default)]
+    pub struct ChannelOptions: u32 {
+        const CHANNEL_OPTION_INITIALIZED = 0x8000_0000;
+        const CHANNEL_OPTION_ENCRYPT_RDP = 0x4000_0000;
+        const CHANNEL_OPTION_ENCRYPT_SC = 0x2000_0000;
+        const CHANNEL_OPTION_ENCRYPT_CS = 0x1000_0000;
+        const CHANNEL_OPTION_PRI_HIGH = 0x0800_0000;
+        const CHANNEL_OPTION_PRI_MED = 0x0400_0000;
+        const CHANNEL_OPTION_PRI_LOW = 0x0200_0000;
+        const CHANNEL_OPTION_COMPRESS_RDP = 0x0080_0000;
+        const CHANNEL_OPTION_COMPRESS = 0x0040_0000;
+        const CHANNEL_OPTION_SHOW_PROTOCOL = 0x0020_0000;
+        const REMOTE_CONTROL_PERSISTENT = 0x0010_0000;
+    }
+}
+
+/// well-known static virtual channels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChannelKind {
+    /// cliprdr, ms-rdpeclip
+    Clipboard,
+    /// rdpdr, ms-rdpefs: drives, printers, ports and smart cards
+    DeviceRedirection,
+    /// rdpsnd, ms-rdpea
+    Audio,
+    /// drdynvc, ms-rdpedyc
+    DynamicChannels,
+    /// rail, ms-rdperp
+    RemoteApp,
+    Other,
+}
+
+impl ChannelDef {
+    /// channel names are case-insensitive
+    pub fn kind(&self) -> ChannelKind {
+        match self.name.to_lowercase().as_str() {
+            "cliprdr" => ChannelKind::Clipboard,
+            "rdpdr" => ChannelKind::DeviceRedirection,
+            "rdpsnd" => ChannelKind::Audio,
+            "drdynvc" => ChannelKind::DynamicChannels,
+            "rail" => ChannelKind::RemoteApp,
+            _ => ChannelKind::Other,
+        }
+    }
 }
 
 /// generic structure
@@ -425,7 +489,230 @@ pub struct CsUnknown {
 
 /// rdp-spec, section 2.2.1.4
 #[derive(Clone, Debug, PartialEq)]
-pub struct McsConnectResponse {}
+pub struct McsConnectResponse {
+    pub children: Vec<McsConnectResponseChild>,
+}
+
+/// variants that an mcs connection response can hold
+#[derive(Clone, Debug, PartialEq)]
+pub enum McsConnectResponseChild {
+    ScCore(ScCoreData),
+    ScSecurity(ScSecurityData),
+    ScNet(ScNet),
+    ScUnknown(ScUnknown),
+}
+
+/// rdp-spec, section 2.2.1.4.1
+#[derive(Clone, Debug, FromPrimitive, PartialEq)]
+pub enum ScType {
+    Core = 0x0c01,
+    Security = 0x0c02,
+    Net = 0x0c03,
+}
+
+/// rdp-spec, section 2.2.1.4.2
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScCoreData {
+    pub version: Option<RdpClientVersion>,
+    // optional fields
+    pub client_requested_protocols: Option<ProtocolFlags>,
+    pub early_capability_flags: Option<ServerEarlyCapabilityFlags>,
+}
+
+// rdp-spec, section 2.2.1.4.2
+bitflags! {
+    #[derive(Default)]
+    pub struct ServerEarlyCapabilityFlags: u32 {
+        const RNS_UD_SC_EDGE_ACTIONS_SUPPORTED_V1 = 0x1;
+        const RNS_UD_SC_DYNAMIC_DST_SUPPORTED = 0x2;
+        const RNS_UD_SC_EDGE_ACTIONS_SUPPORTED_V2 = 0x4;
+        const RNS_UD_SC_SKIP_CHANNELJOIN_SUPPORTED = 0x8;
+    }
+}
+
+/// rdp-spec, section 2.2.1.4.3
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScSecurityData {
+    pub encryption_method: Option<EncryptionMethod>,
+    pub encryption_level: Option<EncryptionLevel>,
+    pub server_random: Vec<u8>,
+    pub server_certificate: Option<ServerCertificate>,
+}
+
+/// rdp-spec, section 2.2.1.4.3
+#[derive(Clone, Debug, FromPrimitive, PartialEq)]
+pub enum EncryptionMethod {
+    EncryptionMethodNone = 0x0,
+    EncryptionMethod40Bit = 0x1,
+    EncryptionMethod128Bit = 0x2,
+    EncryptionMethod56Bit = 0x8,
+    EncryptionMethodFips = 0x10,
+}
+
+/// rdp-spec, section 2.2.1.4.3
+#[derive(Clone, Debug, FromPrimitive, PartialEq)]
+pub enum EncryptionLevel {
+    EncryptionLevelNone = 0,
+    EncryptionLevelLow = 1,
+    EncryptionLevelClientCompatible = 2,
+    EncryptionLevelHigh = 3,
+    EncryptionLevelFips = 4,
+}
+
+/// rdp-spec, section 2.2.1.4.3.1
+#[derive(Clone, Debug, PartialEq)]
+pub enum ServerCertificate {
+    Proprietary(ProprietaryCertificate),
+    X509Chain(Vec<Vec<u8>>),
+}
+
+/// rdp-spec, section 2.2.1.4.3.1.1
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProprietaryCertificate {
+    pub key_bitlen: u32,
+    pub public_exponent: u32,
+    pub modulus: Vec<u8>,
+}
+
+/// rdp-spec, section 2.2.1.4.3.1
+pub const CERT_CHAIN_VERSION_1: u32 = 0x1;
+pub const CERT_CHAIN_VERSION_2: u32 = 0x2;
+
+/// rdp-spec, section 2.2.1.4.4
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScNet {
+    pub mcs_channel_id: u16,
+    pub channel_ids: Vec<u16>,
+}
+
+/// generic structure
+/// cf. rdp-spec, section 2.2.1.4.1
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScUnknown {
+    pub typ: u16,
+    pub data: Vec<u8>,
+}
+
+/// t.125-spec, section 7, part 10 (DomainMCSPDU choices)
+#[derive(Clone, Debug, FromPrimitive, PartialEq)]
+pub enum McsDomainPduType {
+    ErectDomainRequest = 1,
+    AttachUserRequest = 10,
+    AttachUserConfirm = 11,
+    ChannelJoinRequest = 14,
+    ChannelJoinConfirm = 15,
+    SendDataRequest = 25,
+}
+
+/// offset of the PER encoded UserId, t.125-spec, section 7, part 1
+const MCS_USER_ID_BASE: u16 = 1001;
+
+/// rdp-spec, section 2.2.1.7
+#[derive(Clone, Debug, PartialEq)]
+pub struct McsAttachUserConfirm {
+    pub result: u8,
+    pub initiator: Option<u16>,
+}
+
+/// rdp-spec, section 2.2.1.8
+#[derive(Clone, Debug, PartialEq)]
+pub struct McsChannelJoinRequest {
+    pub initiator: u16,
+    pub channel_id: u16,
+}
+
+/// rdp-spec, section 2.2.1.9
+#[derive(Clone, Debug, PartialEq)]
+pub struct McsChannelJoinConfirm {
+    pub result: u8,
+    pub initiator: u16,
+    pub requested: u16,
+    pub channel_id: Option<u16>,
+}
+
+/// t.125-spec, section 7, part 10 (SendDataRequest)
+#[derive(Clone, Debug, PartialEq)]
+pub struct McsSendDataRequest {
+    pub initiator: u16,
+    pub channel_id: u16,
+    pub child: SendDataRequestChild,
+}
+
+/// variants of the client PDUs sent before the connection is encrypted
+#[derive(Clone, Debug, PartialEq)]
+pub enum SendDataRequestChild {
+    SecurityExchange(SecurityExchange),
+    ClientInfo(ClientInfo),
+    EncryptedClientInfo,
+    Raw(Vec<u8>),
+}
+
+// rdp-spec, section 2.2.8.1.1.2.1
+bitflags! {
+    #[derive(Default)]
+    pub struct SecurityFlags: u16 {
+        const SEC_EXCHANGE_PKT = 0x0001;
+        const SEC_TRANSPORT_REQ = 0x0002;
+        const RDP_SEC_TRANSPORT_RSP = 0x0004;
+        const SEC_ENCRYPT = 0x0008;
+        const SEC_RESET_SEQNO = 0x0010;
+        const SEC_IGNORE_SEQNO = 0x0020;
+        const SEC_INFO_PKT = 0x0040;
+        const SEC_LICENSE_PKT = 0x0080;
+        const SEC_LICENSE_ENCRYPT_CS = 0x0200;
+        const SEC_REDIRECTION_PKT = 0x0400;
+        const SEC_SECURE_CHECKSUM = 0x0800;
+        const SEC_AUTODETECT_REQ = 0x1000;
+        const SEC_AUTODETECT_RSP = 0x2000;
+        const SEC_HEARTBEAT = 0x4000;
+        const SEC_FLAGSHI_VALID = 0x8000;
+    }
+}
+
+/// rdp-spec, section 2.2.1.10.1
+#[derive(Clone, Debug, PartialEq)]
+pub struct SecurityExchange {
+    pub encrypted_client_random_len: u32,
+}
+
+/// rdp-spec, section 2.2.1.11.1.1
+/// The password is skipped, only its presence is recorded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClientInfo {
+    pub code_page: u32,
+    pub flags: ClientInfoFlags,
+    pub domain: String,
+    pub user_name: String,
+    pub password_present: bool,
+    pub alternate_shell: String,
+    pub working_dir: String,
+}
+
+// rdp-spec, section 2.2.1.11.1.1
+bitflags! {
+    #[derive(Default)]
+    pub struct ClientInfoFlags: u32 {
+        const INFO_MOUSE = 0x1;
+        const INFO_DISABLECTRLALTDEL = 0x2;
+        const INFO_AUTOLOGON = 0x8;
+        const INFO_UNICODE = 0x10;
+        const INFO_MAXIMIZESHELL = 0x20;
+        const INFO_LOGONNOTIFY = 0x40;
+        const INFO_COMPRESSION = 0x80;
+        const INFO_ENABLEWINDOWSKEY = 0x100;
+        const INFO_REMOTECONSOLEAUDIO = 0x2000;
+        const INFO_FORCE_ENCRYPTED_CS_PDU = 0x4000;
+        const INFO_RAIL = 0x8000;
+        const INFO_LOGONERRORS = 0x10000;
+        const INFO_MOUSE_HAS_WHEEL = 0x20000;
+        const INFO_PASSWORD_IS_SC_PIN = 0x40000;
+        const INFO_NOAUDIOPLAYBACK = 0x80000;
+        const INFO_USING_SAVED_CREDS = 0x100000;
+        const INFO_AUDIOCAPTURE = 0x200000;
+        const INFO_VIDEO_DISABLE = 0x400000;
+        const INFO_HIDEF_RAIL_SUPPORTED = 0x2000000;
+    }
+}
 
 // ==================
 
@@ -774,7 +1061,16 @@ fn parse_x223_data_class_0(input: &[u8]) -> IResult<&[u8], X223Data> {
         },
     };
 
-    let child: X223DataChild = match opt2 {
+    // the tpkt holds the complete message, so incomplete is an error here
+    let opt3: Option<X223DataChild> = match opt2 {
+        Some(x) => Some(x),
+        None => match opt!(i3, complete!(parse_mcs_domain_pdu)) {
+            Ok((_remainder, opt)) => opt,
+            Err(e) => return Err(e),
+        },
+    };
+
+    let child: X223DataChild = match opt3 {
         Some(x) => x,
         None => X223DataChild::Raw(i3.to_vec()),
     };
@@ -1061,9 +1357,11 @@ fn parse_cs_net(input: &[u8]) -> IResult<&[u8], CsNet> {
     for _index in 0..count {
         // a channel name is 8 bytes, section 2.2.1.3.4.1
         let (j1, name) = map_res!(remainder, take!(8), utf7_slice_to_string)?;
-        channels.push(name);
-        // options (u32) are discarded for now
-        let (j2, _options) = try_parse!(j1, le_u32);
+        let (j2, options) = try_parse!(j1, le_u32);
+        channels.push(ChannelDef {
+            name,
+            options: ChannelOptions::from_bits_truncate(options),
+        });
         remainder = j2;
     }
 
@@ -1092,21 +1390,389 @@ fn parse_cs_unknown(input: &[u8]) -> IResult<&[u8], CsUnknown> {
     }
 }
 
-// rdp-spec, section 2.2.1.4
+/// rdp-spec, section 2.2.1.4
 fn parse_mcs_connect_response(
     input: &[u8],
 ) -> IResult<&[u8], McsConnectResponse> {
+    let (i1, _ber_type) = verify!(
+        input,
+        le_u8,
+        // BER: 0b01=application, 0b1=non-primitive, 0b11111
+        |x| x == 0x7f
+    )?;
+    let (i2, _t125_type) = verify!(i1, le_u8, |x| x
+        == T125Type::T125TypeMcsConnectResponse as u8)?;
+
+    // skip to, and consume, H.221 server-to-client key.  Without it there
+    // are no server data blocks, but it is still a connect response
+    let mut children = Vec::new();
+    let offset = match i2.windows(4).position(|x| x == b"McDn") {
+        Some(offset) => offset + 4,
+        None => return Ok((&[], McsConnectResponse { children })),
+    };
+
+    let (i3, data) =
+        length_data!(&i2[offset..], parse_per_length_determinant)?;
+    let mut remainder: &[u8] = data;
+
+    // repeatedly parse server data blocks until data buffer is exhausted
+    while remainder.len() > 0 {
+        match parse_sc_data_block(remainder) {
+            Ok((rem, child)) => {
+                children.push(child);
+                remainder = rem;
+            }
+            Err(_) => break,
+        }
+    }
+
+    return Ok((i3, McsConnectResponse { children }));
+}
+
+/// rdp-spec, section 2.2.1.4.1
+fn parse_sc_data_block(
+    input: &[u8],
+) -> IResult<&[u8], McsConnectResponseChild> {
+    let (i1, typ) = try_parse!(input, le_u16);
+    // less u16, u16
+    let (i2, sz) = map_opt!(i1, le_u16, |x: u16| x.checked_sub(4))?;
+    let (i3, data) = take!(i2, sz)?;
+
+    // a known block that fails to parse is kept as an unknown block
+    let opt: Option<ScType> = num::FromPrimitive::from_u16(typ);
+    let child = match opt {
+        Some(ScType::Core) => match parse_sc_core_data(data) {
+            Ok((_, core)) => Some(McsConnectResponseChild::ScCore(core)),
+            Err(_) => None,
+        },
+        Some(ScType::Security) => match parse_sc_security_data(data) {
+            Ok((_, sec)) => Some(McsConnectResponseChild::ScSecurity(sec)),
+            Err(_) => None,
+        },
+        Some(ScType::Net) => match parse_sc_net(data) {
+            Ok((_, net)) => Some(McsConnectResponseChild::ScNet(net)),
+            Err(_) => None,
+        },
+        None => None,
+    };
+
+    match child {
+        Some(child) => Ok((i3, child)),
+        None => Ok((
+            i3,
+            McsConnectResponseChild::ScUnknown(ScUnknown {
+                typ,
+                data: data.to_vec(),
+            }),
+        )),
+    }
+}
+
+/// rdp-spec, section 2.2.1.4.2
+fn parse_sc_core_data(input: &[u8]) -> IResult<&[u8], ScCoreData> {
+    let (i1, version) = map!(input, le_u32, num::FromPrimitive::from_u32)?;
+
+    //
+    // optional fields below (but each requires the previous)
+    //
+
+    let (i2, client_requested_protocols) =
+        match opt!(i1, map_opt!(le_u32, ProtocolFlags::from_bits)) {
+            Ok((rem, obj)) => (rem, obj),
+            _ => (i1, None),
+        };
+
+    let (i3, early_capability_flags) = match client_requested_protocols {
+        None => (i2, None),
+        Some(_) => match opt!(
+            i2,
+            map!(le_u32, ServerEarlyCapabilityFlags::from_bits_truncate)
+        ) {
+            Ok((rem, obj)) => (rem, obj),
+            _ => (i2, None),
+        },
+    };
+
+    return Ok((
+        i3,
+        ScCoreData {
+            version,
+            client_requested_protocols,
+            early_capability_flags,
+        },
+    ));
+}
+
+/// rdp-spec, section 2.2.1.4.3
+fn parse_sc_security_data(input: &[u8]) -> IResult<&[u8], ScSecurityData> {
+    let (i1, method) = try_parse!(input, le_u32);
+    let (i2, level) = try_parse!(i1, le_u32);
+    let encryption_method = num::FromPrimitive::from_u32(method);
+    let encryption_level = num::FromPrimitive::from_u32(level);
+
+    // random and certificate are only present if encryption is used
+    if method == 0 && level == 0 {
+        return Ok((
+            i2,
+            ScSecurityData {
+                encryption_method,
+                encryption_level,
+                server_random: Vec::new(),
+                server_certificate: None,
+            },
+        ));
+    }
+
+    let (i3, random_len) = try_parse!(i2, le_u32);
+    let (i4, cert_len) = try_parse!(i3, le_u32);
+    let (i5, server_random) = take!(i4, random_len)?;
+    let (i6, cert) = take!(i5, cert_len)?;
+    let server_certificate = match parse_server_certificate(cert) {
+        Ok((_, x)) => Some(x),
+        Err(_) => None,
+    };
+
+    return Ok((
+        i6,
+        ScSecurityData {
+            encryption_method,
+            encryption_level,
+            server_random: server_random.to_vec(),
+            server_certificate,
+        },
+    ));
+}
+
+/// rdp-spec, section 2.2.1.4.3.1
+fn parse_server_certificate(input: &[u8]) -> IResult<&[u8], ServerCertificate> {
+    let (i1, version) = try_parse!(input, le_u32);
+    // the top bit only marks a temporary certificate
+    match version & 0x7fff_ffff {
+        CERT_CHAIN_VERSION_1 => {
+            let (i2, cert) = parse_proprietary_certificate(i1)?;
+            Ok((i2, ServerCertificate::Proprietary(cert)))
+        }
+        CERT_CHAIN_VERSION_2 => {
+            let (i2, count) = try_parse!(i1, le_u32);
+            let mut remainder = i2;
+            let mut chain = Vec::new();
+            for _index in 0..count {
+                let (j1, cert) = length_data!(remainder, le_u32)?;
+                chain.push(cert.to_vec());
+                remainder = j1;
+            }
+            Ok((remainder, ServerCertificate::X509Chain(chain)))
+        }
+        _ => Err(nom::Err::Error(error_position!(
+            input,
+            ErrorKind::Custom(RDP_UNKNOWN_CERTIFICATE_VERSION)
+        ))),
+    }
+}
+
+/// rdp-spec, section 2.2.1.4.3.1.1 and 2.2.1.4.3.1.1.1
+fn parse_proprietary_certificate(
+    input: &[u8],
+) -> IResult<&[u8], ProprietaryCertificate> {
+    let (i1, _sig_alg_id) = try_parse!(input, le_u32);
+    let (i2, _key_alg_id) = try_parse!(i1, le_u32);
+    let (i3, _blob_type) = verify!(i2, le_u16, |x| x == 0x6)?; // BB_RSA_KEY_BLOB
+    let (i4, blob) = length_data!(i3, le_u16)?;
+    let (j1, _magic) = verify!(blob, le_u32, |x| x == 0x3141_5352)?; // "RSA1"
+    let (j2, key_len) = try_parse!(j1, le_u32);
+    let (j3, key_bitlen) = try_parse!(j2, le_u32);
+    let (j4, _data_len) = try_parse!(j3, le_u32);
+    let (j5, public_exponent) = try_parse!(j4, le_u32);
+    let (_j6, modulus) = take!(j5, key_len)?;
+    // signature blob not parsed
+
+    return Ok((
+        i4,
+        ProprietaryCertificate {
+            key_bitlen,
+            public_exponent,
+            modulus: modulus.to_vec(),
+        },
+    ));
+}
+
+/// rdp-spec, section 2.2.1.4.4
+fn parse_sc_net(input: &[u8]) -> IResult<&[u8], ScNet> {
     do_parse! {
         input,
-        _ber_type: verify!(
-            le_u8,
-            // BER: 0b01=application, 0b1=non-primitive, 0b11111
-            |x| x == 0x7f)
-        >> _t125_type: verify!(
-            le_u8,
-            |x| x == T125Type::T125TypeMcsConnectResponse as u8)
-        >> (McsConnectResponse {})
+        mcs_channel_id: le_u16
+        >> count: le_u16
+        >> channel_ids: count!(le_u16, count as usize)
+        >> (ScNet { mcs_channel_id, channel_ids })
+    }
+}
+
+/// t.125-spec, section 7, part 10
+/// The PER encoded choice is in the upper 6 bits of the first byte.
+fn parse_mcs_domain_pdu(input: &[u8]) -> IResult<&[u8], X223DataChild> {
+    let (i1, choice) = try_parse!(input, be_u8);
+    // bit 1 signals the presence of an optional field
+    let optional = choice & 0x2 != 0;
+    let opt: Option<McsDomainPduType> = num::FromPrimitive::from_u8(choice >> 2);
+    match opt {
+        Some(McsDomainPduType::ErectDomainRequest) => {
+            Ok((&[], X223DataChild::McsErectDomainRequest))
+        }
+        Some(McsDomainPduType::AttachUserRequest) => {
+            Ok((&[], X223DataChild::McsAttachUserRequest))
+        }
+        Some(McsDomainPduType::AttachUserConfirm) => {
+            let (i2, result) = try_parse!(i1, be_u8);
+            let (i3, initiator) = if optional {
+                let (j1, initiator) = parse_mcs_user_id(i2)?;
+                (j1, Some(initiator))
+            } else {
+                (i2, None)
+            };
+            Ok((
+                i3,
+                X223DataChild::McsAttachUserConfirm(McsAttachUserConfirm {
+                    result,
+                    initiator,
+                }),
+            ))
+        }
+        Some(McsDomainPduType::ChannelJoinRequest) => {
+            let (i2, initiator) = parse_mcs_user_id(i1)?;
+            let (i3, channel_id) = try_parse!(i2, be_u16);
+            Ok((
+                i3,
+                X223DataChild::McsChannelJoinRequest(McsChannelJoinRequest {
+                    initiator,
+                    channel_id,
+                }),
+            ))
+        }
+        Some(McsDomainPduType::ChannelJoinConfirm) => {
+            let (i2, result) = try_parse!(i1, be_u8);
+            let (i3, initiator) = parse_mcs_user_id(i2)?;
+            let (i4, requested) = try_parse!(i3, be_u16);
+            let (i5, channel_id) = if optional {
+                let (j1, channel_id) = try_parse!(i4, be_u16);
+                (j1, Some(channel_id))
+            } else {
+                (i4, None)
+            };
+            Ok((
+                i5,
+                X223DataChild::McsChannelJoinConfirm(McsChannelJoinConfirm {
+                    result,
+                    initiator,
+                    requested,
+                    channel_id,
+                }),
+            ))
+        }
+        Some(McsDomainPduType::SendDataRequest) => {
+            let (i2, initiator) = parse_mcs_user_id(i1)?;
+            let (i3, channel_id) = try_parse!(i2, be_u16);
+            let (i4, _priority_segmentation) = try_parse!(i3, be_u8);
+            let (i5, data) = length_data!(i4, parse_per_length_determinant)?;
+            let child = match parse_send_data_request_child(data) {
+                Ok((_, child)) => child,
+                Err(_) => SendDataRequestChild::Raw(data.to_vec()),
+            };
+            Ok((
+                i5,
+                X223DataChild::McsSendDataRequest(McsSendDataRequest {
+                    initiator,
+                    channel_id,
+                    child,
+                }),
+            ))
+        }
+        None => Err(nom::Err::Error(error_position!(
+            input,
+            ErrorKind::Custom(RDP_NOT_MCS_DOMAIN_PDU)
+        ))),
+    }
+}
+
+/// t.125-spec, section 7, part 1 (UserId)
+fn parse_mcs_user_id(input: &[u8]) -> IResult<&[u8], u16> {
+    map!(input, be_u16, |x: u16| x.wrapping_add(MCS_USER_ID_BASE))
+}
+
+/// rdp-spec, section 2.2.1.10 and 2.2.1.11
+/// Both PDUs start with a basic security header.
+fn parse_send_data_request_child(
+    input: &[u8],
+) -> IResult<&[u8], SendDataRequestChild> {
+    let (i1, flags) = map!(input, le_u16, SecurityFlags::from_bits_truncate)?;
+    let (i2, _flags_hi) = try_parse!(i1, le_u16);
+
+    if flags.contains(SecurityFlags::SEC_EXCHANGE_PKT) {
+        let (i3, length) = try_parse!(i2, le_u32);
+        let (i4, _random) = take!(i3, length)?;
+        return Ok((
+            i4,
+            SendDataRequestChild::SecurityExchange(SecurityExchange {
+                encrypted_client_random_len: length,
+            }),
+        ));
+    }
+
+    if flags.contains(SecurityFlags::SEC_INFO_PKT) {
+        if flags.contains(SecurityFlags::SEC_ENCRYPT) {
+            return Ok((&[], SendDataRequestChild::EncryptedClientInfo));
+        }
+        let (i3, info) = parse_client_info(i2)?;
+        return Ok((i3, SendDataRequestChild::ClientInfo(info)));
     }
+
+    Err(nom::Err::Error(error_position!(
+        input,
+        ErrorKind::Custom(RDP_UNKNOWN_SECURITY_PDU)
+    )))
+}
+
+/// rdp-spec, section 2.2.1.11.1.1
+fn parse_client_info(input: &[u8]) -> IResult<&[u8], ClientInfo> {
+    let (i1, code_page) = try_parse!(input, le_u32);
+    let (i2, flags) = map!(i1, le_u32, ClientInfoFlags::from_bits_truncate)?;
+    let (i3, cb_domain) = try_parse!(i2, le_u16);
+    let (i4, cb_user_name) = try_parse!(i3, le_u16);
+    let (i5, cb_password) = try_parse!(i4, le_u16);
+    let (i6, cb_alternate_shell) = try_parse!(i5, le_u16);
+    let (i7, cb_working_dir) = try_parse!(i6, le_u16);
+
+    // the sizes do not include the mandatory null terminator
+    let (null_sz, to_string): (
+        usize,
+        fn(&[u8]) -> Result<String, Box<dyn std::error::Error>>,
+    ) = if flags.contains(ClientInfoFlags::INFO_UNICODE) {
+        (2, le_slice_to_string)
+    } else {
+        (1, utf7_slice_to_string)
+    };
+    let (i8, domain) =
+        map_res!(i7, take!(cb_domain as usize + null_sz), to_string)?;
+    let (i9, user_name) =
+        map_res!(i8, take!(cb_user_name as usize + null_sz), to_string)?;
+    let (i10, _password) = take!(i9, cb_password as usize + null_sz)?;
+    let (i11, alternate_shell) =
+        map_res!(i10, take!(cb_alternate_shell as usize + null_sz), to_string)?;
+    let (i12, working_dir) =
+        map_res!(i11, take!(cb_working_dir as usize + null_sz), to_string)?;
+    // extended info not parsed
+
+    return Ok((
+        i12,
+        ClientInfo {
+            code_page,
+            flags,
+            domain,
+            user_name,
+            password_present: cb_password > 0,
+            alternate_shell,
+            working_dir,
+        },
+    ));
 }
 
 #[cfg(test)]
@@ -1247,6 +1913,7 @@ mod tests_core_49350 {
             sas_sequence: Some(SasSequence::RnsUdSasDel),
             keyboard_layout: 0x409,
             client_build: windows::OperatingSystem {
+                number: 6001,
                 build: windows::Build::Vista_6001,
                 suffix: windows::Suffix::Sp1,
             },
@@ -1289,10 +1956,29 @@ mod tests_core_49350 {
             data: BYTES[0x16c..0x16c + 0x8].to_vec(),
         }));
         let mut channels = Vec::new();
-        channels.push(String::from("rdpdr"));
-        channels.push(String::from("rdpsnd"));
-        channels.push(String::from("drdynvc"));
-        channels.push(String::from("cliprdr"));
+        channels.push(ChannelDef {
+            name: String::from("rdpdr"),
+            options: ChannelOptions::CHANNEL_OPTION_INITIALIZED
+                | ChannelOptions::CHANNEL_OPTION_COMPRESS_RDP,
+        });
+        channels.push(ChannelDef {
+            name: String::from("rdpsnd"),
+            options: ChannelOptions::CHANNEL_OPTION_INITIALIZED
+                | ChannelOptions::CHANNEL_OPTION_ENCRYPT_RDP,
+        });
+        channels.push(ChannelDef {
+            name: String::from("drdynvc"),
+            options: ChannelOptions::CHANNEL_OPTION_INITIALIZED
+                | ChannelOptions::CHANNEL_OPTION_ENCRYPT_RDP
+                | ChannelOptions::CHANNEL_OPTION_COMPRESS_RDP,
+        });
+        channels.push(ChannelDef {
+            name: String::from("cliprdr"),
+            options: ChannelOptions::CHANNEL_OPTION_INITIALIZED
+                | ChannelOptions::CHANNEL_OPTION_ENCRYPT_RDP
+                | ChannelOptions::CHANNEL_OPTION_COMPRESS_RDP
+                | ChannelOptions::CHANNEL_OPTION_SHOW_PROTOCOL,
+        });
         children.push(McsConnectRequestChild::CsNet(CsNet { channels }));
         let t123_tpkt: T123Tpkt = T123Tpkt {
             child: T123TpktChild::Data(X223Data {
@@ -1325,7 +2011,9 @@ mod tests_x223_response_49350 {
                 T123Tpkt {
                     child: T123TpktChild::Data(X223Data {
                         child: X223DataChild::McsConnectResponse(
-                            McsConnectResponse {}
+                            McsConnectResponse {
+                                children: Vec::new()
+                            }
                         ),
                     })
                 }
@@ -1542,3 +2230,121 @@ mod tests_core_incomplete_49350 {
         )
     }
 }
+
+#[cfg(test)]
+mod tests_connect_response_data {
+    use rdp::parser::*;
+
+    static BYTES: [u8; 51] = [
+        0x03, 0x00, 0x00, 0x33, 0x02, 0xf0, 0x80, 0x7f, 0x66, 0x00, 0x4d, 0x63,
+        0x44, 0x6e, 0x24, 0x01, 0x0c, 0x0c, 0x00, 0x04, 0x00, 0x08, 0x00, 0x03,
+        0x00, 0x00, 0x00, 0x02, 0x0c, 0x0c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x03, 0x0c, 0x0c, 0x00, 0xeb, 0x03, 0x02, 0x00, 0xec,
+        0x03, 0xed, 0x03,
+    ];
+
+    #[test]
+    fn test_mcs_connect_response_data_blocks() {
+        let mut children = Vec::new();
+        children.push(McsConnectResponseChild::ScCore(ScCoreData {
+            version: Some(RdpClientVersion::V5_V8_1),
+            client_requested_protocols: Some(
+                ProtocolFlags::PROTOCOL_SSL | ProtocolFlags::PROTOCOL_HYBRID,
+            ),
+            early_capability_flags: None,
+        }));
+        children.push(McsConnectResponseChild::ScSecurity(ScSecurityData {
+            encryption_method: Some(EncryptionMethod::EncryptionMethodNone),
+            encryption_level: Some(EncryptionLevel::EncryptionLevelNone),
+            server_random: Vec::new(),
+            server_certificate: None,
+        }));
+        children.push(McsConnectResponseChild::ScNet(ScNet {
+            mcs_channel_id: 1003,
+            channel_ids: vec![1004, 1005],
+        }));
+        let t123_tpkt: T123Tpkt = T123Tpkt {
+            child: T123TpktChild::Data(X223Data {
+                child: X223DataChild::McsConnectResponse(McsConnectResponse {
+                    children,
+                }),
+            }),
+        };
+        assert_eq!(Ok((&[][..], t123_tpkt)), parse_t123_tpkt(&BYTES[..]));
+    }
+
+    #[test]
+    fn test_sc_net() {
+        let bytes = &[0xeb, 0x03, 0x02, 0x00, 0xec, 0x03, 0xed, 0x03];
+        assert_eq!(
+            Ok((
+                &[][..],
+                ScNet {
+                    mcs_channel_id: 1003,
+                    channel_ids: vec![1004, 1005],
+                }
+            )),
+            parse_sc_net(bytes)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests_mcs_domain_pdu {
+    use rdp::parser::*;
+
+    #[test]
+    fn test_channel_join_request() {
+        let bytes = &[0x38, 0x00, 0x06, 0x03, 0xeb];
+        assert_eq!(
+            Ok((
+                &[][..],
+                X223DataChild::McsChannelJoinRequest(McsChannelJoinRequest {
+                    initiator: 1007,
+                    channel_id: 1003,
+                })
+            )),
+            parse_mcs_domain_pdu(bytes)
+        );
+    }
+
+    #[test]
+    fn test_channel_join_confirm() {
+        let bytes = &[0x3e, 0x00, 0x00, 0x06, 0x03, 0xec, 0x03, 0xec];
+        assert_eq!(
+            Ok((
+                &[][..],
+                X223DataChild::McsChannelJoinConfirm(McsChannelJoinConfirm {
+                    result: 0,
+                    initiator: 1007,
+                    requested: 1004,
+                    channel_id: Some(1004),
+                })
+            )),
+            parse_mcs_domain_pdu(bytes)
+        );
+    }
+
+    #[test]
+    fn test_security_exchange() {
+        let bytes = &[
+            0x64, 0x00, 0x06, 0x03, 0xeb, 0x70, 0x0c, 0x01, 0x00, 0x00, 0x00,
+            0x04, 0x00, 0x00, 0x00, 0xaa, 0xbb, 0xcc, 0xdd,
+        ];
+        assert_eq!(
+            Ok((
+                &[][..],
+                X223DataChild::McsSendDataRequest(McsSendDataRequest {
+                    initiator: 1007,
+                    channel_id: 1003,
+                    child: SendDataRequestChild::SecurityExchange(
+                        SecurityExchange {
+                            encrypted_client_random_len: 4,
+                        }
+                    ),
+                })
+            )),
+            parse_mcs_domain_pdu(bytes)
+        );
+    }
+}
@@ -19,6 +19,7 @@
 
 //! RDP application layer
 
+use applayer::AppLayerEvent;
 use core::{
     self, AppProto, DetectEngineState, Flow, ALPROTO_UNKNOWN, IPPROTO_TCP,
 };
@@ -34,6 +35,19 @@ use tls_parser::{
 
 static mut ALPROTO_RDP: AppProto = ALPROTO_UNKNOWN;
 
+//
+// events
+//
+
+#[repr(u32)]
+#[derive(AppLayerEvent)]
+pub enum RdpEvent {
+    /// server assigned an id to the clipboard channel (cliprdr)
+    ClipboardRedirection = 0,
+    /// server assigned an id to the device redirection channel (rdpdr)
+    DriveRedirection,
+}
+
 //
 // transactions
 //
@@ -49,13 +63,34 @@ pub enum RdpTransactionItem {
     X224ConnectionConfirm(X224ConnectionConfirm),
     McsConnectRequest(McsConnectRequest),
     McsConnectResponse(McsConnectResponse),
+    McsErectDomainRequest,
+    McsAttachUserRequest,
+    McsAttachUserConfirm(McsAttachUserConfirm),
+    McsChannelJoinRequest(McsChannelJoinRequest),
+    McsChannelJoinConfirm(McsChannelJoinConfirm),
+    SecurityExchange(SecurityExchange),
+    ClientInfo(ClientInfo),
     TlsCertificateChain(Vec<CertificateBlob>),
 }
 
+/// a static virtual channel requested by the client,
+/// with the mcs channel id the server assigned to it
+#[derive(Debug, PartialEq)]
+pub struct RdpChannel {
+    pub name: String,
+    pub options: ChannelOptions,
+    pub kind: ChannelKind,
+    /// 0 when the server did not assign an id
+    pub channel_id: u16,
+}
+
 #[derive(Debug, PartialEq)]
 pub struct RdpTransaction {
     pub id: u64,
     pub item: RdpTransactionItem,
+    /// channel inventory, only set on the mcs connect response
+    pub channels: Vec<RdpChannel>,
+    events: *mut core::AppLayerDecoderEvents,
     // managed by macros `export_tx_get_detect_state!` and `export_tx_set_detect_state!`
     de_state: Option<*mut DetectEngineState>,
 }
@@ -65,11 +100,23 @@ impl RdpTransaction {
         Self {
             id,
             item,
+            channels: Vec::new(),
+            events: std::ptr::null_mut(),
             de_state: None,
         }
     }
 
+    fn set_event(&mut self, event: RdpEvent) {
+        core::sc_app_layer_decoder_events_set_event_raw(
+            &mut self.events,
+            event as u8,
+        );
+    }
+
     fn free(&mut self) {
+        if self.events != std::ptr::null_mut() {
+            core::sc_app_layer_decoder_events_free_events(&mut self.events);
+        }
         if let Some(de_state) = self.de_state {
             core::sc_detect_engine_state_free(de_state);
         }
@@ -136,6 +183,11 @@ pub struct RdpState {
     transactions: Vec<RdpTransaction>,
     tls_parsing: bool,
     bypass_parsing: bool,
+    // set once the mcs connect response is seen, what follows is either
+    // standard rdp security or already encrypted data
+    mcs_connected: bool,
+    // static virtual channels from the mcs connect request
+    channels: Vec<ChannelDef>,
 }
 
 impl RdpState {
@@ -147,6 +199,8 @@ impl RdpState {
             transactions: Vec::new(),
             tls_parsing: false,
             bypass_parsing: false,
+            mcs_connected: false,
+            channels: Vec::new(),
         }
     }
 
@@ -182,6 +236,46 @@ impl RdpState {
         return tx;
     }
 
+    /// pair the requested channels with the ids assigned by the server,
+    /// in order, cf. rdp-spec section 2.2.1.4.4
+    fn assign_channels(&self, tx: &mut RdpTransaction) {
+        let mut channel_ids: &[u16] = &[];
+        if let RdpTransactionItem::McsConnectResponse(ref mcs) = tx.item {
+            for child in &mcs.children {
+                if let McsConnectResponseChild::ScNet(ref net) = child {
+                    channel_ids = &net.channel_ids[..];
+                }
+            }
+        }
+        let mut channels = Vec::new();
+        let mut events = Vec::new();
+        for (index, def) in self.channels.iter().enumerate() {
+            let channel_id = channel_ids.get(index).cloned().unwrap_or(0);
+            let kind = def.kind();
+            if channel_id != 0 {
+                match kind {
+                    ChannelKind::Clipboard => {
+                        events.push(RdpEvent::ClipboardRedirection)
+                    }
+                    ChannelKind::DeviceRedirection => {
+                        events.push(RdpEvent::DriveRedirection)
+                    }
+                    _ => (),
+                }
+            }
+            channels.push(RdpChannel {
+                name: def.name.clone(),
+                options: def.options,
+                kind,
+                channel_id,
+            });
+        }
+        tx.channels = channels;
+        for event in events {
+            tx.set_event(event);
+        }
+    }
+
     /// parse buffer captures from client to server
     fn parse_ts(&mut self, input: &[u8]) -> bool {
         // no need to process input buffer
@@ -237,10 +331,54 @@ impl RdpState {
                             T123TpktChild::Data(x223) => {
                                 match x223.child {
                                     X223DataChild::McsConnectRequest(mcs) => {
+                                        for child in &mcs.children {
+                                            if let McsConnectRequestChild::CsNet(ref net) = child {
+                                                self.channels = net.channels.clone();
+                                            }
+                                        }
                                         let tx =
                                             self.new_tx(RdpTransactionItem::McsConnectRequest(mcs));
                                         self.transactions.push(tx);
                                     }
+                                    X223DataChild::McsErectDomainRequest => {
+                                        let tx =
+                                            self.new_tx(RdpTransactionItem::McsErectDomainRequest);
+                                        self.transactions.push(tx);
+                                    }
+                                    X223DataChild::McsAttachUserRequest => {
+                                        let tx =
+                                            self.new_tx(RdpTransactionItem::McsAttachUserRequest);
+                                        self.transactions.push(tx);
+                                    }
+                                    X223DataChild::McsChannelJoinRequest(join) => {
+                                        let tx =
+                                            self.new_tx(RdpTransactionItem::McsChannelJoinRequest(join));
+                                        self.transactions.push(tx);
+                                    }
+                                    X223DataChild::McsSendDataRequest(req) => {
+                                        match req.child {
+                                            SendDataRequestChild::SecurityExchange(exchange) => {
+                                                let tx = self.new_tx(
+                                                    RdpTransactionItem::SecurityExchange(exchange),
+                                                );
+                                                self.transactions.push(tx);
+                                            }
+                                            // the client info is the last message of interest
+                                            SendDataRequestChild::ClientInfo(info) => {
+                                                let tx = self.new_tx(
+                                                    RdpTransactionItem::ClientInfo(info),
+                                                );
+                                                self.transactions.push(tx);
+                                                self.bypass_parsing = true;
+                                                return true;
+                                            }
+                                            SendDataRequestChild::EncryptedClientInfo => {
+                                                self.bypass_parsing = true;
+                                                return true;
+                                            }
+                                            SendDataRequestChild::Raw(_) => (),
+                                        }
+                                    }
                                     // unknown message in X.223, skip
                                     _ => (),
                                 }
@@ -261,6 +399,10 @@ impl RdpState {
                         if probe_tls_handshake(available) {
                             self.tls_parsing = true;
                             return self.parse_ts(available);
+                        } else if self.mcs_connected {
+                            // e.g. fast-path input, nothing more to log
+                            self.bypass_parsing = true;
+                            return true;
                         } else {
                             return false;
                         }
@@ -347,11 +489,21 @@ impl RdpState {
                             T123TpktChild::Data(x223) => {
                                 match x223.child {
                                     X223DataChild::McsConnectResponse(mcs) => {
-                                        let tx = self
+                                        let mut tx = self
                                             .new_tx(RdpTransactionItem::McsConnectResponse(mcs));
+                                        self.assign_channels(&mut tx);
+                                        self.transactions.push(tx);
+                                        self.mcs_connected = true;
+                                    }
+                                    X223DataChild::McsAttachUserConfirm(confirm) => {
+                                        let tx = self
+                                            .new_tx(RdpTransactionItem::McsAttachUserConfirm(confirm));
+                                        self.transactions.push(tx);
+                                    }
+                                    X223DataChild::McsChannelJoinConfirm(confirm) => {
+                                        let tx = self
+                                            .new_tx(RdpTransactionItem::McsChannelJoinConfirm(confirm));
                                         self.transactions.push(tx);
-                                        self.bypass_parsing = true;
-                                        return true;
                                     }
 
                                     // unknown message in X.223, skip
@@ -373,6 +525,10 @@ impl RdpState {
                         if probe_tls_handshake(available) {
                             self.tls_parsing = true;
                             return self.parse_tc(available);
+                        } else if self.mcs_connected {
+                            // e.g. fast-path output, nothing more to log
+                            self.bypass_parsing = true;
+                            return true;
                         } else {
                             return false;
                         }
@@ -404,6 +560,18 @@ pub extern "C" fn rs_rdp_state_tx_free(
     state.free_tx(tx_id);
 }
 
+//
+// events
+//
+
+#[no_mangle]
+pub extern "C" fn rs_rdp_state_get_events(
+    tx: *mut std::os::raw::c_void,
+) -> *mut core::AppLayerDecoderEvents {
+    let tx = cast_pointer!(tx, RdpTransaction);
+    return tx.events;
+}
+
 //
 // detection state
 //
@@ -522,9 +690,9 @@ pub unsafe extern "C" fn rs_rdp_register_parser() {
         set_tx_logged: None,
         get_de_state: rs_rdp_tx_get_detect_state,
         set_de_state: rs_rdp_tx_set_detect_state,
-        get_events: None,
-        get_eventinfo: None,
-        get_eventinfo_byid: None,
+        get_events: Some(rs_rdp_state_get_events),
+        get_eventinfo: Some(RdpEvent::get_event_info),
+        get_eventinfo_byid: Some(RdpEvent::get_event_info_by_id),
         localstorage_new: None,
         localstorage_free: None,
         get_tx_mpm_id: None,
@@ -559,6 +727,7 @@ pub unsafe extern "C" fn rs_rdp_register_parser() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rdp::detect::*;
     use rdp::parser::{RdpCookie, X224ConnectionRequest};
 
     #[test]
@@ -614,6 +783,30 @@ mod tests {
         assert_eq!(item, state.transactions[0].item);
     }
 
+    #[test]
+    fn test_tx_get_cookie() {
+        let buf: &[u8] = &[
+            0x03, 0x00, 0x00, 0x25, 0x20, 0xe0, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x43, 0x6f, 0x6f, 0x6b, 0x69, 0x65, 0x3a, 0x20, 0x6d, 0x73, 0x74,
+            0x73, 0x68, 0x61, 0x73, 0x68, 0x3d, 0x75, 0x73, 0x65, 0x72, 0x31,
+            0x32, 0x33, 0x0d, 0x0a,
+        ];
+        let mut state = RdpState::new();
+        assert_eq!(true, state.parse_ts(&buf));
+        let tx = &mut state.transactions[0];
+        let mut buffer: *const u8 = std::ptr::null();
+        let mut buffer_len: u32 = 0;
+        let mut value: u32 = 0;
+        unsafe {
+            assert_eq!(1, rs_rdp_tx_get_cookie(tx, &mut buffer, &mut buffer_len));
+            let cookie = std::slice::from_raw_parts(buffer, buffer_len as usize);
+            assert_eq!(b"user123", cookie);
+            // no negotiation request in this connection request
+            assert_eq!(0, rs_rdp_tx_get_requested_protocols(tx, &mut value));
+            assert_eq!(0, rs_rdp_tx_get_client_build(tx, &mut value));
+        }
+    }
+
     #[test]
     fn test_parse_ts_other() {
         let buf: &[u8] = &[0x03, 0x00, 0x00, 0x01, 0x00];
@@ -631,8 +824,91 @@ mod tests {
         assert_eq!(true, state.parse_tc(&buf_2));
         assert_eq!(1, state.transactions.len());
         let item =
-            RdpTransactionItem::McsConnectResponse(McsConnectResponse {});
+            RdpTransactionItem::McsConnectResponse(McsConnectResponse {
+                children: Vec::new(),
+            });
         assert_eq!(item, state.transactions[0].item);
+        assert_eq!(true, state.mcs_connected);
+        assert_eq!(false, state.bypass_parsing);
+    }
+
+    #[test]
+    fn test_parse_ts_mcs_domain() {
+        // erect domain request and attach user request
+        let buf: &[u8] = &[
+            0x03, 0x00, 0x00, 0x0c, 0x02, 0xf0, 0x80, 0x04, 0x01, 0x00, 0x01,
+            0x00, 0x03, 0x00, 0x00, 0x08, 0x02, 0xf0, 0x80, 0x28,
+        ];
+        let mut state = RdpState::new();
+        assert_eq!(true, state.parse_ts(&buf));
+        assert_eq!(2, state.transactions.len());
+        assert_eq!(
+            RdpTransactionItem::McsErectDomainRequest,
+            state.transactions[0].item
+        );
+        assert_eq!(
+            RdpTransactionItem::McsAttachUserRequest,
+            state.transactions[1].item
+        );
+        assert_eq!(false, state.bypass_parsing);
+    }
+
+    #[test]
+    fn test_parse_ts_client_info() {
+        // send data request with an unencrypted client info pdu
+        let buf: &[u8] = &[
+            0x03, 0x00, 0x00, 0x38, 0x02, 0xf0, 0x80, 0x64, 0x00, 0x06, 0x03,
+            0xeb, 0x70, 0x2a, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x1b, 0x00, 0x00, 0x00, 0x04, 0x00, 0x06, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x41, 0x00, 0x42, 0x00, 0x00, 0x00, 0x62, 0x00,
+            0x6f, 0x00, 0x62, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00,
+        ];
+        let mut state = RdpState::new();
+        assert_eq!(true, state.parse_ts(&buf));
+        assert_eq!(1, state.transactions.len());
+        match state.transactions[0].item {
+            RdpTransactionItem::ClientInfo(ref info) => {
+                assert_eq!("AB", info.domain);
+                assert_eq!("bob", info.user_name);
+                assert!(info.flags.contains(ClientInfoFlags::INFO_AUTOLOGON));
+            }
+            _ => panic!("expected client info"),
+        }
+        assert_eq!(true, state.bypass_parsing);
+    }
+
+    #[test]
+    fn test_assign_channels() {
+        let mut state = RdpState::new();
+        state.channels.push(ChannelDef {
+            name: String::from("rdpdr"),
+            options: ChannelOptions::CHANNEL_OPTION_INITIALIZED,
+        });
+        state.channels.push(ChannelDef {
+            name: String::from("CLIPRDR"),
+            options: ChannelOptions::CHANNEL_OPTION_INITIALIZED,
+        });
+        state.channels.push(ChannelDef {
+            name: String::from("rail"),
+            options: ChannelOptions::CHANNEL_OPTION_INITIALIZED,
+        });
+        let mut children = Vec::new();
+        children.push(McsConnectResponseChild::ScNet(ScNet {
+            mcs_channel_id: 1003,
+            channel_ids: vec![1004, 0],
+        }));
+        let mut tx = state.new_tx(RdpTransactionItem::McsConnectResponse(
+            McsConnectResponse { children },
+        ));
+        state.assign_channels(&mut tx);
+        assert_eq!(3, tx.channels.len());
+        assert_eq!(ChannelKind::DeviceRedirection, tx.channels[0].kind);
+        assert_eq!(1004, tx.channels[0].channel_id);
+        assert_eq!(ChannelKind::Clipboard, tx.channels[1].kind);
+        assert_eq!(0, tx.channels[1].channel_id);
+        assert_eq!(ChannelKind::RemoteApp, tx.channels[2].kind);
+        assert_eq!(0, tx.channels[2].channel_id);
     }
 
     #[test]
@@ -709,4 +985,17 @@ mod tests {
         assert_eq!(2, state.transactions[1].id);
         assert_eq!(None, state.get_tx(1));
     }
+
+    #[test]
+    fn test_rdp_event_info() {
+        let mut event_id: std::os::raw::c_int = 0;
+        let mut event_type: crate::core::AppLayerEventType = 0;
+        let name = std::ffi::CString::new("clipboard_redirection").unwrap();
+        assert_eq!(RdpEvent::get_event_info(name.as_ptr(), &mut event_id, &mut event_type), 0);
+        assert_eq!(event_id, RdpEvent::ClipboardRedirection as std::os::raw::c_int);
+
+        // An unknown name fails, so the rule using it fails to load.
+        let name = std::ffi::CString::new("no_such_event").unwrap();
+        assert_eq!(RdpEvent::get_event_info(name.as_ptr(), &mut event_id, &mut event_type), -1);
+    }
 }
@@ -478,6 +478,8 @@ pub fn lcid_to_string<'a>(lcid: u32, default: &'a str) -> String {
 /// Windows operating system type (build and suffix/pack)
 #[derive(Clone, Debug, PartialEq)]
 pub struct OperatingSystem {
+    /// raw build number, as sent by the client
+    pub number: u32,
     pub build: Build,
     pub suffix: Suffix,
 }
@@ -546,7 +548,11 @@ pub fn build_number_to_os(number: u32) -> OperatingSystem {
         17763 => Suffix::Rs5,
         _ => Suffix::Empty,
     };
-    OperatingSystem { build, suffix }
+    OperatingSystem {
+        number,
+        build,
+        suffix,
+    }
 }
 
 /// convert an OperatingSystem into a string description
@@ -611,6 +617,7 @@ mod tests {
     #[test]
     fn test_build_os_win10() {
         let w10_rs5 = OperatingSystem {
+            number: 17763,
             build: Build::Win10_17763,
             suffix: Suffix::Rs5,
         };
@@ -620,6 +627,7 @@ mod tests {
     #[test]
     fn test_build_os_other() {
         let other = OperatingSystem {
+            number: 1,
             build: Build::Other,
             suffix: Suffix::Empty,
         };
@@ -631,6 +639,7 @@ mod tests {
         let w7_sp1 = "Windows 7 SP1";
         let default = "default-os-name";
         let w7_os = OperatingSystem {
+            number: 7601,
             build: Build::Win7_7601,
             suffix: Suffix::Sp1,
         };
@@ -642,6 +651,7 @@ mod tests {
         let w81 = "Windows 8.1";
         let default = "default-os-name";
         let w81_os = OperatingSystem {
+            number: 9600,
             build: Build::Win81,
             suffix: Suffix::Empty,
         };
@@ -652,6 +662,7 @@ mod tests {
     fn test_os_string_default() {
         let default = "default-os-name";
         let other_os = OperatingSystem {
+            number: 1,
             build: Build::Other,
             suffix: Suffix::Empty,
         };
@@ -41,6 +41,7 @@ pub struct Response {
     pub version: String,
     pub code: String,
     pub reason: String,
+    pub headers: HashMap<String, String>,
 }
 
 #[derive(PartialEq, Debug, Clone)]
@@ -98,7 +99,9 @@ named!(pub sip_parse_response<&[u8], Response>,
         version: parse_version >> char!(' ') >>
         code: parse_code >> char!(' ') >>
         reason: parse_reason >> crlf >>
-        (Response { version: version.into(), code: code.into(), reason: reason.into() })
+        headers: parse_headers >>
+        crlf >>
+        (Response { version: version.into(), code: code.into(), reason: reason.into(), headers: headers})
     )
 );
 
@@ -175,6 +178,27 @@ pub fn parse_headers(mut input: &[u8]) -> IResult<&[u8], HashMap<String, String>
     Ok((input, headers_map))
 }
 
+/// Return the value of a header. Header names are case-insensitive, and
+/// some headers have a compact form (RFC 3261 section 7.3.3).
+pub fn sip_header<'a>(headers: &'a HashMap<String, String>, name: &str,
+                      compact: Option<&str>) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|&(k, _)| {
+            k.eq_ignore_ascii_case(name) || compact.map_or(false, |c| k.eq_ignore_ascii_case(c))
+        })
+        .map(|(_, v)| v.as_str())
+}
+
+/// Return the value of the Content-Length header, 0 if absent, or None if
+/// invalid.
+pub fn sip_content_length(headers: &HashMap<String, String>) -> Option<usize> {
+    match sip_header(headers, "Content-Length", Some("l")) {
+        Some(v) => v.trim().parse::<usize>().ok(),
+        None => Some(0),
+    }
+}
+
 fn parse_header_value(buf: &[u8]) -> IResult<&[u8], &[u8]> {
     let mut end_pos = 0;
     let mut idx = 0;
@@ -246,10 +270,29 @@ mod tests {
                 assert_eq!(resp.version, "SIP/2.0");
                 assert_eq!(resp.code, "401");
                 assert_eq!(resp.reason, "Unauthorized");
+                assert!(resp.headers.is_empty());
             }
             _ => {
                 assert!(false);
             }
         }
     }
+
+    #[test]
+    fn test_parse_response_headers() {
+        let buf: &[u8] = "SIP/2.0 200 OK\r\n\
+                          Call-ID: 1-2@192.168.1.2\r\n\
+                          CSeq: 2 REGISTER\r\n\
+                          l: 5\r\n\
+                          \r\n\
+                          v=0\r\n"
+            .as_bytes();
+
+        let (rem, resp) = sip_parse_response(buf).unwrap();
+        assert_eq!(rem, b"v=0\r\n");
+        assert_eq!(resp.headers["CSeq"], "2 REGISTER");
+        assert_eq!(sip_header(&resp.headers, "call-id", Some("i")), Some("1-2@192.168.1.2"));
+        assert_eq!(sip_content_length(&resp.headers), Some(5));
+        assert!(sip_parse_response(&buf[..40]).is_err());
+    }
 }
@@ -19,43 +19,53 @@
 
 extern crate nom;
 
-use applayer;
+use applayer::{self, AppLayerEvent};
 use conf;
 use core;
-use core::{sc_detect_engine_state_free, AppProto, Flow, ALPROTO_UNKNOWN};
+use core::{sc_detect_engine_state_free, AppProto, Flow, ALPROTO_UNKNOWN, STREAM_TOCLIENT,
+           STREAM_TOSERVER};
 use log::*;
 use parser::*;
 use sip::parser::*;
 use std;
-use std::ffi::{CStr, CString};
+use std::collections::HashMap;
+use std::ffi::CString;
 
 #[repr(u32)]
+#[derive(AppLayerEvent)]
 pub enum SIPEvent {
     IncompleteData = 0,
     InvalidData,
 }
 
-impl SIPEvent {
-    fn from_i32(value: i32) -> Option<SIPEvent> {
-        match value {
-            0 => Some(SIPEvent::IncompleteData),
-            1 => Some(SIPEvent::InvalidData),
-            _ => None,
-        }
-    }
-}
+/// Maximum size of a message over TCP
+const SIP_MAX_MESSAGE_SIZE: usize = 65536;
+
+/// Maximum number of requests waiting for a final response. When reached,
+/// the oldest request is considered unanswered.
+const SIP_MAX_PENDING_REQUESTS: usize = 256;
 
 pub struct SIPState {
     transactions: Vec<SIPTransaction>,
     tx_id: u64,
+    /// Incomplete messages, when running over TCP
+    buffer_ts: Vec<u8>,
+    buffer_tc: Vec<u8>,
 }
 
 pub struct SIPTransaction {
     id: u64,
     pub request: Option<Request>,
+    /// Last response: a final response, or a provisional one while the
+    /// final response has not been seen
     pub response: Option<Response>,
     pub request_line: Option<String>,
     pub response_line: Option<String>,
+    /// Call-ID and CSeq, used to pair the request and its responses
+    pub call_id: Option<String>,
+    pub cseq: Option<String>,
+    /// True if a final response was seen, or if none is expected
+    response_done: bool,
     de_state: Option<*mut core::DetectEngineState>,
     events: *mut core::AppLayerDecoderEvents,
     logged: applayer::LoggerFlags,
@@ -66,6 +76,8 @@ impl SIPState {
         SIPState {
             transactions: Vec::new(),
             tx_id: 0,
+            buffer_ts: Vec::new(),
+            buffer_tc: Vec::new(),
         }
     }
 
@@ -100,15 +112,91 @@ impl SIPState {
         }
     }
 
-    fn parse_request(&mut self, input: &[u8]) -> bool {
-        match sip_parse_request(input) {
-            Ok((_, request)) => {
+    /// Find the transaction waiting for a response with this Call-ID and
+    /// CSeq
+    fn find_request(&self, call_id: &Option<String>, cseq: &Option<String>) -> Option<usize> {
+        if call_id.is_none() || cseq.is_none() {
+            return None;
+        }
+        self.transactions.iter().rposition(|tx| {
+            !tx.response_done && tx.request.is_some() && tx.call_id == *call_id &&
+                tx.cseq == *cseq
+        })
+    }
+
+    fn handle_request(&mut self, request: Request, request_line: Option<String>) {
+        let call_id = sip_header(&request.headers, "Call-ID", Some("i")).map(String::from);
+        let cseq = sip_header(&request.headers, "CSeq", None).map(sip_normalize_cseq);
+        // retransmission of a request waiting for its response
+        if self.find_request(&call_id, &cseq).is_some() {
+            return;
+        }
+        let mut tx = self.new_tx();
+        // ACK requests are not answered
+        tx.response_done = request.method == "ACK";
+        tx.request = Some(request);
+        tx.request_line = request_line;
+        tx.call_id = call_id;
+        tx.cseq = cseq;
+        self.transactions.push(tx);
+
+        // requests over UDP may never be answered
+        let mut pending = self.transactions.iter_mut().filter(|tx| !tx.response_done);
+        if let Some(oldest) = pending.next() {
+            if pending.count() >= SIP_MAX_PENDING_REQUESTS {
+                oldest.response_done = true;
+            }
+        }
+    }
+
+    fn handle_response(&mut self, response: Response, response_line: Option<String>) {
+        let call_id = sip_header(&response.headers, "Call-ID", Some("i")).map(String::from);
+        let cseq = sip_header(&response.headers, "CSeq", None).map(sip_normalize_cseq);
+        // 1xx responses are provisional
+        let is_final = response.code.parse::<u16>().map_or(true, |code| code >= 200);
+        match self.find_request(&call_id, &cseq) {
+            Some(idx) => {
+                let tx = &mut self.transactions[idx];
+                tx.response = Some(response);
+                tx.response_line = response_line;
+                tx.response_done = is_final;
+            }
+            None => {
                 let mut tx = self.new_tx();
-                tx.request = Some(request);
-                if let Ok((_, req_line)) = sip_take_line(input) {
-                    tx.request_line = req_line;
-                }
+                tx.response = Some(response);
+                tx.response_line = response_line;
+                tx.call_id = call_id;
+                tx.cseq = cseq;
+                tx.response_done = true;
                 self.transactions.push(tx);
+            }
+        }
+    }
+
+    /// Parse a request or a response. When `stream` is set, the body must
+    /// be complete. Returns the length of the message.
+    fn parse_message<'a>(&mut self, input: &'a [u8], stream: bool) -> Result<usize, nom::Err<&'a [u8]>> {
+        let line = match sip_take_line(input) {
+            Ok((_, line)) => line,
+            Err(_) => None,
+        };
+        if input.starts_with(b"SIP/") {
+            let (rem, response) = sip_parse_response(input)?;
+            let len = message_length(input, rem, &response.headers, stream)?;
+            self.handle_response(response, line);
+            Ok(len)
+        } else {
+            let (rem, request) = sip_parse_request(input)?;
+            let len = message_length(input, rem, &request.headers, stream)?;
+            self.handle_request(request, line);
+            Ok(len)
+        }
+    }
+
+    /// Parse a datagram
+    fn parse_udp(&mut self, input: &[u8]) -> bool {
+        match self.parse_message(input, false) {
+            Ok(_) => {
                 return true;
             }
             Err(nom::Err::Incomplete(_)) => {
@@ -122,26 +210,78 @@ impl SIPState {
         }
     }
 
-    fn parse_response(&mut self, input: &[u8]) -> bool {
-        match sip_parse_response(input) {
-            Ok((_, response)) => {
-                let mut tx = self.new_tx();
-                tx.response = Some(response);
-                if let Ok((_, resp_line)) = sip_take_line(input) {
-                    tx.response_line = resp_line;
-                }
-                self.transactions.push(tx);
-                return true;
+    /// Parse stream data. Messages are delimited by the Content-Length
+    /// header, and may be pipelined.
+    fn parse_tcp(&mut self, input: &[u8], direction: u8) -> bool {
+        let mut v: Vec<u8>;
+        let buffer = if direction == STREAM_TOSERVER {
+            &mut self.buffer_ts
+        } else {
+            &mut self.buffer_tc
+        };
+        let tcp_buffer = if buffer.is_empty() {
+            input
+        } else {
+            v = buffer.split_off(0);
+            v.extend_from_slice(input);
+            v.as_slice()
+        };
+        let mut cur_i = tcp_buffer;
+        // empty lines are used as keep-alives (RFC 5626 section 3.5.1)
+        while cur_i.len() > 0 {
+            if cur_i.starts_with(b"\r\n") {
+                cur_i = &cur_i[2..];
+                continue;
             }
-            Err(nom::Err::Incomplete(_)) => {
-                self.set_event(SIPEvent::IncompleteData);
-                return false;
+            match self.parse_message(cur_i, true) {
+                Ok(len) => {
+                    cur_i = &cur_i[len..];
+                }
+                Err(nom::Err::Incomplete(_)) => {
+                    if cur_i.len() > SIP_MAX_MESSAGE_SIZE {
+                        self.set_event(SIPEvent::InvalidData);
+                        return false;
+                    }
+                    let buffer = if direction == STREAM_TOSERVER {
+                        &mut self.buffer_ts
+                    } else {
+                        &mut self.buffer_tc
+                    };
+                    buffer.extend_from_slice(cur_i);
+                    return true;
+                }
+                Err(_) => {
+                    self.set_event(SIPEvent::InvalidData);
+                    return false;
+                }
             }
-            Err(_) => {
-                self.set_event(SIPEvent::InvalidData);
-                return false;
+        }
+        true
+    }
+}
+
+/// Normalize the whitespace of a CSeq value, like `1 INVITE`
+fn sip_normalize_cseq(cseq: &str) -> String {
+    cseq.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Return the length of a message, from the remaining input after the
+/// headers
+fn message_length<'a>(input: &'a [u8], rem: &'a [u8], headers: &HashMap<String, String>,
+                      stream: bool) -> Result<usize, nom::Err<&'a [u8]>> {
+    let header_len = input.len() - rem.len();
+    if !stream {
+        return Ok(input.len());
+    }
+    match sip_content_length(headers) {
+        Some(len) if len <= SIP_MAX_MESSAGE_SIZE => {
+            if rem.len() < len {
+                Err(nom::Err::Incomplete(nom::Needed::Size(len - rem.len())))
+            } else {
+                Ok(header_len + len)
             }
         }
+        _ => Err(nom::Err::Error(error_position!(rem, nom::ErrorKind::Verify))),
     }
 }
 
@@ -154,6 +294,9 @@ impl SIPTransaction {
             response: None,
             request_line: None,
             response_line: None,
+            call_id: None,
+            cseq: None,
+            response_done: false,
             events: std::ptr::null_mut(),
             logged: applayer::LoggerFlags::new(),
         }
@@ -215,9 +358,13 @@ pub extern "C" fn rs_sip_state_progress_completion_status(_direction: u8) -> std
 
 #[no_mangle]
 pub extern "C" fn rs_sip_tx_get_alstate_progress(
-    _tx: *mut std::os::raw::c_void,
-    _direction: u8,
+    tx: *mut std::os::raw::c_void,
+    direction: u8,
 ) -> std::os::raw::c_int {
+    let tx = cast_pointer!(tx, SIPTransaction);
+    if direction == STREAM_TOCLIENT && !tx.response_done {
+        return 0;
+    }
     1
 }
 
@@ -269,54 +416,6 @@ pub extern "C" fn rs_sip_state_get_events(
     return tx.events;
 }
 
-#[no_mangle]
-pub extern "C" fn rs_sip_state_get_event_info(
-    event_name: *const std::os::raw::c_char,
-    event_id: *mut std::os::raw::c_int,
-    event_type: *mut core::AppLayerEventType,
-) -> std::os::raw::c_int {
-    if event_name == std::ptr::null() {
-        return -1;
-    }
-    let c_event_name: &CStr = unsafe { CStr::from_ptr(event_name) };
-    let event = match c_event_name.to_str() {
-        Ok(s) => {
-            match s {
-                "incomplete_data" => SIPEvent::IncompleteData as i32,
-                "invalid_data" => SIPEvent::InvalidData as i32,
-                _ => -1, // unknown event
-            }
-        }
-        Err(_) => -1, // UTF-8 conversion failed
-    };
-    unsafe {
-        *event_type = core::APP_LAYER_EVENT_TYPE_TRANSACTION;
-        *event_id = event as std::os::raw::c_int;
-    };
-    0
-}
-
-#[no_mangle]
-pub extern "C" fn rs_sip_state_get_event_info_by_id(
-    event_id: std::os::raw::c_int,
-    event_name: *mut *const std::os::raw::c_char,
-    event_type: *mut core::AppLayerEventType,
-) -> i8 {
-    if let Some(e) = SIPEvent::from_i32(event_id as i32) {
-        let estr = match e {
-            SIPEvent::IncompleteData => "incomplete_data\0",
-            SIPEvent::InvalidData => "invalid_data\0",
-        };
-        unsafe {
-            *event_name = estr.as_ptr() as *const std::os::raw::c_char;
-            *event_type = core::APP_LAYER_EVENT_TYPE_TRANSACTION;
-        };
-        0
-    } else {
-        -1
-    }
-}
-
 static mut ALPROTO_SIP: AppProto = ALPROTO_UNKNOWN;
 
 #[no_mangle]
@@ -361,7 +460,7 @@ pub extern "C" fn rs_sip_parse_request(
 ) -> i32 {
     let buf = build_slice!(input, input_len as usize);
     let state = cast_pointer!(state, SIPState);
-    if state.parse_request(buf) {
+    if state.parse_udp(buf) {
         1
     } else {
         -1
@@ -380,7 +479,45 @@ pub extern "C" fn rs_sip_parse_response(
 ) -> i32 {
     let buf = build_slice!(input, input_len as usize);
     let state = cast_pointer!(state, SIPState);
-    if state.parse_response(buf) {
+    if state.parse_udp(buf) {
+        1
+    } else {
+        -1
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn rs_sip_parse_request_tcp(
+    _flow: *const core::Flow,
+    state: *mut std::os::raw::c_void,
+    _pstate: *mut std::os::raw::c_void,
+    input: *const u8,
+    input_len: u32,
+    _data: *const std::os::raw::c_void,
+    _flags: u8,
+) -> i32 {
+    let buf = build_slice!(input, input_len as usize);
+    let state = cast_pointer!(state, SIPState);
+    if state.parse_tcp(buf, STREAM_TOSERVER) {
+        1
+    } else {
+        -1
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn rs_sip_parse_response_tcp(
+    _flow: *const core::Flow,
+    state: *mut std::os::raw::c_void,
+    _pstate: *mut std::os::raw::c_void,
+    input: *const u8,
+    input_len: u32,
+    _data: *const std::os::raw::c_void,
+    _flags: u8,
+) -> i32 {
+    let buf = build_slice!(input, input_len as usize);
+    let state = cast_pointer!(state, SIPState);
+    if state.parse_tcp(buf, STREAM_TOCLIENT) {
         1
     } else {
         -1
@@ -392,7 +529,7 @@ const PARSER_NAME: &'static [u8] = b"sip\0";
 #[no_mangle]
 pub unsafe extern "C" fn rs_sip_register_parser() {
     let default_port = CString::new("5060").unwrap();
-    let parser = RustParser {
+    let mut parser = RustParser {
         name: PARSER_NAME.as_ptr() as *const std::os::raw::c_char,
         default_port: default_port.as_ptr(),
         ipproto: core::IPPROTO_UDP,
@@ -414,8 +551,8 @@ pub unsafe extern "C" fn rs_sip_register_parser() {
         get_de_state: rs_sip_state_get_tx_detect_state,
         set_de_state: rs_sip_state_set_tx_detect_state,
         get_events: Some(rs_sip_state_get_events),
-        get_eventinfo: Some(rs_sip_state_get_event_info),
-        get_eventinfo_byid: Some(rs_sip_state_get_event_info_by_id),
+        get_eventinfo: Some(SIPEvent::get_event_info),
+        get_eventinfo_byid: Some(SIPEvent::get_event_info_by_id),
         localstorage_new: None,
         localstorage_free: None,
         get_tx_mpm_id: None,
@@ -440,4 +577,83 @@ pub unsafe extern "C" fn rs_sip_register_parser() {
     } else {
         SCLogDebug!("Protocol detecter and parser disabled for SIP/UDP.");
     }
+
+    parser.ipproto = core::IPPROTO_TCP;
+    parser.parse_ts = rs_sip_parse_request_tcp;
+    parser.parse_tc = rs_sip_parse_response_tcp;
+    let ip_proto_str = CString::new("tcp").unwrap();
+    if AppLayerProtoDetectConfProtoDetectionEnabled(ip_proto_str.as_ptr(), parser.name) != 0 {
+        let alproto = AppLayerRegisterProtocolDetection(&parser, 1);
+        ALPROTO_SIP = alproto;
+        if AppLayerParserConfParserEnabled(ip_proto_str.as_ptr(), parser.name) != 0 {
+            let _ = AppLayerRegisterParser(&parser, alproto);
+        }
+    } else {
+        SCLogDebug!("Protocol detecter and parser disabled for SIP/TCP.");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INVITE: &[u8] = b"INVITE sip:bob@example.com SIP/2.0\r\n\
+                            Call-ID: a84b4c76e66710@pc33.example.com\r\n\
+                            CSeq: 314159 INVITE\r\n\
+                            Content-Length: 4\r\n\
+                            \r\n\
+                            v=0\n";
+
+    const RESPONSES: &[u8] = b"SIP/2.0 100 Trying\r\n\
+                               Call-ID: a84b4c76e66710@pc33.example.com\r\n\
+                               CSeq: 314159 INVITE\r\n\
+                               Content-Length: 0\r\n\
+                               \r\n\
+                               SIP/2.0 200 OK\r\n\
+                               Call-ID: a84b4c76e66710@pc33.example.com\r\n\
+                               CSeq: 314159  INVITE\r\n\
+                               Content-Length: 4\r\n\
+                               \r\n\
+                               v=0\n";
+
+    #[test]
+    fn test_sip_tcp_pairing() {
+        let mut state = SIPState::new();
+        // request split in the body
+        assert!(state.parse_tcp(&INVITE[..INVITE.len() - 2], STREAM_TOSERVER));
+        assert_eq!(state.transactions.len(), 0);
+        assert!(state.parse_tcp(&INVITE[INVITE.len() - 2..], STREAM_TOSERVER));
+        assert_eq!(state.transactions.len(), 1);
+        assert!(!state.transactions[0].response_done);
+
+        // pipelined provisional and final responses
+        assert!(state.parse_tcp(RESPONSES, STREAM_TOCLIENT));
+        assert_eq!(state.transactions.len(), 1);
+        let tx = &state.transactions[0];
+        assert!(tx.response_done);
+        assert_eq!(tx.cseq, Some("314159 INVITE".to_string()));
+        assert_eq!(tx.response.as_ref().unwrap().code, "200");
+    }
+
+    #[test]
+    fn test_sip_unsolicited_response() {
+        let mut state = SIPState::new();
+        assert!(state.parse_udp(&RESPONSES[..104]));
+        assert_eq!(state.transactions.len(), 1);
+        assert!(state.transactions[0].request.is_none());
+        assert!(state.transactions[0].response_done);
+    }
+
+    #[test]
+    fn test_sip_event_info() {
+        let mut event_id: std::os::raw::c_int = 0;
+        let mut event_type: crate::core::AppLayerEventType = 0;
+        let name = std::ffi::CString::new("invalid_data").unwrap();
+        assert_eq!(SIPEvent::get_event_info(name.as_ptr(), &mut event_id, &mut event_type), 0);
+        assert_eq!(event_id, SIPEvent::InvalidData as std::os::raw::c_int);
+
+        // An unknown name fails, so the rule using it fails to load.
+        let name = std::ffi::CString::new("no_such_event").unwrap();
+        assert_eq!(SIPEvent::get_event_info(name.as_ptr(), &mut event_id, &mut event_type), -1);
+    }
 }
@@ -28,7 +28,6 @@
 use std;
 use std::mem::transmute;
 use std::str;
-use std::ffi::CStr;
 
 use std::collections::HashMap;
 
@@ -37,7 +36,7 @@ use nom;
 use crate::core::*;
 use crate::log::*;
 use crate::applayer;
-use crate::applayer::LoggerFlags;
+use crate::applayer::{AppLayerEvent, LoggerFlags};
 
 use crate::smb::nbss_records::*;
 use crate::smb::smb1_records::*;
@@ -2117,24 +2116,7 @@ pub extern "C" fn rs_smb_state_get_event_info_by_id(event_id: std::os::raw::c_in
                                               event_type: *mut AppLayerEventType)
                                               -> i8
 {
-    if let Some(e) = SMBEvent::from_i32(event_id as i32) {
-        let estr = match e {
-            SMBEvent::InternalError => { "internal_error\0" },
-            SMBEvent::MalformedData => { "malformed_data\0" },
-            SMBEvent::RecordOverflow => { "record_overflow\0" },
-            SMBEvent::MalformedNtlmsspRequest => { "malformed_ntlmssp_request\0" },
-            SMBEvent::MalformedNtlmsspResponse => { "malformed_ntlmssp_response\0" },
-            SMBEvent::DuplicateNegotiate => { "duplicate_negotiate\0" },
-            SMBEvent::NegotiateMalformedDialects => { "netogiate_malformed_dialects\0" },
-        };
-        unsafe{
-            *event_name = estr.as_ptr() as *const std::os::raw::c_char;
-            *event_type = APP_LAYER_EVENT_TYPE_TRANSACTION;
-        };
-        0
-    } else {
-        -1
-    }
+    SMBEvent::get_event_info_by_id(event_id, event_name, event_type)
 }
 
 #[no_mangle]
@@ -2143,22 +2125,5 @@ pub extern "C" fn rs_smb_state_get_event_info(event_name: *const std::os::raw::c
                                               event_type: *mut AppLayerEventType)
                                               -> i8
 {
-    if event_name == std::ptr::null() {
-        return -1;
-    }
-    let c_event_name: &CStr = unsafe { CStr::from_ptr(event_name) };
-    let event = match c_event_name.to_str() {
-        Ok(s) => {
-            smb_str_to_event(s)
-        },
-        Err(_) => -1, // UTF-8 conversion failed
-    };
-    unsafe {
-        *event_type = APP_LAYER_EVENT_TYPE_TRANSACTION;
-        *event_id = event as std::os::raw::c_int;
-    };
-    if event == -1 {
-        return -1;
-    }
-    0
+    SMBEvent::get_event_info(event_name, event_id, event_type) as i8
 }
@@ -17,7 +17,10 @@
 
 // written by Pierre Chifflier  <chifflier@wzdftpd.net>
 
+use crate::snmp::mib::{oid_arcs,parse_oid_arcs};
 use crate::snmp::snmp::SNMPTransaction;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_void};
 
 #[no_mangle]
 pub extern "C" fn rs_snmp_tx_get_version(tx: &mut SNMPTransaction,
@@ -47,11 +50,12 @@ pub extern "C" fn rs_snmp_tx_get_community(tx: &mut SNMPTransaction,
 
 #[no_mangle]
 pub extern "C" fn rs_snmp_tx_get_pdu_type(tx: &mut SNMPTransaction,
+                                          direction: u8,
                                           pdu_type: *mut u32)
 {
     unsafe {
-        match tx.info {
-            Some(ref info) => {
+        match tx.pdu_info(direction) {
+            Some(info) => {
                 *pdu_type = info.pdu_type.0 as u32;
             },
             None           => {
@@ -60,3 +64,162 @@ pub extern "C" fn rs_snmp_tx_get_pdu_type(tx: &mut SNMPTransaction,
         }
     }
 }
+
+#[no_mangle]
+pub extern "C" fn rs_snmp_tx_get_usm(tx: &mut SNMPTransaction,
+                                     buf: *mut *const u8,
+                                     len: *mut u32)
+{
+    match tx.usm {
+        Some(ref c) => {
+            unsafe {
+                *buf = (&c).as_ptr();
+                *len = c.len() as u32;
+            }
+        },
+        None        => ()
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn rs_snmp_tx_get_security_level(tx: &mut SNMPTransaction,
+                                                level: *mut u32)
+{
+    unsafe {
+        match tx.security_level {
+            Some(l) => {
+                *level = l as u32;
+            },
+            None    => {
+                *level = 0;
+            }
+        }
+    }
+}
+
+/// OID pattern of the `snmp.oid` keyword. Each arc is a number or `*`,
+/// matching any single arc. A trailing `*` matches one or more arcs.
+#[derive(Debug, PartialEq)]
+pub struct DetectSNMPOid {
+    arcs: Vec<Option<u64>>,
+    subtree: bool,
+}
+
+fn parse_oid_pattern(pattern: &str) -> Result<DetectSNMPOid, ()> {
+    let pattern = pattern.trim().trim_start_matches('.');
+    let mut arcs = Vec::new();
+    for arc in pattern.split('.') {
+        match arc {
+            "*" => arcs.push(None),
+            _ => {
+                let mut n = parse_oid_arcs(arc).ok_or(())?;
+                arcs.push(Some(n.pop().ok_or(())?));
+            }
+        }
+    }
+    let subtree = arcs.len() > 1 && arcs.last() == Some(&None);
+    if subtree {
+        arcs.pop();
+    }
+    if arcs.len() == 0 {
+        return Err(());
+    }
+    Ok(DetectSNMPOid { arcs, subtree })
+}
+
+fn match_oid_pattern(detect: &DetectSNMPOid, oid: &[u64]) -> bool {
+    if detect.subtree {
+        if oid.len() <= detect.arcs.len() {
+            return false;
+        }
+    } else if oid.len() != detect.arcs.len() {
+        return false;
+    }
+    detect.arcs.iter().zip(oid.iter()).all(|(p, a)| p.map_or(true, |p| p == *a))
+}
+
+/// Match the OIDs of the variable bindings against the pattern.
+///
+/// 1 will be returned if any OID matches, otherwise 0 will be returned.
+#[no_mangle]
+pub extern "C" fn rs_snmp_tx_match_oid(tx: &mut SNMPTransaction,
+                                       direction: u8,
+                                       detect: &mut DetectSNMPOid) -> u8
+{
+    if let Some(info) = tx.pdu_info(direction) {
+        for var in info.vars.iter() {
+            if match_oid_pattern(detect, &oid_arcs(&var.oid)) {
+                return 1;
+            }
+        }
+    }
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_snmp_detect_oid_parse(carg: *const c_char) -> *mut c_void {
+    if carg.is_null() {
+        return std::ptr::null_mut();
+    }
+    let arg = match CStr::from_ptr(carg).to_str() {
+        Ok(arg) => arg,
+        _ => {
+            return std::ptr::null_mut();
+        }
+    };
+    match parse_oid_pattern(arg) {
+        Ok(detect) => std::mem::transmute(Box::new(detect)),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rs_snmp_detect_oid_free(ptr: *mut c_void) {
+    if ptr != std::ptr::null_mut() {
+        let _: Box<DetectSNMPOid> = std::mem::transmute(ptr);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_snmp_parse_oid_pattern() {
+        assert_eq!(parse_oid_pattern("1.3.6.1.2.1.1.5.0"),
+                   Ok(DetectSNMPOid { arcs: vec![Some(1), Some(3), Some(6), Some(1),
+                                                 Some(2), Some(1), Some(1), Some(5),
+                                                 Some(0)],
+                                      subtree: false }));
+        assert_eq!(parse_oid_pattern("1.3.6.1.4.1.9.*"),
+                   Ok(DetectSNMPOid { arcs: vec![Some(1), Some(3), Some(6), Some(1),
+                                                 Some(4), Some(1), Some(9)],
+                                      subtree: true }));
+        assert!(parse_oid_pattern("").is_err());
+        assert!(parse_oid_pattern("1.3.a").is_err());
+        assert!(parse_oid_pattern("1..3").is_err());
+    }
+
+    #[test]
+    fn test_snmp_match_oid_pattern() {
+        let sys_name = [1, 3, 6, 1, 2, 1, 1, 5, 0];
+        let detect = parse_oid_pattern("1.3.6.1.2.1.1.5.0").unwrap();
+        assert!(match_oid_pattern(&detect, &sys_name));
+        assert!(!match_oid_pattern(&detect, &sys_name[..8]));
+
+        let detect = parse_oid_pattern("1.3.6.1.2.1.1.*").unwrap();
+        assert!(match_oid_pattern(&detect, &sys_name));
+        assert!(!match_oid_pattern(&detect, &sys_name[..7]));
+
+        // ifAdminStatus of any interface
+        let detect = parse_oid_pattern("1.3.6.1.2.1.2.2.1.7.*").unwrap();
+        assert!(match_oid_pattern(&detect, &[1, 3, 6, 1, 2, 1, 2, 2, 1, 7, 3]));
+        assert!(!match_oid_pattern(&detect, &[1, 3, 6, 1, 2, 1, 2, 2, 1, 8, 3]));
+
+        // ifDescr and ifAdminStatus of interface 2
+        let detect = parse_oid_pattern("1.3.6.1.2.1.2.2.1.*.2").unwrap();
+        assert!(match_oid_pattern(&detect, &[1, 3, 6, 1, 2, 1, 2, 2, 1, 2, 2]));
+        assert!(match_oid_pattern(&detect, &[1, 3, 6, 1, 2, 1, 2, 2, 1, 7, 2]));
+        assert!(!match_oid_pattern(&detect, &[1, 3, 6, 1, 2, 1, 2, 2, 1, 7, 3]));
+    }
+}
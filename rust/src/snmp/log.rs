@@ -18,7 +18,8 @@
 // written by Pierre Chifflier  <chifflier@wzdftpd.net>
 
 use crate::json::*;
-use crate::snmp::snmp::{SNMPState,SNMPTransaction};
+use crate::snmp::snmp::{SNMPState,SNMPTransaction,SNMPPduInfo,SNMPValue,SNMPSecurityLevel};
+use crate::snmp::mib::snmp_oid_name;
 use crate::snmp::snmp_parser::{NetworkAddress,PduType};
 use std::borrow::Cow;
 
@@ -37,37 +38,129 @@ fn str_of_pdu_type(t:&PduType) -> Cow<str> {
     }
 }
 
+fn str_of_value_type(v: &SNMPValue) -> &'static str {
+    match *v {
+        SNMPValue::Integer(_)   => "integer",
+        SNMPValue::String(_)    => "string",
+        SNMPValue::Oid(_)       => "oid",
+        SNMPValue::IpAddress(_) => "ip_address",
+        SNMPValue::Counter(_)   => "counter",
+        SNMPValue::Gauge(_)     => "gauge",
+        SNMPValue::TimeTicks(_) => "timeticks",
+        SNMPValue::Opaque(_)    => "opaque",
+        SNMPValue::Null         => "null",
+        SNMPValue::Unknown      => "unknown",
+    }
+}
+
+fn str_of_security_level(l: SNMPSecurityLevel) -> &'static str {
+    match l {
+        SNMPSecurityLevel::NoAuthNoPriv => "noAuthNoPriv",
+        SNMPSecurityLevel::AuthNoPriv   => "authNoPriv",
+        SNMPSecurityLevel::AuthPriv     => "authPriv",
+    }
+}
+
+/// Set `key` to the value, if it can be represented
+fn set_value(js: &Json, key: &str, v: &SNMPValue) {
+    match *v {
+        SNMPValue::Integer(i) => {
+            if i >= 0 {
+                js.set_integer(key, i as u64);
+            } else {
+                js.set_string(key, &i.to_string());
+            }
+        },
+        SNMPValue::String(ref s) => {
+            // printable strings are logged as is, binary ones in hex
+            if s.iter().all(|&c| c >= 0x20 && c < 0x7f) {
+                js.set_string_from_bytes(key, s);
+            } else {
+                js.set_string(key, &s.iter().map(|b| format!("{:02x}", b)).collect::<String>());
+            }
+        },
+        SNMPValue::Oid(ref oid)     => js.set_string(key, &oid.to_string()),
+        SNMPValue::IpAddress(ip)    => js.set_string(key, &ip.to_string()),
+        SNMPValue::Counter(c)       => js.set_integer(key, c),
+        SNMPValue::Gauge(g)         => js.set_integer(key, g as u64),
+        SNMPValue::TimeTicks(t)     => js.set_integer(key, t as u64),
+        SNMPValue::Opaque(ref o)    => {
+            js.set_string(key, &o.iter().map(|b| format!("{:02x}", b)).collect::<String>());
+        },
+        SNMPValue::Null | SNMPValue::Unknown => (),
+    }
+}
+
+fn log_pdu(js: &Json, info: &SNMPPduInfo) {
+    js.set_string("pdu_type", &str_of_pdu_type(&info.pdu_type));
+    if info.err.0 != 0 {
+        js.set_string("error", &format!("{:?}", info.err));
+    }
+    match info.trap_type {
+        Some((trap_type, ref oid, address)) => {
+            js.set_string("trap_type", &format!("{:?}", trap_type));
+            js.set_string("trap_oid", &oid.to_string());
+            match address {
+                NetworkAddress::IPv4(ip) => js.set_string("trap_address", &ip.to_string())
+            }
+        },
+        _ => ()
+    }
+    if let Some(ref oid) = info.trap_oid {
+        js.set_string("trap_oid", &oid.to_string());
+        if let Some(name) = snmp_oid_name(oid) {
+            js.set_string("trap_name", &name);
+        }
+    }
+    if info.vars.len() > 0 {
+        let jsa = Json::array();
+        for var in info.vars.iter() {
+            jsa.array_append_string(&var.oid.to_string());
+        }
+        js.set("vars", jsa);
+        let jsa = Json::array();
+        for var in info.vars.iter() {
+            let jsv = Json::object();
+            jsv.set_string("oid", &var.oid.to_string());
+            if let Some(name) = snmp_oid_name(&var.oid) {
+                jsv.set_string("name", &name);
+            }
+            jsv.set_string("type", str_of_value_type(&var.value));
+            set_value(&jsv, "value", &var.value);
+            if let SNMPValue::Oid(ref oid) = var.value {
+                if let Some(name) = snmp_oid_name(oid) {
+                    jsv.set_string("value_name", &name);
+                }
+            }
+            if let Some(ref previous) = var.previous {
+                set_value(&jsv, "previous_value", previous);
+            }
+            jsa.array_append(jsv);
+        }
+        js.set("varbinds", jsa);
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn rs_snmp_log_json_response(state: &mut SNMPState, tx: &mut SNMPTransaction) -> *mut JsonT
 {
     let js = Json::object();
     js.set_integer("version", state.version as u64);
-    if tx.encrypted {
+    if let Some(id) = tx.request_id {
+        js.set_integer("request_id", id as u64);
+    }
+    if tx.encrypted && tx.info.is_none() {
         js.set_string("pdu_type", "encrypted");
     } else {
         match tx.info {
+            Some(ref info) => log_pdu(&js, info),
+            _ => ()
+        }
+        match tx.response {
             Some(ref info) => {
-                js.set_string("pdu_type", &str_of_pdu_type(&info.pdu_type));
-                if info.err.0 != 0 {
-                    js.set_string("error", &format!("{:?}", info.err));
-                }
-                match info.trap_type {
-                    Some((trap_type, ref oid, address)) => {
-                        js.set_string("trap_type", &format!("{:?}", trap_type));
-                        js.set_string("trap_oid", &oid.to_string());
-                        match address {
-                            NetworkAddress::IPv4(ip) => js.set_string("trap_address", &ip.to_string())
-                        }
-                    },
-                    _ => ()
-                }
-                if info.vars.len() > 0 {
-                    let jsa = Json::array();
-                    for var in info.vars.iter() {
-                        jsa.array_append_string(&var.to_string());
-                    }
-                    js.set("vars", jsa);
-                }
+                let jsr = Json::object();
+                log_pdu(&jsr, info);
+                js.set("response", jsr);
             },
             _ => ()
         }
@@ -75,10 +168,24 @@ pub extern "C" fn rs_snmp_log_json_response(state: &mut SNMPState, tx: &mut SNMP
             Some(ref c) => js.set_string("community", c),
             _           => ()
         }
-        match tx.usm {
-            Some(ref s) => js.set_string("usm", s),
-            _           => ()
-        }
+    }
+    // USM parameters are sent in clear, even when the PDU is encrypted
+    match tx.usm {
+        Some(ref s) => js.set_string("usm", s),
+        _           => ()
+    }
+    if let Some(ref engine) = tx.usm_engine {
+        let jse = Json::object();
+        jse.set_string("engine_id", &engine.engine_id.iter().map(|b| format!("{:02x}", b)).collect::<String>());
+        jse.set_integer("engine_boots", engine.engine_boots as u64);
+        jse.set_integer("engine_time", engine.engine_time as u64);
+        js.set("usm_engine", jse);
+    }
+    if let Some(level) = tx.security_level {
+        js.set_string("security_level", str_of_security_level(level));
+    }
+    if let Some(report) = tx.usm_report {
+        js.set_string("usm_report", report);
     }
     js.unwrap()
 }
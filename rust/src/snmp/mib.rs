@@ -0,0 +1,244 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+//! Resolution of OIDs to symbolic names, using a subset of the MIBs.
+//!
+//! A built-in table is always loaded. Additional entries can be loaded
+//! from the file set in `app-layer.protocols.snmp.mib-file`, one entry per
+//! line: the OID in dotted notation, followed by the name. Lines starting
+//! with `#` are ignored.
+
+use std;
+
+use der_parser::oid::Oid;
+
+use crate::conf::conf_get;
+use crate::log::*;
+
+/// Objects known by default: SNMPv2-MIB system group and traps, IF-MIB,
+/// SNMP-USM-MIB and SNMP-MPD-MIB statistics and a few enterprise subtrees.
+const BUILTIN_MIB: &[(&str, &str)] = &[
+    ("1.3.6.1.2.1.1", "system"),
+    ("1.3.6.1.2.1.1.1", "sysDescr"),
+    ("1.3.6.1.2.1.1.2", "sysObjectID"),
+    ("1.3.6.1.2.1.1.3", "sysUpTime"),
+    ("1.3.6.1.2.1.1.4", "sysContact"),
+    ("1.3.6.1.2.1.1.5", "sysName"),
+    ("1.3.6.1.2.1.1.6", "sysLocation"),
+    ("1.3.6.1.2.1.1.7", "sysServices"),
+    ("1.3.6.1.2.1.2", "interfaces"),
+    ("1.3.6.1.2.1.2.1", "ifNumber"),
+    ("1.3.6.1.2.1.2.2", "ifTable"),
+    ("1.3.6.1.2.1.2.2.1", "ifEntry"),
+    ("1.3.6.1.2.1.2.2.1.1", "ifIndex"),
+    ("1.3.6.1.2.1.2.2.1.2", "ifDescr"),
+    ("1.3.6.1.2.1.2.2.1.3", "ifType"),
+    ("1.3.6.1.2.1.2.2.1.4", "ifMtu"),
+    ("1.3.6.1.2.1.2.2.1.5", "ifSpeed"),
+    ("1.3.6.1.2.1.2.2.1.6", "ifPhysAddress"),
+    ("1.3.6.1.2.1.2.2.1.7", "ifAdminStatus"),
+    ("1.3.6.1.2.1.2.2.1.8", "ifOperStatus"),
+    ("1.3.6.1.2.1.2.2.1.10", "ifInOctets"),
+    ("1.3.6.1.2.1.2.2.1.16", "ifOutOctets"),
+    ("1.3.6.1.2.1.4", "ip"),
+    ("1.3.6.1.2.1.4.20", "ipAddrTable"),
+    ("1.3.6.1.2.1.4.21", "ipRouteTable"),
+    ("1.3.6.1.2.1.25", "host"),
+    ("1.3.6.1.2.1.31.1.1.1.1", "ifName"),
+    ("1.3.6.1.2.1.31.1.1.1.18", "ifAlias"),
+    ("1.3.6.1.4.1", "enterprises"),
+    ("1.3.6.1.4.1.9", "cisco"),
+    ("1.3.6.1.4.1.9.2.1.55", "writeNet"),
+    ("1.3.6.1.4.1.9.9.96.1.1.1.1", "ccCopyEntry"),
+    ("1.3.6.1.4.1.2636", "juniperMIB"),
+    ("1.3.6.1.4.1.12356", "fortinet"),
+    ("1.3.6.1.4.1.25461", "paloAltoNetworks"),
+    ("1.3.6.1.6.3.1.1.4.1", "snmpTrapOID"),
+    ("1.3.6.1.6.3.1.1.4.3", "snmpTrapEnterprise"),
+    ("1.3.6.1.6.3.1.1.5.1", "coldStart"),
+    ("1.3.6.1.6.3.1.1.5.2", "warmStart"),
+    ("1.3.6.1.6.3.1.1.5.3", "linkDown"),
+    ("1.3.6.1.6.3.1.1.5.4", "linkUp"),
+    ("1.3.6.1.6.3.1.1.5.5", "authenticationFailure"),
+    ("1.3.6.1.6.3.10.2.1.1", "snmpEngineID"),
+    ("1.3.6.1.6.3.10.2.1.2", "snmpEngineBoots"),
+    ("1.3.6.1.6.3.10.2.1.3", "snmpEngineTime"),
+    ("1.3.6.1.6.3.11.2.1.1", "snmpUnknownSecurityModels"),
+    ("1.3.6.1.6.3.11.2.1.2", "snmpInvalidMsgs"),
+    ("1.3.6.1.6.3.11.2.1.3", "snmpUnknownPDUHandlers"),
+    ("1.3.6.1.6.3.15.1.1.1", "usmStatsUnsupportedSecLevels"),
+    ("1.3.6.1.6.3.15.1.1.2", "usmStatsNotInTimeWindows"),
+    ("1.3.6.1.6.3.15.1.1.3", "usmStatsUnknownUserNames"),
+    ("1.3.6.1.6.3.15.1.1.4", "usmStatsUnknownEngineIDs"),
+    ("1.3.6.1.6.3.15.1.1.5", "usmStatsWrongDigests"),
+    ("1.3.6.1.6.3.15.1.1.6", "usmStatsDecryptionErrors"),
+];
+
+/// Parse an OID in dotted notation
+pub fn parse_oid_arcs(s: &str) -> Option<Vec<u64>> {
+    let s = s.trim().trim_start_matches('.');
+    if s.len() == 0 {
+        return None;
+    }
+    s.split('.').map(|a| a.parse::<u64>().ok()).collect()
+}
+
+/// Return the arcs of an OID
+pub fn oid_arcs(oid: &Oid) -> Vec<u64> {
+    parse_oid_arcs(&oid.to_string()).unwrap_or_default()
+}
+
+pub struct MibTable {
+    entries: Vec<(Vec<u64>, String)>,
+}
+
+impl MibTable {
+    pub fn new() -> MibTable {
+        MibTable {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Create a table with the built-in entries.
+    pub fn builtin() -> MibTable {
+        let mut table = MibTable::new();
+        for &(oid, name) in BUILTIN_MIB {
+            if let Some(arcs) = parse_oid_arcs(oid) {
+                table.add(arcs, name);
+            }
+        }
+        table
+    }
+
+    pub fn add(&mut self, arcs: Vec<u64>, name: &str) {
+        self.entries.push((arcs, name.to_string()));
+    }
+
+    /// Load entries from the content of a MIB file. Returns the number of
+    /// entries added, or the number of the first invalid line.
+    pub fn load(&mut self, content: &str) -> Result<usize, usize> {
+        let mut count = 0;
+        for (n, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.len() == 0 || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let arcs = match parts.next().and_then(parse_oid_arcs) {
+                Some(arcs) => arcs,
+                None => return Err(n + 1),
+            };
+            let name = match parts.next() {
+                Some(name) => name,
+                None => return Err(n + 1),
+            };
+            self.add(arcs, name);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Return the symbolic name of an OID, using the longest matching
+    /// object followed by the remaining arcs, like `ifDescr.2`.
+    pub fn lookup(&self, arcs: &[u64]) -> Option<String> {
+        self.entries.iter()
+            .filter(|&&(ref prefix, _)| arcs.starts_with(prefix))
+            .max_by_key(|&&(ref prefix, _)| prefix.len())
+            .map(|&(ref prefix, ref name)| {
+                let mut s = name.clone();
+                for arc in &arcs[prefix.len()..] {
+                    s.push_str(&format!(".{}", arc));
+                }
+                s
+            })
+    }
+}
+
+static mut MIB: Option<MibTable> = None;
+
+/// Initialize the global table with the built-in entries and the entries
+/// of the configured file, if any.
+pub fn snmp_mib_init() {
+    let mut table = MibTable::builtin();
+    if let Some(filename) = conf_get("app-layer.protocols.snmp.mib-file") {
+        match std::fs::read_to_string(filename) {
+            Ok(content) => {
+                match table.load(&content) {
+                    Ok(count) => {
+                        SCLogConfig!("SNMP: loaded {} MIB objects from {}", count, filename);
+                    },
+                    Err(line) => {
+                        SCLogWarning!("SNMP: invalid MIB entry in {} line {}", filename, line);
+                    },
+                }
+            },
+            Err(e) => {
+                SCLogWarning!("SNMP: failed to read MIB file {}: {}", filename, e);
+            },
+        }
+    }
+    unsafe {
+        MIB = Some(table);
+    }
+}
+
+/// Return the symbolic name of an OID, using the global table.
+pub fn snmp_oid_name(oid: &Oid) -> Option<String> {
+    unsafe {
+        match MIB {
+            Some(ref table) => table.lookup(&oid_arcs(oid)),
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mib_builtin() {
+        let table = MibTable::builtin();
+        let arcs = parse_oid_arcs("1.3.6.1.2.1.1.5.0").unwrap();
+        assert_eq!(table.lookup(&arcs), Some("sysName.0".to_string()));
+        let arcs = parse_oid_arcs(".1.3.6.1.2.1.2.2.1.2.12").unwrap();
+        assert_eq!(table.lookup(&arcs), Some("ifDescr.12".to_string()));
+        let arcs = parse_oid_arcs("1.3.6.1.4.1.9.9.96.1.1.1.1.2.111").unwrap();
+        assert_eq!(table.lookup(&arcs), Some("ccCopyEntry.2.111".to_string()));
+        let arcs = parse_oid_arcs("1.2.840.113549").unwrap();
+        assert_eq!(table.lookup(&arcs), None);
+    }
+
+    #[test]
+    fn test_mib_load() {
+        let mut table = MibTable::builtin();
+        let content = "# local objects\n\n1.3.6.1.4.1.9.9.96.1.1.1.1.2 ccCopyProtocol\n";
+        assert_eq!(table.load(content), Ok(1));
+        let arcs = parse_oid_arcs("1.3.6.1.4.1.9.9.96.1.1.1.1.2.111").unwrap();
+        assert_eq!(table.lookup(&arcs), Some("ccCopyProtocol.111".to_string()));
+
+        assert_eq!(table.load("1.3.x.1 foo\n"), Err(1));
+        assert_eq!(table.load("# comment\n1.3.6.1\n"), Err(2));
+    }
+
+    #[test]
+    fn test_parse_oid_arcs() {
+        assert_eq!(parse_oid_arcs("1.3.6.1"), Some(vec![1, 3, 6, 1]));
+        assert_eq!(parse_oid_arcs(""), None);
+        assert_eq!(parse_oid_arcs("1..3"), None);
+    }
+}
@@ -20,5 +20,6 @@
 extern crate snmp_parser;
 
 pub mod snmp;
+pub mod mib;
 pub mod log;
 pub mod detect;
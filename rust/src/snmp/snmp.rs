@@ -20,40 +20,47 @@
 use crate::snmp::snmp_parser::*;
 use crate::core;
 use crate::core::{AppProto,Flow,ALPROTO_UNKNOWN,ALPROTO_FAILED,STREAM_TOSERVER,STREAM_TOCLIENT};
-use crate::applayer;
+use crate::applayer::{self, AppLayerEvent};
 use crate::parser::*;
+use crate::snmp::mib::{oid_arcs,snmp_mib_init};
 use std;
-use std::ffi::{CStr,CString};
+use std::collections::HashMap;
+use std::ffi::CString;
 use std::mem::transmute;
+use std::net::Ipv4Addr;
 
 use crate::log::*;
 
-use der_parser::{DerObjectContent,parse_der_sequence};
+use der_parser::{DerObjectContent,der_read_element_header,parse_der_sequence};
 use der_parser::oid::Oid;
 use nom;
 use nom::{ErrorKind,IResult};
 
 #[repr(u32)]
+#[derive(AppLayerEvent, Debug, PartialEq)]
 pub enum SNMPEvent {
     MalformedData = 0,
     UnknownSecurityModel,
     VersionMismatch,
-}
-
-impl SNMPEvent {
-    fn from_i32(value: i32) -> Option<SNMPEvent> {
-        match value {
-            0 => Some(SNMPEvent::MalformedData),
-            1 => Some(SNMPEvent::UnknownSecurityModel),
-            _ => None,
-        }
-    }
+    UsmEngineDiscovery,
+    UsmUserEnumeration,
+    UsmNoAuth,
 }
 
 pub struct SNMPState {
     /// SNMP protocol version
     pub version: u32,
 
+    /// Last values seen in responses, by OID
+    values: HashMap<String,SNMPValue>,
+
+    /// USM user names reported as unknown by the agent
+    unknown_usm_users: Vec<String>,
+
+    /// Incomplete messages, when running over TCP
+    defrag_buf_ts: Vec<u8>,
+    defrag_buf_tc: Vec<u8>,
+
     /// List of transactions for this session
     transactions: Vec<SNMPTransaction>,
 
@@ -68,22 +75,207 @@ pub struct SNMPPduInfo {
 
     pub trap_type: Option<(TrapType,Oid,NetworkAddress)>,
 
-    pub vars: Vec<Oid>,
+    /// Value of snmpTrapOID.0, for SNMPv2 traps and informs
+    pub trap_oid: Option<Oid>,
+
+    /// request-id, absent from SNMPv1 traps
+    pub request_id: Option<u32>,
+
+    pub vars: Vec<SNMPVariable>,
+}
+
+/// Decoded value of a variable binding
+#[derive(Clone, Debug, PartialEq)]
+pub enum SNMPValue {
+    Integer(i64),
+    String(Vec<u8>),
+    Oid(Oid),
+    IpAddress(Ipv4Addr),
+    Counter(u64),
+    Gauge(u32),
+    TimeTicks(u32),
+    Opaque(Vec<u8>),
+    Null,
+    Unknown,
+}
+
+pub struct SNMPVariable {
+    pub oid: Oid,
+    pub value: SNMPValue,
+    /// Value seen in a previous response, for variables of SET requests
+    pub previous: Option<SNMPValue>,
+}
+
+/// msgAuthoritativeEngineID, msgAuthoritativeEngineBoots and
+/// msgAuthoritativeEngineTime of the USM security parameters
+pub struct SNMPUsmEngine {
+    pub engine_id: Vec<u8>,
+    pub engine_boots: u32,
+    pub engine_time: u32,
+}
+
+/// securityLevel (RFC 3411 section 5), derived from the msgFlags
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SNMPSecurityLevel {
+    NoAuthNoPriv = 1,
+    AuthNoPriv = 2,
+    AuthPriv = 3,
+}
+
+impl SNMPSecurityLevel {
+    pub fn from_msg_flags(flags: u8) -> SNMPSecurityLevel {
+        match flags & 0x03 {
+            0x03 => SNMPSecurityLevel::AuthPriv,
+            0x01 => SNMPSecurityLevel::AuthNoPriv,
+            // privacy without authentication is invalid
+            _    => SNMPSecurityLevel::NoAuthNoPriv,
+        }
+    }
+}
+
+/// usmStats counters (RFC 3414 section 5), reported in Report PDUs
+const USM_STATS: &[(u64, &str)] = &[
+    (1, "usmStatsUnsupportedSecLevels"),
+    (2, "usmStatsNotInTimeWindows"),
+    (3, "usmStatsUnknownUserNames"),
+    (4, "usmStatsUnknownEngineIDs"),
+    (5, "usmStatsWrongDigests"),
+    (6, "usmStatsDecryptionErrors"),
+];
+const USM_STATS_OID: &[u64] = &[1, 3, 6, 1, 6, 3, 15, 1, 1];
+
+/// Return the name of the usmStats counter of a Report PDU, if any
+fn usm_report_name(info: &SNMPPduInfo) -> Option<&'static str> {
+    if info.pdu_type != PduType::Report {
+        return None;
+    }
+    for var in info.vars.iter() {
+        let arcs = oid_arcs(&var.oid);
+        if arcs.len() > USM_STATS_OID.len() && arcs.starts_with(USM_STATS_OID) {
+            let counter = arcs[USM_STATS_OID.len()];
+            if let Some(&(_, name)) = USM_STATS.iter().find(|&&(n, _)| n == counter) {
+                return Some(name);
+            }
+        }
+    }
+    None
+}
+
+/// Return the event to set for the USM parameters of a request, if any
+fn usm_request_event(usm: &UsmSecurityParameters, level: SNMPSecurityLevel) -> Option<SNMPEvent> {
+    if usm.msg_authoritative_engine_id.is_empty() && usm.msg_user_name.is_empty() {
+        // RFC 3414 section 4: discovery of the engine ID
+        Some(SNMPEvent::UsmEngineDiscovery)
+    } else if level == SNMPSecurityLevel::NoAuthNoPriv && !usm.msg_user_name.is_empty() {
+        Some(SNMPEvent::UsmNoAuth)
+    } else {
+        None
+    }
+}
+
+/// snmpTrapOID.0 (RFC 3416 section 4.2.6)
+const SNMP_TRAP_OID: &[u64] = &[1, 3, 6, 1, 6, 3, 1, 1, 4, 1, 0];
+
+/// Return true if the PDU answers a request
+fn pdu_is_response(pdu_type: &PduType) -> bool {
+    *pdu_type == PduType::Response || *pdu_type == PduType::Report
+}
+
+/// Return true if no response is expected for the PDU
+fn pdu_is_notification(pdu_type: &PduType) -> bool {
+    *pdu_type == PduType::TrapV1 || *pdu_type == PduType::TrapV2
+}
+
+/// Maximum number of requests waiting for a response. When reached, the
+/// oldest request is considered unanswered.
+const SNMP_MAX_PENDING_REQUESTS: usize = 256;
+
+/// Maximum size of a message over TCP. Agents and managers must accept
+/// at least 484 bytes, and messages are bounded by msgMaxSize (RFC 3412).
+const SNMP_TCP_MAX_MESSAGE_SIZE: usize = 65535;
+
+/// Number of distinct user names reported as unknown in a flow before
+/// raising the UsmUserEnumeration event
+const SNMP_USER_ENUMERATION_USERS: usize = 3;
+
+/// Maximum number of values kept per flow to report the previous values
+/// of SET requests
+const SNMP_MAX_VALUES: usize = 1024;
+
+/// Decode a DER INTEGER
+fn der_integer_value(i: &[u8]) -> Option<i64> {
+    if i.len() == 0 || i.len() > 8 {
+        return None;
+    }
+    // sign extension
+    let mut value: i64 = if i[0] & 0x80 != 0 { -1 } else { 0 };
+    for &b in i {
+        value = (value << 8) | b as i64;
+    }
+    Some(value)
+}
+
+/// Decode the value of a variable binding
+fn snmp_value(val: &ObjectSyntax) -> SNMPValue {
+    match *val {
+        ObjectSyntax::Number(ref obj) => {
+            match obj.content {
+                DerObjectContent::Integer(i) => {
+                    der_integer_value(i).map_or(SNMPValue::Unknown, SNMPValue::Integer)
+                },
+                _ => SNMPValue::Unknown,
+            }
+        },
+        ObjectSyntax::String(s)                      => SNMPValue::String(s.to_vec()),
+        ObjectSyntax::Object(ref oid)                => SNMPValue::Oid(oid.clone()),
+        ObjectSyntax::IpAddress(NetworkAddress::IPv4(ip)) => SNMPValue::IpAddress(ip),
+        ObjectSyntax::Counter32(c)                   => SNMPValue::Counter(c as u64),
+        ObjectSyntax::Counter64(c)                   => SNMPValue::Counter(c),
+        ObjectSyntax::Gauge32(g)                     => SNMPValue::Gauge(g),
+        ObjectSyntax::UInteger32(u)                  => SNMPValue::Gauge(u),
+        ObjectSyntax::TimeTicks(t)                   => SNMPValue::TimeTicks(t),
+        ObjectSyntax::Opaque(o)                      => SNMPValue::Opaque(o.to_vec()),
+        ObjectSyntax::Empty                          => SNMPValue::Null,
+        _                                            => SNMPValue::Unknown,
+    }
 }
 
 pub struct SNMPTransaction {
     /// PDU version
     pub version: u32,
 
-    /// PDU info, if present (and cleartext)
+    /// PDU info of the first message of the transaction (request,
+    /// notification or unsolicited response), if present (and cleartext)
     pub info: Option<SNMPPduInfo>,
 
+    /// PDU info of the response, if present (and cleartext)
+    pub response: Option<SNMPPduInfo>,
+
+    /// request-id (SNMPv1 and SNMPv2c) or msgID (SNMPv3) used to pair
+    /// the request and its response
+    pub request_id: Option<u32>,
+
+    /// Direction of the first message of the transaction
+    direction: u8,
+
+    /// True if the response was seen, or if none is expected
+    response_done: bool,
+
     /// Community, if present (SNMPv2)
     pub community: Option<String>,
 
     /// USM info, if present (SNMPv3)
     pub usm: Option<String>,
 
+    /// USM authoritative engine parameters, if present (SNMPv3)
+    pub usm_engine: Option<SNMPUsmEngine>,
+
+    /// Security level (SNMPv3)
+    pub security_level: Option<SNMPSecurityLevel>,
+
+    /// USM statistics counter reported by the agent in a Report PDU
+    pub usm_report: Option<&'static str>,
+
     /// True if transaction was encrypted
     pub encrypted: bool,
 
@@ -105,6 +297,10 @@ impl SNMPState {
     pub fn new() -> SNMPState {
         SNMPState{
             version: 0,
+            values: HashMap::new(),
+            unknown_usm_users: Vec::new(),
+            defrag_buf_ts: Vec::new(),
+            defrag_buf_tc: Vec::new(),
             transactions: Vec::new(),
             tx_id: 0,
         }
@@ -117,67 +313,201 @@ impl Default for SNMPPduInfo {
             pdu_type: PduType(0),
             err: ErrorStatus::NoError,
             trap_type: None,
+            trap_oid: None,
+            request_id: None,
             vars: Vec::new()
         }
     }
 }
 
 impl SNMPState {
-    fn add_pdu_info(&mut self, pdu: &SnmpPdu, tx: &mut SNMPTransaction) {
+    fn pdu_info(&mut self, pdu: &SnmpPdu) -> SNMPPduInfo {
         let mut pdu_info = SNMPPduInfo::default();
         pdu_info.pdu_type = pdu.pdu_type();
         match *pdu {
             SnmpPdu::Generic(ref pdu) => {
                 pdu_info.err = pdu.err;
+                pdu_info.request_id = Some(pdu.req_id);
             },
-            SnmpPdu::Bulk(_) => {
+            SnmpPdu::Bulk(ref pdu) => {
+                pdu_info.request_id = Some(pdu.req_id);
             },
             SnmpPdu::TrapV1(ref t)    => {
                 pdu_info.trap_type = Some((t.generic_trap,t.enterprise.clone(),t.agent_addr.clone()));
             }
         }
         for ref var in pdu.vars_iter() {
-            pdu_info.vars.push(var.oid.clone());
+            let value = snmp_value(&var.val);
+            let key = var.oid.to_string();
+            let previous = if pdu_info.pdu_type == PduType::SetRequest {
+                self.values.get(&key).cloned()
+            } else {
+                None
+            };
+            // keep the values read or written by the agent
+            if pdu_info.pdu_type == PduType::Response && pdu_info.err.0 == 0 &&
+                value != SNMPValue::Null &&
+                (self.values.len() < SNMP_MAX_VALUES || self.values.contains_key(&key)) {
+                self.values.insert(key, value.clone());
+            }
+            // the second variable of traps and informs is snmpTrapOID.0
+            if pdu_info.pdu_type == PduType::TrapV2 || pdu_info.pdu_type == PduType::InformRequest {
+                if let SNMPValue::Oid(ref oid) = value {
+                    if pdu_info.trap_oid.is_none() && oid_arcs(&var.oid) == SNMP_TRAP_OID {
+                        pdu_info.trap_oid = Some(oid.clone());
+                    }
+                }
+            }
+            pdu_info.vars.push(SNMPVariable{
+                oid: var.oid.clone(),
+                value,
+                previous,
+            });
+        }
+        pdu_info
+    }
+
+    /// Find the index of the transaction waiting for the response with
+    /// this request-id. If `direction` is set, the request must have been
+    /// sent in the other direction.
+    fn find_request(&self, request_id: u32, direction: Option<u8>) -> Option<usize> {
+        self.transactions.iter().rposition(|tx| {
+            !tx.response_done && tx.request_id == Some(request_id) &&
+                direction.map_or(true, |d| tx.direction != d)
+        })
+    }
+
+    /// Return the transaction at `idx`, removed from the list, or a new one
+    fn take_tx(&mut self, idx: Option<usize>) -> SNMPTransaction {
+        match idx {
+            Some(idx) => self.transactions.remove(idx),
+            None      => self.new_tx(),
         }
-        tx.info = Some(pdu_info);
     }
 
-    fn handle_snmp_v12(&mut self, msg:SnmpMessage, _direction: u8) -> i32 {
-        let mut tx = self.new_tx();
+    /// Store a transaction returned by `take_tx`
+    fn store_tx(&mut self, tx: SNMPTransaction, idx: Option<usize>) {
+        match idx {
+            Some(idx) => self.transactions.insert(idx, tx),
+            None      => {
+                self.transactions.push(tx);
+                // requests over UDP may never be answered
+                let mut pending = self.transactions.iter_mut().filter(|tx| !tx.response_done);
+                if let Some(oldest) = pending.next() {
+                    if pending.count() >= SNMP_MAX_PENDING_REQUESTS {
+                        oldest.response_done = true;
+                    }
+                }
+            },
+        }
+    }
+
+    fn handle_snmp_v12(&mut self, msg:SnmpMessage, direction: u8) -> i32 {
+        let info = self.pdu_info(&msg.pdu);
+        let is_response = pdu_is_response(&info.pdu_type);
+        let idx = match info.request_id {
+            Some(id) if is_response => self.find_request(id, None),
+            _                       => None,
+        };
+        let mut tx = self.take_tx(idx);
         // in the message, version is encoded as 0 (version 1) or 1 (version 2)
         if self.version != msg.version + 1 {
             SCLogDebug!("SNMP version mismatch: expected {}, received {}", self.version, msg.version+1);
             self.set_event_tx(&mut tx, SNMPEvent::VersionMismatch);
         }
-        self.add_pdu_info(&msg.pdu, &mut tx);
-        tx.community = Some(msg.community.clone());
-        self.transactions.push(tx);
+        if idx.is_some() {
+            tx.response = Some(info);
+            tx.response_done = true;
+        } else {
+            tx.request_id = info.request_id;
+            tx.direction = direction;
+            tx.response_done = is_response || pdu_is_notification(&info.pdu_type);
+            tx.info = Some(info);
+        }
+        if tx.community.is_none() {
+            tx.community = Some(msg.community.clone());
+        }
+        self.store_tx(tx, idx);
         0
     }
 
-    fn handle_snmp_v3(&mut self, msg: SnmpV3Message, _direction: u8) -> i32 {
-        let mut tx = self.new_tx();
+    fn handle_snmp_v3(&mut self, msg: SnmpV3Message, direction: u8) -> i32 {
+        let msg_id = msg.header_data.msg_id;
+        let info = match msg.data {
+            ScopedPduData::Plaintext(pdu) => Some(self.pdu_info(&pdu.data)),
+            _                             => None,
+        };
+        let idx = match info {
+            Some(ref info) if pdu_is_response(&info.pdu_type) => self.find_request(msg_id, None),
+            Some(_) => None,
+            // the type of encrypted PDUs is unknown: a message with the
+            // msgID of a request sent in the other direction is a response
+            None => self.find_request(msg_id, Some(direction)),
+        };
+        let mut tx = self.take_tx(idx);
         if self.version != msg.version {
             SCLogDebug!("SNMP version mismatch: expected {}, received {}", self.version, msg.version);
             self.set_event_tx(&mut tx, SNMPEvent::VersionMismatch);
         }
-        match msg.data {
-            ScopedPduData::Plaintext(pdu) => {
-                self.add_pdu_info(&pdu.data, &mut tx);
-            },
-            _                             => {
-                tx.encrypted = true;
-            }
+        if info.is_none() {
+            tx.encrypted = true;
+        }
+        let is_request = idx.is_none() && info.as_ref().map_or(true, |i| {
+            !pdu_is_response(&i.pdu_type) && !pdu_is_notification(&i.pdu_type)
+        });
+        let usm_report = info.as_ref().and_then(usm_report_name);
+        if usm_report.is_some() {
+            tx.usm_report = usm_report;
+        }
+        if idx.is_some() {
+            tx.response = info;
+            tx.response_done = true;
+        } else {
+            tx.request_id = Some(msg_id);
+            tx.direction = direction;
+            tx.response_done = info.as_ref().map_or(false, |i| {
+                pdu_is_response(&i.pdu_type) || pdu_is_notification(&i.pdu_type)
+            });
+            tx.info = info;
+        }
+        let level = SNMPSecurityLevel::from_msg_flags(msg.header_data.msg_flags);
+        if tx.security_level.is_none() {
+            tx.security_level = Some(level);
         }
         match msg.security_params {
             SecurityParameters::USM(usm) => {
-                tx.usm = Some(usm.msg_user_name.clone());
+                if tx.usm.is_none() {
+                    tx.usm = Some(usm.msg_user_name.clone());
+                }
+                // the engine ID is discovered from the response
+                if tx.usm_engine.as_ref().map_or(true, |e| e.engine_id.is_empty()) {
+                    tx.usm_engine = Some(SNMPUsmEngine{
+                        engine_id: usm.msg_authoritative_engine_id.to_vec(),
+                        engine_boots: usm.msg_authoritative_engine_boots,
+                        engine_time: usm.msg_authoritative_engine_time,
+                    });
+                }
+                if is_request {
+                    if let Some(event) = usm_request_event(&usm, level) {
+                        self.set_event_tx(&mut tx, event);
+                    }
+                }
             },
             _                            => {
                 self.set_event_tx(&mut tx, SNMPEvent::UnknownSecurityModel);
             }
         }
-        self.transactions.push(tx);
+        if idx.is_some() && tx.usm_report == Some("usmStatsUnknownUserNames") {
+            if let Some(ref user) = tx.usm {
+                if !self.unknown_usm_users.contains(user) {
+                    self.unknown_usm_users.push(user.clone());
+                }
+            }
+            if self.unknown_usm_users.len() >= SNMP_USER_ENUMERATION_USERS {
+                self.set_event_tx(&mut tx, SNMPEvent::UsmUserEnumeration);
+            }
+        }
+        self.store_tx(tx, idx);
         0
     }
 
@@ -203,6 +533,62 @@ impl SNMPState {
         }
     }
 
+    /// Parse SNMP messages over TCP (RFC 3430). Messages are not framed,
+    /// their length is read from the BER header.
+    fn parse_tcp(&mut self, i: &[u8], direction: u8) -> i32 {
+        let mut v : Vec<u8>;
+        let defrag_buf = if direction == STREAM_TOSERVER {
+            &mut self.defrag_buf_ts
+        } else {
+            &mut self.defrag_buf_tc
+        };
+        let tcp_buffer = if defrag_buf.is_empty() {
+            i
+        } else {
+            v = defrag_buf.split_off(0);
+            v.extend_from_slice(i);
+            v.as_slice()
+        };
+        let mut cur_i = tcp_buffer;
+        while cur_i.len() > 0 {
+            let msg_len = match der_read_element_header(cur_i) {
+                // messages are SEQUENCEs
+                Ok((rem,ref hdr)) if hdr.class == 0 && hdr.tag == 16 => {
+                    (cur_i.len() - rem.len()).saturating_add(hdr.len as usize)
+                },
+                Err(nom::Err::Incomplete(_)) if cur_i.len() < 8 => {
+                    cur_i.len() + 1
+                },
+                _ => {
+                    SCLogDebug!("parse_tcp: invalid message header");
+                    self.set_event(SNMPEvent::MalformedData);
+                    return -1;
+                },
+            };
+            if msg_len > SNMP_TCP_MAX_MESSAGE_SIZE {
+                SCLogDebug!("parse_tcp: message too large ({} bytes)", msg_len);
+                self.set_event(SNMPEvent::MalformedData);
+                return -1;
+            }
+            if cur_i.len() < msg_len {
+                // more fragments required
+                let defrag_buf = if direction == STREAM_TOSERVER {
+                    &mut self.defrag_buf_ts
+                } else {
+                    &mut self.defrag_buf_tc
+                };
+                defrag_buf.extend_from_slice(cur_i);
+                return 0;
+            }
+            let status = self.parse(&cur_i[..msg_len], direction);
+            if status != 0 {
+                return status;
+            }
+            cur_i = &cur_i[msg_len..];
+        }
+        0
+    }
+
     fn free(&mut self) {
         // All transactions are freed when the `transactions` object is freed.
         // But let's be explicit
@@ -267,8 +653,15 @@ impl SNMPTransaction {
         SNMPTransaction {
             version,
             info: None,
+            response: None,
+            request_id: None,
+            direction: STREAM_TOSERVER,
+            response_done: false,
             community: None,
             usm: None,
+            usm_engine: None,
+            security_level: None,
+            usm_report: None,
             encrypted: false,
             id: id,
             de_state: None,
@@ -277,6 +670,15 @@ impl SNMPTransaction {
         }
     }
 
+    /// Return the PDU info of the message sent in `direction`
+    pub fn pdu_info(&self, direction: u8) -> Option<&SNMPPduInfo> {
+        if direction & self.direction != 0 {
+            self.info.as_ref()
+        } else {
+            self.response.as_ref()
+        }
+    }
+
     fn free(&mut self) {
         if self.events != std::ptr::null_mut() {
             core::sc_app_layer_decoder_events_free_events(&mut self.events);
@@ -338,6 +740,32 @@ pub extern "C" fn rs_snmp_parse_response(_flow: *const core::Flow,
     state.parse(buf, STREAM_TOCLIENT)
 }
 
+#[no_mangle]
+pub extern "C" fn rs_snmp_parse_request_tcp(_flow: *const core::Flow,
+                                       state: *mut std::os::raw::c_void,
+                                       _pstate: *mut std::os::raw::c_void,
+                                       input: *const u8,
+                                       input_len: u32,
+                                       _data: *const std::os::raw::c_void,
+                                       _flags: u8) -> i32 {
+    let buf = build_slice!(input,input_len as usize);
+    let state = cast_pointer!(state,SNMPState);
+    state.parse_tcp(buf, STREAM_TOSERVER)
+}
+
+#[no_mangle]
+pub extern "C" fn rs_snmp_parse_response_tcp(_flow: *const core::Flow,
+                                       state: *mut std::os::raw::c_void,
+                                       _pstate: *mut std::os::raw::c_void,
+                                       input: *const u8,
+                                       input_len: u32,
+                                       _data: *const std::os::raw::c_void,
+                                       _flags: u8) -> i32 {
+    let buf = build_slice!(input,input_len as usize);
+    let state = cast_pointer!(state,SNMPState);
+    state.parse_tcp(buf, STREAM_TOCLIENT)
+}
+
 #[no_mangle]
 pub extern "C" fn rs_snmp_state_get_tx(state: *mut std::os::raw::c_void,
                                       tx_id: u64)
@@ -375,10 +803,15 @@ pub extern "C" fn rs_snmp_state_progress_completion_status(
 }
 
 #[no_mangle]
-pub extern "C" fn rs_snmp_tx_get_alstate_progress(_tx: *mut std::os::raw::c_void,
-                                                 _direction: u8)
+pub extern "C" fn rs_snmp_tx_get_alstate_progress(tx: *mut std::os::raw::c_void,
+                                                 direction: u8)
                                                  -> std::os::raw::c_int
 {
+    let tx = cast_pointer!(tx,SNMPTransaction);
+    // waiting for the response, sent in the other direction
+    if direction & tx.direction == 0 && !tx.response_done {
+        return 0;
+    }
     1
 }
 
@@ -436,54 +869,6 @@ pub extern "C" fn rs_snmp_state_get_events(tx: *mut std::os::raw::c_void)
     return tx.events;
 }
 
-#[no_mangle]
-pub extern "C" fn rs_snmp_state_get_event_info_by_id(event_id: std::os::raw::c_int,
-                                                     event_name: *mut *const std::os::raw::c_char,
-                                                     event_type: *mut core::AppLayerEventType)
-                                                     -> i8
-{
-    if let Some(e) = SNMPEvent::from_i32(event_id as i32) {
-        let estr = match e {
-            SNMPEvent::MalformedData         => { "malformed_data\0" },
-            SNMPEvent::UnknownSecurityModel  => { "unknown_security_model\0" },
-            SNMPEvent::VersionMismatch       => { "version_mismatch\0" },
-        };
-        unsafe{
-            *event_name = estr.as_ptr() as *const std::os::raw::c_char;
-            *event_type = core::APP_LAYER_EVENT_TYPE_TRANSACTION;
-        };
-        0
-    } else {
-        -1
-    }
-}
-
-#[no_mangle]
-pub extern "C" fn rs_snmp_state_get_event_info(event_name: *const std::os::raw::c_char,
-                                              event_id: *mut std::os::raw::c_int,
-                                              event_type: *mut core::AppLayerEventType)
-                                              -> std::os::raw::c_int
-{
-    if event_name == std::ptr::null() { return -1; }
-    let c_event_name: &CStr = unsafe { CStr::from_ptr(event_name) };
-    let event = match c_event_name.to_str() {
-        Ok(s) => {
-            match s {
-                "malformed_data"         => SNMPEvent::MalformedData as i32,
-                "unknown_security_model" => SNMPEvent::UnknownSecurityModel as i32,
-                "version_mismatch"       => SNMPEvent::VersionMismatch as i32,
-                _                        => -1, // unknown event
-            }
-        },
-        Err(_) => -1, // UTF-8 conversion failed
-    };
-    unsafe{
-        *event_type = core::APP_LAYER_EVENT_TYPE_TRANSACTION;
-        *event_id = event as std::os::raw::c_int;
-    };
-    0
-}
-
 // for use with the C API call StateGetTxIterator
 #[no_mangle]
 pub extern "C" fn rs_snmp_state_get_tx_iterator(
@@ -576,6 +961,7 @@ const PARSER_NAME : &'static [u8] = b"snmp\0";
 
 #[no_mangle]
 pub unsafe extern "C" fn rs_register_snmp_parser() {
+    snmp_mib_init();
     let default_port = CString::new("161").unwrap();
     let mut parser = RustParser {
         name               : PARSER_NAME.as_ptr() as *const std::os::raw::c_char,
@@ -599,8 +985,8 @@ pub unsafe extern "C" fn rs_register_snmp_parser() {
         get_de_state       : rs_snmp_state_get_tx_detect_state,
         set_de_state       : rs_snmp_state_set_tx_detect_state,
         get_events         : Some(rs_snmp_state_get_events),
-        get_eventinfo      : Some(rs_snmp_state_get_event_info),
-        get_eventinfo_byid : Some(rs_snmp_state_get_event_info_by_id),
+        get_eventinfo      : Some(SNMPEvent::get_event_info),
+        get_eventinfo_byid : Some(SNMPEvent::get_event_info_by_id),
         localstorage_new   : None,
         localstorage_free  : None,
         get_tx_mpm_id      : None,
@@ -628,4 +1014,191 @@ pub unsafe extern "C" fn rs_register_snmp_parser() {
     } else {
         SCLogDebug!("Protocol detector and parser disabled for SNMP.");
     }
+    // register TCP parser (RFC 3430)
+    let default_port = CString::new("[161,162]").unwrap();
+    parser.default_port = default_port.as_ptr();
+    parser.ipproto = core::IPPROTO_TCP;
+    parser.parse_ts = rs_snmp_parse_request_tcp;
+    parser.parse_tc = rs_snmp_parse_response_tcp;
+    let ip_proto_str = CString::new("tcp").unwrap();
+    if AppLayerProtoDetectConfProtoDetectionEnabled(ip_proto_str.as_ptr(), parser.name) != 0 {
+        let alproto = AppLayerRegisterProtocolDetection(&parser, 1);
+        ALPROTO_SNMP = alproto;
+        if AppLayerParserConfParserEnabled(ip_proto_str.as_ptr(), parser.name) != 0 {
+            let _ = AppLayerRegisterParser(&parser, alproto);
+        }
+        AppLayerParserRegisterGetTxIterator(core::IPPROTO_TCP as u8, alproto, rs_snmp_get_tx_iterator);
+    } else {
+        SCLogDebug!("Protocol detector and parser disabled for SNMP/TCP.");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snmp_der_integer_value() {
+        assert_eq!(der_integer_value(&[0x01]), Some(1));
+        assert_eq!(der_integer_value(&[0x00, 0x80]), Some(128));
+        assert_eq!(der_integer_value(&[0xff]), Some(-1));
+        assert_eq!(der_integer_value(&[0xff, 0x7f]), Some(-129));
+        assert_eq!(der_integer_value(&[]), None);
+    }
+
+    #[test]
+    fn test_snmp_v3_engine_discovery() {
+        let buf = [
+            0x30, 0x3b, 0x02, 0x01, 0x03, 0x30, 0x11, 0x02,
+            0x04, 0x00, 0x00, 0x00, 0x01, 0x02, 0x03, 0x00,
+            0xff, 0xe3, 0x04, 0x01, 0x04, 0x02, 0x01, 0x03,
+            0x04, 0x10, 0x30, 0x0e, 0x04, 0x00, 0x02, 0x01,
+            0x00, 0x02, 0x01, 0x00, 0x04, 0x00, 0x04, 0x00,
+            0x04, 0x00, 0x30, 0x11, 0x04, 0x00, 0x04, 0x00,
+            0xa0, 0x0b, 0x02, 0x01, 0x01, 0x02, 0x01, 0x00,
+            0x02, 0x01, 0x00, 0x30, 0x00,
+        ];
+        let mut state = SNMPState::new();
+        assert_eq!(state.parse(&buf, STREAM_TOSERVER), 0);
+        let tx = state.transactions.last().unwrap();
+        assert_eq!(tx.version, 3);
+        assert_eq!(tx.security_level, Some(SNMPSecurityLevel::NoAuthNoPriv));
+        assert_eq!(tx.usm, Some("".to_string()));
+        assert!(tx.usm_engine.as_ref().unwrap().engine_id.is_empty());
+        assert_eq!(tx.usm_report, None);
+        let (_, msg) = parse_snmp_v3(&buf).unwrap();
+        match msg.security_params {
+            SecurityParameters::USM(usm) => {
+                assert_eq!(usm_request_event(&usm, SNMPSecurityLevel::NoAuthNoPriv),
+                           Some(SNMPEvent::UsmEngineDiscovery));
+            },
+            _ => panic!("expected USM security parameters"),
+        }
+    }
+
+    #[test]
+    fn test_snmp_security_level() {
+        assert_eq!(SNMPSecurityLevel::from_msg_flags(0x04), SNMPSecurityLevel::NoAuthNoPriv);
+        assert_eq!(SNMPSecurityLevel::from_msg_flags(0x05), SNMPSecurityLevel::AuthNoPriv);
+        assert_eq!(SNMPSecurityLevel::from_msg_flags(0x07), SNMPSecurityLevel::AuthPriv);
+    }
+
+    // get-request for sysContact.0, request-id 1
+    const GET_REQUEST: &[u8] = &[
+        0x30, 0x27, 0x02, 0x01, 0x01, 0x04, 0x07, 0x70,
+        0x72, 0x69, 0x76, 0x61, 0x74, 0x65, 0xa0, 0x19,
+        0x02, 0x01, 0x01, 0x02, 0x01, 0x00, 0x02, 0x01,
+        0x00, 0x30, 0x0e, 0x30, 0x0c, 0x06, 0x08, 0x2b,
+        0x06, 0x01, 0x02, 0x01, 0x01, 0x04, 0x00, 0x05,
+        0x00,
+    ];
+    // get-response for sysContact.0, request-id 1
+    const GET_RESPONSE: &[u8] = &[
+        0x30, 0x2c, 0x02, 0x01, 0x01, 0x04, 0x07, 0x70,
+        0x72, 0x69, 0x76, 0x61, 0x74, 0x65, 0xa2, 0x1e,
+        0x02, 0x01, 0x01, 0x02, 0x01, 0x00, 0x02, 0x01,
+        0x00, 0x30, 0x13, 0x30, 0x11, 0x06, 0x08, 0x2b,
+        0x06, 0x01, 0x02, 0x01, 0x01, 0x04, 0x00, 0x04,
+        0x05, 0x61, 0x6c, 0x69, 0x63, 0x65,
+    ];
+    // SNMPv2 trap linkDown
+    const TRAP_V2: &[u8] = &[
+        0x30, 0x40, 0x02, 0x01, 0x01, 0x04, 0x06, 0x70,
+        0x75, 0x62, 0x6c, 0x69, 0x63, 0xa7, 0x33, 0x02,
+        0x01, 0x02, 0x02, 0x01, 0x00, 0x02, 0x01, 0x00,
+        0x30, 0x28, 0x30, 0x0d, 0x06, 0x08, 0x2b, 0x06,
+        0x01, 0x02, 0x01, 0x01, 0x03, 0x00, 0x43, 0x01,
+        0x64, 0x30, 0x17, 0x06, 0x0a, 0x2b, 0x06, 0x01,
+        0x06, 0x03, 0x01, 0x01, 0x04, 0x01, 0x00, 0x06,
+        0x09, 0x2b, 0x06, 0x01, 0x06, 0x03, 0x01, 0x01,
+        0x05, 0x03,
+    ];
+
+    #[test]
+    fn test_snmp_request_response() {
+        let mut state = SNMPState::new();
+        assert_eq!(state.parse(GET_REQUEST, STREAM_TOSERVER), 0);
+        assert_eq!(state.transactions.len(), 1);
+        assert!(!state.transactions[0].response_done);
+        assert_eq!(state.parse(GET_RESPONSE, STREAM_TOCLIENT), 0);
+        assert_eq!(state.transactions.len(), 1);
+        let tx = &state.transactions[0];
+        assert!(tx.response_done);
+        assert_eq!(tx.request_id, Some(1));
+        assert_eq!(tx.pdu_info(STREAM_TOSERVER).unwrap().pdu_type, PduType::GetRequest);
+        assert_eq!(tx.pdu_info(STREAM_TOCLIENT).unwrap().pdu_type, PduType::Response);
+        // unsolicited response
+        assert_eq!(state.parse(GET_RESPONSE, STREAM_TOCLIENT), 0);
+        assert_eq!(state.transactions.len(), 2);
+        let tx = &state.transactions[1];
+        assert!(tx.response_done);
+        assert_eq!(tx.pdu_info(STREAM_TOCLIENT).unwrap().pdu_type, PduType::Response);
+        assert!(tx.pdu_info(STREAM_TOSERVER).is_none());
+    }
+
+    #[test]
+    fn test_snmp_trap_v2() {
+        let mut state = SNMPState::new();
+        assert_eq!(state.parse(TRAP_V2, STREAM_TOSERVER), 0);
+        let tx = &state.transactions[0];
+        assert!(tx.response_done);
+        let info = tx.info.as_ref().unwrap();
+        assert_eq!(info.pdu_type, PduType::TrapV2);
+        assert_eq!(info.trap_oid.as_ref().map(|o| o.to_string()),
+                   Some("1.3.6.1.6.3.1.1.5.3".to_string()));
+    }
+
+    #[test]
+    fn test_snmp_tcp() {
+        let mut buf = GET_REQUEST.to_vec();
+        buf.extend_from_slice(TRAP_V2);
+        let mut state = SNMPState::new();
+        // first message and the start of the second one
+        assert_eq!(state.parse_tcp(&buf[..GET_REQUEST.len() + 10], STREAM_TOSERVER), 0);
+        assert_eq!(state.transactions.len(), 1);
+        assert_eq!(state.parse_tcp(&buf[GET_REQUEST.len() + 10..], STREAM_TOSERVER), 0);
+        assert_eq!(state.transactions.len(), 2);
+        // header split
+        assert_eq!(state.parse_tcp(&GET_RESPONSE[..1], STREAM_TOCLIENT), 0);
+        assert_eq!(state.parse_tcp(&GET_RESPONSE[1..], STREAM_TOCLIENT), 0);
+        assert!(state.transactions[0].response_done);
+        // not a SEQUENCE
+        assert_eq!(state.parse_tcp(&[0x04, 0x01, 0x00], STREAM_TOSERVER), -1);
+    }
+
+    #[test]
+    fn test_snmp_set_previous_value() {
+        // get-response, then set-request for sysContact.0
+        let set = [
+            0x30, 0x2a, 0x02, 0x01, 0x01, 0x04, 0x07, 0x70,
+            0x72, 0x69, 0x76, 0x61, 0x74, 0x65, 0xa3, 0x1c,
+            0x02, 0x01, 0x02, 0x02, 0x01, 0x00, 0x02, 0x01,
+            0x00, 0x30, 0x11, 0x30, 0x0f, 0x06, 0x08, 0x2b,
+            0x06, 0x01, 0x02, 0x01, 0x01, 0x04, 0x00, 0x04,
+            0x03, 0x62, 0x6f, 0x62,
+        ];
+        let mut state = SNMPState::new();
+        assert_eq!(state.parse(GET_RESPONSE, STREAM_TOCLIENT), 0);
+        assert_eq!(state.parse(&set, STREAM_TOSERVER), 0);
+        let tx = state.transactions.last().unwrap();
+        let info = tx.info.as_ref().unwrap();
+        assert_eq!(info.pdu_type, PduType::SetRequest);
+        assert_eq!(info.vars.len(), 1);
+        assert_eq!(info.vars[0].oid.to_string(), "1.3.6.1.2.1.1.4.0");
+        assert_eq!(info.vars[0].value, SNMPValue::String(b"bob".to_vec()));
+        assert_eq!(info.vars[0].previous, Some(SNMPValue::String(b"alice".to_vec())));
+    }
+
+    #[test]
+    fn test_snmp_event_info() {
+        let mut event_id: std::os::raw::c_int = 0;
+        let mut event_type: crate::core::AppLayerEventType = 0;
+        let name = std::ffi::CString::new("malformed_data").unwrap();
+        assert_eq!(SNMPEvent::get_event_info(name.as_ptr(), &mut event_id, &mut event_type), 0);
+        assert_eq!(event_id, SNMPEvent::MalformedData as std::os::raw::c_int);
+
+        // An unknown name fails, so the rule using it fails to load.
+        let name = std::ffi::CString::new("no_such_event").unwrap();
+        assert_eq!(SNMPEvent::get_event_info(name.as_ptr(), &mut event_id, &mut event_type), -1);
+    }
 }
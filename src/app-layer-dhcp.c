@@ -26,10 +26,12 @@
 #include "app-layer-parser.h"
 #include "app-layer-dhcp.h"
 #include "rust-dhcp-dhcp-gen.h"
+#include "rust-dhcp-dhcpv6-gen.h"
 
 void RegisterDHCPParsers(void)
 {
     rs_dhcp_register_parser();
+    rs_dhcpv6_register_parser();
 #ifdef UNITTESTS
     AppLayerParserRegisterProtocolUnittests(IPPROTO_TCP, ALPROTO_DHCP,
         DHCPParserRegisterTests);
@@ -27,4 +27,7 @@
 void RegisterDHCPParsers(void);
 void DHCPParserRegisterTests(void);
 
+/** Opaque Rust types. */
+typedef struct DHCPTransaction_ DHCPTransaction;
+
 #endif /* __APP_LAYER_DHCP_H__ */
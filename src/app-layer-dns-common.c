@@ -23,49 +23,18 @@
 
 #include "suricata-common.h"
 #include "app-layer-dns-common.h"
+#include "rust-dns-dns-gen.h"
 
-SCEnumCharMap dns_decoder_event_table[ ] = {
-    { "UNSOLLICITED_RESPONSE",      DNS_DECODER_EVENT_UNSOLLICITED_RESPONSE, },
-    { "MALFORMED_DATA",             DNS_DECODER_EVENT_MALFORMED_DATA, },
-    { "NOT_A_REQUEST",              DNS_DECODER_EVENT_NOT_A_REQUEST, },
-    { "NOT_A_RESPONSE",             DNS_DECODER_EVENT_NOT_A_RESPONSE, },
-    { "Z_FLAG_SET",                 DNS_DECODER_EVENT_Z_FLAG_SET, },
-    { "FLOODED",                    DNS_DECODER_EVENT_FLOODED, },
-    { "STATE_MEMCAP_REACHED",       DNS_DECODER_EVENT_STATE_MEMCAP_REACHED, },
-
-    { NULL,                         -1 },
-};
-
-int DNSStateGetEventInfo(const char *event_name,
-                         int *event_id, AppLayerEventType *event_type)
+static int DNSStateGetEventInfo(const char *event_name,
+                                int *event_id, AppLayerEventType *event_type)
 {
-    *event_id = SCMapEnumNameToValue(event_name, dns_decoder_event_table);
-    if (*event_id == -1) {
-        SCLogError(SC_ERR_INVALID_ENUM_MAP, "event \"%s\" not present in "
-                   "dns's enum map table.",  event_name);
-        /* this should be treated as fatal */
-        return -1;
-    }
-
-    *event_type = APP_LAYER_EVENT_TYPE_TRANSACTION;
-
-    return 0;
+    return rs_dns_state_get_event_info(event_name, event_id, event_type);
 }
 
-int DNSStateGetEventInfoById(int event_id, const char **event_name,
-                             AppLayerEventType *event_type)
+static int DNSStateGetEventInfoById(int event_id, const char **event_name,
+                                    AppLayerEventType *event_type)
 {
-    *event_name = SCMapEnumValueToName(event_id, dns_decoder_event_table);
-    if (*event_name == NULL) {
-        SCLogError(SC_ERR_INVALID_ENUM_MAP, "event \"%d\" not present in "
-                   "dns's enum map table.",  event_id);
-        /* this should be treated as fatal */
-        return -1;
-    }
-
-    *event_type = APP_LAYER_EVENT_TYPE_TRANSACTION;
-
-    return 0;
+    return rs_dns_state_get_event_info_by_id(event_id, event_name, event_type);
 }
 
 void DNSAppLayerRegisterGetEventInfo(uint8_t ipproto, AppProto alproto)
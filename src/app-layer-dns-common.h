@@ -110,16 +110,6 @@
 #define DNS_RCODE_BADALG        21
 #define DNS_RCODE_BADTRUNC      22
 
-enum {
-    DNS_DECODER_EVENT_UNSOLLICITED_RESPONSE,
-    DNS_DECODER_EVENT_MALFORMED_DATA,
-    DNS_DECODER_EVENT_NOT_A_REQUEST,
-    DNS_DECODER_EVENT_NOT_A_RESPONSE,
-    DNS_DECODER_EVENT_Z_FLAG_SET,
-    DNS_DECODER_EVENT_FLOODED,
-    DNS_DECODER_EVENT_STATE_MEMCAP_REACHED,
-};
-
 /** Opaque Rust types. */
 typedef struct RSDNSState_ RSDNSState;
 typedef struct RSDNSTransaction_ RSDNSTransaction;
@@ -134,10 +124,6 @@ typedef struct DNSHeader_ {
     uint16_t additional_rr;
 } __attribute__((__packed__)) DNSHeader;
 
-int DNSStateGetEventInfo(const char *event_name,
-                         int *event_id, AppLayerEventType *event_type);
-int DNSStateGetEventInfoById(int event_id, const char **event_name,
-                             AppLayerEventType *event_type);
 void DNSAppLayerRegisterGetEventInfo(uint8_t ipproto, AppProto alproto);
 void DNSAppLayerRegisterGetEventInfoById(uint8_t ipproto, AppProto alproto);
 
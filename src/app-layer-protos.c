@@ -96,6 +96,9 @@ const char *AppProtoToString(AppProto alproto)
         case ALPROTO_DHCP:
             proto_name = "dhcp";
             break;
+        case ALPROTO_DHCPV6:
+            proto_name = "dhcpv6";
+            break;
         case ALPROTO_SNMP:
             proto_name = "snmp";
             break;
@@ -148,6 +151,7 @@ AppProto StringToAppProto(const char *proto_name)
     if (strcmp(proto_name,"ikev2")==0) return ALPROTO_IKEV2;
     if (strcmp(proto_name,"krb5")==0) return ALPROTO_KRB5;
     if (strcmp(proto_name,"dhcp")==0) return ALPROTO_DHCP;
+    if (strcmp(proto_name,"dhcpv6")==0) return ALPROTO_DHCPV6;
     if (strcmp(proto_name,"snmp")==0) return ALPROTO_SNMP;
     if (strcmp(proto_name,"sip")==0) return ALPROTO_SIP;
     if (strcmp(proto_name,"template")==0) return ALPROTO_TEMPLATE;
@@ -49,6 +49,7 @@ enum AppProtoEnum {
     ALPROTO_IKEV2,
     ALPROTO_KRB5,
     ALPROTO_DHCP,
+    ALPROTO_DHCPV6,
     ALPROTO_SNMP,
     ALPROTO_SIP,
     ALPROTO_TEMPLATE,
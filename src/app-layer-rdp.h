@@ -24,6 +24,8 @@
 #ifndef __APP_LAYER_RDP_H__
 #define __APP_LAYER_RDP_H__
 
+typedef struct RdpTransaction_ RdpTransaction;
+
 void RegisterRdpParsers(void);
 void RdpParserRegisterTests(void);
 
@@ -20,7 +20,8 @@
  *
  * \author Pierre Chifflier <chifflier@wzdftpd.net>
  *
- * Parser for SNMP v2c/v3 application layer running on UDP port 161.
+ * Parser for SNMP v1/v2c/v3 application layer running on UDP and TCP
+ * ports 161 and 162.
  *
  */
 
@@ -0,0 +1,113 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+/**
+ * \file
+ *
+ * Set up of the "dhcp.fingerprint" keyword to allow content
+ * inspections on the DHCP client fingerprint.
+ */
+
+#include "suricata-common.h"
+#include "conf.h"
+#include "detect.h"
+#include "detect-parse.h"
+#include "detect-engine.h"
+#include "detect-engine-mpm.h"
+#include "detect-engine-prefilter.h"
+#include "detect-engine-content-inspection.h"
+#include "detect-dhcp-fingerprint.h"
+#include "app-layer-parser.h"
+#include "app-layer-dhcp.h"
+
+#include "rust-dhcp-detect-gen.h"
+
+static int DetectDHCPFingerprintSetup(DetectEngineCtx *, Signature *,
+    const char *);
+static InspectionBuffer *GetData(DetectEngineThreadCtx *det_ctx,
+       const DetectEngineTransforms *transforms,
+       Flow *f, const uint8_t flow_flags,
+       void *txv, const int list_id);
+#ifdef UNITTESTS
+static void DetectDHCPFingerprintRegisterTests(void);
+#endif
+static int g_dhcp_fingerprint_buffer_id = 0;
+
+void DetectDHCPFingerprintRegister(void)
+{
+    sigmatch_table[DETECT_AL_DHCP_FINGERPRINT].name = "dhcp.fingerprint";
+    sigmatch_table[DETECT_AL_DHCP_FINGERPRINT].desc =
+        "sticky buffer to match on the DHCP client fingerprint";
+    sigmatch_table[DETECT_AL_DHCP_FINGERPRINT].Setup =
+        DetectDHCPFingerprintSetup;
+    sigmatch_table[DETECT_AL_DHCP_FINGERPRINT].url = DOC_URL DOC_VERSION "/rules/dhcp-keywords.html#dhcp-fingerprint";
+#ifdef UNITTESTS
+    sigmatch_table[DETECT_AL_DHCP_FINGERPRINT].RegisterTests =
+        DetectDHCPFingerprintRegisterTests;
+#endif
+
+    sigmatch_table[DETECT_AL_DHCP_FINGERPRINT].flags |= SIGMATCH_NOOPT|SIGMATCH_INFO_STICKY_BUFFER;
+
+    /* register inspect engines */
+    DetectAppLayerInspectEngineRegister2("dhcp.fingerprint",
+            ALPROTO_DHCP, SIG_FLAG_TOSERVER, 0,
+            DetectEngineInspectBufferGeneric, GetData);
+    DetectAppLayerMpmRegister2("dhcp.fingerprint", SIG_FLAG_TOSERVER, 2,
+            PrefilterGenericMpmRegister, GetData, ALPROTO_DHCP, 0);
+
+    DetectBufferTypeSetDescriptionByName("dhcp.fingerprint", "DHCP client fingerprint");
+
+    g_dhcp_fingerprint_buffer_id = DetectBufferTypeGetByName("dhcp.fingerprint");
+}
+
+static int DetectDHCPFingerprintSetup(DetectEngineCtx *de_ctx, Signature *s,
+    const char *str)
+{
+    if (DetectBufferSetActiveList(s, g_dhcp_fingerprint_buffer_id) < 0)
+        return -1;
+
+    if (DetectSignatureSetAppProto(s, ALPROTO_DHCP) != 0)
+        return -1;
+
+    return 0;
+}
+
+static InspectionBuffer *GetData(DetectEngineThreadCtx *det_ctx,
+        const DetectEngineTransforms *transforms, Flow *f,
+        const uint8_t flow_flags, void *txv, const int list_id)
+{
+    InspectionBuffer *buffer = InspectionBufferGet(det_ctx, list_id);
+    if (buffer->inspect == NULL) {
+        uint32_t data_len = 0;
+        const uint8_t *data = NULL;
+
+        if (rs_dhcp_tx_get_fingerprint(txv, &data, &data_len) != 1)
+            return NULL;
+        if (data == NULL || data_len == 0) {
+            return NULL;
+        }
+
+        InspectionBufferSetup(buffer, data, data_len);
+        InspectionBufferApplyTransforms(buffer, transforms);
+    }
+
+    return buffer;
+}
+
+#ifdef UNITTESTS
+#include "tests/detect-dhcp-fingerprint.c"
+#endif /* UNITTESTS */
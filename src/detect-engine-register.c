@@ -191,6 +191,25 @@
 #include "detect-snmp-version.h"
 #include "detect-snmp-community.h"
 #include "detect-snmp-pdu_type.h"
+#include "detect-snmp-oid.h"
+#include "detect-snmp-usm.h"
+#include "detect-snmp-security-level.h"
+#include "detect-dhcp-fingerprint.h"
+#include "detect-ntp-mode.h"
+#include "detect-ntp-stratum.h"
+#include "detect-ntp-version.h"
+#include "detect-ikev2-exchtype.h"
+#include "detect-ikev2-init-spi.h"
+#include "detect-ikev2-nb-proposals.h"
+#include "detect-ikev2-notify.h"
+#include "detect-ikev2-resp-spi.h"
+#include "detect-ikev2-sa-attribute.h"
+#include "detect-rdp-channel.h"
+#include "detect-rdp-cookie.h"
+#include "detect-rdp-client-name.h"
+#include "detect-rdp-client-build.h"
+#include "detect-rdp-keyboard-layout.h"
+#include "detect-rdp-requested-protocols.h"
 #include "detect-template-buffer.h"
 #include "detect-bypass.h"
 #include "detect-ftpdata.h"
@@ -563,6 +582,25 @@ void SigTableSetup(void)
     DetectSNMPVersionRegister();
     DetectSNMPCommunityRegister();
     DetectSNMPPduTypeRegister();
+    DetectSNMPOidRegister();
+    DetectSNMPUsmRegister();
+    DetectSNMPSecurityLevelRegister();
+    DetectDHCPFingerprintRegister();
+    DetectNTPModeRegister();
+    DetectNTPStratumRegister();
+    DetectNTPVersionRegister();
+    DetectIKEv2ExchTypeRegister();
+    DetectIKEv2InitSpiRegister();
+    DetectIKEv2NbProposalsRegister();
+    DetectIKEv2NotifyRegister();
+    DetectIKEv2RespSpiRegister();
+    DetectIKEv2SaAttributeRegister();
+    DetectRdpChannelRegister();
+    DetectRdpCookieRegister();
+    DetectRdpClientNameRegister();
+    DetectRdpClientBuildRegister();
+    DetectRdpKeyboardLayoutRegister();
+    DetectRdpRequestedProtocolsRegister();
     DetectTemplateBufferRegister();
     DetectBypassRegister();
 
@@ -247,6 +247,26 @@ enum DetectKeywordId {
     DETECT_AL_SNMP_VERSION,
     DETECT_AL_SNMP_COMMUNITY,
     DETECT_AL_SNMP_PDU_TYPE,
+    DETECT_AL_SNMP_OID,
+    DETECT_AL_SNMP_USM,
+    DETECT_AL_SNMP_SECURITY_LEVEL,
+    DETECT_AL_DHCP_FINGERPRINT,
+    DETECT_AL_NTP_MODE,
+    DETECT_AL_NTP_STRATUM,
+    DETECT_AL_NTP_VERSION,
+    DETECT_AL_IKEV2_EXCHTYPE,
+    DETECT_AL_IKEV2_INIT_SPI,
+    DETECT_AL_IKEV2_NB_PROPOSALS,
+    DETECT_AL_IKEV2_NOTIFY,
+    DETECT_AL_IKEV2_RESP_SPI,
+    DETECT_AL_IKEV2_CHOSEN_SA_ATTRIBUTE,
+    DETECT_AL_IKEV2_PROPOSED_SA_ATTRIBUTE,
+    DETECT_AL_RDP_CHANNEL,
+    DETECT_AL_RDP_COOKIE,
+    DETECT_AL_RDP_CLIENT_NAME,
+    DETECT_AL_RDP_CLIENT_BUILD,
+    DETECT_AL_RDP_KEYBOARD_LAYOUT,
+    DETECT_AL_RDP_REQUESTED_PROTOCOLS,
     DETECT_AL_TEMPLATE_BUFFER,
 
     DETECT_BYPASS,
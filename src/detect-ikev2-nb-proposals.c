@@ -0,0 +1,311 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+/**
+ * \file
+ *
+ * Implements the ikev2.nb_proposals keyword
+ */
+
+#include "suricata-common.h"
+#include "conf.h"
+#include "detect.h"
+#include "detect-parse.h"
+#include "detect-engine.h"
+#include "detect-engine-content-inspection.h"
+#include "detect-ikev2-nb-proposals.h"
+#include "app-layer-parser.h"
+
+#include "rust-ikev2-detect-gen.h"
+
+/**
+ *   [ikev2.nb_proposals]:[<|>|<=|>=]<nb_proposals>;
+ */
+#define PARSE_REGEX "^\\s*(<=|>=|<|>)?\\s*([0-9]+)\\s*$"
+static pcre *parse_regex;
+static pcre_extra *parse_regex_study;
+
+enum DetectIKEv2NbProposalsMode {
+    PROCEDURE_EQ = 1, /* equal */
+    PROCEDURE_LT, /* less than */
+    PROCEDURE_LE, /* less than */
+    PROCEDURE_GT, /* greater than */
+    PROCEDURE_GE, /* greater than */
+};
+
+typedef struct DetectIKEv2NbProposalsData_ {
+    uint32_t nb_proposals;
+    enum DetectIKEv2NbProposalsMode mode;
+} DetectIKEv2NbProposalsData;
+
+static DetectIKEv2NbProposalsData *DetectIKEv2NbProposalsParse (const char *);
+static int DetectIKEv2NbProposalsSetup (DetectEngineCtx *, Signature *s, const char *str);
+static void DetectIKEv2NbProposalsFree(void *);
+#ifdef UNITTESTS
+static void DetectIKEv2NbProposalsRegisterTests(void);
+#endif
+static int g_ikev2_nb_proposals_buffer_id = 0;
+
+static int DetectEngineInspectIKEv2NbProposalsGeneric(ThreadVars *tv,
+        DetectEngineCtx *de_ctx, DetectEngineThreadCtx *det_ctx,
+        const Signature *s, const SigMatchData *smd,
+        Flow *f, uint8_t flags, void *alstate,
+        void *txv, uint64_t tx_id);
+
+static int DetectIKEv2NbProposalsMatch (DetectEngineThreadCtx *, Flow *,
+                                   uint8_t, void *, void *, const Signature *,
+                                   const SigMatchCtx *);
+
+/**
+ * \brief Registration function for ikev2.nb_proposals keyword.
+ */
+void DetectIKEv2NbProposalsRegister (void)
+{
+    sigmatch_table[DETECT_AL_IKEV2_NB_PROPOSALS].name = "ikev2.nb_proposals";
+    sigmatch_table[DETECT_AL_IKEV2_NB_PROPOSALS].desc = "match number of proposals of the IKE initiator";
+    sigmatch_table[DETECT_AL_IKEV2_NB_PROPOSALS].url = DOC_URL DOC_VERSION "/rules/ikev2-keywords.html#ikev2-nb-proposals";
+    sigmatch_table[DETECT_AL_IKEV2_NB_PROPOSALS].Match = NULL;
+    sigmatch_table[DETECT_AL_IKEV2_NB_PROPOSALS].AppLayerTxMatch = DetectIKEv2NbProposalsMatch;
+    sigmatch_table[DETECT_AL_IKEV2_NB_PROPOSALS].Setup = DetectIKEv2NbProposalsSetup;
+    sigmatch_table[DETECT_AL_IKEV2_NB_PROPOSALS].Free = DetectIKEv2NbProposalsFree;
+#ifdef UNITTESTS
+    sigmatch_table[DETECT_AL_IKEV2_NB_PROPOSALS].RegisterTests = DetectIKEv2NbProposalsRegisterTests;
+#endif
+    sigmatch_table[DETECT_AL_IKEV2_NB_PROPOSALS].flags |= SIGMATCH_NOOPT;
+
+    DetectSetupParseRegexes(PARSE_REGEX, &parse_regex, &parse_regex_study);
+
+    DetectAppLayerInspectEngineRegister("ikev2.nb_proposals",
+            ALPROTO_IKEV2, SIG_FLAG_TOSERVER, 0,
+            DetectEngineInspectIKEv2NbProposalsGeneric);
+
+    DetectAppLayerInspectEngineRegister("ikev2.nb_proposals",
+            ALPROTO_IKEV2, SIG_FLAG_TOCLIENT, 0,
+            DetectEngineInspectIKEv2NbProposalsGeneric);
+
+    g_ikev2_nb_proposals_buffer_id = DetectBufferTypeGetByName("ikev2.nb_proposals");
+}
+
+static int DetectEngineInspectIKEv2NbProposalsGeneric(ThreadVars *tv,
+        DetectEngineCtx *de_ctx, DetectEngineThreadCtx *det_ctx,
+        const Signature *s, const SigMatchData *smd,
+        Flow *f, uint8_t flags, void *alstate,
+        void *txv, uint64_t tx_id)
+{
+    return DetectEngineInspectGenericList(tv, de_ctx, det_ctx, s, smd,
+                                          f, flags, alstate, txv, tx_id);
+}
+
+static inline int
+ValueMatch(const uint32_t value,
+        enum DetectIKEv2NbProposalsMode mode, uint32_t ref_value)
+{
+    switch (mode) {
+        case PROCEDURE_EQ:
+            if (value == ref_value)
+                SCReturnInt(1);
+            break;
+        case PROCEDURE_LT:
+            if (value < ref_value)
+                SCReturnInt(1);
+            break;
+        case PROCEDURE_LE:
+            if (value <= ref_value)
+                SCReturnInt(1);
+            break;
+        case PROCEDURE_GT:
+            if (value > ref_value)
+                SCReturnInt(1);
+            break;
+        case PROCEDURE_GE:
+            if (value >= ref_value)
+                SCReturnInt(1);
+            break;
+    }
+    SCReturnInt(0);
+}
+
+/**
+ * \internal
+ * \brief Function to match number of proposals of a TX
+ *
+ * \param t       Pointer to thread vars.
+ * \param det_ctx Pointer to the pattern matcher thread.
+ * \param f       Pointer to the current flow.
+ * \param flags   Flags.
+ * \param state   App layer state.
+ * \param s       Pointer to the Signature.
+ * \param m       Pointer to the sigmatch that we will cast into
+ *                DetectIKEv2NbProposalsData.
+ *
+ * \retval 0 no match.
+ * \retval 1 match.
+ */
+static int DetectIKEv2NbProposalsMatch (DetectEngineThreadCtx *det_ctx,
+                                   Flow *f, uint8_t flags, void *state,
+                                   void *txv, const Signature *s,
+                                   const SigMatchCtx *ctx)
+{
+    SCEnter();
+
+    const DetectIKEv2NbProposalsData *dd = (const DetectIKEv2NbProposalsData *)ctx;
+    uint32_t value;
+    if (rs_ikev2_state_get_nb_proposals(state, txv, &value) != 1)
+        SCReturnInt(0);
+    SCLogDebug("nb_proposals %u mode %u ref %u",
+            value, dd->mode, dd->nb_proposals);
+    if (ValueMatch(value, dd->mode, dd->nb_proposals))
+        SCReturnInt(1);
+    SCReturnInt(0);
+}
+
+/**
+ * \internal
+ * \brief Function to parse options passed via ikev2.nb_proposals keywords.
+ *
+ * \param rawstr Pointer to the user provided options.
+ *
+ * \retval dd pointer to DetectIKEv2NbProposalsData on success.
+ * \retval NULL on failure.
+ */
+static DetectIKEv2NbProposalsData *DetectIKEv2NbProposalsParse (const char *rawstr)
+{
+    DetectIKEv2NbProposalsData *dd = NULL;
+#define MAX_SUBSTRINGS 30
+    int ret = 0, res = 0;
+    int ov[MAX_SUBSTRINGS];
+    char mode[2] = "";
+    char value1[20] = "";
+    char *endptr = NULL;
+
+    ret = pcre_exec(parse_regex, parse_regex_study, rawstr, strlen(rawstr), 0,
+                    0, ov, MAX_SUBSTRINGS);
+    if (ret < 3 || ret > 5) {
+        SCLogError(SC_ERR_PCRE_MATCH, "Parse error %s", rawstr);
+        goto error;
+    }
+
+    res = pcre_copy_substring((char *)rawstr, ov, MAX_SUBSTRINGS, 1, mode,
+                              sizeof(mode));
+    if (res < 0) {
+        SCLogError(SC_ERR_PCRE_GET_SUBSTRING, "pcre_copy_substring failed");
+        goto error;
+    }
+
+    res = pcre_copy_substring((char *)rawstr, ov, MAX_SUBSTRINGS, 2, value1,
+                              sizeof(value1));
+    if (res < 0) {
+        SCLogError(SC_ERR_PCRE_GET_SUBSTRING, "pcre_copy_substring failed");
+        goto error;
+    }
+
+    dd = SCCalloc(1, sizeof(DetectIKEv2NbProposalsData));
+    if (unlikely(dd == NULL))
+        goto error;
+
+    if (strlen(mode) == 1) {
+        if (mode[0] == '<')
+            dd->mode = PROCEDURE_LT;
+        else if (mode[0] == '>')
+            dd->mode = PROCEDURE_GT;
+    } else if (strlen(mode) == 2) {
+        if (strcmp(mode, "<=") == 0)
+            dd->mode = PROCEDURE_LE;
+        if (strcmp(mode, ">=") == 0)
+            dd->mode = PROCEDURE_GE;
+    }
+
+    if (dd->mode == 0) {
+        dd->mode = PROCEDURE_EQ;
+    }
+
+    /* set the first value */
+    dd->nb_proposals = strtoul(value1, &endptr, 10);
+    if (endptr == NULL || *endptr != '\0') {
+        SCLogError(SC_ERR_INVALID_SIGNATURE, "invalid character as arg "
+                   "to ikev2.nb_proposals keyword");
+        goto error;
+    }
+
+    return dd;
+
+error:
+    if (dd)
+        SCFree(dd);
+    return NULL;
+}
+
+
+
+/**
+ * \brief Function to add the parsed ikev2.nb_proposals field into the current signature.
+ *
+ * \param de_ctx Pointer to the Detection Engine Context.
+ * \param s      Pointer to the Current Signature.
+ * \param rawstr Pointer to the user provided flags options.
+ *
+ * \retval 0 on Success.
+ * \retval -1 on Failure.
+ */
+static int DetectIKEv2NbProposalsSetup (DetectEngineCtx *de_ctx, Signature *s,
+                                   const char *rawstr)
+{
+    DetectIKEv2NbProposalsData *dd = NULL;
+    SigMatch *sm = NULL;
+
+    if (DetectSignatureSetAppProto(s, ALPROTO_IKEV2) != 0)
+        return -1;
+
+    dd = DetectIKEv2NbProposalsParse(rawstr);
+    if (dd == NULL) {
+        SCLogError(SC_ERR_INVALID_ARGUMENT,"Parsing \'%s\' failed", rawstr);
+        goto error;
+    }
+
+    /* okay so far so good, lets get this into a SigMatch
+     * and put it in the Signature. */
+    sm = SigMatchAlloc();
+    if (sm == NULL)
+        goto error;
+
+    sm->type = DETECT_AL_IKEV2_NB_PROPOSALS;
+    sm->ctx = (void *)dd;
+
+    SCLogDebug("ikev2.nb_proposals %u", dd->nb_proposals);
+    SigMatchAppendSMToList(s, sm, g_ikev2_nb_proposals_buffer_id);
+    return 0;
+
+error:
+    DetectIKEv2NbProposalsFree(dd);
+    return -1;
+}
+
+/**
+ * \internal
+ * \brief Function to free memory associated with DetectIKEv2NbProposalsData.
+ *
+ * \param de_ptr Pointer to DetectIKEv2NbProposalsData.
+ */
+static void DetectIKEv2NbProposalsFree(void *ptr)
+{
+    SCFree(ptr);
+}
+
+
+#ifdef UNITTESTS
+#include "tests/detect-ikev2-nb-proposals.c"
+#endif /* UNITTESTS */
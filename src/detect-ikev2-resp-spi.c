@@ -0,0 +1,104 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+/**
+ * \file
+ *
+ * Set up of the "ikev2.resp_spi" keyword to allow content
+ * inspections on the IKE responder SPI, as a 16 characters hex string.
+ */
+
+#include "suricata-common.h"
+#include "conf.h"
+#include "detect.h"
+#include "detect-parse.h"
+#include "detect-engine.h"
+#include "detect-engine-mpm.h"
+#include "detect-engine-prefilter.h"
+#include "detect-engine-content-inspection.h"
+#include "detect-ikev2-resp-spi.h"
+#include "app-layer-parser.h"
+
+#include "rust-ikev2-detect-gen.h"
+
+static int DetectIKEv2RespSpiSetup(DetectEngineCtx *, Signature *,
+    const char *);
+static InspectionBuffer *GetData(DetectEngineThreadCtx *det_ctx,
+       const DetectEngineTransforms *transforms,
+       Flow *f, const uint8_t flow_flags,
+       void *txv, const int list_id);
+static int g_ikev2_resp_spi_buffer_id = 0;
+
+void DetectIKEv2RespSpiRegister(void)
+{
+    sigmatch_table[DETECT_AL_IKEV2_RESP_SPI].name = "ikev2.resp_spi";
+    sigmatch_table[DETECT_AL_IKEV2_RESP_SPI].desc =
+        "sticky buffer to match on the IKE responder SPI";
+    sigmatch_table[DETECT_AL_IKEV2_RESP_SPI].Setup =
+        DetectIKEv2RespSpiSetup;
+    sigmatch_table[DETECT_AL_IKEV2_RESP_SPI].url = DOC_URL DOC_VERSION "/rules/ikev2-keywords.html#ikev2-resp-spi";
+
+    sigmatch_table[DETECT_AL_IKEV2_RESP_SPI].flags |= SIGMATCH_NOOPT|SIGMATCH_INFO_STICKY_BUFFER;
+
+    /* register inspect engines */
+    DetectAppLayerInspectEngineRegister2("ikev2.resp_spi",
+            ALPROTO_IKEV2, SIG_FLAG_TOSERVER, 0,
+            DetectEngineInspectBufferGeneric, GetData);
+    DetectAppLayerInspectEngineRegister2("ikev2.resp_spi",
+            ALPROTO_IKEV2, SIG_FLAG_TOCLIENT, 0,
+            DetectEngineInspectBufferGeneric, GetData);
+    DetectAppLayerMpmRegister2("ikev2.resp_spi", SIG_FLAG_TOSERVER, 2,
+            PrefilterGenericMpmRegister, GetData, ALPROTO_IKEV2, 0);
+    DetectAppLayerMpmRegister2("ikev2.resp_spi", SIG_FLAG_TOCLIENT, 2,
+            PrefilterGenericMpmRegister, GetData, ALPROTO_IKEV2, 0);
+
+    DetectBufferTypeSetDescriptionByName("ikev2.resp_spi", "IKE responder SPI");
+
+    g_ikev2_resp_spi_buffer_id = DetectBufferTypeGetByName("ikev2.resp_spi");
+}
+
+static int DetectIKEv2RespSpiSetup(DetectEngineCtx *de_ctx, Signature *s,
+    const char *str)
+{
+    if (DetectBufferSetActiveList(s, g_ikev2_resp_spi_buffer_id) < 0)
+        return -1;
+
+    if (DetectSignatureSetAppProto(s, ALPROTO_IKEV2) != 0)
+        return -1;
+
+    return 0;
+}
+
+static InspectionBuffer *GetData(DetectEngineThreadCtx *det_ctx,
+        const DetectEngineTransforms *transforms, Flow *f,
+        const uint8_t flow_flags, void *txv, const int list_id)
+{
+    InspectionBuffer *buffer = InspectionBufferGet(det_ctx, list_id);
+    if (buffer->inspect == NULL) {
+        uint64_t spi = 0;
+        char spi_str[17];
+
+        if (rs_ikev2_tx_get_resp_spi(txv, &spi) != 1)
+            return NULL;
+
+        snprintf(spi_str, sizeof(spi_str), "%016"PRIx64, spi);
+        InspectionBufferCopy(buffer, (uint8_t *)spi_str, strlen(spi_str));
+        InspectionBufferApplyTransforms(buffer, transforms);
+    }
+
+    return buffer;
+}
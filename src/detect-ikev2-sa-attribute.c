@@ -0,0 +1,312 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+/**
+ * \file
+ *
+ * Implements the ikev2.chosen_sa_attribute and ikev2.proposed_sa_attribute
+ * keywords
+ */
+
+#include "suricata-common.h"
+#include "conf.h"
+#include "detect.h"
+#include "detect-parse.h"
+#include "detect-engine.h"
+#include "detect-engine-content-inspection.h"
+#include "detect-ikev2-sa-attribute.h"
+#include "app-layer-parser.h"
+#include "util-byte.h"
+
+#include "rust-ikev2-detect-gen.h"
+
+/**
+ *   [ikev2.chosen_sa_attribute]:<attribute>=<value>;
+ *   [ikev2.proposed_sa_attribute]:<attribute>=<value>;
+ */
+#define PARSE_REGEX "^\\s*(alg_enc|alg_prf|alg_auth|alg_dh|alg_esn)\\s*=\\s*([0-9]+)\\s*$"
+static pcre *parse_regex;
+static pcre_extra *parse_regex_study;
+
+/* IKEv2 transform types, RFC 7296 section 3.3.2 */
+enum DetectIKEv2TransformType {
+    TRANSFORM_ENC = 1,
+    TRANSFORM_PRF,
+    TRANSFORM_AUTH,
+    TRANSFORM_DH,
+    TRANSFORM_ESN,
+};
+
+typedef struct DetectIKEv2SaAttributeData_ {
+    enum DetectIKEv2TransformType type;
+    uint32_t value;
+} DetectIKEv2SaAttributeData;
+
+static DetectIKEv2SaAttributeData *DetectIKEv2SaAttributeParse (const char *);
+static int DetectIKEv2ChosenSaAttributeSetup (DetectEngineCtx *, Signature *s, const char *str);
+static int DetectIKEv2ProposedSaAttributeSetup (DetectEngineCtx *, Signature *s, const char *str);
+static void DetectIKEv2SaAttributeFree(void *);
+#ifdef UNITTESTS
+static void DetectIKEv2SaAttributeRegisterTests(void);
+#endif
+static int g_ikev2_chosen_sa_attribute_buffer_id = 0;
+static int g_ikev2_proposed_sa_attribute_buffer_id = 0;
+
+static int DetectEngineInspectIKEv2SaAttributeGeneric(ThreadVars *tv,
+        DetectEngineCtx *de_ctx, DetectEngineThreadCtx *det_ctx,
+        const Signature *s, const SigMatchData *smd,
+        Flow *f, uint8_t flags, void *alstate,
+        void *txv, uint64_t tx_id);
+
+static int DetectIKEv2ChosenSaAttributeMatch (DetectEngineThreadCtx *, Flow *,
+        uint8_t, void *, void *, const Signature *, const SigMatchCtx *);
+static int DetectIKEv2ProposedSaAttributeMatch (DetectEngineThreadCtx *, Flow *,
+        uint8_t, void *, void *, const Signature *, const SigMatchCtx *);
+
+/**
+ * \brief Registration function for ikev2.chosen_sa_attribute and
+ *        ikev2.proposed_sa_attribute keywords.
+ */
+void DetectIKEv2SaAttributeRegister (void)
+{
+    sigmatch_table[DETECT_AL_IKEV2_CHOSEN_SA_ATTRIBUTE].name = "ikev2.chosen_sa_attribute";
+    sigmatch_table[DETECT_AL_IKEV2_CHOSEN_SA_ATTRIBUTE].desc = "match IKEv2 transform selected by the responder";
+    sigmatch_table[DETECT_AL_IKEV2_CHOSEN_SA_ATTRIBUTE].url = DOC_URL DOC_VERSION "/rules/ikev2-keywords.html#ikev2-chosen-sa-attribute";
+    sigmatch_table[DETECT_AL_IKEV2_CHOSEN_SA_ATTRIBUTE].Match = NULL;
+    sigmatch_table[DETECT_AL_IKEV2_CHOSEN_SA_ATTRIBUTE].AppLayerTxMatch = DetectIKEv2ChosenSaAttributeMatch;
+    sigmatch_table[DETECT_AL_IKEV2_CHOSEN_SA_ATTRIBUTE].Setup = DetectIKEv2ChosenSaAttributeSetup;
+    sigmatch_table[DETECT_AL_IKEV2_CHOSEN_SA_ATTRIBUTE].Free = DetectIKEv2SaAttributeFree;
+#ifdef UNITTESTS
+    sigmatch_table[DETECT_AL_IKEV2_CHOSEN_SA_ATTRIBUTE].RegisterTests = DetectIKEv2SaAttributeRegisterTests;
+#endif
+    sigmatch_table[DETECT_AL_IKEV2_CHOSEN_SA_ATTRIBUTE].flags |= SIGMATCH_NOOPT;
+
+    sigmatch_table[DETECT_AL_IKEV2_PROPOSED_SA_ATTRIBUTE].name = "ikev2.proposed_sa_attribute";
+    sigmatch_table[DETECT_AL_IKEV2_PROPOSED_SA_ATTRIBUTE].desc = "match IKEv2 transform proposed by the initiator";
+    sigmatch_table[DETECT_AL_IKEV2_PROPOSED_SA_ATTRIBUTE].url = DOC_URL DOC_VERSION "/rules/ikev2-keywords.html#ikev2-proposed-sa-attribute";
+    sigmatch_table[DETECT_AL_IKEV2_PROPOSED_SA_ATTRIBUTE].Match = NULL;
+    sigmatch_table[DETECT_AL_IKEV2_PROPOSED_SA_ATTRIBUTE].AppLayerTxMatch = DetectIKEv2ProposedSaAttributeMatch;
+    sigmatch_table[DETECT_AL_IKEV2_PROPOSED_SA_ATTRIBUTE].Setup = DetectIKEv2ProposedSaAttributeSetup;
+    sigmatch_table[DETECT_AL_IKEV2_PROPOSED_SA_ATTRIBUTE].Free = DetectIKEv2SaAttributeFree;
+    sigmatch_table[DETECT_AL_IKEV2_PROPOSED_SA_ATTRIBUTE].flags |= SIGMATCH_NOOPT;
+
+    DetectSetupParseRegexes(PARSE_REGEX, &parse_regex, &parse_regex_study);
+
+    DetectAppLayerInspectEngineRegister("ikev2.chosen_sa_attribute",
+            ALPROTO_IKEV2, SIG_FLAG_TOSERVER, 0,
+            DetectEngineInspectIKEv2SaAttributeGeneric);
+
+    DetectAppLayerInspectEngineRegister("ikev2.chosen_sa_attribute",
+            ALPROTO_IKEV2, SIG_FLAG_TOCLIENT, 0,
+            DetectEngineInspectIKEv2SaAttributeGeneric);
+
+    DetectAppLayerInspectEngineRegister("ikev2.proposed_sa_attribute",
+            ALPROTO_IKEV2, SIG_FLAG_TOSERVER, 0,
+            DetectEngineInspectIKEv2SaAttributeGeneric);
+
+    DetectAppLayerInspectEngineRegister("ikev2.proposed_sa_attribute",
+            ALPROTO_IKEV2, SIG_FLAG_TOCLIENT, 0,
+            DetectEngineInspectIKEv2SaAttributeGeneric);
+
+    g_ikev2_chosen_sa_attribute_buffer_id =
+        DetectBufferTypeGetByName("ikev2.chosen_sa_attribute");
+    g_ikev2_proposed_sa_attribute_buffer_id =
+        DetectBufferTypeGetByName("ikev2.proposed_sa_attribute");
+}
+
+static int DetectEngineInspectIKEv2SaAttributeGeneric(ThreadVars *tv,
+        DetectEngineCtx *de_ctx, DetectEngineThreadCtx *det_ctx,
+        const Signature *s, const SigMatchData *smd,
+        Flow *f, uint8_t flags, void *alstate,
+        void *txv, uint64_t tx_id)
+{
+    return DetectEngineInspectGenericList(tv, de_ctx, det_ctx, s, smd,
+                                          f, flags, alstate, txv, tx_id);
+}
+
+/**
+ * \internal
+ * \brief Function to match the transforms selected by the responder
+ *
+ * \retval 0 no match.
+ * \retval 1 match.
+ */
+static int DetectIKEv2ChosenSaAttributeMatch (DetectEngineThreadCtx *det_ctx,
+        Flow *f, uint8_t flags, void *state, void *txv,
+        const Signature *s, const SigMatchCtx *ctx)
+{
+    SCEnter();
+
+    const DetectIKEv2SaAttributeData *dd = (const DetectIKEv2SaAttributeData *)ctx;
+    SCLogDebug("chosen transform type %u value %u", dd->type, dd->value);
+    if (rs_ikev2_state_has_chosen_transform(state, dd->type, dd->value) == 1)
+        SCReturnInt(1);
+    SCReturnInt(0);
+}
+
+/**
+ * \internal
+ * \brief Function to match the transforms proposed by the initiator
+ *
+ * \retval 0 no match.
+ * \retval 1 match.
+ */
+static int DetectIKEv2ProposedSaAttributeMatch (DetectEngineThreadCtx *det_ctx,
+        Flow *f, uint8_t flags, void *state, void *txv,
+        const Signature *s, const SigMatchCtx *ctx)
+{
+    SCEnter();
+
+    const DetectIKEv2SaAttributeData *dd = (const DetectIKEv2SaAttributeData *)ctx;
+    SCLogDebug("proposed transform type %u value %u", dd->type, dd->value);
+    if (rs_ikev2_state_has_proposed_transform(state, dd->type, dd->value) == 1)
+        SCReturnInt(1);
+    SCReturnInt(0);
+}
+
+/**
+ * \internal
+ * \brief Function to parse options passed via ikev2.*_sa_attribute keywords.
+ *
+ * \param rawstr Pointer to the user provided options.
+ *
+ * \retval dd pointer to DetectIKEv2SaAttributeData on success.
+ * \retval NULL on failure.
+ */
+static DetectIKEv2SaAttributeData *DetectIKEv2SaAttributeParse (const char *rawstr)
+{
+    DetectIKEv2SaAttributeData *dd = NULL;
+#define MAX_SUBSTRINGS 30
+    int ret = 0, res = 0;
+    int ov[MAX_SUBSTRINGS];
+    char attribute[16] = "";
+    char value[16] = "";
+
+    ret = pcre_exec(parse_regex, parse_regex_study, rawstr, strlen(rawstr), 0,
+                    0, ov, MAX_SUBSTRINGS);
+    if (ret != 3) {
+        SCLogError(SC_ERR_PCRE_MATCH, "Parse error %s", rawstr);
+        goto error;
+    }
+
+    res = pcre_copy_substring((char *)rawstr, ov, MAX_SUBSTRINGS, 1, attribute,
+                              sizeof(attribute));
+    if (res < 0) {
+        SCLogError(SC_ERR_PCRE_GET_SUBSTRING, "pcre_copy_substring failed");
+        goto error;
+    }
+
+    res = pcre_copy_substring((char *)rawstr, ov, MAX_SUBSTRINGS, 2, value,
+                              sizeof(value));
+    if (res < 0) {
+        SCLogError(SC_ERR_PCRE_GET_SUBSTRING, "pcre_copy_substring failed");
+        goto error;
+    }
+
+    dd = SCCalloc(1, sizeof(DetectIKEv2SaAttributeData));
+    if (unlikely(dd == NULL))
+        goto error;
+
+    if (strcmp(attribute, "alg_enc") == 0)
+        dd->type = TRANSFORM_ENC;
+    else if (strcmp(attribute, "alg_prf") == 0)
+        dd->type = TRANSFORM_PRF;
+    else if (strcmp(attribute, "alg_auth") == 0)
+        dd->type = TRANSFORM_AUTH;
+    else if (strcmp(attribute, "alg_dh") == 0)
+        dd->type = TRANSFORM_DH;
+    else if (strcmp(attribute, "alg_esn") == 0)
+        dd->type = TRANSFORM_ESN;
+    else
+        goto error;
+
+    /* transform ids are 16 bits */
+    uint16_t id;
+    if (ByteExtractStringUint16(&id, 10, strlen(value), value) <= 0) {
+        SCLogError(SC_ERR_INVALID_SIGNATURE, "invalid transform id %s", value);
+        goto error;
+    }
+    dd->value = id;
+
+    return dd;
+
+error:
+    if (dd)
+        SCFree(dd);
+    return NULL;
+}
+
+static int DetectIKEv2SaAttributeSetup (DetectEngineCtx *de_ctx, Signature *s,
+        const char *rawstr, int sm_type, int list)
+{
+    DetectIKEv2SaAttributeData *dd = NULL;
+    SigMatch *sm = NULL;
+
+    if (DetectSignatureSetAppProto(s, ALPROTO_IKEV2) != 0)
+        return -1;
+
+    dd = DetectIKEv2SaAttributeParse(rawstr);
+    if (dd == NULL) {
+        SCLogError(SC_ERR_INVALID_ARGUMENT,"Parsing \'%s\' failed", rawstr);
+        goto error;
+    }
+
+    sm = SigMatchAlloc();
+    if (sm == NULL)
+        goto error;
+
+    sm->type = sm_type;
+    sm->ctx = (void *)dd;
+
+    SigMatchAppendSMToList(s, sm, list);
+    return 0;
+
+error:
+    DetectIKEv2SaAttributeFree(dd);
+    return -1;
+}
+
+static int DetectIKEv2ChosenSaAttributeSetup (DetectEngineCtx *de_ctx,
+        Signature *s, const char *rawstr)
+{
+    return DetectIKEv2SaAttributeSetup(de_ctx, s, rawstr,
+            DETECT_AL_IKEV2_CHOSEN_SA_ATTRIBUTE,
+            g_ikev2_chosen_sa_attribute_buffer_id);
+}
+
+static int DetectIKEv2ProposedSaAttributeSetup (DetectEngineCtx *de_ctx,
+        Signature *s, const char *rawstr)
+{
+    return DetectIKEv2SaAttributeSetup(de_ctx, s, rawstr,
+            DETECT_AL_IKEV2_PROPOSED_SA_ATTRIBUTE,
+            g_ikev2_proposed_sa_attribute_buffer_id);
+}
+
+/**
+ * \internal
+ * \brief Function to free memory associated with DetectIKEv2SaAttributeData.
+ *
+ * \param de_ptr Pointer to DetectIKEv2SaAttributeData.
+ */
+static void DetectIKEv2SaAttributeFree(void *ptr)
+{
+    SCFree(ptr);
+}
+
+#ifdef UNITTESTS
+#include "tests/detect-ikev2-sa-attribute.c"
+#endif /* UNITTESTS */
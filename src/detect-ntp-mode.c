@@ -0,0 +1,259 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+/**
+ * \file
+ *
+ * Implements the ntp.mode keyword, matching the association mode of NTP
+ * messages.
+ */
+
+#include "suricata-common.h"
+#include "conf.h"
+#include "detect.h"
+#include "detect-parse.h"
+#include "detect-engine.h"
+#include "detect-engine-content-inspection.h"
+#include "detect-ntp-mode.h"
+#include "app-layer-parser.h"
+
+#include "rust-ntp-ntp-gen.h"
+#include "rust-ntp-detect-gen.h"
+
+/**
+ *   [ntp.mode]:<mode>;
+ */
+#define PARSE_REGEX "^\\s*([0-9]+|[a-z_]+)\\s*$"
+static pcre *parse_regex;
+static pcre_extra *parse_regex_study;
+
+/* association modes, RFC 5905 section 7.3 */
+static const struct {
+    const char *name;
+    uint32_t mode;
+} ntp_modes[] = {
+    { "reserved",           0, },
+    { "symmetric_active",   1, },
+    { "symmetric_passive",  2, },
+    { "client",             3, },
+    { "server",             4, },
+    { "broadcast",          5, },
+    { "control",            6, },
+    { "private",            7, },
+    { NULL,                 0, },
+};
+
+typedef struct DetectNTPModeData_ {
+    uint32_t mode;
+} DetectNTPModeData;
+
+static DetectNTPModeData *DetectNTPModeParse (const char *);
+static int DetectNTPModeSetup (DetectEngineCtx *, Signature *s, const char *str);
+static void DetectNTPModeFree(void *);
+#ifdef UNITTESTS
+static void DetectNTPModeRegisterTests(void);
+#endif
+static int g_ntp_mode_buffer_id = 0;
+
+static int DetectEngineInspectNTPGeneric(ThreadVars *tv,
+        DetectEngineCtx *de_ctx, DetectEngineThreadCtx *det_ctx,
+        const Signature *s, const SigMatchData *smd,
+        Flow *f, uint8_t flags, void *alstate,
+        void *txv, uint64_t tx_id);
+
+static int DetectNTPModeMatch (DetectEngineThreadCtx *, Flow *,
+                               uint8_t, void *, void *, const Signature *,
+                               const SigMatchCtx *);
+
+void DetectNTPModeRegister(void)
+{
+    sigmatch_table[DETECT_AL_NTP_MODE].name = "ntp.mode";
+    sigmatch_table[DETECT_AL_NTP_MODE].desc = "match NTP association mode";
+    sigmatch_table[DETECT_AL_NTP_MODE].url = DOC_URL DOC_VERSION "/rules/ntp-keywords.html#ntp-mode";
+    sigmatch_table[DETECT_AL_NTP_MODE].Match = NULL;
+    sigmatch_table[DETECT_AL_NTP_MODE].AppLayerTxMatch = DetectNTPModeMatch;
+    sigmatch_table[DETECT_AL_NTP_MODE].Setup = DetectNTPModeSetup;
+    sigmatch_table[DETECT_AL_NTP_MODE].Free = DetectNTPModeFree;
+#ifdef UNITTESTS
+    sigmatch_table[DETECT_AL_NTP_MODE].RegisterTests = DetectNTPModeRegisterTests;
+#endif
+    sigmatch_table[DETECT_AL_NTP_MODE].flags |= SIGMATCH_NOOPT;
+
+    DetectSetupParseRegexes(PARSE_REGEX, &parse_regex, &parse_regex_study);
+
+    DetectAppLayerInspectEngineRegister("ntp.mode",
+            ALPROTO_NTP, SIG_FLAG_TOSERVER, 0,
+            DetectEngineInspectNTPGeneric);
+
+    DetectAppLayerInspectEngineRegister("ntp.mode",
+            ALPROTO_NTP, SIG_FLAG_TOCLIENT, 0,
+            DetectEngineInspectNTPGeneric);
+
+    g_ntp_mode_buffer_id = DetectBufferTypeGetByName("ntp.mode");
+}
+
+static int DetectEngineInspectNTPGeneric(ThreadVars *tv,
+        DetectEngineCtx *de_ctx, DetectEngineThreadCtx *det_ctx,
+        const Signature *s, const SigMatchData *smd,
+        Flow *f, uint8_t flags, void *alstate,
+        void *txv, uint64_t tx_id)
+{
+    return DetectEngineInspectGenericList(tv, de_ctx, det_ctx, s, smd,
+                                          f, flags, alstate, txv, tx_id);
+}
+
+/**
+ * \internal
+ * \brief Function to match the association mode of a TX
+ *
+ * \retval 0 no match.
+ * \retval 1 match.
+ */
+static int DetectNTPModeMatch (DetectEngineThreadCtx *det_ctx,
+                               Flow *f, uint8_t flags, void *state,
+                               void *txv, const Signature *s,
+                               const SigMatchCtx *ctx)
+{
+    SCEnter();
+
+    const DetectNTPModeData *dd = (const DetectNTPModeData *)ctx;
+    uint32_t mode;
+    rs_ntp_tx_get_mode(txv, &mode);
+    SCLogDebug("mode %u ref_mode %u", mode, dd->mode);
+    if (mode == dd->mode)
+        SCReturnInt(1);
+    SCReturnInt(0);
+}
+
+/**
+ * \internal
+ * \brief Function to parse options passed via ntp.mode keywords.
+ *
+ * \param rawstr Pointer to the user provided options.
+ *
+ * \retval dd pointer to DetectNTPModeData on success.
+ * \retval NULL on failure.
+ */
+static DetectNTPModeData *DetectNTPModeParse (const char *rawstr)
+{
+    DetectNTPModeData *dd = NULL;
+#define MAX_SUBSTRINGS 30
+    int ret = 0, res = 0;
+    int ov[MAX_SUBSTRINGS];
+    char value1[20] = "";
+    char *endptr = NULL;
+    int i;
+
+    ret = pcre_exec(parse_regex, parse_regex_study, rawstr, strlen(rawstr), 0,
+                    0, ov, MAX_SUBSTRINGS);
+    if (ret != 2) {
+        SCLogError(SC_ERR_PCRE_MATCH, "Parse error %s", rawstr);
+        goto error;
+    }
+
+    res = pcre_copy_substring((char *)rawstr, ov, MAX_SUBSTRINGS, 1, value1,
+                              sizeof(value1));
+    if (res < 0) {
+        SCLogError(SC_ERR_PCRE_GET_SUBSTRING, "pcre_copy_substring failed");
+        goto error;
+    }
+
+    dd = SCCalloc(1, sizeof(DetectNTPModeData));
+    if (unlikely(dd == NULL))
+        goto error;
+
+    if (isdigit((unsigned char)value1[0])) {
+        dd->mode = strtoul(value1, &endptr, 10);
+        if (endptr == NULL || *endptr != '\0' || dd->mode > 7) {
+            SCLogError(SC_ERR_INVALID_SIGNATURE, "invalid value as arg "
+                       "to ntp.mode keyword");
+            goto error;
+        }
+        return dd;
+    }
+
+    for (i = 0; ntp_modes[i].name != NULL; i++) {
+        if (strcmp(value1, ntp_modes[i].name) == 0) {
+            dd->mode = ntp_modes[i].mode;
+            return dd;
+        }
+    }
+    SCLogError(SC_ERR_INVALID_SIGNATURE, "unknown mode \"%s\" as arg "
+               "to ntp.mode keyword", value1);
+
+error:
+    if (dd)
+        SCFree(dd);
+    return NULL;
+}
+
+/**
+ * \brief Function to add the parsed ntp mode field into the current
+ *        signature.
+ *
+ * \param de_ctx Pointer to the Detection Engine Context.
+ * \param s      Pointer to the Current Signature.
+ * \param rawstr Pointer to the user provided options.
+ *
+ * \retval 0 on Success.
+ * \retval -1 on Failure.
+ */
+static int DetectNTPModeSetup (DetectEngineCtx *de_ctx, Signature *s,
+                               const char *rawstr)
+{
+    DetectNTPModeData *dd = NULL;
+    SigMatch *sm = NULL;
+
+    if (DetectSignatureSetAppProto(s, ALPROTO_NTP) != 0)
+        return -1;
+
+    dd = DetectNTPModeParse(rawstr);
+    if (dd == NULL) {
+        SCLogError(SC_ERR_INVALID_ARGUMENT,"Parsing \'%s\' failed", rawstr);
+        goto error;
+    }
+
+    sm = SigMatchAlloc();
+    if (sm == NULL)
+        goto error;
+
+    sm->type = DETECT_AL_NTP_MODE;
+    sm->ctx = (void *)dd;
+
+    SCLogDebug("ntp.mode %u", dd->mode);
+    SigMatchAppendSMToList(s, sm, g_ntp_mode_buffer_id);
+    return 0;
+
+error:
+    DetectNTPModeFree(dd);
+    return -1;
+}
+
+/**
+ * \internal
+ * \brief Function to free memory associated with DetectNTPModeData.
+ *
+ * \param de_ptr Pointer to DetectNTPModeData.
+ */
+static void DetectNTPModeFree(void *ptr)
+{
+    SCFree(ptr);
+}
+
+#ifdef UNITTESTS
+#include "tests/detect-ntp-mode.c"
+#endif /* UNITTESTS */
@@ -0,0 +1,307 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+/**
+ * \file
+ *
+ * Implements the ntp.version keyword, matching the NTP protocol version.
+ */
+
+#include "suricata-common.h"
+#include "conf.h"
+#include "detect.h"
+#include "detect-parse.h"
+#include "detect-engine.h"
+#include "detect-engine-content-inspection.h"
+#include "detect-ntp-version.h"
+#include "app-layer-parser.h"
+
+#include "rust-ntp-ntp-gen.h"
+#include "rust-ntp-detect-gen.h"
+
+/**
+ *   [ntp.version]:[<|>|<=|>=]<version>;
+ */
+#define PARSE_REGEX "^\\s*(<=|>=|<|>)?\\s*([0-9]+)\\s*$"
+static pcre *parse_regex;
+static pcre_extra *parse_regex_study;
+
+enum DetectNTPVersionMode {
+    NTP_VERSION_EQ = 1, /* equal */
+    NTP_VERSION_LT, /* less than */
+    NTP_VERSION_LE, /* less than or equal */
+    NTP_VERSION_GT, /* greater than */
+    NTP_VERSION_GE, /* greater than or equal */
+};
+
+typedef struct DetectNTPVersionData_ {
+    uint32_t version;
+    enum DetectNTPVersionMode mode;
+} DetectNTPVersionData;
+
+static DetectNTPVersionData *DetectNTPVersionParse (const char *);
+static int DetectNTPVersionSetup (DetectEngineCtx *, Signature *s, const char *str);
+static void DetectNTPVersionFree(void *);
+#ifdef UNITTESTS
+static void DetectNTPVersionRegisterTests(void);
+#endif
+static int g_ntp_version_buffer_id = 0;
+
+static int DetectEngineInspectNTPGeneric(ThreadVars *tv,
+        DetectEngineCtx *de_ctx, DetectEngineThreadCtx *det_ctx,
+        const Signature *s, const SigMatchData *smd,
+        Flow *f, uint8_t flags, void *alstate,
+        void *txv, uint64_t tx_id);
+
+static int DetectNTPVersionMatch (DetectEngineThreadCtx *, Flow *,
+                                  uint8_t, void *, void *, const Signature *,
+                                  const SigMatchCtx *);
+
+/**
+ * \brief Registration function for ntp.version keyword.
+ */
+void DetectNTPVersionRegister (void)
+{
+    sigmatch_table[DETECT_AL_NTP_VERSION].name = "ntp.version";
+    sigmatch_table[DETECT_AL_NTP_VERSION].desc = "match NTP version";
+    sigmatch_table[DETECT_AL_NTP_VERSION].url = DOC_URL DOC_VERSION "/rules/ntp-keywords.html#ntp-version";
+    sigmatch_table[DETECT_AL_NTP_VERSION].Match = NULL;
+    sigmatch_table[DETECT_AL_NTP_VERSION].AppLayerTxMatch = DetectNTPVersionMatch;
+    sigmatch_table[DETECT_AL_NTP_VERSION].Setup = DetectNTPVersionSetup;
+    sigmatch_table[DETECT_AL_NTP_VERSION].Free = DetectNTPVersionFree;
+#ifdef UNITTESTS
+    sigmatch_table[DETECT_AL_NTP_VERSION].RegisterTests = DetectNTPVersionRegisterTests;
+#endif
+    sigmatch_table[DETECT_AL_NTP_VERSION].flags |= SIGMATCH_NOOPT;
+
+    DetectSetupParseRegexes(PARSE_REGEX, &parse_regex, &parse_regex_study);
+
+    DetectAppLayerInspectEngineRegister("ntp.version",
+            ALPROTO_NTP, SIG_FLAG_TOSERVER, 0,
+            DetectEngineInspectNTPGeneric);
+
+    DetectAppLayerInspectEngineRegister("ntp.version",
+            ALPROTO_NTP, SIG_FLAG_TOCLIENT, 0,
+            DetectEngineInspectNTPGeneric);
+
+    g_ntp_version_buffer_id = DetectBufferTypeGetByName("ntp.version");
+}
+
+static int DetectEngineInspectNTPGeneric(ThreadVars *tv,
+        DetectEngineCtx *de_ctx, DetectEngineThreadCtx *det_ctx,
+        const Signature *s, const SigMatchData *smd,
+        Flow *f, uint8_t flags, void *alstate,
+        void *txv, uint64_t tx_id)
+{
+    return DetectEngineInspectGenericList(tv, de_ctx, det_ctx, s, smd,
+                                          f, flags, alstate, txv, tx_id);
+}
+
+static inline int
+VersionMatch(const uint32_t version,
+        enum DetectNTPVersionMode mode, uint32_t ref_version)
+{
+    switch (mode) {
+        case NTP_VERSION_EQ:
+            if (version == ref_version)
+                SCReturnInt(1);
+            break;
+        case NTP_VERSION_LT:
+            if (version < ref_version)
+                SCReturnInt(1);
+            break;
+        case NTP_VERSION_LE:
+            if (version <= ref_version)
+                SCReturnInt(1);
+            break;
+        case NTP_VERSION_GT:
+            if (version > ref_version)
+                SCReturnInt(1);
+            break;
+        case NTP_VERSION_GE:
+            if (version >= ref_version)
+                SCReturnInt(1);
+            break;
+    }
+    SCReturnInt(0);
+}
+
+/**
+ * \internal
+ * \brief Function to match version of a TX
+ *
+ * \param t       Pointer to thread vars.
+ * \param det_ctx Pointer to the pattern matcher thread.
+ * \param f       Pointer to the current flow.
+ * \param flags   Flags.
+ * \param state   App layer state.
+ * \param s       Pointer to the Signature.
+ * \param m       Pointer to the sigmatch that we will cast into
+ *                DetectNTPVersionData.
+ *
+ * \retval 0 no match.
+ * \retval 1 match.
+ */
+static int DetectNTPVersionMatch (DetectEngineThreadCtx *det_ctx,
+                                  Flow *f, uint8_t flags, void *state,
+                                  void *txv, const Signature *s,
+                                  const SigMatchCtx *ctx)
+{
+    SCEnter();
+
+    const DetectNTPVersionData *dd = (const DetectNTPVersionData *)ctx;
+    uint32_t version;
+    rs_ntp_tx_get_version(txv, &version);
+    SCLogDebug("version %u mode %u ref_version %d",
+            version, dd->mode, dd->version);
+    if (VersionMatch(version, dd->mode, dd->version))
+        SCReturnInt(1);
+    SCReturnInt(0);
+}
+
+/**
+ * \internal
+ * \brief Function to parse options passed via ntp.version keywords.
+ *
+ * \param rawstr Pointer to the user provided options.
+ *
+ * \retval dd pointer to DetectNTPVersionData on success.
+ * \retval NULL on failure.
+ */
+static DetectNTPVersionData *DetectNTPVersionParse (const char *rawstr)
+{
+    DetectNTPVersionData *dd = NULL;
+#define MAX_SUBSTRINGS 30
+    int ret = 0, res = 0;
+    int ov[MAX_SUBSTRINGS];
+    char mode[2] = "";
+    char value1[20] = "";
+    char *endptr = NULL;
+
+    ret = pcre_exec(parse_regex, parse_regex_study, rawstr, strlen(rawstr), 0,
+                    0, ov, MAX_SUBSTRINGS);
+    if (ret < 3 || ret > 5) {
+        SCLogError(SC_ERR_PCRE_MATCH, "Parse error %s", rawstr);
+        goto error;
+    }
+
+    res = pcre_copy_substring((char *)rawstr, ov, MAX_SUBSTRINGS, 1, mode,
+                              sizeof(mode));
+    if (res < 0) {
+        SCLogError(SC_ERR_PCRE_GET_SUBSTRING, "pcre_copy_substring failed");
+        goto error;
+    }
+
+    res = pcre_copy_substring((char *)rawstr, ov, MAX_SUBSTRINGS, 2, value1,
+                              sizeof(value1));
+    if (res < 0) {
+        SCLogError(SC_ERR_PCRE_GET_SUBSTRING, "pcre_copy_substring failed");
+        goto error;
+    }
+
+    dd = SCCalloc(1, sizeof(DetectNTPVersionData));
+    if (unlikely(dd == NULL))
+        goto error;
+
+    if (strlen(mode) == 1) {
+        if (mode[0] == '<')
+            dd->mode = NTP_VERSION_LT;
+        else if (mode[0] == '>')
+            dd->mode = NTP_VERSION_GT;
+    } else if (strlen(mode) == 2) {
+        if (strcmp(mode, "<=") == 0)
+            dd->mode = NTP_VERSION_LE;
+        if (strcmp(mode, ">=") == 0)
+            dd->mode = NTP_VERSION_GE;
+    }
+
+    if (dd->mode == 0) {
+        dd->mode = NTP_VERSION_EQ;
+    }
+
+    /* set the first value */
+    dd->version = strtoul(value1, &endptr, 10);
+    if (endptr == NULL || *endptr != '\0') {
+        SCLogError(SC_ERR_INVALID_SIGNATURE, "invalid character as arg "
+                   "to ntp.version keyword");
+        goto error;
+    }
+
+    return dd;
+
+error:
+    if (dd)
+        SCFree(dd);
+    return NULL;
+}
+/**
+ * \brief Function to add the parsed ntp version field into the current signature.
+ *
+ * \param de_ctx Pointer to the Detection Engine Context.
+ * \param s      Pointer to the Current Signature.
+ * \param rawstr Pointer to the user provided flags options.
+ *
+ * \retval 0 on Success.
+ * \retval -1 on Failure.
+ */
+static int DetectNTPVersionSetup (DetectEngineCtx *de_ctx, Signature *s,
+                                  const char *rawstr)
+{
+    DetectNTPVersionData *dd = NULL;
+    SigMatch *sm = NULL;
+
+    if (DetectSignatureSetAppProto(s, ALPROTO_NTP) != 0)
+        return -1;
+
+    dd = DetectNTPVersionParse(rawstr);
+    if (dd == NULL) {
+        SCLogError(SC_ERR_INVALID_ARGUMENT,"Parsing \'%s\' failed", rawstr);
+        goto error;
+    }
+
+    /* okay so far so good, lets get this into a SigMatch
+     * and put it in the Signature. */
+    sm = SigMatchAlloc();
+    if (sm == NULL)
+        goto error;
+
+    sm->type = DETECT_AL_NTP_VERSION;
+    sm->ctx = (void *)dd;
+
+    SCLogDebug("ntp.version %d", dd->version);
+    SigMatchAppendSMToList(s, sm, g_ntp_version_buffer_id);
+    return 0;
+
+error:
+    DetectNTPVersionFree(dd);
+    return -1;
+}
+
+/**
+ * \internal
+ * \brief Function to free memory associated with DetectNTPVersionData.
+ *
+ * \param de_ptr Pointer to DetectNTPVersionData.
+ */
+static void DetectNTPVersionFree(void *ptr)
+{
+    SCFree(ptr);
+}
+
+#ifdef UNITTESTS
+#include "tests/detect-ntp-version.c"
+#endif /* UNITTESTS */
@@ -0,0 +1,307 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+/**
+ * \file
+ *
+ * Implements the rdp.client_build keyword
+ */
+
+#include "suricata-common.h"
+#include "conf.h"
+#include "detect.h"
+#include "detect-parse.h"
+#include "detect-engine.h"
+#include "detect-engine-content-inspection.h"
+#include "detect-rdp-client-build.h"
+#include "app-layer-parser.h"
+
+#include "rust-rdp-detect-gen.h"
+
+/**
+ *   [rdp.client_build]:[<|>|<=|>=]<client_build>;
+ */
+#define PARSE_REGEX "^\\s*(<=|>=|<|>)?\\s*(0x[0-9a-fA-F]+|[0-9]+)\\s*$"
+static pcre *parse_regex;
+static pcre_extra *parse_regex_study;
+
+enum DetectRdpClientBuildMode {
+    PROCEDURE_EQ = 1, /* equal */
+    PROCEDURE_LT, /* less than */
+    PROCEDURE_LE, /* less than */
+    PROCEDURE_GT, /* greater than */
+    PROCEDURE_GE, /* greater than */
+};
+
+typedef struct DetectRdpClientBuildData_ {
+    uint32_t client_build;
+    enum DetectRdpClientBuildMode mode;
+} DetectRdpClientBuildData;
+
+static DetectRdpClientBuildData *DetectRdpClientBuildParse (const char *);
+static int DetectRdpClientBuildSetup (DetectEngineCtx *, Signature *s, const char *str);
+static void DetectRdpClientBuildFree(void *);
+#ifdef UNITTESTS
+static void DetectRdpClientBuildRegisterTests(void);
+#endif
+static int g_rdp_client_build_buffer_id = 0;
+
+static int DetectEngineInspectRdpClientBuildGeneric(ThreadVars *tv,
+        DetectEngineCtx *de_ctx, DetectEngineThreadCtx *det_ctx,
+        const Signature *s, const SigMatchData *smd,
+        Flow *f, uint8_t flags, void *alstate,
+        void *txv, uint64_t tx_id);
+
+static int DetectRdpClientBuildMatch (DetectEngineThreadCtx *, Flow *,
+                                   uint8_t, void *, void *, const Signature *,
+                                   const SigMatchCtx *);
+
+/**
+ * \brief Registration function for rdp.client_build keyword.
+ */
+void DetectRdpClientBuildRegister (void)
+{
+    sigmatch_table[DETECT_AL_RDP_CLIENT_BUILD].name = "rdp.client_build";
+    sigmatch_table[DETECT_AL_RDP_CLIENT_BUILD].desc = "match RDP client build number";
+    sigmatch_table[DETECT_AL_RDP_CLIENT_BUILD].url = DOC_URL DOC_VERSION "/rules/rdp-keywords.html#rdp-client-build";
+    sigmatch_table[DETECT_AL_RDP_CLIENT_BUILD].Match = NULL;
+    sigmatch_table[DETECT_AL_RDP_CLIENT_BUILD].AppLayerTxMatch = DetectRdpClientBuildMatch;
+    sigmatch_table[DETECT_AL_RDP_CLIENT_BUILD].Setup = DetectRdpClientBuildSetup;
+    sigmatch_table[DETECT_AL_RDP_CLIENT_BUILD].Free = DetectRdpClientBuildFree;
+#ifdef UNITTESTS
+    sigmatch_table[DETECT_AL_RDP_CLIENT_BUILD].RegisterTests = DetectRdpClientBuildRegisterTests;
+#endif
+    sigmatch_table[DETECT_AL_RDP_CLIENT_BUILD].flags |= SIGMATCH_NOOPT;
+
+    DetectSetupParseRegexes(PARSE_REGEX, &parse_regex, &parse_regex_study);
+
+    DetectAppLayerInspectEngineRegister("rdp.client_build",
+            ALPROTO_RDP, SIG_FLAG_TOSERVER, 0,
+            DetectEngineInspectRdpClientBuildGeneric);
+
+    g_rdp_client_build_buffer_id = DetectBufferTypeGetByName("rdp.client_build");
+}
+
+static int DetectEngineInspectRdpClientBuildGeneric(ThreadVars *tv,
+        DetectEngineCtx *de_ctx, DetectEngineThreadCtx *det_ctx,
+        const Signature *s, const SigMatchData *smd,
+        Flow *f, uint8_t flags, void *alstate,
+        void *txv, uint64_t tx_id)
+{
+    return DetectEngineInspectGenericList(tv, de_ctx, det_ctx, s, smd,
+                                          f, flags, alstate, txv, tx_id);
+}
+
+static inline int
+ValueMatch(const uint32_t value,
+        enum DetectRdpClientBuildMode mode, uint32_t ref_value)
+{
+    switch (mode) {
+        case PROCEDURE_EQ:
+            if (value == ref_value)
+                SCReturnInt(1);
+            break;
+        case PROCEDURE_LT:
+            if (value < ref_value)
+                SCReturnInt(1);
+            break;
+        case PROCEDURE_LE:
+            if (value <= ref_value)
+                SCReturnInt(1);
+            break;
+        case PROCEDURE_GT:
+            if (value > ref_value)
+                SCReturnInt(1);
+            break;
+        case PROCEDURE_GE:
+            if (value >= ref_value)
+                SCReturnInt(1);
+            break;
+    }
+    SCReturnInt(0);
+}
+
+/**
+ * \internal
+ * \brief Function to match client build of a TX
+ *
+ * \param t       Pointer to thread vars.
+ * \param det_ctx Pointer to the pattern matcher thread.
+ * \param f       Pointer to the current flow.
+ * \param flags   Flags.
+ * \param state   App layer state.
+ * \param s       Pointer to the Signature.
+ * \param m       Pointer to the sigmatch that we will cast into
+ *                DetectRdpClientBuildData.
+ *
+ * \retval 0 no match.
+ * \retval 1 match.
+ */
+static int DetectRdpClientBuildMatch (DetectEngineThreadCtx *det_ctx,
+                                   Flow *f, uint8_t flags, void *state,
+                                   void *txv, const Signature *s,
+                                   const SigMatchCtx *ctx)
+{
+    SCEnter();
+
+    const DetectRdpClientBuildData *dd = (const DetectRdpClientBuildData *)ctx;
+    uint32_t value;
+    if (rs_rdp_tx_get_client_build(txv, &value) != 1)
+        SCReturnInt(0);
+    SCLogDebug("client_build %u mode %u ref %u",
+            value, dd->mode, dd->client_build);
+    if (ValueMatch(value, dd->mode, dd->client_build))
+        SCReturnInt(1);
+    SCReturnInt(0);
+}
+
+/**
+ * \internal
+ * \brief Function to parse options passed via rdp.client_build keywords.
+ *
+ * \param rawstr Pointer to the user provided options.
+ *
+ * \retval dd pointer to DetectRdpClientBuildData on success.
+ * \retval NULL on failure.
+ */
+static DetectRdpClientBuildData *DetectRdpClientBuildParse (const char *rawstr)
+{
+    DetectRdpClientBuildData *dd = NULL;
+#define MAX_SUBSTRINGS 30
+    int ret = 0, res = 0;
+    int ov[MAX_SUBSTRINGS];
+    char mode[2] = "";
+    char value1[20] = "";
+    char *endptr = NULL;
+
+    ret = pcre_exec(parse_regex, parse_regex_study, rawstr, strlen(rawstr), 0,
+                    0, ov, MAX_SUBSTRINGS);
+    if (ret < 3 || ret > 5) {
+        SCLogError(SC_ERR_PCRE_MATCH, "Parse error %s", rawstr);
+        goto error;
+    }
+
+    res = pcre_copy_substring((char *)rawstr, ov, MAX_SUBSTRINGS, 1, mode,
+                              sizeof(mode));
+    if (res < 0) {
+        SCLogError(SC_ERR_PCRE_GET_SUBSTRING, "pcre_copy_substring failed");
+        goto error;
+    }
+
+    res = pcre_copy_substring((char *)rawstr, ov, MAX_SUBSTRINGS, 2, value1,
+                              sizeof(value1));
+    if (res < 0) {
+        SCLogError(SC_ERR_PCRE_GET_SUBSTRING, "pcre_copy_substring failed");
+        goto error;
+    }
+
+    dd = SCCalloc(1, sizeof(DetectRdpClientBuildData));
+    if (unlikely(dd == NULL))
+        goto error;
+
+    if (strlen(mode) == 1) {
+        if (mode[0] == '<')
+            dd->mode = PROCEDURE_LT;
+        else if (mode[0] == '>')
+            dd->mode = PROCEDURE_GT;
+    } else if (strlen(mode) == 2) {
+        if (strcmp(mode, "<=") == 0)
+            dd->mode = PROCEDURE_LE;
+        if (strcmp(mode, ">=") == 0)
+            dd->mode = PROCEDURE_GE;
+    }
+
+    if (dd->mode == 0) {
+        dd->mode = PROCEDURE_EQ;
+    }
+
+    /* set the first value, decimal or hex */
+    dd->client_build = strtoul(value1, &endptr, 0);
+    if (endptr == NULL || *endptr != '\0') {
+        SCLogError(SC_ERR_INVALID_SIGNATURE, "invalid character as arg "
+                   "to rdp.client_build keyword");
+        goto error;
+    }
+
+    return dd;
+
+error:
+    if (dd)
+        SCFree(dd);
+    return NULL;
+}
+
+
+
+/**
+ * \brief Function to add the parsed rdp.client_build field into the current signature.
+ *
+ * \param de_ctx Pointer to the Detection Engine Context.
+ * \param s      Pointer to the Current Signature.
+ * \param rawstr Pointer to the user provided flags options.
+ *
+ * \retval 0 on Success.
+ * \retval -1 on Failure.
+ */
+static int DetectRdpClientBuildSetup (DetectEngineCtx *de_ctx, Signature *s,
+                                   const char *rawstr)
+{
+    DetectRdpClientBuildData *dd = NULL;
+    SigMatch *sm = NULL;
+
+    if (DetectSignatureSetAppProto(s, ALPROTO_RDP) != 0)
+        return -1;
+
+    dd = DetectRdpClientBuildParse(rawstr);
+    if (dd == NULL) {
+        SCLogError(SC_ERR_INVALID_ARGUMENT,"Parsing \'%s\' failed", rawstr);
+        goto error;
+    }
+
+    /* okay so far so good, lets get this into a SigMatch
+     * and put it in the Signature. */
+    sm = SigMatchAlloc();
+    if (sm == NULL)
+        goto error;
+
+    sm->type = DETECT_AL_RDP_CLIENT_BUILD;
+    sm->ctx = (void *)dd;
+
+    SCLogDebug("rdp.client_build %u", dd->client_build);
+    SigMatchAppendSMToList(s, sm, g_rdp_client_build_buffer_id);
+    return 0;
+
+error:
+    DetectRdpClientBuildFree(dd);
+    return -1;
+}
+
+/**
+ * \internal
+ * \brief Function to free memory associated with DetectRdpClientBuildData.
+ *
+ * \param de_ptr Pointer to DetectRdpClientBuildData.
+ */
+static void DetectRdpClientBuildFree(void *ptr)
+{
+    SCFree(ptr);
+}
+
+
+#ifdef UNITTESTS
+#include "tests/detect-rdp-client-build.c"
+#endif /* UNITTESTS */
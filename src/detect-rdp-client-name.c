@@ -0,0 +1,101 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+/**
+ * \file
+ *
+ * Set up of the "rdp.client_name" keyword to allow content
+ * inspections on the RDP client name.
+ */
+
+#include "suricata-common.h"
+#include "conf.h"
+#include "detect.h"
+#include "detect-parse.h"
+#include "detect-engine.h"
+#include "detect-engine-mpm.h"
+#include "detect-engine-prefilter.h"
+#include "detect-engine-content-inspection.h"
+#include "detect-rdp-client-name.h"
+#include "app-layer-parser.h"
+
+#include "rust-rdp-detect-gen.h"
+
+static int DetectRdpClientNameSetup(DetectEngineCtx *, Signature *,
+    const char *);
+static InspectionBuffer *GetData(DetectEngineThreadCtx *det_ctx,
+       const DetectEngineTransforms *transforms,
+       Flow *f, const uint8_t flow_flags,
+       void *txv, const int list_id);
+static int g_rdp_client_name_buffer_id = 0;
+
+void DetectRdpClientNameRegister(void)
+{
+    sigmatch_table[DETECT_AL_RDP_CLIENT_NAME].name = "rdp.client_name";
+    sigmatch_table[DETECT_AL_RDP_CLIENT_NAME].desc =
+        "sticky buffer to match on the RDP client computer name";
+    sigmatch_table[DETECT_AL_RDP_CLIENT_NAME].Setup =
+        DetectRdpClientNameSetup;
+    sigmatch_table[DETECT_AL_RDP_CLIENT_NAME].url = DOC_URL DOC_VERSION "/rules/rdp-keywords.html#rdp-client-name";
+
+    sigmatch_table[DETECT_AL_RDP_CLIENT_NAME].flags |= SIGMATCH_NOOPT|SIGMATCH_INFO_STICKY_BUFFER;
+
+    /* register inspect engines */
+    DetectAppLayerInspectEngineRegister2("rdp.client_name",
+            ALPROTO_RDP, SIG_FLAG_TOSERVER, 0,
+            DetectEngineInspectBufferGeneric, GetData);
+    DetectAppLayerMpmRegister2("rdp.client_name", SIG_FLAG_TOSERVER, 2,
+            PrefilterGenericMpmRegister, GetData, ALPROTO_RDP, 0);
+
+    DetectBufferTypeSetDescriptionByName("rdp.client_name", "RDP client name");
+
+    g_rdp_client_name_buffer_id = DetectBufferTypeGetByName("rdp.client_name");
+}
+
+static int DetectRdpClientNameSetup(DetectEngineCtx *de_ctx, Signature *s,
+    const char *str)
+{
+    if (DetectBufferSetActiveList(s, g_rdp_client_name_buffer_id) < 0)
+        return -1;
+
+    if (DetectSignatureSetAppProto(s, ALPROTO_RDP) != 0)
+        return -1;
+
+    return 0;
+}
+
+static InspectionBuffer *GetData(DetectEngineThreadCtx *det_ctx,
+        const DetectEngineTransforms *transforms, Flow *f,
+        const uint8_t flow_flags, void *txv, const int list_id)
+{
+    InspectionBuffer *buffer = InspectionBufferGet(det_ctx, list_id);
+    if (buffer->inspect == NULL) {
+        uint32_t data_len = 0;
+        const uint8_t *data = NULL;
+
+        if (rs_rdp_tx_get_client_name(txv, &data, &data_len) != 1)
+            return NULL;
+        if (data == NULL || data_len == 0) {
+            return NULL;
+        }
+
+        InspectionBufferSetup(buffer, data, data_len);
+        InspectionBufferApplyTransforms(buffer, transforms);
+    }
+
+    return buffer;
+}
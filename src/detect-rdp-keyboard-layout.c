@@ -0,0 +1,307 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+/**
+ * \file
+ *
+ * Implements the rdp.keyboard_layout keyword
+ */
+
+#include "suricata-common.h"
+#include "conf.h"
+#include "detect.h"
+#include "detect-parse.h"
+#include "detect-engine.h"
+#include "detect-engine-content-inspection.h"
+#include "detect-rdp-keyboard-layout.h"
+#include "app-layer-parser.h"
+
+#include "rust-rdp-detect-gen.h"
+
+/**
+ *   [rdp.keyboard_layout]:[<|>|<=|>=]<keyboard_layout>;
+ */
+#define PARSE_REGEX "^\\s*(<=|>=|<|>)?\\s*(0x[0-9a-fA-F]+|[0-9]+)\\s*$"
+static pcre *parse_regex;
+static pcre_extra *parse_regex_study;
+
+enum DetectRdpKeyboardLayoutMode {
+    PROCEDURE_EQ = 1, /* equal */
+    PROCEDURE_LT, /* less than */
+    PROCEDURE_LE, /* less than */
+    PROCEDURE_GT, /* greater than */
+    PROCEDURE_GE, /* greater than */
+};
+
+typedef struct DetectRdpKeyboardLayoutData_ {
+    uint32_t keyboard_layout;
+    enum DetectRdpKeyboardLayoutMode mode;
+} DetectRdpKeyboardLayoutData;
+
+static DetectRdpKeyboardLayoutData *DetectRdpKeyboardLayoutParse (const char *);
+static int DetectRdpKeyboardLayoutSetup (DetectEngineCtx *, Signature *s, const char *str);
+static void DetectRdpKeyboardLayoutFree(void *);
+#ifdef UNITTESTS
+static void DetectRdpKeyboardLayoutRegisterTests(void);
+#endif
+static int g_rdp_keyboard_layout_buffer_id = 0;
+
+static int DetectEngineInspectRdpKeyboardLayoutGeneric(ThreadVars *tv,
+        DetectEngineCtx *de_ctx, DetectEngineThreadCtx *det_ctx,
+        const Signature *s, const SigMatchData *smd,
+        Flow *f, uint8_t flags, void *alstate,
+        void *txv, uint64_t tx_id);
+
+static int DetectRdpKeyboardLayoutMatch (DetectEngineThreadCtx *, Flow *,
+                                   uint8_t, void *, void *, const Signature *,
+                                   const SigMatchCtx *);
+
+/**
+ * \brief Registration function for rdp.keyboard_layout keyword.
+ */
+void DetectRdpKeyboardLayoutRegister (void)
+{
+    sigmatch_table[DETECT_AL_RDP_KEYBOARD_LAYOUT].name = "rdp.keyboard_layout";
+    sigmatch_table[DETECT_AL_RDP_KEYBOARD_LAYOUT].desc = "match RDP client keyboard layout";
+    sigmatch_table[DETECT_AL_RDP_KEYBOARD_LAYOUT].url = DOC_URL DOC_VERSION "/rules/rdp-keywords.html#rdp-keyboard-layout";
+    sigmatch_table[DETECT_AL_RDP_KEYBOARD_LAYOUT].Match = NULL;
+    sigmatch_table[DETECT_AL_RDP_KEYBOARD_LAYOUT].AppLayerTxMatch = DetectRdpKeyboardLayoutMatch;
+    sigmatch_table[DETECT_AL_RDP_KEYBOARD_LAYOUT].Setup = DetectRdpKeyboardLayoutSetup;
+    sigmatch_table[DETECT_AL_RDP_KEYBOARD_LAYOUT].Free = DetectRdpKeyboardLayoutFree;
+#ifdef UNITTESTS
+    sigmatch_table[DETECT_AL_RDP_KEYBOARD_LAYOUT].RegisterTests = DetectRdpKeyboardLayoutRegisterTests;
+#endif
+    sigmatch_table[DETECT_AL_RDP_KEYBOARD_LAYOUT].flags |= SIGMATCH_NOOPT;
+
+    DetectSetupParseRegexes(PARSE_REGEX, &parse_regex, &parse_regex_study);
+
+    DetectAppLayerInspectEngineRegister("rdp.keyboard_layout",
+            ALPROTO_RDP, SIG_FLAG_TOSERVER, 0,
+            DetectEngineInspectRdpKeyboardLayoutGeneric);
+
+    g_rdp_keyboard_layout_buffer_id = DetectBufferTypeGetByName("rdp.keyboard_layout");
+}
+
+static int DetectEngineInspectRdpKeyboardLayoutGeneric(ThreadVars *tv,
+        DetectEngineCtx *de_ctx, DetectEngineThreadCtx *det_ctx,
+        const Signature *s, const SigMatchData *smd,
+        Flow *f, uint8_t flags, void *alstate,
+        void *txv, uint64_t tx_id)
+{
+    return DetectEngineInspectGenericList(tv, de_ctx, det_ctx, s, smd,
+                                          f, flags, alstate, txv, tx_id);
+}
+
+static inline int
+ValueMatch(const uint32_t value,
+        enum DetectRdpKeyboardLayoutMode mode, uint32_t ref_value)
+{
+    switch (mode) {
+        case PROCEDURE_EQ:
+            if (value == ref_value)
+                SCReturnInt(1);
+            break;
+        case PROCEDURE_LT:
+            if (value < ref_value)
+                SCReturnInt(1);
+            break;
+        case PROCEDURE_LE:
+            if (value <= ref_value)
+                SCReturnInt(1);
+            break;
+        case PROCEDURE_GT:
+            if (value > ref_value)
+                SCReturnInt(1);
+            break;
+        case PROCEDURE_GE:
+            if (value >= ref_value)
+                SCReturnInt(1);
+            break;
+    }
+    SCReturnInt(0);
+}
+
+/**
+ * \internal
+ * \brief Function to match keyboard layout of a TX
+ *
+ * \param t       Pointer to thread vars.
+ * \param det_ctx Pointer to the pattern matcher thread.
+ * \param f       Pointer to the current flow.
+ * \param flags   Flags.
+ * \param state   App layer state.
+ * \param s       Pointer to the Signature.
+ * \param m       Pointer to the sigmatch that we will cast into
+ *                DetectRdpKeyboardLayoutData.
+ *
+ * \retval 0 no match.
+ * \retval 1 match.
+ */
+static int DetectRdpKeyboardLayoutMatch (DetectEngineThreadCtx *det_ctx,
+                                   Flow *f, uint8_t flags, void *state,
+                                   void *txv, const Signature *s,
+                                   const SigMatchCtx *ctx)
+{
+    SCEnter();
+
+    const DetectRdpKeyboardLayoutData *dd = (const DetectRdpKeyboardLayoutData *)ctx;
+    uint32_t value;
+    if (rs_rdp_tx_get_keyboard_layout(txv, &value) != 1)
+        SCReturnInt(0);
+    SCLogDebug("keyboard_layout %u mode %u ref %u",
+            value, dd->mode, dd->keyboard_layout);
+    if (ValueMatch(value, dd->mode, dd->keyboard_layout))
+        SCReturnInt(1);
+    SCReturnInt(0);
+}
+
+/**
+ * \internal
+ * \brief Function to parse options passed via rdp.keyboard_layout keywords.
+ *
+ * \param rawstr Pointer to the user provided options.
+ *
+ * \retval dd pointer to DetectRdpKeyboardLayoutData on success.
+ * \retval NULL on failure.
+ */
+static DetectRdpKeyboardLayoutData *DetectRdpKeyboardLayoutParse (const char *rawstr)
+{
+    DetectRdpKeyboardLayoutData *dd = NULL;
+#define MAX_SUBSTRINGS 30
+    int ret = 0, res = 0;
+    int ov[MAX_SUBSTRINGS];
+    char mode[2] = "";
+    char value1[20] = "";
+    char *endptr = NULL;
+
+    ret = pcre_exec(parse_regex, parse_regex_study, rawstr, strlen(rawstr), 0,
+                    0, ov, MAX_SUBSTRINGS);
+    if (ret < 3 || ret > 5) {
+        SCLogError(SC_ERR_PCRE_MATCH, "Parse error %s", rawstr);
+        goto error;
+    }
+
+    res = pcre_copy_substring((char *)rawstr, ov, MAX_SUBSTRINGS, 1, mode,
+                              sizeof(mode));
+    if (res < 0) {
+        SCLogError(SC_ERR_PCRE_GET_SUBSTRING, "pcre_copy_substring failed");
+        goto error;
+    }
+
+    res = pcre_copy_substring((char *)rawstr, ov, MAX_SUBSTRINGS, 2, value1,
+                              sizeof(value1));
+    if (res < 0) {
+        SCLogError(SC_ERR_PCRE_GET_SUBSTRING, "pcre_copy_substring failed");
+        goto error;
+    }
+
+    dd = SCCalloc(1, sizeof(DetectRdpKeyboardLayoutData));
+    if (unlikely(dd == NULL))
+        goto error;
+
+    if (strlen(mode) == 1) {
+        if (mode[0] == '<')
+            dd->mode = PROCEDURE_LT;
+        else if (mode[0] == '>')
+            dd->mode = PROCEDURE_GT;
+    } else if (strlen(mode) == 2) {
+        if (strcmp(mode, "<=") == 0)
+            dd->mode = PROCEDURE_LE;
+        if (strcmp(mode, ">=") == 0)
+            dd->mode = PROCEDURE_GE;
+    }
+
+    if (dd->mode == 0) {
+        dd->mode = PROCEDURE_EQ;
+    }
+
+    /* set the first value, decimal or hex */
+    dd->keyboard_layout = strtoul(value1, &endptr, 0);
+    if (endptr == NULL || *endptr != '\0') {
+        SCLogError(SC_ERR_INVALID_SIGNATURE, "invalid character as arg "
+                   "to rdp.keyboard_layout keyword");
+        goto error;
+    }
+
+    return dd;
+
+error:
+    if (dd)
+        SCFree(dd);
+    return NULL;
+}
+
+
+
+/**
+ * \brief Function to add the parsed rdp.keyboard_layout field into the current signature.
+ *
+ * \param de_ctx Pointer to the Detection Engine Context.
+ * \param s      Pointer to the Current Signature.
+ * \param rawstr Pointer to the user provided flags options.
+ *
+ * \retval 0 on Success.
+ * \retval -1 on Failure.
+ */
+static int DetectRdpKeyboardLayoutSetup (DetectEngineCtx *de_ctx, Signature *s,
+                                   const char *rawstr)
+{
+    DetectRdpKeyboardLayoutData *dd = NULL;
+    SigMatch *sm = NULL;
+
+    if (DetectSignatureSetAppProto(s, ALPROTO_RDP) != 0)
+        return -1;
+
+    dd = DetectRdpKeyboardLayoutParse(rawstr);
+    if (dd == NULL) {
+        SCLogError(SC_ERR_INVALID_ARGUMENT,"Parsing \'%s\' failed", rawstr);
+        goto error;
+    }
+
+    /* okay so far so good, lets get this into a SigMatch
+     * and put it in the Signature. */
+    sm = SigMatchAlloc();
+    if (sm == NULL)
+        goto error;
+
+    sm->type = DETECT_AL_RDP_KEYBOARD_LAYOUT;
+    sm->ctx = (void *)dd;
+
+    SCLogDebug("rdp.keyboard_layout %u", dd->keyboard_layout);
+    SigMatchAppendSMToList(s, sm, g_rdp_keyboard_layout_buffer_id);
+    return 0;
+
+error:
+    DetectRdpKeyboardLayoutFree(dd);
+    return -1;
+}
+
+/**
+ * \internal
+ * \brief Function to free memory associated with DetectRdpKeyboardLayoutData.
+ *
+ * \param de_ptr Pointer to DetectRdpKeyboardLayoutData.
+ */
+static void DetectRdpKeyboardLayoutFree(void *ptr)
+{
+    SCFree(ptr);
+}
+
+
+#ifdef UNITTESTS
+#include "tests/detect-rdp-keyboard-layout.c"
+#endif /* UNITTESTS */
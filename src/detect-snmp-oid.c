@@ -0,0 +1,155 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+/**
+ * \file
+ *
+ * Implements the snmp.oid keyword, matching the OIDs of the variable
+ * bindings against a pattern with wildcards.
+ */
+
+#include "suricata-common.h"
+#include "conf.h"
+#include "detect.h"
+#include "detect-parse.h"
+#include "detect-engine.h"
+#include "detect-engine-content-inspection.h"
+#include "detect-snmp-oid.h"
+#include "app-layer-parser.h"
+
+#include "rust-snmp-snmp-gen.h"
+#include "rust-snmp-detect-gen.h"
+
+static int DetectSNMPOidSetup (DetectEngineCtx *, Signature *s, const char *str);
+static void DetectSNMPOidFree(void *);
+#ifdef UNITTESTS
+static void DetectSNMPOidRegisterTests(void);
+#endif
+static int g_snmp_oid_buffer_id = 0;
+
+static int DetectEngineInspectSNMPOidGeneric(ThreadVars *tv,
+        DetectEngineCtx *de_ctx, DetectEngineThreadCtx *det_ctx,
+        const Signature *s, const SigMatchData *smd,
+        Flow *f, uint8_t flags, void *alstate,
+        void *txv, uint64_t tx_id);
+
+static int DetectSNMPOidMatch (DetectEngineThreadCtx *, Flow *,
+                               uint8_t, void *, void *, const Signature *,
+                               const SigMatchCtx *);
+
+void DetectSNMPOidRegister(void)
+{
+    sigmatch_table[DETECT_AL_SNMP_OID].name = "snmp.oid";
+    sigmatch_table[DETECT_AL_SNMP_OID].desc = "match SNMP variable binding OIDs";
+    sigmatch_table[DETECT_AL_SNMP_OID].url = DOC_URL DOC_VERSION "/rules/snmp-keywords.html#snmp-oid";
+    sigmatch_table[DETECT_AL_SNMP_OID].Match = NULL;
+    sigmatch_table[DETECT_AL_SNMP_OID].AppLayerTxMatch = DetectSNMPOidMatch;
+    sigmatch_table[DETECT_AL_SNMP_OID].Setup = DetectSNMPOidSetup;
+    sigmatch_table[DETECT_AL_SNMP_OID].Free = DetectSNMPOidFree;
+#ifdef UNITTESTS
+    sigmatch_table[DETECT_AL_SNMP_OID].RegisterTests = DetectSNMPOidRegisterTests;
+#endif
+
+    DetectAppLayerInspectEngineRegister("snmp.oid",
+            ALPROTO_SNMP, SIG_FLAG_TOSERVER, 0,
+            DetectEngineInspectSNMPOidGeneric);
+
+    DetectAppLayerInspectEngineRegister("snmp.oid",
+            ALPROTO_SNMP, SIG_FLAG_TOCLIENT, 0,
+            DetectEngineInspectSNMPOidGeneric);
+
+    g_snmp_oid_buffer_id = DetectBufferTypeGetByName("snmp.oid");
+}
+
+static int DetectEngineInspectSNMPOidGeneric(ThreadVars *tv,
+        DetectEngineCtx *de_ctx, DetectEngineThreadCtx *det_ctx,
+        const Signature *s, const SigMatchData *smd,
+        Flow *f, uint8_t flags, void *alstate,
+        void *txv, uint64_t tx_id)
+{
+    return DetectEngineInspectGenericList(tv, de_ctx, det_ctx, s, smd,
+                                          f, flags, alstate, txv, tx_id);
+}
+
+/**
+ * \internal
+ * \brief Function to match the variable binding OIDs of a TX
+ *
+ * \retval 0 no match.
+ * \retval 1 match.
+ */
+static int DetectSNMPOidMatch (DetectEngineThreadCtx *det_ctx,
+                               Flow *f, uint8_t flags, void *state,
+                               void *txv, const Signature *s,
+                               const SigMatchCtx *ctx)
+{
+    SCEnter();
+
+    SCReturnInt(rs_snmp_tx_match_oid(txv, flags, (void *)ctx));
+}
+
+/**
+ * \brief Function to add the snmp.oid keyword to a signature
+ *
+ * \param de_ctx Pointer to the Detection Engine Context.
+ * \param s      Pointer to the Current Signature.
+ * \param rawstr Pointer to the user provided OID pattern.
+ *
+ * \retval 0 on Success.
+ * \retval -1 on Failure.
+ */
+static int DetectSNMPOidSetup (DetectEngineCtx *de_ctx, Signature *s,
+                               const char *rawstr)
+{
+    void *detect = NULL;
+    SigMatch *sm = NULL;
+
+    if (DetectSignatureSetAppProto(s, ALPROTO_SNMP) != 0)
+        return -1;
+
+    detect = rs_snmp_detect_oid_parse(rawstr);
+    if (detect == NULL) {
+        SCLogError(SC_ERR_INVALID_RULE_ARGUMENT,
+                "failed to parse snmp.oid: %s", rawstr);
+        return -1;
+    }
+
+    sm = SigMatchAlloc();
+    if (sm == NULL)
+        goto error;
+
+    sm->type = DETECT_AL_SNMP_OID;
+    sm->ctx = (void *)detect;
+
+    SigMatchAppendSMToList(s, sm, g_snmp_oid_buffer_id);
+    return 0;
+
+error:
+    DetectSNMPOidFree(detect);
+    return -1;
+}
+
+static void DetectSNMPOidFree(void *ptr)
+{
+    if (ptr != NULL) {
+        rs_snmp_detect_oid_free(ptr);
+    }
+}
+
+#ifdef UNITTESTS
+#include "tests/detect-snmp-oid.c"
+#endif /* UNITTESTS */
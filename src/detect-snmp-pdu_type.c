@@ -124,7 +124,7 @@ static int DetectSNMPPduTypeMatch (DetectEngineThreadCtx *det_ctx,
 
     const DetectSNMPPduTypeData *dd = (const DetectSNMPPduTypeData *)ctx;
     uint32_t pdu_type;
-    rs_snmp_tx_get_pdu_type(txv, &pdu_type);
+    rs_snmp_tx_get_pdu_type(txv, flags, &pdu_type);
     SCLogDebug("pdu_type %u ref_pdu_type %d",
             pdu_type, dd->pdu_type);
     if (pdu_type == dd->pdu_type)
@@ -0,0 +1,238 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+/**
+ * \file
+ *
+ * Implements the snmp.security_level keyword, matching the security level
+ * of SNMPv3 messages.
+ */
+
+#include "suricata-common.h"
+#include "conf.h"
+#include "detect.h"
+#include "detect-parse.h"
+#include "detect-engine.h"
+#include "detect-engine-content-inspection.h"
+#include "detect-snmp-security-level.h"
+#include "app-layer-parser.h"
+
+#include "rust-snmp-snmp-gen.h"
+#include "rust-snmp-detect-gen.h"
+
+/**
+ *   [snmp.security_level]:<level>;
+ */
+#define PARSE_REGEX "^\\s*(noAuthNoPriv|authNoPriv|authPriv)\\s*$"
+static pcre *parse_regex;
+static pcre_extra *parse_regex_study;
+
+/* values of the securityLevel, RFC 3411 section 5 */
+#define SNMP_SECURITY_LEVEL_NOAUTHNOPRIV 1
+#define SNMP_SECURITY_LEVEL_AUTHNOPRIV   2
+#define SNMP_SECURITY_LEVEL_AUTHPRIV     3
+
+typedef struct DetectSNMPSecurityLevelData_ {
+    uint32_t level;
+} DetectSNMPSecurityLevelData;
+
+static DetectSNMPSecurityLevelData *DetectSNMPSecurityLevelParse (const char *);
+static int DetectSNMPSecurityLevelSetup (DetectEngineCtx *, Signature *s, const char *str);
+static void DetectSNMPSecurityLevelFree(void *);
+#ifdef UNITTESTS
+static void DetectSNMPSecurityLevelRegisterTests(void);
+#endif
+static int g_snmp_security_level_buffer_id = 0;
+
+static int DetectEngineInspectSNMPRequestGeneric(ThreadVars *tv,
+        DetectEngineCtx *de_ctx, DetectEngineThreadCtx *det_ctx,
+        const Signature *s, const SigMatchData *smd,
+        Flow *f, uint8_t flags, void *alstate,
+        void *txv, uint64_t tx_id);
+
+static int DetectSNMPSecurityLevelMatch (DetectEngineThreadCtx *, Flow *,
+                                         uint8_t, void *, void *, const Signature *,
+                                         const SigMatchCtx *);
+
+void DetectSNMPSecurityLevelRegister(void)
+{
+    sigmatch_table[DETECT_AL_SNMP_SECURITY_LEVEL].name = "snmp.security_level";
+    sigmatch_table[DETECT_AL_SNMP_SECURITY_LEVEL].desc = "match SNMPv3 security level";
+    sigmatch_table[DETECT_AL_SNMP_SECURITY_LEVEL].url = DOC_URL DOC_VERSION "/rules/snmp-keywords.html#snmp-security-level";
+    sigmatch_table[DETECT_AL_SNMP_SECURITY_LEVEL].Match = NULL;
+    sigmatch_table[DETECT_AL_SNMP_SECURITY_LEVEL].AppLayerTxMatch = DetectSNMPSecurityLevelMatch;
+    sigmatch_table[DETECT_AL_SNMP_SECURITY_LEVEL].Setup = DetectSNMPSecurityLevelSetup;
+    sigmatch_table[DETECT_AL_SNMP_SECURITY_LEVEL].Free = DetectSNMPSecurityLevelFree;
+#ifdef UNITTESTS
+    sigmatch_table[DETECT_AL_SNMP_SECURITY_LEVEL].RegisterTests = DetectSNMPSecurityLevelRegisterTests;
+#endif
+    sigmatch_table[DETECT_AL_SNMP_SECURITY_LEVEL].flags |= SIGMATCH_NOOPT;
+
+    DetectSetupParseRegexes(PARSE_REGEX, &parse_regex, &parse_regex_study);
+
+    DetectAppLayerInspectEngineRegister("snmp.security_level",
+            ALPROTO_SNMP, SIG_FLAG_TOSERVER, 0,
+            DetectEngineInspectSNMPRequestGeneric);
+
+    DetectAppLayerInspectEngineRegister("snmp.security_level",
+            ALPROTO_SNMP, SIG_FLAG_TOCLIENT, 0,
+            DetectEngineInspectSNMPRequestGeneric);
+
+    g_snmp_security_level_buffer_id = DetectBufferTypeGetByName("snmp.security_level");
+}
+
+static int DetectEngineInspectSNMPRequestGeneric(ThreadVars *tv,
+        DetectEngineCtx *de_ctx, DetectEngineThreadCtx *det_ctx,
+        const Signature *s, const SigMatchData *smd,
+        Flow *f, uint8_t flags, void *alstate,
+        void *txv, uint64_t tx_id)
+{
+    return DetectEngineInspectGenericList(tv, de_ctx, det_ctx, s, smd,
+                                          f, flags, alstate, txv, tx_id);
+}
+
+/**
+ * \internal
+ * \brief Function to match the security level of a TX
+ *
+ * \retval 0 no match.
+ * \retval 1 match.
+ */
+static int DetectSNMPSecurityLevelMatch (DetectEngineThreadCtx *det_ctx,
+                                         Flow *f, uint8_t flags, void *state,
+                                         void *txv, const Signature *s,
+                                         const SigMatchCtx *ctx)
+{
+    SCEnter();
+
+    const DetectSNMPSecurityLevelData *dd = (const DetectSNMPSecurityLevelData *)ctx;
+    uint32_t level;
+    rs_snmp_tx_get_security_level(txv, &level);
+    SCLogDebug("security_level %u ref_security_level %u",
+            level, dd->level);
+    if (level == dd->level)
+        SCReturnInt(1);
+    SCReturnInt(0);
+}
+
+/**
+ * \internal
+ * \brief Function to parse options passed via snmp.security_level keywords.
+ *
+ * \param rawstr Pointer to the user provided options.
+ *
+ * \retval dd pointer to DetectSNMPSecurityLevelData on success.
+ * \retval NULL on failure.
+ */
+static DetectSNMPSecurityLevelData *DetectSNMPSecurityLevelParse (const char *rawstr)
+{
+    DetectSNMPSecurityLevelData *dd = NULL;
+#define MAX_SUBSTRINGS 30
+    int ret = 0, res = 0;
+    int ov[MAX_SUBSTRINGS];
+    char value1[20] = "";
+
+    ret = pcre_exec(parse_regex, parse_regex_study, rawstr, strlen(rawstr), 0,
+                    0, ov, MAX_SUBSTRINGS);
+    if (ret != 2) {
+        SCLogError(SC_ERR_PCRE_MATCH, "Parse error %s", rawstr);
+        goto error;
+    }
+
+    res = pcre_copy_substring((char *)rawstr, ov, MAX_SUBSTRINGS, 1, value1,
+                              sizeof(value1));
+    if (res < 0) {
+        SCLogError(SC_ERR_PCRE_GET_SUBSTRING, "pcre_copy_substring failed");
+        goto error;
+    }
+
+    dd = SCCalloc(1, sizeof(DetectSNMPSecurityLevelData));
+    if (unlikely(dd == NULL))
+        goto error;
+
+    if (strcmp(value1, "noAuthNoPriv") == 0) {
+        dd->level = SNMP_SECURITY_LEVEL_NOAUTHNOPRIV;
+    } else if (strcmp(value1, "authNoPriv") == 0) {
+        dd->level = SNMP_SECURITY_LEVEL_AUTHNOPRIV;
+    } else {
+        dd->level = SNMP_SECURITY_LEVEL_AUTHPRIV;
+    }
+
+    return dd;
+
+error:
+    if (dd)
+        SCFree(dd);
+    return NULL;
+}
+
+/**
+ * \brief Function to add the parsed snmp security_level field into the
+ *        current signature.
+ *
+ * \param de_ctx Pointer to the Detection Engine Context.
+ * \param s      Pointer to the Current Signature.
+ * \param rawstr Pointer to the user provided options.
+ *
+ * \retval 0 on Success.
+ * \retval -1 on Failure.
+ */
+static int DetectSNMPSecurityLevelSetup (DetectEngineCtx *de_ctx, Signature *s,
+                                         const char *rawstr)
+{
+    DetectSNMPSecurityLevelData *dd = NULL;
+    SigMatch *sm = NULL;
+
+    if (DetectSignatureSetAppProto(s, ALPROTO_SNMP) != 0)
+        return -1;
+
+    dd = DetectSNMPSecurityLevelParse(rawstr);
+    if (dd == NULL) {
+        SCLogError(SC_ERR_INVALID_ARGUMENT,"Parsing \'%s\' failed", rawstr);
+        goto error;
+    }
+
+    sm = SigMatchAlloc();
+    if (sm == NULL)
+        goto error;
+
+    sm->type = DETECT_AL_SNMP_SECURITY_LEVEL;
+    sm->ctx = (void *)dd;
+
+    SCLogDebug("snmp.security_level %u", dd->level);
+    SigMatchAppendSMToList(s, sm, g_snmp_security_level_buffer_id);
+    return 0;
+
+error:
+    DetectSNMPSecurityLevelFree(dd);
+    return -1;
+}
+
+/**
+ * \internal
+ * \brief Function to free memory associated with DetectSNMPSecurityLevelData.
+ *
+ * \param de_ptr Pointer to DetectSNMPSecurityLevelData.
+ */
+static void DetectSNMPSecurityLevelFree(void *ptr)
+{
+    SCFree(ptr);
+}
+
+#ifdef UNITTESTS
+#include "tests/detect-snmp-security-level.c"
+#endif /* UNITTESTS */
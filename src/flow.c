@@ -1101,6 +1101,38 @@ uint8_t FlowGetDisruptionFlags(const Flow *f, uint8_t flags)
     return newflags;
 }
 
+/**
+ *  \brief Get the last time seen of the flow as seconds and microseconds,
+ *         for use by the Rust app-layer parsers.
+ */
+void FlowGetLastTimeAsParts(const Flow *f, uint64_t *secs, uint64_t *usecs)
+{
+    *secs = (uint64_t)f->lastts.tv_sec;
+    *usecs = (uint64_t)f->lastts.tv_usec;
+}
+
+/**
+ *  \brief Get the address of the client of the flow, for use by the Rust
+ *         app-layer parsers.
+ *
+ *  \param addr buffer of 16 bytes receiving the address
+ *
+ *  \retval 4 or 16, the length of the address, or 0 if the flow is neither
+ *          IPv4 nor IPv6
+ */
+uint8_t FlowGetClientAddr(const Flow *f, uint8_t *addr)
+{
+    const FlowAddress *client = (f->flags & FLOW_DIR_REVERSED) ? &f->dst : &f->src;
+    if (FLOW_IS_IPV4(f)) {
+        memcpy(addr, client->addr_data8, 4);
+        return 4;
+    } else if (FLOW_IS_IPV6(f)) {
+        memcpy(addr, client->addr_data8, 16);
+        return 16;
+    }
+    return 0;
+}
+
 void FlowUpdateState(Flow *f, enum FlowState s)
 {
     /* set the state */
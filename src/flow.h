@@ -639,6 +639,8 @@ int FlowClearMemory(Flow *,uint8_t );
 AppProto FlowGetAppProtocol(const Flow *f);
 void *FlowGetAppState(const Flow *f);
 uint8_t FlowGetDisruptionFlags(const Flow *f, uint8_t flags);
+void FlowGetLastTimeAsParts(const Flow *f, uint64_t *secs, uint64_t *usecs);
+uint8_t FlowGetClientAddr(const Flow *f, uint8_t *addr);
 
 void FlowHandlePacketUpdate(Flow *f, Packet *p);
 
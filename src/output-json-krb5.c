@@ -167,6 +167,127 @@ static TmEcode JsonKRB5LogThreadDeinit(ThreadVars *t, void *data)
     return TM_ECODE_OK;
 }
 
+#ifdef UNITTESTS
+#include "flow-util.h"
+
+static void JsonKRB5LogTestFlowInit(Flow *f)
+{
+    memset(f, 0, sizeof(*f));
+    FLOW_INITIALIZE(f);
+    f->alproto = ALPROTO_KRB5;
+    f->proto = IPPROTO_UDP;
+    f->protomap = FlowGetProtoMapping(f->proto);
+    f->flags |= FLOW_IPV4;
+}
+
+/**
+ * \test The client principal of a kpasswd request is taken from the
+ *       TGS-REP which issued the kadmin/changepw ticket, seen in another
+ *       flow, and logged in the kpasswd object.
+ */
+static int JsonKRB5LogTest01(void)
+{
+    AppLayerParserThreadCtx *alp_tctx = AppLayerParserThreadCtxAlloc();
+    Flow f1, f2;
+
+    /* TGS-REP issuing a kadmin/changepw ticket to alice@CONTOSO.LOCAL */
+    uint8_t tgs_rep[] = {
+        0x6d, 0x81, 0xbc, 0x30, 0x81, 0xb9, 0xa0, 0x03,
+        0x02, 0x01, 0x05, 0xa1, 0x03, 0x02, 0x01, 0x0d,
+        0xa3, 0x0f, 0x1b, 0x0d, 0x43, 0x4f, 0x4e, 0x54,
+        0x4f, 0x53, 0x4f, 0x2e, 0x4c, 0x4f, 0x43, 0x41,
+        0x4c, 0xa4, 0x12, 0x30, 0x10, 0xa0, 0x03, 0x02,
+        0x01, 0x01, 0xa1, 0x09, 0x30, 0x07, 0x1b, 0x05,
+        0x61, 0x6c, 0x69, 0x63, 0x65, 0xa5, 0x6b, 0x61,
+        0x69, 0x30, 0x67, 0xa0, 0x03, 0x02, 0x01, 0x05,
+        0xa1, 0x0f, 0x1b, 0x0d, 0x43, 0x4f, 0x4e, 0x54,
+        0x4f, 0x53, 0x4f, 0x2e, 0x4c, 0x4f, 0x43, 0x41,
+        0x4c, 0xa2, 0x1d, 0x30, 0x1b, 0xa0, 0x03, 0x02,
+        0x01, 0x02, 0xa1, 0x14, 0x30, 0x12, 0x1b, 0x06,
+        0x6b, 0x61, 0x64, 0x6d, 0x69, 0x6e, 0x1b, 0x08,
+        0x63, 0x68, 0x61, 0x6e, 0x67, 0x65, 0x70, 0x77,
+        0xa3, 0x30, 0x30, 0x2e, 0xa0, 0x03, 0x02, 0x01,
+        0x12, 0xa1, 0x03, 0x02, 0x01, 0x02, 0xa2, 0x22,
+        0x04, 0x20, 0x10, 0x11, 0x12, 0x13, 0x14, 0x15,
+        0x16, 0x17, 0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d,
+        0x1e, 0x1f, 0x20, 0x21, 0x22, 0x23, 0x24, 0x25,
+        0x26, 0x27, 0x28, 0x29, 0x2a, 0x2b, 0x2c, 0x2d,
+        0x2e, 0x2f, 0xa6, 0x1b, 0x30, 0x19, 0xa0, 0x03,
+        0x02, 0x01, 0x12, 0xa2, 0x12, 0x04, 0x10, 0x40,
+        0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48,
+        0x49, 0x4a, 0x4b, 0x4c, 0x4d, 0x4e, 0x4f
+    };
+
+    /* kpasswd change password request using the ticket */
+    uint8_t kpasswd_req[] = {
+        0x00, 0xd4, 0x00, 0x01, 0x00, 0xa3, 0x6e, 0x81,
+        0xa0, 0x30, 0x81, 0x9d, 0xa0, 0x03, 0x02, 0x01,
+        0x05, 0xa1, 0x03, 0x02, 0x01, 0x0e, 0xa2, 0x07,
+        0x03, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0xa3,
+        0x6b, 0x61, 0x69, 0x30, 0x67, 0xa0, 0x03, 0x02,
+        0x01, 0x05, 0xa1, 0x0f, 0x1b, 0x0d, 0x43, 0x4f,
+        0x4e, 0x54, 0x4f, 0x53, 0x4f, 0x2e, 0x4c, 0x4f,
+        0x43, 0x41, 0x4c, 0xa2, 0x1d, 0x30, 0x1b, 0xa0,
+        0x03, 0x02, 0x01, 0x02, 0xa1, 0x14, 0x30, 0x12,
+        0x1b, 0x06, 0x6b, 0x61, 0x64, 0x6d, 0x69, 0x6e,
+        0x1b, 0x08, 0x63, 0x68, 0x61, 0x6e, 0x67, 0x65,
+        0x70, 0x77, 0xa3, 0x30, 0x30, 0x2e, 0xa0, 0x03,
+        0x02, 0x01, 0x12, 0xa1, 0x03, 0x02, 0x01, 0x02,
+        0xa2, 0x22, 0x04, 0x20, 0x10, 0x11, 0x12, 0x13,
+        0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x1b,
+        0x1c, 0x1d, 0x1e, 0x1f, 0x20, 0x21, 0x22, 0x23,
+        0x24, 0x25, 0x26, 0x27, 0x28, 0x29, 0x2a, 0x2b,
+        0x2c, 0x2d, 0x2e, 0x2f, 0xa4, 0x1b, 0x30, 0x19,
+        0xa0, 0x03, 0x02, 0x01, 0x12, 0xa2, 0x12, 0x04,
+        0x10, 0x60, 0x61, 0x62, 0x63, 0x64, 0x65, 0x66,
+        0x67, 0x68, 0x69, 0x6a, 0x6b, 0x6c, 0x6d, 0x6e,
+        0x6f, 0x75, 0x29, 0x30, 0x27, 0xa0, 0x03, 0x02,
+        0x01, 0x05, 0xa1, 0x03, 0x02, 0x01, 0x15, 0xa3,
+        0x1b, 0x30, 0x19, 0xa0, 0x03, 0x02, 0x01, 0x12,
+        0xa2, 0x12, 0x04, 0x10, 0x80, 0x81, 0x82, 0x83,
+        0x84, 0x85, 0x86, 0x87, 0x88, 0x89, 0x8a, 0x8b,
+        0x8c, 0x8d, 0x8e, 0x8f
+    };
+
+    FAIL_IF_NULL(alp_tctx);
+    JsonKRB5LogTestFlowInit(&f1);
+    JsonKRB5LogTestFlowInit(&f2);
+
+    int r = AppLayerParserParse(NULL, alp_tctx, &f1, ALPROTO_KRB5,
+                                STREAM_TOCLIENT, tgs_rep, sizeof(tgs_rep));
+    FAIL_IF(r != 0);
+    r = AppLayerParserParse(NULL, alp_tctx, &f2, ALPROTO_KRB5,
+                            STREAM_TOSERVER, kpasswd_req, sizeof(kpasswd_req));
+    FAIL_IF(r != 0);
+    FAIL_IF_NULL(f2.alstate);
+
+    void *tx = AppLayerParserGetTx(IPPROTO_UDP, ALPROTO_KRB5, f2.alstate, 0);
+    FAIL_IF_NULL(tx);
+    json_t *js = rs_krb5_log_json_response(f2.alstate, tx);
+    FAIL_IF_NULL(js);
+
+    json_t *kpasswd = json_object_get(js, "kpasswd");
+    FAIL_IF_NULL(kpasswd);
+    const char *operation = json_string_value(json_object_get(kpasswd, "operation"));
+    FAIL_IF_NULL(operation);
+    FAIL_IF(strcmp(operation, "change_password") != 0);
+    const char *principal = json_string_value(json_object_get(kpasswd, "principal"));
+    FAIL_IF_NULL(principal);
+    FAIL_IF(strcmp(principal, "alice@CONTOSO.LOCAL") != 0);
+
+    json_decref(js);
+    AppLayerParserThreadCtxFree(alp_tctx);
+    FLOW_DESTROY(&f1);
+    FLOW_DESTROY(&f2);
+    PASS;
+}
+
+void JsonKRB5LogRegisterTests(void)
+{
+    UtRegisterTest("JsonKRB5LogTest01", JsonKRB5LogTest01);
+}
+#endif
+
 void JsonKRB5LogRegister(void)
 {
     /* Register as an eve sub-module. */
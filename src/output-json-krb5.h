@@ -25,5 +25,6 @@
 #define __OUTPUT_JSON_KRB5_H__
 
 void JsonKRB5LogRegister(void);
+void JsonKRB5LogRegisterTests(void);
 
 #endif /* __OUTPUT_JSON_KRB5_H__ */
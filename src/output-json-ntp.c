@@ -0,0 +1,176 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+/**
+ * \file
+ *
+ * Implement JSON/eve logging app-layer NTP.
+ */
+
+#include "suricata-common.h"
+#include "debug.h"
+#include "detect.h"
+#include "pkt-var.h"
+#include "conf.h"
+
+#include "threads.h"
+#include "threadvars.h"
+#include "tm-threads.h"
+
+#include "util-unittest.h"
+#include "util-buffer.h"
+#include "util-debug.h"
+#include "util-byte.h"
+
+#include "output.h"
+#include "output-json.h"
+
+#include "app-layer.h"
+#include "app-layer-parser.h"
+
+#include "app-layer-ntp.h"
+#include "output-json-ntp.h"
+
+#include "rust.h"
+#include "rust-ntp-log-gen.h"
+
+typedef struct LogNTPFileCtx_ {
+    LogFileCtx *file_ctx;
+    OutputJsonCommonSettings cfg;
+} LogNTPFileCtx;
+
+typedef struct LogNTPLogThread_ {
+    LogNTPFileCtx *ntplog_ctx;
+    MemBuffer          *buffer;
+} LogNTPLogThread;
+
+static int JsonNTPLogger(ThreadVars *tv, void *thread_data,
+    const Packet *p, Flow *f, void *state, void *tx, uint64_t tx_id)
+{
+    NTPTransaction *ntptx = tx;
+    LogNTPLogThread *thread = thread_data;
+    json_t *js, *ntpjs;
+
+    js = CreateJSONHeader(p, LOG_DIR_PACKET, "ntp");
+    if (unlikely(js == NULL)) {
+        return TM_ECODE_FAILED;
+    }
+
+    JsonAddCommonOptions(&thread->ntplog_ctx->cfg, p, f, js);
+
+    ntpjs = rs_ntp_log_json(state, ntptx);
+    if (unlikely(ntpjs == NULL)) {
+        goto error;
+    }
+    json_object_set_new(js, "ntp", ntpjs);
+
+    MemBufferReset(thread->buffer);
+    OutputJSONBuffer(js, thread->ntplog_ctx->file_ctx, &thread->buffer);
+
+    json_decref(js);
+    return TM_ECODE_OK;
+
+error:
+    json_decref(js);
+    return TM_ECODE_FAILED;
+}
+
+static void OutputNTPLogDeInitCtxSub(OutputCtx *output_ctx)
+{
+    LogNTPFileCtx *ntplog_ctx = (LogNTPFileCtx *)output_ctx->data;
+    SCFree(ntplog_ctx);
+    SCFree(output_ctx);
+}
+
+static OutputInitResult OutputNTPLogInitSub(ConfNode *conf,
+    OutputCtx *parent_ctx)
+{
+    OutputInitResult result = { NULL, false };
+    OutputJsonCtx *ajt = parent_ctx->data;
+
+    LogNTPFileCtx *ntplog_ctx = SCCalloc(1, sizeof(*ntplog_ctx));
+    if (unlikely(ntplog_ctx == NULL)) {
+        return result;
+    }
+    ntplog_ctx->file_ctx = ajt->file_ctx;
+    ntplog_ctx->cfg = ajt->cfg;
+
+    OutputCtx *output_ctx = SCCalloc(1, sizeof(*output_ctx));
+    if (unlikely(output_ctx == NULL)) {
+        SCFree(ntplog_ctx);
+        return result;
+    }
+    output_ctx->data = ntplog_ctx;
+    output_ctx->DeInit = OutputNTPLogDeInitCtxSub;
+
+    SCLogDebug("NTP log sub-module initialized.");
+
+    AppLayerParserRegisterLogger(IPPROTO_UDP, ALPROTO_NTP);
+
+    result.ctx = output_ctx;
+    result.ok = true;
+    return result;
+}
+
+static TmEcode JsonNTPLogThreadInit(ThreadVars *t, const void *initdata, void **data)
+{
+    LogNTPLogThread *thread = SCCalloc(1, sizeof(*thread));
+    if (unlikely(thread == NULL)) {
+        return TM_ECODE_FAILED;
+    }
+
+    if (initdata == NULL) {
+        SCLogDebug("Error getting context for EveLogNTP.  \"initdata\" is NULL.");
+        SCFree(thread);
+        return TM_ECODE_FAILED;
+    }
+
+    thread->buffer = MemBufferCreateNew(JSON_OUTPUT_BUFFER_SIZE);
+    if (unlikely(thread->buffer == NULL)) {
+        SCFree(thread);
+        return TM_ECODE_FAILED;
+    }
+
+    thread->ntplog_ctx = ((OutputCtx *)initdata)->data;
+    *data = (void *)thread;
+
+    return TM_ECODE_OK;
+}
+
+static TmEcode JsonNTPLogThreadDeinit(ThreadVars *t, void *data)
+{
+    LogNTPLogThread *thread = (LogNTPLogThread *)data;
+    if (thread == NULL) {
+        return TM_ECODE_OK;
+    }
+    if (thread->buffer != NULL) {
+        MemBufferFree(thread->buffer);
+    }
+    SCFree(thread);
+    return TM_ECODE_OK;
+}
+
+void JsonNTPLogRegister(void)
+{
+    /* Register as an eve sub-module. */
+    OutputRegisterTxSubModule(LOGGER_JSON_NTP, "eve-log", "JsonNTPLog",
+        "eve-log.ntp", OutputNTPLogInitSub, ALPROTO_NTP,
+        JsonNTPLogger, JsonNTPLogThreadInit,
+        JsonNTPLogThreadDeinit, NULL);
+
+    SCLogDebug("NTP JSON logger registered.");
+}
@@ -122,6 +122,7 @@ static OutputInitResult OutputSNMPLogInitSub(ConfNode *conf,
     SCLogDebug("SNMP log sub-module initialized.");
 
     AppLayerParserRegisterLogger(IPPROTO_UDP, ALPROTO_SNMP);
+    AppLayerParserRegisterLogger(IPPROTO_TCP, ALPROTO_SNMP);
 
     result.ctx = output_ctx;
     result.ok = true;
@@ -66,6 +66,8 @@
 
 #include "source-pcap-file.h"
 
+#include "rust-dhcp-lease-gen.h"
+
 #define DEFAULT_LOG_FILENAME "eve.json"
 #define DEFAULT_ALERT_SYSLOG_FACILITY_STR       "local0"
 #define DEFAULT_ALERT_SYSLOG_FACILITY           LOG_LOCAL0
@@ -387,6 +389,37 @@ static void JsonAddMetadata(const Packet *p, const Flow *f, json_t *js)
     }
 }
 
+/**
+ * \brief Add the MAC address and host name of the source and destination
+ *        from the DHCP lease table.
+ */
+static void JsonAddDHCPLeases(const Packet *p, const Flow *f, json_t *js)
+{
+    uint64_t now;
+    if (p != NULL) {
+        now = p->ts.tv_sec;
+    } else if (f != NULL) {
+        now = f->lastts.tv_sec;
+    } else {
+        return;
+    }
+    const char *src_ip = json_string_value(json_object_get(js, "src_ip"));
+    const char *dest_ip = json_string_value(json_object_get(js, "dest_ip"));
+    rs_dhcp_lease_log_json(js, src_ip, dest_ip, now);
+}
+
+/**
+ * \brief Add the DHCP lease fields if enabled, for the loggers which do
+ *        not use the other common options.
+ */
+void JsonAddDHCPLeasesOption(const OutputJsonCommonSettings *cfg,
+        const Packet *p, const Flow *f, json_t *js)
+{
+    if (cfg->include_dhcp_leases) {
+        JsonAddDHCPLeases(p, f, js);
+    }
+}
+
 void JsonAddCommonOptions(const OutputJsonCommonSettings *cfg,
         const Packet *p, const Flow *f, json_t *js)
 {
@@ -396,6 +429,7 @@ void JsonAddCommonOptions(const OutputJsonCommonSettings *cfg,
     if (cfg->include_community_id && f != NULL) {
         CreateJSONCommunityFlowId(js, f, cfg->community_id_seed);
     }
+    JsonAddDHCPLeasesOption(cfg, p, f, js);
 }
 
 /**
@@ -1043,6 +1077,16 @@ OutputInitResult OutputJsonInitCtx(ConfNode *conf)
             }
         }
 
+        /* See if we want to add the MAC addresses and host names from
+         * the DHCP lease table */
+        const ConfNode *dhcp_leases = ConfNodeLookupChild(conf, "dhcp-leases");
+        if (dhcp_leases && dhcp_leases->val && ConfValIsTrue(dhcp_leases->val)) {
+            SCLogConfig("Enabling eve DHCP lease logging.");
+            json_ctx->cfg.include_dhcp_leases = true;
+        } else {
+            json_ctx->cfg.include_dhcp_leases = false;
+        }
+
         /* Do we have a global eve xff configuration? */
         const ConfNode *xff = ConfNodeLookupChild(conf, "xff");
         if (xff != NULL) {
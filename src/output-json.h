@@ -70,6 +70,7 @@ typedef struct OutputJsonCommonSettings_ {
     bool include_metadata;
     bool include_community_id;
     uint16_t community_id_seed;
+    bool include_dhcp_leases;
 } OutputJsonCommonSettings;
 
 /*
@@ -94,5 +95,7 @@ void SCJsonDecref(json_t *js);
 
 void JsonAddCommonOptions(const OutputJsonCommonSettings *cfg,
         const Packet *p, const Flow *f, json_t *js);
+void JsonAddDHCPLeasesOption(const OutputJsonCommonSettings *cfg,
+        const Packet *p, const Flow *f, json_t *js);
 
 #endif /* __OUTPUT_JSON_H__ */
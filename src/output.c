@@ -74,7 +74,9 @@
 #include "output-json-ikev2.h"
 #include "output-json-krb5.h"
 #include "output-json-dhcp.h"
+#include "output-json-dhcpv6.h"
 #include "output-json-snmp.h"
+#include "output-json-ntp.h"
 #include "output-json-sip.h"
 #include "output-json-template.h"
 #include "output-json-template-rust.h"
@@ -1109,8 +1111,12 @@ void OutputRegisterLoggers(void)
     JsonKRB5LogRegister();
     /* DHCP JSON logger. */
     JsonDHCPLogRegister();
+    /* DHCPv6 JSON logger. */
+    JsonDHCPv6LogRegister();
     /* SNMP JSON logger. */
     JsonSNMPLogRegister();
+    /* NTP JSON logger. */
+    JsonNTPLogRegister();
     /* SIP JSON logger. */
     JsonSIPLogRegister();
     /* Template JSON logger. */
@@ -66,6 +66,8 @@
 #include "app-layer-ssh.h"
 #include "app-layer-smtp.h"
 
+#include "output-json-krb5.h"
+
 #include "util-action.h"
 #include "util-radix-tree.h"
 #include "util-host-os-info.h"
@@ -198,6 +200,7 @@ static void RegisterUnittests(void)
     SCAtomicRegisterTests();
     MemrchrRegisterTests();
     AppLayerUnittestsRegister();
+    JsonKRB5LogRegisterTests();
     MimeDecRegisterTests();
     StreamingBufferRegisterTests();
 #ifdef OS_WIN32
@@ -437,7 +437,9 @@ typedef enum {
     LOGGER_JSON_IKEV2,
     LOGGER_JSON_KRB5,
     LOGGER_JSON_DHCP,
+    LOGGER_JSON_DHCPV6,
     LOGGER_JSON_SNMP,
+    LOGGER_JSON_NTP,
     LOGGER_JSON_SIP,
     LOGGER_JSON_TEMPLATE_RUST,
     LOGGER_JSON_TEMPLATE,
@@ -0,0 +1,72 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+#include "util-unittest.h"
+#include "util-unittest-helper.h"
+
+/**
+ * \test This is a test for a valid decimal value.
+ *
+ * \retval 1 on success.
+ * \retval 0 on failure.
+ */
+static int IKEv2ExchTypeTestParse01 (void)
+{
+    DetectIKEv2ExchTypeData *dd = NULL;
+    dd = DetectIKEv2ExchTypeParse("34");
+    FAIL_IF_NULL(dd);
+    FAIL_IF_NOT(dd->exch_type == 34 && dd->mode == PROCEDURE_EQ);
+    DetectIKEv2ExchTypeFree(dd);
+    PASS;
+}
+
+/**
+ * \test This is a test for a valid value with an operator.
+ *
+ * \retval 1 on success.
+ * \retval 0 on failure.
+ */
+static int IKEv2ExchTypeTestParse02 (void)
+{
+    DetectIKEv2ExchTypeData *dd = NULL;
+    dd = DetectIKEv2ExchTypeParse(">=35");
+    FAIL_IF_NULL(dd);
+    FAIL_IF_NOT(dd->exch_type == 35 && dd->mode == PROCEDURE_GE);
+    DetectIKEv2ExchTypeFree(dd);
+    PASS;
+}
+
+/**
+ * \test This is a test for an invalid value.
+ *
+ * \retval 1 on success.
+ * \retval 0 on failure.
+ */
+static int IKEv2ExchTypeTestParse03 (void)
+{
+    DetectIKEv2ExchTypeData *dd = NULL;
+    dd = DetectIKEv2ExchTypeParse("abc");
+    FAIL_IF_NOT_NULL(dd);
+    PASS;
+}
+
+static void DetectIKEv2ExchTypeRegisterTests(void)
+{
+    UtRegisterTest("IKEv2ExchTypeTestParse01", IKEv2ExchTypeTestParse01);
+    UtRegisterTest("IKEv2ExchTypeTestParse02", IKEv2ExchTypeTestParse02);
+    UtRegisterTest("IKEv2ExchTypeTestParse03", IKEv2ExchTypeTestParse03);
+}
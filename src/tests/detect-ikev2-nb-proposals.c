@@ -0,0 +1,72 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+#include "util-unittest.h"
+#include "util-unittest-helper.h"
+
+/**
+ * \test This is a test for a valid decimal value.
+ *
+ * \retval 1 on success.
+ * \retval 0 on failure.
+ */
+static int IKEv2NbProposalsTestParse01 (void)
+{
+    DetectIKEv2NbProposalsData *dd = NULL;
+    dd = DetectIKEv2NbProposalsParse("1");
+    FAIL_IF_NULL(dd);
+    FAIL_IF_NOT(dd->nb_proposals == 1 && dd->mode == PROCEDURE_EQ);
+    DetectIKEv2NbProposalsFree(dd);
+    PASS;
+}
+
+/**
+ * \test This is a test for a valid value with an operator.
+ *
+ * \retval 1 on success.
+ * \retval 0 on failure.
+ */
+static int IKEv2NbProposalsTestParse02 (void)
+{
+    DetectIKEv2NbProposalsData *dd = NULL;
+    dd = DetectIKEv2NbProposalsParse(">=10");
+    FAIL_IF_NULL(dd);
+    FAIL_IF_NOT(dd->nb_proposals == 10 && dd->mode == PROCEDURE_GE);
+    DetectIKEv2NbProposalsFree(dd);
+    PASS;
+}
+
+/**
+ * \test This is a test for an invalid value.
+ *
+ * \retval 1 on success.
+ * \retval 0 on failure.
+ */
+static int IKEv2NbProposalsTestParse03 (void)
+{
+    DetectIKEv2NbProposalsData *dd = NULL;
+    dd = DetectIKEv2NbProposalsParse("abc");
+    FAIL_IF_NOT_NULL(dd);
+    PASS;
+}
+
+static void DetectIKEv2NbProposalsRegisterTests(void)
+{
+    UtRegisterTest("IKEv2NbProposalsTestParse01", IKEv2NbProposalsTestParse01);
+    UtRegisterTest("IKEv2NbProposalsTestParse02", IKEv2NbProposalsTestParse02);
+    UtRegisterTest("IKEv2NbProposalsTestParse03", IKEv2NbProposalsTestParse03);
+}
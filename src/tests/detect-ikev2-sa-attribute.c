@@ -0,0 +1,60 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+#include "util-unittest.h"
+#include "util-unittest-helper.h"
+
+/**
+ * \test This is a test for a valid attribute.
+ *
+ * \retval 1 on success.
+ * \retval 0 on failure.
+ */
+static int IKEv2SaAttributeTestParse01 (void)
+{
+    DetectIKEv2SaAttributeData *dd = NULL;
+    dd = DetectIKEv2SaAttributeParse("alg_enc=12");
+    FAIL_IF_NULL(dd);
+    FAIL_IF_NOT(dd->type == TRANSFORM_ENC && dd->value == 12);
+    DetectIKEv2SaAttributeFree(dd);
+
+    dd = DetectIKEv2SaAttributeParse(" alg_dh = 2 ");
+    FAIL_IF_NULL(dd);
+    FAIL_IF_NOT(dd->type == TRANSFORM_DH && dd->value == 2);
+    DetectIKEv2SaAttributeFree(dd);
+    PASS;
+}
+
+/**
+ * \test This is a test for invalid attributes and values.
+ *
+ * \retval 1 on success.
+ * \retval 0 on failure.
+ */
+static int IKEv2SaAttributeTestParse02 (void)
+{
+    FAIL_IF_NOT_NULL(DetectIKEv2SaAttributeParse("alg_foo=12"));
+    FAIL_IF_NOT_NULL(DetectIKEv2SaAttributeParse("alg_enc"));
+    FAIL_IF_NOT_NULL(DetectIKEv2SaAttributeParse("alg_enc=70000"));
+    PASS;
+}
+
+static void DetectIKEv2SaAttributeRegisterTests(void)
+{
+    UtRegisterTest("IKEv2SaAttributeTestParse01", IKEv2SaAttributeTestParse01);
+    UtRegisterTest("IKEv2SaAttributeTestParse02", IKEv2SaAttributeTestParse02);
+}
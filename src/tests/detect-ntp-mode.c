@@ -0,0 +1,47 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+#include "util-unittest.h"
+#include "util-unittest-helper.h"
+
+/**
+ * \test Test the parsing of valid and invalid modes.
+ */
+static int DetectNTPModeTestParse01 (void)
+{
+    DetectNTPModeData *dd = DetectNTPModeParse("client");
+    FAIL_IF_NULL(dd);
+    FAIL_IF_NOT(dd->mode == 3);
+    DetectNTPModeFree(dd);
+
+    dd = DetectNTPModeParse(" 7 ");
+    FAIL_IF_NULL(dd);
+    FAIL_IF_NOT(dd->mode == 7);
+    DetectNTPModeFree(dd);
+
+    dd = DetectNTPModeParse("8");
+    FAIL_IF_NOT_NULL(dd);
+
+    dd = DetectNTPModeParse("monlist");
+    FAIL_IF_NOT_NULL(dd);
+    PASS;
+}
+
+static void DetectNTPModeRegisterTests(void)
+{
+    UtRegisterTest("DetectNTPModeTestParse01", DetectNTPModeTestParse01);
+}
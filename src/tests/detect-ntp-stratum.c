@@ -0,0 +1,44 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+#include "util-unittest.h"
+#include "util-unittest-helper.h"
+
+/**
+ * \test Test the parsing of ntp.stratum values.
+ */
+static int DetectNTPStratumTestParse01 (void)
+{
+    DetectNTPStratumData *dd = DetectNTPStratumParse("1");
+    FAIL_IF_NULL(dd);
+    FAIL_IF_NOT(dd->stratum == 1 && dd->mode == NTP_STRATUM_EQ);
+    DetectNTPStratumFree(dd);
+
+    dd = DetectNTPStratumParse(" >= 15 ");
+    FAIL_IF_NULL(dd);
+    FAIL_IF_NOT(dd->stratum == 15 && dd->mode == NTP_STRATUM_GE);
+    DetectNTPStratumFree(dd);
+
+    dd = DetectNTPStratumParse("!1");
+    FAIL_IF_NOT_NULL(dd);
+    PASS;
+}
+
+static void DetectNTPStratumRegisterTests(void)
+{
+    UtRegisterTest("DetectNTPStratumTestParse01", DetectNTPStratumTestParse01);
+}
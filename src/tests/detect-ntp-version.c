@@ -0,0 +1,44 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+#include "util-unittest.h"
+#include "util-unittest-helper.h"
+
+/**
+ * \test Test the parsing of ntp.version values.
+ */
+static int DetectNTPVersionTestParse01 (void)
+{
+    DetectNTPVersionData *dd = DetectNTPVersionParse("4");
+    FAIL_IF_NULL(dd);
+    FAIL_IF_NOT(dd->version == 4 && dd->mode == NTP_VERSION_EQ);
+    DetectNTPVersionFree(dd);
+
+    dd = DetectNTPVersionParse("<3");
+    FAIL_IF_NULL(dd);
+    FAIL_IF_NOT(dd->version == 3 && dd->mode == NTP_VERSION_LT);
+    DetectNTPVersionFree(dd);
+
+    dd = DetectNTPVersionParse("v4");
+    FAIL_IF_NOT_NULL(dd);
+    PASS;
+}
+
+static void DetectNTPVersionRegisterTests(void)
+{
+    UtRegisterTest("DetectNTPVersionTestParse01", DetectNTPVersionTestParse01);
+}
@@ -0,0 +1,72 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+#include "util-unittest.h"
+#include "util-unittest-helper.h"
+
+/**
+ * \test This is a test for a valid decimal value.
+ *
+ * \retval 1 on success.
+ * \retval 0 on failure.
+ */
+static int RdpClientBuildTestParse01 (void)
+{
+    DetectRdpClientBuildData *dd = NULL;
+    dd = DetectRdpClientBuildParse("7601");
+    FAIL_IF_NULL(dd);
+    FAIL_IF_NOT(dd->client_build == 7601 && dd->mode == PROCEDURE_EQ);
+    DetectRdpClientBuildFree(dd);
+    PASS;
+}
+
+/**
+ * \test This is a test for a valid hex value with an operator.
+ *
+ * \retval 1 on success.
+ * \retval 0 on failure.
+ */
+static int RdpClientBuildTestParse02 (void)
+{
+    DetectRdpClientBuildData *dd = NULL;
+    dd = DetectRdpClientBuildParse(">=0x4563");
+    FAIL_IF_NULL(dd);
+    FAIL_IF_NOT(dd->client_build == 0x4563 && dd->mode == PROCEDURE_GE);
+    DetectRdpClientBuildFree(dd);
+    PASS;
+}
+
+/**
+ * \test This is a test for an invalid value.
+ *
+ * \retval 1 on success.
+ * \retval 0 on failure.
+ */
+static int RdpClientBuildTestParse03 (void)
+{
+    DetectRdpClientBuildData *dd = NULL;
+    dd = DetectRdpClientBuildParse("abc");
+    FAIL_IF_NOT_NULL(dd);
+    PASS;
+}
+
+static void DetectRdpClientBuildRegisterTests(void)
+{
+    UtRegisterTest("RdpClientBuildTestParse01", RdpClientBuildTestParse01);
+    UtRegisterTest("RdpClientBuildTestParse02", RdpClientBuildTestParse02);
+    UtRegisterTest("RdpClientBuildTestParse03", RdpClientBuildTestParse03);
+}
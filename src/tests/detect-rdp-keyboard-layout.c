@@ -0,0 +1,72 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+#include "util-unittest.h"
+#include "util-unittest-helper.h"
+
+/**
+ * \test This is a test for a valid decimal value.
+ *
+ * \retval 1 on success.
+ * \retval 0 on failure.
+ */
+static int RdpKeyboardLayoutTestParse01 (void)
+{
+    DetectRdpKeyboardLayoutData *dd = NULL;
+    dd = DetectRdpKeyboardLayoutParse("1033");
+    FAIL_IF_NULL(dd);
+    FAIL_IF_NOT(dd->keyboard_layout == 1033 && dd->mode == PROCEDURE_EQ);
+    DetectRdpKeyboardLayoutFree(dd);
+    PASS;
+}
+
+/**
+ * \test This is a test for a valid hex value with an operator.
+ *
+ * \retval 1 on success.
+ * \retval 0 on failure.
+ */
+static int RdpKeyboardLayoutTestParse02 (void)
+{
+    DetectRdpKeyboardLayoutData *dd = NULL;
+    dd = DetectRdpKeyboardLayoutParse(">=0x409");
+    FAIL_IF_NULL(dd);
+    FAIL_IF_NOT(dd->keyboard_layout == 0x409 && dd->mode == PROCEDURE_GE);
+    DetectRdpKeyboardLayoutFree(dd);
+    PASS;
+}
+
+/**
+ * \test This is a test for an invalid value.
+ *
+ * \retval 1 on success.
+ * \retval 0 on failure.
+ */
+static int RdpKeyboardLayoutTestParse03 (void)
+{
+    DetectRdpKeyboardLayoutData *dd = NULL;
+    dd = DetectRdpKeyboardLayoutParse("abc");
+    FAIL_IF_NOT_NULL(dd);
+    PASS;
+}
+
+static void DetectRdpKeyboardLayoutRegisterTests(void)
+{
+    UtRegisterTest("RdpKeyboardLayoutTestParse01", RdpKeyboardLayoutTestParse01);
+    UtRegisterTest("RdpKeyboardLayoutTestParse02", RdpKeyboardLayoutTestParse02);
+    UtRegisterTest("RdpKeyboardLayoutTestParse03", RdpKeyboardLayoutTestParse03);
+}
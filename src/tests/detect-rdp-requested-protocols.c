@@ -0,0 +1,72 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+#include "util-unittest.h"
+#include "util-unittest-helper.h"
+
+/**
+ * \test This is a test for a valid decimal value.
+ *
+ * \retval 1 on success.
+ * \retval 0 on failure.
+ */
+static int RdpRequestedProtocolsTestParse01 (void)
+{
+    DetectRdpRequestedProtocolsData *dd = NULL;
+    dd = DetectRdpRequestedProtocolsParse("0");
+    FAIL_IF_NULL(dd);
+    FAIL_IF_NOT(dd->requested_protocols == 0 && dd->mode == PROCEDURE_EQ);
+    DetectRdpRequestedProtocolsFree(dd);
+    PASS;
+}
+
+/**
+ * \test This is a test for a valid hex value with an operator.
+ *
+ * \retval 1 on success.
+ * \retval 0 on failure.
+ */
+static int RdpRequestedProtocolsTestParse02 (void)
+{
+    DetectRdpRequestedProtocolsData *dd = NULL;
+    dd = DetectRdpRequestedProtocolsParse(">=0x3");
+    FAIL_IF_NULL(dd);
+    FAIL_IF_NOT(dd->requested_protocols == 0x3 && dd->mode == PROCEDURE_GE);
+    DetectRdpRequestedProtocolsFree(dd);
+    PASS;
+}
+
+/**
+ * \test This is a test for an invalid value.
+ *
+ * \retval 1 on success.
+ * \retval 0 on failure.
+ */
+static int RdpRequestedProtocolsTestParse03 (void)
+{
+    DetectRdpRequestedProtocolsData *dd = NULL;
+    dd = DetectRdpRequestedProtocolsParse("abc");
+    FAIL_IF_NOT_NULL(dd);
+    PASS;
+}
+
+static void DetectRdpRequestedProtocolsRegisterTests(void)
+{
+    UtRegisterTest("RdpRequestedProtocolsTestParse01", RdpRequestedProtocolsTestParse01);
+    UtRegisterTest("RdpRequestedProtocolsTestParse02", RdpRequestedProtocolsTestParse02);
+    UtRegisterTest("RdpRequestedProtocolsTestParse03", RdpRequestedProtocolsTestParse03);
+}
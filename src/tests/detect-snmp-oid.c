@@ -0,0 +1,71 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+#include "util-unittest.h"
+#include "util-unittest-helper.h"
+
+/**
+ * \test Test the parsing of valid and invalid OID patterns.
+ */
+static int DetectSNMPOidTestParse01 (void)
+{
+    void *detect = rs_snmp_detect_oid_parse("1.3.6.1.2.1.1.5.0");
+    FAIL_IF_NULL(detect);
+    DetectSNMPOidFree(detect);
+
+    detect = rs_snmp_detect_oid_parse("1.3.6.1.4.1.9.*");
+    FAIL_IF_NULL(detect);
+    DetectSNMPOidFree(detect);
+
+    detect = rs_snmp_detect_oid_parse("1.3.6.1.2.1.2.2.1.*.2");
+    FAIL_IF_NULL(detect);
+    DetectSNMPOidFree(detect);
+
+    detect = rs_snmp_detect_oid_parse("1.3.6.x");
+    FAIL_IF_NOT_NULL(detect);
+
+    detect = rs_snmp_detect_oid_parse("");
+    FAIL_IF_NOT_NULL(detect);
+    PASS;
+}
+
+/**
+ * \test Test the keyword in a signature.
+ */
+static int DetectSNMPOidTestSig01 (void)
+{
+    DetectEngineCtx *de_ctx = DetectEngineCtxInit();
+    FAIL_IF_NULL(de_ctx);
+
+    Signature *s = DetectEngineAppendSig(de_ctx, "alert snmp any any -> any any "
+            "(msg:\"SNMP SET on Cisco config copy\"; snmp.pdu_type:3; "
+            "snmp.oid:1.3.6.1.4.1.9.9.96.1.1.1.1.*; sid:1; rev:1;)");
+    FAIL_IF_NULL(s);
+
+    s = DetectEngineAppendSig(de_ctx, "alert snmp any any -> any any "
+            "(msg:\"invalid\"; snmp.oid:foo; sid:2; rev:1;)");
+    FAIL_IF_NOT_NULL(s);
+
+    DetectEngineCtxFree(de_ctx);
+    PASS;
+}
+
+static void DetectSNMPOidRegisterTests(void)
+{
+    UtRegisterTest("DetectSNMPOidTestParse01", DetectSNMPOidTestParse01);
+    UtRegisterTest("DetectSNMPOidTestSig01", DetectSNMPOidTestSig01);
+}
@@ -0,0 +1,50 @@
+/* Copyright (C) 2019 Open Information Security Foundation
+ *
+ * You can copy, redistribute or modify this Program under the terms of
+ * the GNU General Public License version 2 as published by the Free
+ * Software Foundation.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * version 2 along with this program; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA
+ * 02110-1301, USA.
+ */
+
+#include "util-unittest.h"
+#include "util-unittest-helper.h"
+
+/**
+ * \test Test the parsing of valid and invalid security levels.
+ */
+static int DetectSNMPSecurityLevelTestParse01 (void)
+{
+    DetectSNMPSecurityLevelData *dd = DetectSNMPSecurityLevelParse("noAuthNoPriv");
+    FAIL_IF_NULL(dd);
+    FAIL_IF_NOT(dd->level == SNMP_SECURITY_LEVEL_NOAUTHNOPRIV);
+    DetectSNMPSecurityLevelFree(dd);
+
+    dd = DetectSNMPSecurityLevelParse(" authNoPriv ");
+    FAIL_IF_NULL(dd);
+    FAIL_IF_NOT(dd->level == SNMP_SECURITY_LEVEL_AUTHNOPRIV);
+    DetectSNMPSecurityLevelFree(dd);
+
+    dd = DetectSNMPSecurityLevelParse("authPriv");
+    FAIL_IF_NULL(dd);
+    FAIL_IF_NOT(dd->level == SNMP_SECURITY_LEVEL_AUTHPRIV);
+    DetectSNMPSecurityLevelFree(dd);
+
+    dd = DetectSNMPSecurityLevelParse("privNoAuth");
+    FAIL_IF_NOT_NULL(dd);
+    PASS;
+}
+
+static void DetectSNMPSecurityLevelRegisterTests(void)
+{
+    UtRegisterTest("DetectSNMPSecurityLevelTestParse01",
+            DetectSNMPSecurityLevelTestParse01);
+}
@@ -1311,12 +1311,14 @@ const char * PacketProfileLoggertIdToString(LoggerId id)
         CASE_CODE (LOGGER_JSON_DNP3_TC);
         CASE_CODE (LOGGER_JSON_HTTP);
         CASE_CODE (LOGGER_JSON_DHCP);
+        CASE_CODE (LOGGER_JSON_DHCPV6);
         CASE_CODE (LOGGER_JSON_KRB5);
         CASE_CODE (LOGGER_JSON_IKEV2);
         CASE_CODE (LOGGER_JSON_FTP);
         CASE_CODE (LOGGER_JSON_TFTP);
         CASE_CODE (LOGGER_JSON_SMTP);
         CASE_CODE (LOGGER_JSON_SNMP);
+        CASE_CODE (LOGGER_JSON_NTP);
         CASE_CODE (LOGGER_JSON_TLS);
         CASE_CODE (LOGGER_JSON_SIP);
         CASE_CODE (LOGGER_JSON_TEMPLATE_RUST);